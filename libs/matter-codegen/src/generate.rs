@@ -0,0 +1,533 @@
+//! Rust code generation from parsed definitions.
+
+use anyhow::{anyhow, Result};
+
+use crate::model::*;
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "box", "break", "const", "continue", "crate", "dyn", "else", "enum",
+    "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+    "mut", "pub", "ref", "return", "self", "static", "struct", "super", "trait", "true", "try",
+    "type", "unsafe", "use", "where", "while", "yield",
+];
+
+const DERIVE_TLV: &str =
+    "::tlv_derive::TlvMergeDecodable, ::tlv_derive::TlvEncodable, ::tlv_derive::TlvSchema";
+
+/// Converts a spec name like `AdminNodeID` or `Access Control` to `admin_node_id`
+/// or `access_control`.
+pub fn snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut result = String::new();
+
+    for (idx, &c) in chars.iter().enumerate() {
+        if !c.is_ascii_alphanumeric() {
+            if !result.is_empty() && !result.ends_with('_') {
+                result.push('_');
+            }
+            continue;
+        }
+
+        if c.is_ascii_uppercase() && idx > 0 {
+            let previous = chars[idx - 1];
+            let next_lower = matches!(chars.get(idx + 1), Some(n) if n.is_ascii_lowercase());
+            let word_start = previous.is_ascii_lowercase()
+                || previous.is_ascii_digit()
+                || (previous.is_ascii_uppercase() && next_lower);
+
+            if word_start && !result.is_empty() && !result.ends_with('_') {
+                result.push('_');
+            }
+        }
+
+        result.push(c.to_ascii_lowercase());
+    }
+
+    result.trim_end_matches('_').into()
+}
+
+/// Converts a spec name to an `UpperCamelCase` type name.
+pub fn type_name(name: &str) -> String {
+    let mut result = String::new();
+    let mut upper_next = true;
+
+    for c in name.chars() {
+        if !c.is_ascii_alphanumeric() {
+            upper_next = true;
+            continue;
+        }
+
+        if upper_next {
+            result.push(c.to_ascii_uppercase());
+        } else {
+            result.push(c);
+        }
+        upper_next = false;
+    }
+
+    match result.chars().next() {
+        Some(c) if c.is_ascii_digit() => format!("_{}", result),
+        _ => result,
+    }
+}
+
+/// Name for a constant, like `ADMIN_NODE_ID`.
+pub fn constant_name(name: &str) -> String {
+    let result = snake_case(name).to_ascii_uppercase();
+    match result.chars().next() {
+        Some(c) if c.is_ascii_digit() => format!("_{}", result),
+        _ => result,
+    }
+}
+
+/// Name for a field or module, avoiding keywords.
+pub fn field_name(name: &str) -> String {
+    let result = snake_case(name);
+    match result.chars().next() {
+        Some(c) if c.is_ascii_digit() => format!("_{}", result),
+        _ if KEYWORDS.contains(&result.as_str()) => format!("{}_", result),
+        _ => result,
+    }
+}
+
+/// How constraints apply to a resolved type.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum TypeKind {
+    Numeric,
+    Text,
+    Other,
+}
+
+/// Maps a spec data type to a Rust type.
+fn primitive_type(name: &str) -> Option<(&'static str, TypeKind)> {
+    let numeric = |ty| Some((ty, TypeKind::Numeric));
+
+    match name.to_ascii_lowercase().as_str() {
+        "boolean" | "bool" => Some(("bool", TypeKind::Other)),
+        "int8u" | "uint8" | "enum8" | "bitmap8" | "percent" | "fabric_idx" | "action_id"
+        | "status" | "priority" => numeric("u8"),
+        "int16u" | "uint16" | "enum16" | "bitmap16" | "percent100ths" | "vendor_id"
+        | "group_id" | "endpoint_no" | "entry_idx" => numeric("u16"),
+        "int24u" | "int32u" | "uint32" | "bitmap32" | "cluster_id" | "attrib_id" | "command_id"
+        | "event_id" | "field_id" | "devtype_id" | "trans_id" | "data_ver" | "epoch_s"
+        | "elapsed_s" | "utc" | "date" | "tod" => numeric("u32"),
+        "int40u" | "int48u" | "int56u" | "int64u" | "uint64" | "bitmap64" | "node_id"
+        | "fabric_id" | "event_no" | "epoch_us" | "systime_us" | "systime_ms" | "posix_ms" => {
+            numeric("u64")
+        }
+        "int8s" => numeric("i8"),
+        "int16s" | "temperature" => numeric("i16"),
+        "int24s" | "int32s" => numeric("i32"),
+        "int40s" | "int48s" | "int56s" | "int64s" | "amperage_ma" | "voltage_mv" | "power_mw"
+        | "energy_mwh" | "money" => numeric("i64"),
+        "single" => numeric("f32"),
+        "double" => numeric("f64"),
+        "char_string" | "long_char_string" => Some(("String", TypeKind::Text)),
+        "octet_string" | "long_octet_string" | "hwadr" | "ipadr" | "ipv4adr" | "ipv6adr"
+        | "ipv6pre" => Some(("Vec<u8>", TypeKind::Text)),
+        _ => None,
+    }
+}
+
+/// Validates a numeric constraint from the XML, returning it as a Rust literal.
+fn numeric_literal(value: &str) -> Result<String> {
+    let value = value.trim();
+    let valid = match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u64::from_str_radix(hex, 16).is_ok(),
+        None => value.parse::<i64>().is_ok() || value.parse::<u64>().is_ok(),
+    };
+
+    match valid {
+        true => Ok(value.into()),
+        false => Err(anyhow!("Invalid numeric constraint `{}`", value)),
+    }
+}
+
+/// Appends indented lines of Rust code.
+#[derive(Default)]
+struct CodeWriter {
+    code: String,
+    indent: usize,
+}
+
+impl CodeWriter {
+    fn line(&mut self, line: impl AsRef<str>) {
+        let line = line.as_ref();
+        if line.is_empty() && self.code.ends_with("\n\n") {
+            return;
+        }
+        if !line.is_empty() {
+            self.code.push_str(&"    ".repeat(self.indent));
+        }
+        self.code.push_str(line);
+        self.code.push('\n');
+    }
+
+    fn open(&mut self, line: impl AsRef<str>) {
+        self.line(line);
+        self.indent += 1;
+    }
+
+    fn close(&mut self, line: impl AsRef<str>) {
+        while self.code.ends_with("\n\n") {
+            self.code.pop();
+        }
+        self.indent -= 1;
+        self.line(line);
+    }
+}
+
+struct Generator<'a> {
+    definitions: &'a Definitions,
+    out: CodeWriter,
+}
+
+impl<'a> Generator<'a> {
+    /// Resolves a spec type as seen from the given cluster (`None` for global types).
+    fn resolve_type(&self, name: &str, cluster: Option<u32>) -> Result<(String, TypeKind)> {
+        let visible = |clusters: &[u32]| {
+            clusters.is_empty() || cluster.is_some_and(|code| clusters.contains(&code))
+        };
+
+        let defined = self
+            .definitions
+            .enums
+            .iter()
+            .any(|e| e.name == name && visible(&e.clusters))
+            || self
+                .definitions
+                .bitmaps
+                .iter()
+                .any(|b| b.name == name && visible(&b.clusters))
+            || self
+                .definitions
+                .structs
+                .iter()
+                .any(|s| s.name == name && visible(&s.clusters));
+
+        if defined {
+            return Ok((type_name(name), TypeKind::Other));
+        }
+
+        match primitive_type(name) {
+            Some((ty, kind)) => Ok((ty.into(), kind)),
+            None => Err(anyhow!("Unknown type `{}`", name)),
+        }
+    }
+
+    fn tlv_struct(
+        &mut self,
+        doc: &str,
+        name: &str,
+        fields: &[Field],
+        fabric_scoped: bool,
+        cluster: Option<u32>,
+    ) -> Result<()> {
+        self.out.line(format!("/// {}", doc));
+        self.out.line(format!(
+            "#[derive(Debug, Clone, Default, PartialEq, {})]",
+            DERIVE_TLV
+        ));
+        if fabric_scoped {
+            self.out.line("#[tlv(fabric_scoped)]");
+        }
+        self.out.open(format!("pub struct {} {{", type_name(name)));
+
+        for field in fields {
+            let (base, kind) = self.resolve_type(&field.field_type.name, cluster)?;
+            let is_fabric_index = fabric_scoped && field.id == 254;
+
+            let mut attributes = match is_fabric_index {
+                true => vec!["fabric_index".to_string()],
+                false => vec![format!("tag = {}", field.id)],
+            };
+
+            let mut ty = base;
+            if field.field_type.array {
+                ty = format!("Vec<{}>", ty);
+            } else {
+                if let (Some(max_len), TypeKind::Text) = (field.max_length, kind) {
+                    attributes.push(format!("max_len = {}", max_len));
+                }
+
+                if kind == TypeKind::Numeric {
+                    let min = field.min.as_deref().map(numeric_literal).transpose()?;
+                    let max = field.max.as_deref().map(numeric_literal).transpose()?;
+                    match (min, max) {
+                        (Some(min), Some(max)) => {
+                            attributes.push(format!("range = {}..={}", min, max))
+                        }
+                        (Some(min), None) => attributes.push(format!("range = {}..", min)),
+                        (None, Some(max)) => attributes.push(format!("range = ..={}", max)),
+                        (None, None) => {}
+                    }
+                }
+            }
+
+            // optional nullable fields are not distinguished from absent ones
+            if field.optional || field.nullable {
+                ty = format!("Option<{}>", ty);
+            }
+            if field.nullable {
+                attributes.push("nullable".into());
+            }
+            if fabric_scoped && field.fabric_sensitive && !is_fabric_index {
+                attributes.push("fabric_sensitive".into());
+            }
+
+            self.out.line(format!("#[tlv({})]", attributes.join(", ")));
+            self.out
+                .line(format!("pub {}: {},", field_name(&field.name), ty));
+        }
+
+        if fabric_scoped && !fields.iter().any(|f| f.id == 254) {
+            self.out.line("#[tlv(fabric_index)]");
+            self.out.line("pub fabric_index: u8,");
+        }
+
+        self.out.close("}");
+        self.out.line("");
+        Ok(())
+    }
+
+    fn enum_type(&mut self, e: &EnumType) -> Result<()> {
+        let (base, _) = primitive_type(&e.base_type)
+            .ok_or_else(|| anyhow!("Invalid base type `{}` for enum `{}`", e.base_type, e.name))?;
+        let max = match base {
+            "u8" => u8::MAX as u64,
+            "u16" => u16::MAX as u64,
+            "u32" => u32::MAX as u64,
+            "u64" => u64::MAX,
+            _ => {
+                return Err(anyhow!(
+                    "Invalid base type `{}` for enum `{}`",
+                    e.base_type,
+                    e.name
+                ))
+            }
+        };
+        if let Some((item, value)) = e.items.iter().find(|(_, value)| *value > max) {
+            return Err(anyhow!(
+                "Value {:#X} of item `{}` does not fit the base type `{}` of enum `{}`",
+                value,
+                item,
+                e.base_type,
+                e.name
+            ));
+        }
+        let name = type_name(&e.name);
+
+        // enums are open newtypes, so values unknown to this version are preserved
+        self.out
+            .line(format!("/// Enumeration `{}` ({}).", e.name, e.base_type));
+        self.out.line(format!(
+            "#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, {})]",
+            DERIVE_TLV
+        ));
+        self.out.line(format!("pub struct {}(pub {});", name, base));
+        self.out.line("");
+        self.out.open(format!("impl {} {{", name));
+        for (item, value) in &e.items {
+            self.out.line(format!(
+                "pub const {}: Self = Self({:#04X});",
+                constant_name(item),
+                value
+            ));
+        }
+        self.out.close("}");
+        self.out.line("");
+        Ok(())
+    }
+
+    fn bitmap_type(&mut self, b: &BitmapType) -> Result<()> {
+        let (base, _) = primitive_type(&b.base_type).ok_or_else(|| {
+            anyhow!(
+                "Invalid base type `{}` for bitmap `{}`",
+                b.base_type,
+                b.name
+            )
+        })?;
+        let name = type_name(&b.name);
+
+        self.out.open("::bitflags::bitflags! {");
+        self.out
+            .line(format!("/// Bitmap `{}` ({}).", b.name, b.base_type));
+        self.out.line("#[derive(Default)]");
+        self.out.open(format!("pub struct {}: {} {{", name, base));
+        for (field, mask) in &b.fields {
+            self.out
+                .line(format!("const {} = {:#X};", constant_name(field), mask));
+        }
+        self.out.close("}");
+        self.out.close("}");
+        self.out.line(format!(
+            "::tlv_packed::impl_tlv_bitflags!({}: {});",
+            name, base
+        ));
+        self.out.line("");
+        Ok(())
+    }
+
+    /// Emits all enums, bitmaps and structures visible in the given scope.
+    fn types(&mut self, cluster: Option<u32>) -> Result<()> {
+        let in_scope = |clusters: &[u32]| match cluster {
+            Some(code) => clusters.contains(&code),
+            None => clusters.is_empty(),
+        };
+        let definitions = self.definitions;
+
+        for e in definitions.enums.iter().filter(|e| in_scope(&e.clusters)) {
+            self.enum_type(e)?;
+        }
+        for b in definitions.bitmaps.iter().filter(|b| in_scope(&b.clusters)) {
+            self.bitmap_type(b)?;
+        }
+        for s in definitions.structs.iter().filter(|s| in_scope(&s.clusters)) {
+            let doc = format!("Structure `{}`.", s.name);
+            self.tlv_struct(&doc, &s.name, &s.fields, s.fabric_scoped, cluster)?;
+        }
+        Ok(())
+    }
+
+    fn cluster(&mut self, cluster: &Cluster) -> Result<()> {
+        let code = Some(cluster.code);
+
+        self.out.line(format!(
+            "/// Cluster `{}` ({:#06X}).",
+            cluster.name, cluster.code
+        ));
+        self.out
+            .open(format!("pub mod {} {{", field_name(&cluster.name)));
+        self.out.line("#[allow(unused_imports)]");
+        self.out.line("use super::*;");
+        self.out.line("");
+        self.out.line(format!(
+            "pub const CLUSTER_ID: u32 = {:#06X};",
+            cluster.code
+        ));
+        self.out.line("");
+        self.types(code)?;
+
+        self.out.open("pub mod attributes {");
+        for a in &cluster.attributes {
+            let mut details = vec![a.field_type.name.clone()];
+            if a.field_type.array {
+                details[0] = format!("list of {}", a.field_type.name);
+            }
+            if a.writable {
+                details.push("writable".into());
+            }
+            if a.optional {
+                details.push("optional".into());
+            }
+
+            self.out
+                .line(format!("/// `{}` ({}).", a.name, details.join(", ")));
+            self.out.line(format!(
+                "pub const {}: u32 = {:#06X};",
+                constant_name(&a.name),
+                a.code
+            ));
+        }
+        self.out.close("}");
+        self.out.line("");
+
+        self.out.open("pub mod commands {");
+        self.out.line("#[allow(unused_imports)]");
+        self.out.line("use super::*;");
+        self.out.line("");
+        for c in &cluster.commands {
+            self.out.line(format!(
+                "pub const {}: u32 = {:#04X};",
+                constant_name(&c.name),
+                c.code
+            ));
+        }
+        self.out.line("");
+        for c in &cluster.commands {
+            let source = match c.source {
+                CommandSource::Client => "client",
+                CommandSource::Server => "server",
+            };
+            let mut doc = format!(
+                "Command `{}` ({:#04X}), sent by the {}",
+                c.name, c.code, source
+            );
+            if let Some(ref response) = c.response {
+                doc.push_str(&format!(", answered by `{}`", response));
+            }
+            doc.push('.');
+            self.tlv_struct(&doc, &c.name, &c.fields, false, code)?;
+        }
+        self.out.close("}");
+        self.out.line("");
+
+        self.out.open("pub mod events {");
+        self.out.line("#[allow(unused_imports)]");
+        self.out.line("use super::*;");
+        self.out.line("");
+        for e in &cluster.events {
+            self.out.line(format!(
+                "pub const {}: u32 = {:#04X};",
+                constant_name(&e.name),
+                e.code
+            ));
+        }
+        self.out.line("");
+        for e in &cluster.events {
+            let doc = format!(
+                "Event `{}` ({:#04X}), priority {}.",
+                e.name, e.code, e.priority
+            );
+            self.tlv_struct(&doc, &e.name, &e.fields, false, code)?;
+        }
+        self.out.close("}");
+
+        self.out.close("}");
+        self.out.line("");
+        Ok(())
+    }
+}
+
+/// Generates Rust code for all clusters and types in `definitions`.
+///
+/// Global types are emitted at the top level, cluster specific types and
+/// `attributes`, `commands` and `events` modules in one module per cluster.
+/// The generated code uses `tlv-derive`, `tlv-packed` and `bitflags`, which
+/// need to be dependencies of the crate including it.
+pub fn generate(definitions: &Definitions) -> Result<String> {
+    let mut generator = Generator {
+        definitions,
+        out: CodeWriter::default(),
+    };
+
+    generator
+        .out
+        .line("// Generated by matter-codegen from Matter cluster definitions. Do not edit.");
+    generator.out.line("");
+    generator.types(None)?;
+
+    for cluster in &definitions.clusters {
+        generator.cluster(cluster)?;
+    }
+
+    Ok(generator.out.code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names() {
+        assert_eq!(snake_case("AdminNodeID"), "admin_node_id");
+        assert_eq!(snake_case("Access Control"), "access_control");
+        assert_eq!(snake_case("ACL"), "acl");
+        assert_eq!(field_name("Type"), "type_");
+        assert_eq!(constant_name("GroupNames"), "GROUP_NAMES");
+        assert_eq!(type_name("Access Control"), "AccessControl");
+    }
+}
@@ -0,0 +1,63 @@
+//! Generates Rust modules from Matter cluster XML definitions.
+//!
+//! The generated code contains cluster, attribute, command and event ID
+//! constants as well as TLV structures (via `tlv-derive`) for commands,
+//! events and structures. Enumerations are open newtypes with one constant per
+//! value, bitmaps use `bitflags`.
+//!
+//! Meant to be used from build scripts:
+//!
+//! ```no_run
+//! let out_dir = std::env::var("OUT_DIR").unwrap();
+//! let inputs = ["clusters/identify-cluster.xml"];
+//!
+//! for input in inputs {
+//!     println!("cargo:rerun-if-changed={}", input);
+//! }
+//!
+//! matter_codegen::generate_file(&inputs, format!("{}/clusters.rs", out_dir)).unwrap();
+//! ```
+//!
+//! and the generated file included with
+//! `include!(concat!(env!("OUT_DIR"), "/clusters.rs"));`.
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+pub mod generate;
+pub mod model;
+pub mod parse;
+
+pub use generate::generate;
+pub use model::Definitions;
+pub use parse::parse_definitions;
+
+/// Reads and merges the definitions of all given XML files.
+pub fn read_definitions<P: AsRef<Path>>(inputs: &[P]) -> Result<Definitions> {
+    let mut result = Definitions::default();
+
+    for input in inputs {
+        let input = input.as_ref();
+        let xml = fs::read_to_string(input)
+            .with_context(|| format!("Failed to read {}", input.display()))?;
+        let definitions = parse_definitions(&xml)
+            .with_context(|| format!("Failed to parse {}", input.display()))?;
+        result.extend(definitions);
+    }
+
+    Ok(result)
+}
+
+/// Generates Rust code for the given XML files.
+pub fn generate_from_files<P: AsRef<Path>>(inputs: &[P]) -> Result<String> {
+    generate(&read_definitions(inputs)?)
+}
+
+/// Generates Rust code for the given XML files into `output`.
+pub fn generate_file<P: AsRef<Path>, O: AsRef<Path>>(inputs: &[P], output: O) -> Result<()> {
+    let output = output.as_ref();
+    let code = generate_from_files(inputs)?;
+    fs::write(output, code).with_context(|| format!("Failed to write {}", output.display()))
+}
@@ -0,0 +1,37 @@
+use std::io::Write;
+
+use anyhow::{anyhow, Result};
+
+const USAGE: &str = "Usage: matter-codegen [-o OUTPUT] INPUT.xml...";
+
+fn main() -> Result<()> {
+    let mut output = None;
+    let mut inputs = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => {
+                output = Some(args.next().ok_or_else(|| anyhow!("{}", USAGE))?);
+            }
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            _ => inputs.push(arg),
+        }
+    }
+
+    if inputs.is_empty() {
+        return Err(anyhow!("{}", USAGE));
+    }
+
+    match output {
+        Some(output) => matter_codegen::generate_file(&inputs, output),
+        None => {
+            let code = matter_codegen::generate_from_files(&inputs)?;
+            std::io::stdout().write_all(code.as_bytes())?;
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! Data model definitions read from Matter cluster XML files.
+
+/// All definitions read from one or more XML files.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Definitions {
+    pub clusters: Vec<Cluster>,
+    pub enums: Vec<EnumType>,
+    pub bitmaps: Vec<BitmapType>,
+    pub structs: Vec<StructType>,
+}
+
+impl Definitions {
+    /// Adds all definitions of `other`.
+    pub fn extend(&mut self, other: Definitions) {
+        self.clusters.extend(other.clusters);
+        self.enums.extend(other.enums);
+        self.bitmaps.extend(other.bitmaps);
+        self.structs.extend(other.structs);
+    }
+
+    /// Finds the cluster with the given code.
+    pub fn cluster(&self, code: u32) -> Option<&Cluster> {
+        self.clusters.iter().find(|c| c.code == code)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cluster {
+    pub name: String,
+    pub code: u32,
+    pub attributes: Vec<Attribute>,
+    pub commands: Vec<Command>,
+    pub events: Vec<Event>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attribute {
+    pub name: String,
+    pub code: u32,
+
+    /// Type of the attribute value (the entry type for lists)
+    pub field_type: FieldType,
+    pub writable: bool,
+    pub optional: bool,
+}
+
+/// Which side of the interaction sends a command.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CommandSource {
+    Client,
+    Server,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Command {
+    pub name: String,
+    pub code: u32,
+    pub source: CommandSource,
+
+    /// Name of the response command, if any
+    pub response: Option<String>,
+    pub fabric_scoped: bool,
+    pub fields: Vec<Field>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub name: String,
+    pub code: u32,
+    pub priority: String,
+    pub fields: Vec<Field>,
+}
+
+/// Types that are not global are only defined within the listed clusters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumType {
+    pub name: String,
+
+    /// Underlying type, like `enum8`
+    pub base_type: String,
+    pub clusters: Vec<u32>,
+    pub items: Vec<(String, u64)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BitmapType {
+    pub name: String,
+
+    /// Underlying type, like `bitmap8`
+    pub base_type: String,
+    pub clusters: Vec<u32>,
+    pub fields: Vec<(String, u64)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructType {
+    pub name: String,
+    pub clusters: Vec<u32>,
+    pub fabric_scoped: bool,
+    pub fields: Vec<Field>,
+}
+
+/// The type of a field or attribute, as named in the XML (like `int16u`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldType {
+    pub name: String,
+
+    /// The value is a list of `name` entries
+    pub array: bool,
+}
+
+/// A member of a structure, command or event.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub name: String,
+
+    /// Context tag of the field
+    pub id: u8,
+    pub field_type: FieldType,
+    pub optional: bool,
+    pub nullable: bool,
+    pub fabric_sensitive: bool,
+
+    /// Maximum length of strings
+    pub max_length: Option<u32>,
+
+    /// Numeric constraints, as written in the XML
+    pub min: Option<String>,
+    pub max: Option<String>,
+}
@@ -0,0 +1,263 @@
+//! Parsing of Matter cluster XML definitions (the format used by ZAP).
+
+use anyhow::{anyhow, Context, Result};
+use roxmltree::{Document, Node};
+
+use crate::model::*;
+
+/// Parses a number given in decimal or `0x` prefixed hexadecimal.
+fn parse_number(value: &str) -> Result<u64> {
+    let value = value.trim();
+    let parsed = match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+    parsed.with_context(|| format!("Invalid number `{}`", value))
+}
+
+fn child_elements<'a, 'input: 'a>(
+    node: Node<'a, 'input>,
+    name: &'a str,
+) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.children()
+        .filter(move |n| n.is_element() && n.has_tag_name(name))
+}
+
+fn child_text<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.children()
+        .find(|n| n.is_element() && n.has_tag_name(name))
+        .and_then(|n| n.text())
+        .map(str::trim)
+}
+
+fn required_attribute<'a>(node: Node<'a, '_>, name: &str) -> Result<&'a str> {
+    node.attribute(name).ok_or_else(|| {
+        anyhow!(
+            "Missing attribute `{}` on <{}> (line {})",
+            name,
+            node.tag_name().name(),
+            node.document().text_pos_at(node.range().start).row
+        )
+    })
+}
+
+fn flag(node: Node, name: &str) -> bool {
+    node.attribute(name) == Some("true")
+}
+
+fn code_attribute(node: Node, name: &str) -> Result<u32> {
+    let code = parse_number(required_attribute(node, name)?)?;
+    u32::try_from(code).with_context(|| format!("Code `{}` does not fit 32 bits", code))
+}
+
+/// Codes of the `<cluster code="..."/>` children of a type definition.
+fn type_clusters(node: Node) -> Result<Vec<u32>> {
+    child_elements(node, "cluster")
+        .map(|c| code_attribute(c, "code"))
+        .collect()
+}
+
+fn parse_field_type(node: Node) -> Result<FieldType> {
+    let name = required_attribute(node, "type")?;
+
+    // lists are either flagged as arrays or use an `array` type with an entry type
+    Ok(match (name, node.attribute("entryType")) {
+        ("array" | "ARRAY", Some(entry)) => FieldType {
+            name: entry.into(),
+            array: true,
+        },
+        _ => FieldType {
+            name: name.into(),
+            array: flag(node, "array"),
+        },
+    })
+}
+
+/// Parses a structure item, event field or command argument.
+///
+/// Command arguments have no id attribute and use their position instead.
+fn parse_field(node: Node, id_attribute: &str, position: usize) -> Result<Field> {
+    let id = match node.attribute(id_attribute) {
+        Some(id) => parse_number(id)?,
+        None => position as u64,
+    };
+
+    let max_length = match node.attribute("length") {
+        Some(length) => Some(parse_number(length)? as u32),
+        None => None,
+    };
+
+    Ok(Field {
+        name: required_attribute(node, "name")?.into(),
+        id: u8::try_from(id).with_context(|| format!("Field id `{}` is not a context tag", id))?,
+        field_type: parse_field_type(node)?,
+        optional: flag(node, "optional"),
+        nullable: flag(node, "isNullable"),
+        fabric_sensitive: flag(node, "isFabricSensitive"),
+        max_length,
+        min: node.attribute("min").map(Into::into),
+        max: node.attribute("max").map(Into::into),
+    })
+}
+
+fn parse_enum(node: Node) -> Result<EnumType> {
+    let items = child_elements(node, "item")
+        .map(|item| {
+            let name = required_attribute(item, "name")?;
+            Ok((
+                name.into(),
+                parse_number(required_attribute(item, "value")?)?,
+            ))
+        })
+        .collect::<Result<_>>()?;
+
+    Ok(EnumType {
+        name: required_attribute(node, "name")?.into(),
+        base_type: required_attribute(node, "type")?.into(),
+        clusters: type_clusters(node)?,
+        items,
+    })
+}
+
+fn parse_bitmap(node: Node) -> Result<BitmapType> {
+    let fields = child_elements(node, "field")
+        .map(|field| {
+            let name = required_attribute(field, "name")?;
+            Ok((
+                name.into(),
+                parse_number(required_attribute(field, "mask")?)?,
+            ))
+        })
+        .collect::<Result<_>>()?;
+
+    Ok(BitmapType {
+        name: required_attribute(node, "name")?.into(),
+        base_type: required_attribute(node, "type")?.into(),
+        clusters: type_clusters(node)?,
+        fields,
+    })
+}
+
+fn parse_struct(node: Node) -> Result<StructType> {
+    let fields = child_elements(node, "item")
+        .enumerate()
+        .map(|(idx, item)| parse_field(item, "fieldId", idx))
+        .collect::<Result<_>>()?;
+
+    Ok(StructType {
+        name: required_attribute(node, "name")?.into(),
+        clusters: type_clusters(node)?,
+        fabric_scoped: flag(node, "isFabricScoped"),
+        fields,
+    })
+}
+
+fn parse_attribute(node: Node) -> Result<Attribute> {
+    // the name is the element text in older files and a <description> child in newer ones
+    let name = node
+        .text()
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .or_else(|| child_text(node, "description"))
+        .ok_or_else(|| anyhow!("Attribute without a name"))?;
+
+    Ok(Attribute {
+        name: name.into(),
+        code: code_attribute(node, "code")?,
+        field_type: parse_field_type(node)?,
+        writable: flag(node, "writable"),
+        optional: flag(node, "optional"),
+    })
+}
+
+fn parse_command(node: Node) -> Result<Command> {
+    let source = match required_attribute(node, "source")? {
+        "client" => CommandSource::Client,
+        "server" => CommandSource::Server,
+        other => return Err(anyhow!("Invalid command source `{}`", other)),
+    };
+
+    let fields = child_elements(node, "arg")
+        .enumerate()
+        .map(|(idx, arg)| parse_field(arg, "fieldId", idx))
+        .collect::<Result<_>>()?;
+
+    Ok(Command {
+        name: required_attribute(node, "name")?.into(),
+        code: code_attribute(node, "code")?,
+        source,
+        response: node.attribute("response").map(Into::into),
+        fabric_scoped: flag(node, "isFabricScoped"),
+        fields,
+    })
+}
+
+fn parse_event(node: Node) -> Result<Event> {
+    let fields = child_elements(node, "field")
+        .enumerate()
+        .map(|(idx, field)| parse_field(field, "id", idx))
+        .collect::<Result<_>>()?;
+
+    Ok(Event {
+        name: required_attribute(node, "name")?.into(),
+        code: code_attribute(node, "code")?,
+        priority: node.attribute("priority").unwrap_or("info").into(),
+        fields,
+    })
+}
+
+fn parse_cluster(node: Node) -> Result<Cluster> {
+    let name = child_text(node, "name").ok_or_else(|| anyhow!("Cluster without a <name>"))?;
+    let code =
+        child_text(node, "code").ok_or_else(|| anyhow!("Cluster `{}` without a <code>", name))?;
+
+    let parse_children = || -> Result<Cluster> {
+        Ok(Cluster {
+            name: name.into(),
+            code: u32::try_from(parse_number(code)?)?,
+            attributes: child_elements(node, "attribute")
+                .map(parse_attribute)
+                .collect::<Result<_>>()?,
+            commands: child_elements(node, "command")
+                .map(parse_command)
+                .collect::<Result<_>>()?,
+            events: child_elements(node, "event")
+                .map(parse_event)
+                .collect::<Result<_>>()?,
+        })
+    };
+
+    parse_children().with_context(|| format!("Invalid cluster `{}`", name))
+}
+
+/// Parses the content of a cluster XML file (a `<configurator>` document).
+pub fn parse_definitions(xml: &str) -> Result<Definitions> {
+    let document = Document::parse(xml)?;
+    let root = document.root_element();
+
+    if !root.has_tag_name("configurator") {
+        return Err(anyhow!(
+            "Expected a <configurator> root element, found <{}>",
+            root.tag_name().name()
+        ));
+    }
+
+    let mut result = Definitions::default();
+
+    for node in root.children().filter(Node::is_element) {
+        match node.tag_name().name() {
+            "cluster" => result.clusters.push(parse_cluster(node)?),
+            "enum" => result.enums.push(parse_enum(node)?),
+            "bitmap" => result.bitmaps.push(parse_bitmap(node)?),
+            "struct" => result.structs.push(parse_struct(node)?),
+
+            // domains, device types, global attributes, ... are not needed for code generation
+            _ => {}
+        }
+    }
+
+    Ok(result)
+}
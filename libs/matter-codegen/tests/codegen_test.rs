@@ -0,0 +1,161 @@
+use std::path::PathBuf;
+
+use matter_codegen::model::{CommandSource, FieldType};
+use matter_codegen::{generate, parse_definitions, read_definitions, Definitions};
+
+/// Code generated from the fixtures, compiled as part of this test.
+#[allow(dead_code)]
+mod clusters {
+    include!("generated/clusters.rs");
+}
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("fixtures")
+        .join(name)
+}
+
+fn fixtures() -> Definitions {
+    read_definitions(&[
+        fixture("identify-cluster.xml"),
+        fixture("groups-cluster.xml"),
+        fixture("access-control-cluster.xml"),
+    ])
+    .unwrap()
+}
+
+#[test]
+fn test_parse_clusters() {
+    let definitions = fixtures();
+    assert_eq!(definitions.clusters.len(), 3);
+
+    let identify = definitions.cluster(0x0003).unwrap();
+    assert_eq!(identify.name, "Identify");
+    assert_eq!(identify.attributes[0].name, "IdentifyTime");
+    assert!(identify.attributes[0].writable);
+    assert_eq!(identify.commands[1].code, 0x40);
+    assert_eq!(identify.commands[1].fields[1].id, 1);
+
+    let groups = definitions.cluster(0x0004).unwrap();
+    let add_group = &groups.commands[0];
+    assert_eq!(add_group.source, CommandSource::Client);
+    assert_eq!(add_group.response.as_deref(), Some("AddGroupResponse"));
+    assert_eq!(add_group.fields[1].max_length, Some(16));
+
+    let acl = definitions.cluster(0x001F).unwrap();
+    assert_eq!(
+        acl.attributes[0].field_type,
+        FieldType {
+            name: "AccessControlEntryStruct".into(),
+            array: true,
+        }
+    );
+    assert_eq!(acl.events[0].fields[4].id, 254);
+
+    let entry = &definitions.structs[1];
+    assert!(entry.fabric_scoped);
+    assert!(entry.fields.iter().all(|f| f.fabric_sensitive));
+    assert_eq!(entry.clusters, [0x001F]);
+}
+
+#[test]
+fn test_generate() {
+    let code = generate(&fixtures()).unwrap();
+
+    assert!(code.contains("pub mod access_control {"));
+    assert!(code.contains("pub const CLUSTER_ID: u32 = 0x001F;"));
+    assert!(code.contains("pub const IDENTIFY_TIME: u32 = 0x0000;"));
+    assert!(code.contains("pub const STOP_EFFECT: Self = Self(0xFF);"));
+    assert!(code.contains("const GROUP_NAMES = 0x80;"));
+
+    // constraints and fabric scoping
+    assert!(code.contains("#[tlv(tag = 0, range = 1..)]\n            pub group_id: u16,"));
+    assert!(code.contains("#[tlv(tag = 1, max_len = 16)]\n            pub group_name: String,"));
+    assert!(code.contains("#[tlv(fabric_scoped)]\n    pub struct AccessControlEntryStruct {"));
+    assert!(code.contains(
+        "#[tlv(tag = 3, nullable, fabric_sensitive)]\n        pub subjects: Option<Vec<u64>>,"
+    ));
+    assert!(code.contains("#[tlv(fabric_index)]\n        pub fabric_index: u8,"));
+}
+
+#[test]
+fn test_generated_code_is_current() {
+    let code = generate(&fixtures()).unwrap();
+    let generated = include_str!("generated/clusters.rs");
+    assert!(
+        code == generated,
+        "tests/generated/clusters.rs is out of date, regenerate it with `cargo run -p \
+         matter-codegen -- -o tests/generated/clusters.rs fixtures/identify-cluster.xml \
+         fixtures/groups-cluster.xml fixtures/access-control-cluster.xml`"
+    );
+}
+
+#[test]
+fn test_decode_generated_command() {
+    use clusters::groups::commands::AddGroup;
+
+    // GroupID 0x012C, GroupName "Kitchen"
+    let data = [
+        0x15, 0x25, 0x00, 0x2C, 0x01, 0x2C, 0x01, 0x07, 0x4B, 0x69, 0x74, 0x63, 0x68, 0x65, 0x6E,
+        0x18,
+    ];
+    let command: AddGroup = tlv_packed::decode_from_bytes(&data).unwrap();
+    assert_eq!(
+        command,
+        AddGroup {
+            group_id: 0x012C,
+            group_name: "Kitchen".into(),
+        }
+    );
+    assert_eq!(tlv_packed::encode_to_vec(&command).unwrap(), data);
+
+    // the minimum and maximum length constraints of the XML are enforced
+    let zero_group = [0x15, 0x24, 0x00, 0x00, 0x2C, 0x01, 0x00, 0x18];
+    assert!(tlv_packed::decode_from_bytes::<AddGroup>(&zero_group).is_err());
+    let long_name = AddGroup {
+        group_id: 1,
+        group_name: "A".repeat(17),
+    };
+    let data = tlv_packed::encode_to_vec(&long_name).unwrap();
+    assert!(tlv_packed::decode_from_bytes::<AddGroup>(&data).is_err());
+}
+
+#[test]
+fn test_enum_value_out_of_range() {
+    let xml = r#"
+        <configurator>
+          <enum name="Narrow" type="enum8">
+            <item name="Small" value="0x01"/>
+            <item name="TooLarge" value="0x100"/>
+          </enum>
+        </configurator>
+    "#;
+
+    let definitions = parse_definitions(xml).unwrap();
+    let error = generate(&definitions).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Value 0x100 of item `TooLarge` does not fit the base type `enum8` of enum `Narrow`"
+    );
+}
+
+#[test]
+fn test_unknown_type() {
+    let xml = r#"
+        <configurator>
+          <struct name="Broken">
+            <item fieldId="0" name="Value" type="NoSuchType"/>
+          </struct>
+        </configurator>
+    "#;
+
+    let definitions = parse_definitions(xml).unwrap();
+    let error = generate(&definitions).unwrap_err();
+    assert_eq!(error.to_string(), "Unknown type `NoSuchType`");
+}
+
+#[test]
+fn test_invalid_document() {
+    assert!(parse_definitions("<cluster/>").is_err());
+    assert!(parse_definitions("<configurator><enum name=\"E\"/></configurator>").is_err());
+}
@@ -0,0 +1,283 @@
+// Generated by matter-codegen from Matter cluster definitions. Do not edit.
+
+/// Cluster `Identify` (0x0003).
+pub mod identify {
+    #[allow(unused_imports)]
+    use super::*;
+
+    pub const CLUSTER_ID: u32 = 0x0003;
+
+    /// Enumeration `IdentifyTypeEnum` (enum8).
+    #[derive(Debug, Default, Copy, Clone, PartialEq, Eq, ::tlv_derive::TlvMergeDecodable, ::tlv_derive::TlvEncodable, ::tlv_derive::TlvSchema)]
+    pub struct IdentifyTypeEnum(pub u8);
+
+    impl IdentifyTypeEnum {
+        pub const NONE: Self = Self(0x00);
+        pub const LIGHT_OUTPUT: Self = Self(0x01);
+        pub const VISIBLE_INDICATOR: Self = Self(0x02);
+        pub const AUDIBLE_BEEP: Self = Self(0x03);
+        pub const DISPLAY: Self = Self(0x04);
+        pub const ACTUATOR: Self = Self(0x05);
+    }
+
+    /// Enumeration `EffectIdentifierEnum` (enum8).
+    #[derive(Debug, Default, Copy, Clone, PartialEq, Eq, ::tlv_derive::TlvMergeDecodable, ::tlv_derive::TlvEncodable, ::tlv_derive::TlvSchema)]
+    pub struct EffectIdentifierEnum(pub u8);
+
+    impl EffectIdentifierEnum {
+        pub const BLINK: Self = Self(0x00);
+        pub const BREATHE: Self = Self(0x01);
+        pub const OKAY: Self = Self(0x02);
+        pub const CHANNEL_CHANGE: Self = Self(0x0B);
+        pub const FINISH_EFFECT: Self = Self(0xFE);
+        pub const STOP_EFFECT: Self = Self(0xFF);
+    }
+
+    /// Enumeration `EffectVariantEnum` (enum8).
+    #[derive(Debug, Default, Copy, Clone, PartialEq, Eq, ::tlv_derive::TlvMergeDecodable, ::tlv_derive::TlvEncodable, ::tlv_derive::TlvSchema)]
+    pub struct EffectVariantEnum(pub u8);
+
+    impl EffectVariantEnum {
+        pub const DEFAULT: Self = Self(0x00);
+    }
+
+    pub mod attributes {
+        /// `IdentifyTime` (int16u, writable).
+        pub const IDENTIFY_TIME: u32 = 0x0000;
+        /// `IdentifyType` (IdentifyTypeEnum).
+        pub const IDENTIFY_TYPE: u32 = 0x0001;
+    }
+
+    pub mod commands {
+        #[allow(unused_imports)]
+        use super::*;
+
+        pub const IDENTIFY: u32 = 0x00;
+        pub const TRIGGER_EFFECT: u32 = 0x40;
+
+        /// Command `Identify` (0x00), sent by the client.
+        #[derive(Debug, Clone, Default, PartialEq, ::tlv_derive::TlvMergeDecodable, ::tlv_derive::TlvEncodable, ::tlv_derive::TlvSchema)]
+        pub struct Identify {
+            #[tlv(tag = 0)]
+            pub identify_time: u16,
+        }
+
+        /// Command `TriggerEffect` (0x40), sent by the client.
+        #[derive(Debug, Clone, Default, PartialEq, ::tlv_derive::TlvMergeDecodable, ::tlv_derive::TlvEncodable, ::tlv_derive::TlvSchema)]
+        pub struct TriggerEffect {
+            #[tlv(tag = 0)]
+            pub effect_identifier: EffectIdentifierEnum,
+            #[tlv(tag = 1)]
+            pub effect_variant: EffectVariantEnum,
+        }
+    }
+
+    pub mod events {
+        #[allow(unused_imports)]
+        use super::*;
+    }
+}
+
+/// Cluster `Groups` (0x0004).
+pub mod groups {
+    #[allow(unused_imports)]
+    use super::*;
+
+    pub const CLUSTER_ID: u32 = 0x0004;
+
+    ::bitflags::bitflags! {
+        /// Bitmap `Feature` (bitmap32).
+        #[derive(Default)]
+        pub struct Feature: u32 {
+            const GROUP_NAMES = 0x1;
+        }
+    }
+    ::tlv_packed::impl_tlv_bitflags!(Feature: u32);
+
+    ::bitflags::bitflags! {
+        /// Bitmap `NameSupportBitmap` (bitmap8).
+        #[derive(Default)]
+        pub struct NameSupportBitmap: u8 {
+            const GROUP_NAMES = 0x80;
+        }
+    }
+    ::tlv_packed::impl_tlv_bitflags!(NameSupportBitmap: u8);
+
+    pub mod attributes {
+        /// `NameSupport` (NameSupportBitmap).
+        pub const NAME_SUPPORT: u32 = 0x0000;
+    }
+
+    pub mod commands {
+        #[allow(unused_imports)]
+        use super::*;
+
+        pub const ADD_GROUP: u32 = 0x00;
+        pub const GET_GROUP_MEMBERSHIP: u32 = 0x02;
+        pub const REMOVE_ALL_GROUPS: u32 = 0x04;
+        pub const ADD_GROUP_RESPONSE: u32 = 0x00;
+        pub const GET_GROUP_MEMBERSHIP_RESPONSE: u32 = 0x02;
+
+        /// Command `AddGroup` (0x00), sent by the client, answered by `AddGroupResponse`.
+        #[derive(Debug, Clone, Default, PartialEq, ::tlv_derive::TlvMergeDecodable, ::tlv_derive::TlvEncodable, ::tlv_derive::TlvSchema)]
+        pub struct AddGroup {
+            #[tlv(tag = 0, range = 1..)]
+            pub group_id: u16,
+            #[tlv(tag = 1, max_len = 16)]
+            pub group_name: String,
+        }
+
+        /// Command `GetGroupMembership` (0x02), sent by the client, answered by `GetGroupMembershipResponse`.
+        #[derive(Debug, Clone, Default, PartialEq, ::tlv_derive::TlvMergeDecodable, ::tlv_derive::TlvEncodable, ::tlv_derive::TlvSchema)]
+        pub struct GetGroupMembership {
+            #[tlv(tag = 0)]
+            pub group_list: Vec<u16>,
+        }
+
+        /// Command `RemoveAllGroups` (0x04), sent by the client.
+        #[derive(Debug, Clone, Default, PartialEq, ::tlv_derive::TlvMergeDecodable, ::tlv_derive::TlvEncodable, ::tlv_derive::TlvSchema)]
+        pub struct RemoveAllGroups {
+        }
+
+        /// Command `AddGroupResponse` (0x00), sent by the server.
+        #[derive(Debug, Clone, Default, PartialEq, ::tlv_derive::TlvMergeDecodable, ::tlv_derive::TlvEncodable, ::tlv_derive::TlvSchema)]
+        pub struct AddGroupResponse {
+            #[tlv(tag = 0)]
+            pub status: u8,
+            #[tlv(tag = 1, range = 1..)]
+            pub group_id: u16,
+        }
+
+        /// Command `GetGroupMembershipResponse` (0x02), sent by the server.
+        #[derive(Debug, Clone, Default, PartialEq, ::tlv_derive::TlvMergeDecodable, ::tlv_derive::TlvEncodable, ::tlv_derive::TlvSchema)]
+        pub struct GetGroupMembershipResponse {
+            #[tlv(tag = 0, nullable)]
+            pub capacity: Option<u8>,
+            #[tlv(tag = 1)]
+            pub group_list: Vec<u16>,
+        }
+    }
+
+    pub mod events {
+        #[allow(unused_imports)]
+        use super::*;
+    }
+}
+
+/// Cluster `Access Control` (0x001F).
+pub mod access_control {
+    #[allow(unused_imports)]
+    use super::*;
+
+    pub const CLUSTER_ID: u32 = 0x001F;
+
+    /// Enumeration `AccessControlEntryPrivilegeEnum` (enum8).
+    #[derive(Debug, Default, Copy, Clone, PartialEq, Eq, ::tlv_derive::TlvMergeDecodable, ::tlv_derive::TlvEncodable, ::tlv_derive::TlvSchema)]
+    pub struct AccessControlEntryPrivilegeEnum(pub u8);
+
+    impl AccessControlEntryPrivilegeEnum {
+        pub const VIEW: Self = Self(0x01);
+        pub const PROXY_VIEW: Self = Self(0x02);
+        pub const OPERATE: Self = Self(0x03);
+        pub const MANAGE: Self = Self(0x04);
+        pub const ADMINISTER: Self = Self(0x05);
+    }
+
+    /// Enumeration `AccessControlEntryAuthModeEnum` (enum8).
+    #[derive(Debug, Default, Copy, Clone, PartialEq, Eq, ::tlv_derive::TlvMergeDecodable, ::tlv_derive::TlvEncodable, ::tlv_derive::TlvSchema)]
+    pub struct AccessControlEntryAuthModeEnum(pub u8);
+
+    impl AccessControlEntryAuthModeEnum {
+        pub const PASE: Self = Self(0x01);
+        pub const CASE: Self = Self(0x02);
+        pub const GROUP: Self = Self(0x03);
+    }
+
+    /// Enumeration `ChangeTypeEnum` (enum8).
+    #[derive(Debug, Default, Copy, Clone, PartialEq, Eq, ::tlv_derive::TlvMergeDecodable, ::tlv_derive::TlvEncodable, ::tlv_derive::TlvSchema)]
+    pub struct ChangeTypeEnum(pub u8);
+
+    impl ChangeTypeEnum {
+        pub const CHANGED: Self = Self(0x00);
+        pub const ADDED: Self = Self(0x01);
+        pub const REMOVED: Self = Self(0x02);
+    }
+
+    /// Structure `AccessControlTargetStruct`.
+    #[derive(Debug, Clone, Default, PartialEq, ::tlv_derive::TlvMergeDecodable, ::tlv_derive::TlvEncodable, ::tlv_derive::TlvSchema)]
+    pub struct AccessControlTargetStruct {
+        #[tlv(tag = 0, nullable)]
+        pub cluster: Option<u32>,
+        #[tlv(tag = 1, nullable)]
+        pub endpoint: Option<u16>,
+        #[tlv(tag = 2, nullable)]
+        pub device_type: Option<u32>,
+    }
+
+    /// Structure `AccessControlEntryStruct`.
+    #[derive(Debug, Clone, Default, PartialEq, ::tlv_derive::TlvMergeDecodable, ::tlv_derive::TlvEncodable, ::tlv_derive::TlvSchema)]
+    #[tlv(fabric_scoped)]
+    pub struct AccessControlEntryStruct {
+        #[tlv(tag = 1, fabric_sensitive)]
+        pub privilege: AccessControlEntryPrivilegeEnum,
+        #[tlv(tag = 2, fabric_sensitive)]
+        pub auth_mode: AccessControlEntryAuthModeEnum,
+        #[tlv(tag = 3, nullable, fabric_sensitive)]
+        pub subjects: Option<Vec<u64>>,
+        #[tlv(tag = 4, nullable, fabric_sensitive)]
+        pub targets: Option<Vec<AccessControlTargetStruct>>,
+        #[tlv(fabric_index)]
+        pub fabric_index: u8,
+    }
+
+    /// Structure `AccessControlExtensionStruct`.
+    #[derive(Debug, Clone, Default, PartialEq, ::tlv_derive::TlvMergeDecodable, ::tlv_derive::TlvEncodable, ::tlv_derive::TlvSchema)]
+    #[tlv(fabric_scoped)]
+    pub struct AccessControlExtensionStruct {
+        #[tlv(tag = 1, max_len = 128, fabric_sensitive)]
+        pub data: Vec<u8>,
+        #[tlv(fabric_index)]
+        pub fabric_index: u8,
+    }
+
+    pub mod attributes {
+        /// `ACL` (list of AccessControlEntryStruct, writable).
+        pub const ACL: u32 = 0x0000;
+        /// `Extension` (list of AccessControlExtensionStruct, writable, optional).
+        pub const EXTENSION: u32 = 0x0001;
+        /// `SubjectsPerAccessControlEntry` (int16u).
+        pub const SUBJECTS_PER_ACCESS_CONTROL_ENTRY: u32 = 0x0002;
+        /// `TargetsPerAccessControlEntry` (int16u).
+        pub const TARGETS_PER_ACCESS_CONTROL_ENTRY: u32 = 0x0003;
+        /// `AccessControlEntriesPerFabric` (int16u).
+        pub const ACCESS_CONTROL_ENTRIES_PER_FABRIC: u32 = 0x0004;
+    }
+
+    pub mod commands {
+        #[allow(unused_imports)]
+        use super::*;
+    }
+
+    pub mod events {
+        #[allow(unused_imports)]
+        use super::*;
+
+        pub const ACCESS_CONTROL_ENTRY_CHANGED: u32 = 0x00;
+
+        /// Event `AccessControlEntryChanged` (0x00), priority info.
+        #[derive(Debug, Clone, Default, PartialEq, ::tlv_derive::TlvMergeDecodable, ::tlv_derive::TlvEncodable, ::tlv_derive::TlvSchema)]
+        pub struct AccessControlEntryChanged {
+            #[tlv(tag = 1, nullable)]
+            pub admin_node_id: Option<u64>,
+            #[tlv(tag = 2, nullable)]
+            pub admin_passcode_id: Option<u16>,
+            #[tlv(tag = 3)]
+            pub change_type: ChangeTypeEnum,
+            #[tlv(tag = 4, nullable)]
+            pub latest_value: Option<AccessControlEntryStruct>,
+            #[tlv(tag = 254)]
+            pub fabric_index: u8,
+        }
+    }
+}
+
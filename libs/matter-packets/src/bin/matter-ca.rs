@@ -0,0 +1,73 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use matter_packets::ca::CertificateAuthority;
+use matter_types::NodeId;
+
+const USAGE: &str = "Usage: matter-ca [-d DIR] init FABRIC_ID [--icac]
+       matter-ca [-d DIR] node NODE_ID [--cat CAT]...
+
+Numbers are decimal or hexadecimal with a 0x prefix. DIR defaults to the
+current directory.";
+
+fn parse_number(value: &str) -> Result<u64> {
+    let parsed = match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+    parsed.map_err(|_| anyhow!("Invalid number `{}`\n{}", value, USAGE))
+}
+
+fn main() -> Result<()> {
+    let mut dir = PathBuf::from(".");
+    let mut positional = Vec::new();
+    let mut with_intermediate = false;
+    let mut case_authenticated_tags = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-d" | "--dir" => {
+                dir = args.next().ok_or_else(|| anyhow!("{}", USAGE))?.into();
+            }
+            "--icac" => with_intermediate = true,
+            "--cat" => {
+                let tag = parse_number(&args.next().ok_or_else(|| anyhow!("{}", USAGE))?)?;
+                case_authenticated_tags.push(u32::try_from(tag)?);
+            }
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            _ => positional.push(arg),
+        }
+    }
+
+    match positional.as_slice() {
+        [command, fabric_id] if command == "init" => {
+            if dir.join("rcac.tlv").exists() {
+                return Err(anyhow!("{} already contains a CA", dir.display()));
+            }
+
+            let ca = CertificateAuthority::new(parse_number(fabric_id)?, with_intermediate)?;
+            ca.save(&dir)?;
+            println!("Created fabric {:016X} in {}", ca.fabric_id, dir.display());
+            Ok(())
+        }
+        [command, node_id] if command == "node" => {
+            let ca = CertificateAuthority::load(&dir)?;
+            let node = ca.issue_noc(NodeId(parse_number(node_id)?), &case_authenticated_tags)?;
+            ca.save_node(&dir, &node)?;
+            println!(
+                "Issued NOC for node {:016X} on fabric {:016X}",
+                node.certificate.node_id().map_or(0, |id| id.0),
+                ca.fabric_id
+            );
+            Ok(())
+        }
+        _ => Err(anyhow!("{}", USAGE)),
+    }
+}
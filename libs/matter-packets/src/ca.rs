@@ -0,0 +1,609 @@
+//! Local certificate authority for test and lab fabrics.
+//!
+//! A [CertificateAuthority] holds the root certificate (RCAC) of a fabric,
+//! optionally an intermediate certificate (ICAC), their keys and the IPK
+//! epoch key. It issues node operational certificates (NOCs) and builds the
+//! [Fabric] credentials needed for CASE, without any external tooling.
+//!
+//! Everything can be persisted in a directory:
+//!
+//! | File                         | Content                              |
+//! |------------------------------|--------------------------------------|
+//! | `rcac.tlv`, `rcac.der`       | Root certificate, Matter TLV and DER |
+//! | `rcac-key.pem`               | Root key, PKCS#8                     |
+//! | `icac.tlv`, `icac.der`       | Intermediate certificate, if any     |
+//! | `icac-key.pem`               | Intermediate key                     |
+//! | `ipk-epoch-key.hex`          | IPK epoch key 0                      |
+//! | `node-<ID>.tlv`, `.der`      | NOC of node `<ID>` (16 hex digits)   |
+//! | `node-<ID>-key.pem`          | Operational key of node `<ID>`       |
+//!
+//! Keys are stored unencrypted: this is meant for test fabrics only. On unix,
+//! key files are only readable by their owner.
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Context, Result};
+use matter_types::{FabricIndex, NodeId};
+use p256::ecdsa::signature::Signer;
+use p256::ecdsa::{Signature, SigningKey, VerifyingKey};
+use p256::pkcs8::{DecodePrivateKey, EncodePrivateKey, LineEnding};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+use crate::certificate::{
+    is_operational_node_id, is_valid_case_authenticated_tag, BasicConstraints, Certificate,
+    DnAttribute, Extension, KeyPurpose, KeyUsage, CURVE_PRIME256V1, KEY_ID_LENGTH,
+    MATTER_EPOCH_UNIX_SECONDS, MAX_CASE_AUTHENTICATED_TAGS, PUBLIC_KEY_ALGORITHM_EC,
+    SIGNATURE_ALGORITHM_ECDSA_SHA256,
+};
+use crate::fabric::{compressed_fabric_id, operational_ipk, Fabric, FabricBuilder, IPK_LENGTH};
+
+/// Subject id of the root certificate.
+const RCAC_ID: u64 = 1;
+
+/// Subject id of the intermediate certificate.
+const ICAC_ID: u64 = 2;
+
+/// Length of generated serial numbers.
+const SERIAL_NUMBER_LENGTH: usize = 8;
+
+const RCAC_NAME: &str = "rcac";
+const ICAC_NAME: &str = "icac";
+const IPK_EPOCH_KEY_FILE: &str = "ipk-epoch-key.hex";
+
+/// A certificate and the key matching its public key.
+#[derive(Debug, Clone)]
+pub struct Credentials {
+    pub certificate: Certificate,
+    pub key: SigningKey,
+}
+
+impl Credentials {
+    /// Writes `<name>.tlv`, `<name>.der` and `<name>-key.pem` to `dir`.
+    pub fn save(&self, dir: &Path, name: &str) -> Result<()> {
+        let pem = self
+            .key
+            .to_pkcs8_pem(LineEnding::LF)
+            .map_err(|e| anyhow!("Cannot encode key: {}", e))?;
+
+        write(dir, &format!("{}.tlv", name), &self.certificate.encode()?)?;
+        write(dir, &format!("{}.der", name), &self.certificate.to_der()?)?;
+        write_private(dir, &format!("{}-key.pem", name), pem.as_bytes())
+    }
+
+    /// Reads credentials written by [Credentials::save].
+    pub fn load(dir: &Path, name: &str) -> Result<Self> {
+        let certificate = Certificate::decode(&read(dir, &format!("{}.tlv", name))?)?;
+        let pem = String::from_utf8(read(dir, &format!("{}-key.pem", name))?)?;
+        let key = SigningKey::from_pkcs8_pem(&pem)
+            .map_err(|e| anyhow!("Invalid key for {}: {}", name, e))?;
+
+        if certificate.public_key != public_key(key.verifying_key()) {
+            return Err(anyhow!("Key does not match the {} certificate", name));
+        }
+
+        Ok(Self { certificate, key })
+    }
+}
+
+/// Certificate authority of a single fabric.
+#[derive(Debug, Clone)]
+pub struct CertificateAuthority {
+    pub fabric_id: u64,
+    pub root: Credentials,
+
+    /// Intermediate CA issuing NOCs, if NOCs are not issued by the root.
+    pub intermediate: Option<Credentials>,
+
+    /// IPK epoch key 0, from which the operational IPK is derived.
+    pub epoch_key: [u8; IPK_LENGTH],
+}
+
+impl CertificateAuthority {
+    /// Creates a new fabric with fresh keys, with or without an intermediate
+    /// certificate.
+    pub fn new(fabric_id: u64, with_intermediate: bool) -> Result<Self> {
+        if fabric_id == 0 {
+            return Err(anyhow!("Fabric id 0 is reserved"));
+        }
+
+        let root_key = random_key();
+        let root_subject = vec![
+            DnAttribute::RcacId(RCAC_ID),
+            DnAttribute::FabricId(fabric_id),
+        ];
+        let root = Credentials {
+            certificate: issue(
+                root_subject.clone(),
+                root_key.verifying_key(),
+                &root_subject,
+                &root_key,
+                ca_extensions(),
+            )?,
+            key: root_key,
+        };
+
+        let intermediate = match with_intermediate {
+            true => {
+                let key = random_key();
+                let certificate = issue(
+                    vec![
+                        DnAttribute::IcacId(ICAC_ID),
+                        DnAttribute::FabricId(fabric_id),
+                    ],
+                    key.verifying_key(),
+                    &root.certificate.subject,
+                    &root.key,
+                    ca_extensions(),
+                )?;
+                Some(Credentials { certificate, key })
+            }
+            false => None,
+        };
+
+        let mut epoch_key = [0; IPK_LENGTH];
+        rand::thread_rng().fill_bytes(&mut epoch_key);
+
+        Ok(Self {
+            fabric_id,
+            root,
+            intermediate,
+            epoch_key,
+        })
+    }
+
+    /// Credentials signing NOCs.
+    fn issuer(&self) -> &Credentials {
+        self.intermediate.as_ref().unwrap_or(&self.root)
+    }
+
+    /// Issues a NOC for `public_key`, e.g. from a node's certificate signing
+    /// request.
+    pub fn sign_noc(
+        &self,
+        node_id: NodeId,
+        case_authenticated_tags: &[u32],
+        public_key: &VerifyingKey,
+    ) -> Result<Certificate> {
+        if !is_operational_node_id(node_id) {
+            return Err(anyhow!("{:?} is not an operational node id", node_id));
+        }
+        if case_authenticated_tags.len() > MAX_CASE_AUTHENTICATED_TAGS {
+            return Err(anyhow!(
+                "{} CASE Authenticated Tags given, at most {} are allowed",
+                case_authenticated_tags.len(),
+                MAX_CASE_AUTHENTICATED_TAGS
+            ));
+        }
+        if let Some(tag) = case_authenticated_tags
+            .iter()
+            .find(|tag| !is_valid_case_authenticated_tag(**tag))
+        {
+            return Err(anyhow!("CASE Authenticated Tag {:08X} has version 0", tag));
+        }
+
+        let mut subject = vec![
+            DnAttribute::NodeId(node_id),
+            DnAttribute::FabricId(self.fabric_id),
+        ];
+        subject.extend(
+            case_authenticated_tags
+                .iter()
+                .map(|tag| DnAttribute::CaseAuthenticatedTag(*tag)),
+        );
+
+        let issuer = self.issuer();
+        issue(
+            subject,
+            public_key,
+            &issuer.certificate.subject,
+            &issuer.key,
+            vec![
+                Extension::BasicConstraints(BasicConstraints::default()),
+                Extension::KeyUsage(KeyUsage::DIGITAL_SIGNATURE),
+                Extension::ExtendedKeyUsage(vec![KeyPurpose::ClientAuth, KeyPurpose::ServerAuth]),
+            ],
+        )
+    }
+
+    /// Issues a NOC with a fresh operational key.
+    pub fn issue_noc(
+        &self,
+        node_id: NodeId,
+        case_authenticated_tags: &[u32],
+    ) -> Result<Credentials> {
+        let key = random_key();
+        let certificate = self.sign_noc(node_id, case_authenticated_tags, key.verifying_key())?;
+        Ok(Credentials { certificate, key })
+    }
+
+    /// Builds the operational credentials of the node owning `node`.
+    pub fn fabric(&self, index: FabricIndex, node: &Credentials) -> Result<Fabric> {
+        let node_id = node
+            .certificate
+            .node_id()
+            .ok_or_else(|| anyhow!("Not a node operational certificate"))?;
+        let root_public_key = self.root.certificate.public_key.as_slice().try_into()?;
+
+        FabricBuilder::default()
+            .index(index)
+            .fabric_id(self.fabric_id)
+            .node_id(node_id)
+            .root_certificate(self.root.certificate.encode()?)
+            .root_public_key(root_public_key)
+            .noc(node.certificate.encode()?)
+            .icac(
+                self.intermediate
+                    .as_ref()
+                    .map(|icac| icac.certificate.encode())
+                    .transpose()?,
+            )
+            .ipk(operational_ipk(
+                &self.epoch_key,
+                &compressed_fabric_id(&root_public_key, self.fabric_id),
+            ))
+            .signing_key(node.key.clone())
+            .build()
+            .map_err(|e| anyhow!("Cannot build fabric: {}", e))
+    }
+
+    /// Writes the CA certificates and keys to `dir`, creating it if needed.
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        fs::create_dir_all(dir).with_context(|| format!("Cannot create {}", dir.display()))?;
+
+        self.root.save(dir, RCAC_NAME)?;
+        if let Some(intermediate) = &self.intermediate {
+            intermediate.save(dir, ICAC_NAME)?;
+        }
+
+        let epoch_key: String = self
+            .epoch_key
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        write_private(
+            dir,
+            IPK_EPOCH_KEY_FILE,
+            format!("{}\n", epoch_key).as_bytes(),
+        )
+    }
+
+    /// Reads a CA written by [CertificateAuthority::save].
+    pub fn load(dir: &Path) -> Result<Self> {
+        let root = Credentials::load(dir, RCAC_NAME)?;
+        let fabric_id = root
+            .certificate
+            .fabric_id()
+            .ok_or_else(|| anyhow!("Root certificate without fabric id"))?;
+
+        let intermediate = match dir.join(format!("{}.tlv", ICAC_NAME)).exists() {
+            true => Some(Credentials::load(dir, ICAC_NAME)?),
+            false => None,
+        };
+
+        let hex = String::from_utf8(read(dir, IPK_EPOCH_KEY_FILE)?)?;
+        let hex = hex.trim();
+        if hex.len() != 2 * IPK_LENGTH {
+            return Err(anyhow!("Invalid IPK epoch key length"));
+        }
+        let mut epoch_key = [0; IPK_LENGTH];
+        for (idx, byte) in epoch_key.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[2 * idx..2 * idx + 2], 16)
+                .map_err(|_| anyhow!("Invalid IPK epoch key"))?;
+        }
+
+        Ok(Self {
+            fabric_id,
+            root,
+            intermediate,
+            epoch_key,
+        })
+    }
+
+    /// Writes the NOC and key of a node to `dir`.
+    pub fn save_node(&self, dir: &Path, node: &Credentials) -> Result<()> {
+        let node_id = node
+            .certificate
+            .node_id()
+            .ok_or_else(|| anyhow!("Not a node operational certificate"))?;
+        node.save(dir, &node_file_name(node_id))
+    }
+
+    /// Reads the NOC and key of a node written by
+    /// [CertificateAuthority::save_node].
+    pub fn load_node(&self, dir: &Path, node_id: NodeId) -> Result<Credentials> {
+        Credentials::load(dir, &node_file_name(node_id))
+    }
+}
+
+fn node_file_name(node_id: NodeId) -> String {
+    format!("node-{:016X}", node_id.0)
+}
+
+fn write(dir: &Path, name: &str, data: &[u8]) -> Result<()> {
+    let path = dir.join(name);
+    fs::write(&path, data).with_context(|| format!("Cannot write {}", path.display()))
+}
+
+/// Writes a file only readable and writable by its owner (mode 0600 on unix).
+fn write_private(dir: &Path, name: &str, data: &[u8]) -> Result<()> {
+    let path = dir.join(name);
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options
+        .open(&path)
+        .with_context(|| format!("Cannot write {}", path.display()))?;
+
+    // the mode only applies to new files
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))
+            .with_context(|| format!("Cannot restrict access to {}", path.display()))?;
+    }
+
+    file.write_all(data)
+        .with_context(|| format!("Cannot write {}", path.display()))
+}
+
+fn read(dir: &Path, name: &str) -> Result<Vec<u8>> {
+    let path = dir.join(name);
+    fs::read(&path).with_context(|| format!("Cannot read {}", path.display()))
+}
+
+fn random_key() -> SigningKey {
+    SigningKey::random(&mut rand::thread_rng())
+}
+
+fn public_key(key: &VerifyingKey) -> Vec<u8> {
+    key.to_encoded_point(false).as_bytes().to_vec()
+}
+
+fn key_id(key: &VerifyingKey) -> Vec<u8> {
+    Sha256::digest(public_key(key))[..KEY_ID_LENGTH].to_vec()
+}
+
+/// Random positive serial number, minimally encoded as a DER integer.
+fn serial_number() -> Vec<u8> {
+    let mut serial = vec![0; SERIAL_NUMBER_LENGTH];
+    rand::thread_rng().fill_bytes(&mut serial);
+    serial[0] = (serial[0] & 0x7F).max(1);
+    serial
+}
+
+/// Current time in seconds since the Matter epoch.
+fn now() -> Result<u32> {
+    let unix = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    Ok(u32::try_from(
+        unix.saturating_sub(MATTER_EPOCH_UNIX_SECONDS),
+    )?)
+}
+
+fn ca_extensions() -> Vec<Extension> {
+    vec![
+        Extension::BasicConstraints(BasicConstraints {
+            is_ca: true,
+            path_length: None,
+        }),
+        Extension::KeyUsage(KeyUsage::KEY_CERT_SIGN | KeyUsage::CRL_SIGN),
+    ]
+}
+
+/// Issues a certificate valid from now on, without expiry.
+fn issue(
+    subject: Vec<DnAttribute>,
+    subject_key: &VerifyingKey,
+    issuer: &[DnAttribute],
+    issuer_key: &SigningKey,
+    mut extensions: Vec<Extension>,
+) -> Result<Certificate> {
+    extensions.push(Extension::SubjectKeyId(key_id(subject_key)));
+    extensions.push(Extension::AuthorityKeyId(key_id(
+        issuer_key.verifying_key(),
+    )));
+
+    let mut certificate = Certificate {
+        serial_number: serial_number(),
+        signature_algorithm: SIGNATURE_ALGORITHM_ECDSA_SHA256,
+        issuer: issuer.to_vec(),
+        not_before: now()?,
+        not_after: 0,
+        subject,
+        public_key_algorithm: PUBLIC_KEY_ALGORITHM_EC,
+        curve_id: CURVE_PRIME256V1,
+        public_key: public_key(subject_key),
+        extensions,
+        signature: Vec::new(),
+    };
+    let signature: Signature = issuer_key.sign(&certificate.tbs_der()?);
+    certificate.signature = signature.to_bytes().to_vec();
+    Ok(certificate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::case::{CaseInitiator, CaseResponder, ResumptionStore, Sigma1Reply};
+    use crate::certificate::{validate_chain, CertificateKind, OperationalCertificateValidator};
+    use crate::fabric::CertificateChainValidator;
+
+    const FABRIC_ID: u64 = 0xFAB0_0000_0000_0A1B;
+
+    /// Fresh directory under the system temporary directory.
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "matter-ca-{}-{}-{:08x}",
+            name,
+            std::process::id(),
+            rand::random::<u32>()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn issued_chains_validate() {
+        for with_intermediate in [false, true] {
+            let ca = CertificateAuthority::new(FABRIC_ID, with_intermediate).unwrap();
+            let node = ca.issue_noc(NodeId(0x1234), &[0x0001_0001]).unwrap();
+
+            assert_eq!(ca.root.certificate.kind(), Some(CertificateKind::Root));
+            assert_eq!(node.certificate.kind(), Some(CertificateKind::Node));
+            assert_eq!(
+                node.certificate
+                    .case_authenticated_tags()
+                    .collect::<Vec<_>>(),
+                [0x0001_0001]
+            );
+
+            let now = now().unwrap();
+            let identity = validate_chain(
+                &node.certificate,
+                ca.intermediate.as_ref().map(|icac| &icac.certificate),
+                &ca.root.certificate,
+                Some(now),
+            )
+            .unwrap();
+            assert_eq!(identity.node_id, NodeId(0x1234));
+            assert_eq!(identity.fabric_id, FABRIC_ID);
+            assert_eq!(&identity.public_key, node.key.verifying_key());
+
+            // DER forms are consistent with the TLV forms
+            for certificate in [&ca.root.certificate, &node.certificate] {
+                let der = certificate.to_der().unwrap();
+                assert_eq!(&Certificate::from_der(&der).unwrap(), certificate);
+            }
+
+            let fabric = ca.fabric(FabricIndex(1), &node).unwrap();
+            assert_eq!(fabric.icac.is_some(), with_intermediate);
+            OperationalCertificateValidator { time: Some(now) }
+                .validate(&fabric, &fabric.noc, fabric.icac.as_deref())
+                .unwrap();
+        }
+
+        assert!(CertificateAuthority::new(0, false).is_err());
+    }
+
+    #[test]
+    fn rejects_non_operational_subjects() {
+        let ca = CertificateAuthority::new(FABRIC_ID, false).unwrap();
+
+        for node_id in [0, 0xFFFF_FFF0_0000_0000, 0xFFFF_FFFF_FFFF_FFFF] {
+            assert!(ca.issue_noc(NodeId(node_id), &[]).is_err());
+        }
+        assert!(ca.issue_noc(NodeId(0xFFFF_FFEF_FFFF_FFFF), &[]).is_ok());
+
+        // CAT version 0
+        assert!(ca.issue_noc(NodeId(1), &[0x0001_0000]).is_err());
+        assert!(ca
+            .issue_noc(NodeId(1), &[0x0001_0001, 0x0002_0000])
+            .is_err());
+
+        // at most three CATs
+        let tags = [0x0001_0001, 0x0002_0001, 0x0003_0001, 0x0004_0001];
+        let noc = ca.issue_noc(NodeId(1), &tags[..3]).unwrap();
+        assert_eq!(noc.certificate.case_authenticated_tags().count(), 3);
+        assert!(ca.issue_noc(NodeId(1), &tags).is_err());
+    }
+
+    #[test]
+    fn save_and_load() {
+        let dir = temp_dir("save");
+        let ca = CertificateAuthority::new(FABRIC_ID, true).unwrap();
+        let node = ca.issue_noc(NodeId(0x42), &[]).unwrap();
+        ca.save(&dir).unwrap();
+        ca.save_node(&dir, &node).unwrap();
+
+        for file in ["rcac.der", "icac.der", "node-0000000000000042.der"] {
+            let der = fs::read(dir.join(file)).unwrap();
+            Certificate::from_der(&der).unwrap();
+        }
+
+        #[cfg(unix)]
+        for file in [
+            "rcac-key.pem",
+            "icac-key.pem",
+            "node-0000000000000042-key.pem",
+            "ipk-epoch-key.hex",
+        ] {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(dir.join(file)).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600, "{}", file);
+        }
+
+        let loaded = CertificateAuthority::load(&dir).unwrap();
+        assert_eq!(loaded.fabric_id, FABRIC_ID);
+        assert_eq!(loaded.epoch_key, ca.epoch_key);
+        assert_eq!(loaded.root.certificate, ca.root.certificate);
+        assert_eq!(loaded.root.key, ca.root.key);
+        let intermediate = loaded.intermediate.as_ref().unwrap();
+        assert_eq!(intermediate.key, ca.intermediate.as_ref().unwrap().key);
+
+        let loaded_node = loaded.load_node(&dir, NodeId(0x42)).unwrap();
+        assert_eq!(loaded_node.certificate, node.certificate);
+        assert_eq!(loaded_node.key, node.key);
+        assert!(loaded.load_node(&dir, NodeId(0x43)).is_err());
+
+        // keys must match their certificates
+        fs::copy(dir.join("icac-key.pem"), dir.join("rcac-key.pem")).unwrap();
+        assert!(CertificateAuthority::load(&dir).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn case_with_issued_certificates() {
+        let ca = CertificateAuthority::new(FABRIC_ID, true).unwrap();
+        let initiator_fabric = ca
+            .fabric(FabricIndex(1), &ca.issue_noc(NodeId(1), &[]).unwrap())
+            .unwrap();
+        let responder_fabric = ca
+            .fabric(FabricIndex(1), &ca.issue_noc(NodeId(2), &[]).unwrap())
+            .unwrap();
+        let validator = OperationalCertificateValidator::default();
+
+        let mut initiator = CaseInitiator::new(initiator_fabric, NodeId(2), 0x1000);
+        let mut responder = CaseResponder::new(0x2000);
+
+        let sigma1 = initiator.start().unwrap();
+        let sigma2 = match responder
+            .on_sigma1(
+                &sigma1,
+                std::slice::from_ref(&responder_fabric),
+                &ResumptionStore::new(1),
+            )
+            .unwrap()
+        {
+            Sigma1Reply::Sigma2(sigma2) => sigma2,
+            other => panic!("Unexpected reply {:?}", other),
+        };
+        let sigma3 = initiator.on_sigma2(&sigma2, &validator).unwrap();
+        let (report, responder_session) = responder.on_sigma3(&sigma3, &validator).unwrap();
+        let initiator_session = initiator.on_status_report(&report).unwrap();
+
+        assert_eq!(initiator_session.params.peer_node, NodeId(2));
+        assert_eq!(responder_session.params.peer_node, NodeId(1));
+        assert_eq!(
+            initiator_session.params.keys.encrypt_key,
+            responder_session.params.keys.decrypt_key
+        );
+
+        // a fabric with the same id but another root is not trusted
+        let other = CertificateAuthority::new(FABRIC_ID, false).unwrap();
+        let stranger = other
+            .fabric(FabricIndex(1), &other.issue_noc(NodeId(3), &[]).unwrap())
+            .unwrap();
+        assert!(validator
+            .validate(&responder_fabric, &stranger.noc, stranger.icac.as_deref())
+            .is_err());
+    }
+}
@@ -0,0 +1,1490 @@
+//! Certificate Authenticated Session Establishment (CASE).
+//!
+//! ```text
+//! initiator                                  responder
+//!     Sigma1              ---------------->
+//!                         <----------------   Sigma2
+//!     Sigma3              ---------------->
+//!                         <----------------   StatusReport
+//! ```
+//!
+//! A node that previously established a session with the same peer may
+//! resume it, skipping certificate exchange and signatures:
+//!
+//! ```text
+//!     Sigma1 (resumption) ---------------->
+//!                         <----------------   Sigma2Resume
+//!     StatusReport        ---------------->
+//! ```
+//!
+//! Like PASE, [CaseInitiator] and [CaseResponder] only deal with message
+//! payloads. Every established session also yields a [ResumptionRecord]
+//! that callers keep in a [ResumptionStore] for later resumption.
+//!
+//! When the responder fails to process a message, it should reply with
+//! [StatusReport::invalid_parameter] and abandon the exchange.
+
+use std::collections::VecDeque;
+
+use anyhow::{anyhow, Result};
+use ccm::aead::{generic_array::GenericArray, AeadInPlace, KeyInit};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use matter_types::{FabricIndex, NodeId};
+use p256::ecdh::EphemeralSecret;
+use p256::ecdsa::signature::{Signer, Verifier};
+use p256::ecdsa::Signature;
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use p256::PublicKey;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use tlv_derive::{TlvEncodable, TlvMergeDecodable, TlvSchema};
+
+use crate::encryption::{Aes128Ccm, SessionKey, KEY_LENGTH};
+use crate::fabric::{CertificateChainValidator, Fabric, PeerIdentity, PublicKeyBytes};
+use crate::message::MIC_LENGTH;
+use crate::pase::{check_length, random, SessionParameters, RANDOM_LENGTH};
+use crate::session::{
+    SecureSessionKind, SecureSessionParams, SecureSessionParamsBuilder, SessionKeys,
+    ATTESTATION_CHALLENGE_LENGTH,
+};
+use crate::spake2p::POINT_LENGTH;
+use crate::status_report::{SecureChannelCode, StatusReport};
+use crate::tlv::{decode, encode};
+
+/// Length of the destination identifier in `Sigma1`.
+pub const DESTINATION_ID_LENGTH: usize = 32;
+
+/// Length of resumption identifiers.
+pub const RESUMPTION_ID_LENGTH: usize = 16;
+
+/// Length of the ECDH shared secret.
+pub const SHARED_SECRET_LENGTH: usize = 32;
+
+/// Length of a raw (`r || s`) ECDSA P-256 signature.
+pub const SIGNATURE_LENGTH: usize = 64;
+
+const SIGMA2_NONCE: &[u8; 13] = b"NCASE_Sigma2N";
+const SIGMA3_NONCE: &[u8; 13] = b"NCASE_Sigma3N";
+const SIGMA1_RESUME_NONCE: &[u8; 13] = b"NCASE_SigmaS1";
+const SIGMA2_RESUME_NONCE: &[u8; 13] = b"NCASE_SigmaS2";
+
+pub type ResumptionId = [u8; RESUMPTION_ID_LENGTH];
+
+/// Payload of `CASE_Sigma1`.
+#[derive(Debug, Default, Clone, PartialEq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+pub struct Sigma1 {
+    #[tlv(tag = 1)]
+    pub initiator_random: Vec<u8>,
+
+    #[tlv(tag = 2)]
+    pub initiator_session_id: u16,
+
+    #[tlv(tag = 3)]
+    pub destination_id: Vec<u8>,
+
+    #[tlv(tag = 4)]
+    pub initiator_eph_pub_key: Vec<u8>,
+
+    #[tlv(tag = 5)]
+    pub initiator_session_params: Option<SessionParameters>,
+
+    #[tlv(tag = 6)]
+    pub resumption_id: Option<Vec<u8>>,
+
+    #[tlv(tag = 7)]
+    pub initiator_resume_mic: Option<Vec<u8>>,
+}
+
+/// Payload of `CASE_Sigma2`.
+#[derive(Debug, Default, Clone, PartialEq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+pub struct Sigma2 {
+    #[tlv(tag = 1)]
+    pub responder_random: Vec<u8>,
+
+    #[tlv(tag = 2)]
+    pub responder_session_id: u16,
+
+    #[tlv(tag = 3)]
+    pub responder_eph_pub_key: Vec<u8>,
+
+    /// Encrypted [SigmaTbeData].
+    #[tlv(tag = 4)]
+    pub encrypted2: Vec<u8>,
+
+    #[tlv(tag = 5)]
+    pub responder_session_params: Option<SessionParameters>,
+}
+
+/// Payload of `CASE_Sigma3`.
+#[derive(Debug, Default, Clone, PartialEq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+pub struct Sigma3 {
+    /// Encrypted [SigmaTbeData].
+    #[tlv(tag = 1)]
+    pub encrypted3: Vec<u8>,
+}
+
+/// Payload of `CASE_Sigma2_Resume`.
+#[derive(Debug, Default, Clone, PartialEq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+pub struct Sigma2Resume {
+    #[tlv(tag = 1)]
+    pub resumption_id: Vec<u8>,
+
+    #[tlv(tag = 2)]
+    pub sigma2_resume_mic: Vec<u8>,
+
+    #[tlv(tag = 3)]
+    pub responder_session_id: u16,
+
+    #[tlv(tag = 4)]
+    pub responder_session_params: Option<SessionParameters>,
+}
+
+/// Data signed by the sender of `Sigma2` and `Sigma3`.
+#[derive(Debug, Default, Clone, PartialEq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+pub struct SigmaTbsData {
+    #[tlv(tag = 1)]
+    pub sender_noc: Vec<u8>,
+
+    #[tlv(tag = 2)]
+    pub sender_icac: Option<Vec<u8>>,
+
+    #[tlv(tag = 3)]
+    pub sender_eph_pub_key: Vec<u8>,
+
+    #[tlv(tag = 4)]
+    pub receiver_eph_pub_key: Vec<u8>,
+}
+
+/// Data encrypted in `Sigma2` and `Sigma3`.
+#[derive(Debug, Default, Clone, PartialEq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+pub struct SigmaTbeData {
+    #[tlv(tag = 1)]
+    pub sender_noc: Vec<u8>,
+
+    #[tlv(tag = 2)]
+    pub sender_icac: Option<Vec<u8>>,
+
+    #[tlv(tag = 3)]
+    pub signature: Vec<u8>,
+
+    /// Only in `Sigma2`: id under which the session may be resumed.
+    #[tlv(tag = 4)]
+    pub resumption_id: Option<Vec<u8>>,
+}
+
+/// Computes the destination identifier by which `Sigma1` selects the fabric
+/// and node of the responder without revealing them.
+///
+/// ```
+/// use hex_literal::hex;
+/// use matter_packets::case::destination_id;
+/// use matter_types::NodeId;
+///
+/// let root_public_key = hex!(
+///     "044a9f42b1ca4840d37292bbc7f6a7e11e22200c976fc900dbc98a7a383a641cb8"
+///     "254a2e56d4e295a847943b4e3897c4a773e930277b4d9fbede8a052686bfacfa"
+/// );
+///
+/// assert_eq!(
+///     destination_id(
+///         &hex!("7e171231568dfa17206b3accf8faec2f4d21b580113196f47c7c4deb810a73dc"),
+///         &root_public_key,
+///         0x2906_C908_D115_D362,
+///         NodeId(0xCD55_44AA_7B13_EF14),
+///         &hex!("9bc61cd9c62a2df6d64dfcaa9dc472d4"),
+///     ),
+///     hex!("dc35dd5fc9134cc5544538c9c3fc4297c1ec3370c839136a80e10796451d4c53")
+/// );
+/// ```
+pub fn destination_id(
+    initiator_random: &[u8],
+    root_public_key: &PublicKeyBytes,
+    fabric_id: u64,
+    node_id: NodeId,
+    ipk: &[u8],
+) -> [u8; DESTINATION_ID_LENGTH] {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(ipk).expect("HMAC accepts any key length");
+    mac.update(initiator_random);
+    mac.update(root_public_key);
+    mac.update(&fabric_id.to_le_bytes());
+    mac.update(&node_id.0.to_le_bytes());
+    mac.finalize().into_bytes().into()
+}
+
+/// State kept to resume a session with a peer.
+#[derive(Clone, PartialEq, Eq)]
+pub struct ResumptionRecord {
+    pub resumption_id: ResumptionId,
+    pub shared_secret: [u8; SHARED_SECRET_LENGTH],
+    pub peer_node: NodeId,
+    pub fabric: FabricIndex,
+}
+
+impl std::fmt::Debug for ResumptionRecord {
+    // the shared secret is intentionally not printed
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResumptionRecord")
+            .field("resumption_id", &self.resumption_id)
+            .field("peer_node", &self.peer_node)
+            .field("fabric", &self.fabric)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Bounded storage of resumption records, with at most one record per peer.
+///
+/// When full, the oldest record is evicted.
+#[derive(Debug)]
+pub struct ResumptionStore {
+    capacity: usize,
+    records: VecDeque<ResumptionRecord>,
+}
+
+impl ResumptionStore {
+    pub fn new(capacity: usize) -> Self {
+        ResumptionStore {
+            capacity,
+            records: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Stores a record, replacing any previous record for the same peer.
+    pub fn insert(&mut self, record: ResumptionRecord) {
+        self.records.retain(|r| {
+            (r.fabric, r.peer_node) != (record.fabric, record.peer_node)
+                && r.resumption_id != record.resumption_id
+        });
+
+        if self.records.len() >= self.capacity {
+            self.records.pop_front();
+        }
+
+        if self.capacity > 0 {
+            self.records.push_back(record);
+        }
+    }
+
+    pub fn find_by_id(&self, resumption_id: &[u8]) -> Option<&ResumptionRecord> {
+        self.records
+            .iter()
+            .find(|r| r.resumption_id == resumption_id)
+    }
+
+    pub fn find_by_peer(
+        &self,
+        fabric: FabricIndex,
+        peer_node: NodeId,
+    ) -> Option<&ResumptionRecord> {
+        self.records
+            .iter()
+            .find(|r| r.fabric == fabric && r.peer_node == peer_node)
+    }
+
+    pub fn remove_peer(&mut self, fabric: FabricIndex, peer_node: NodeId) {
+        self.records
+            .retain(|r| r.fabric != fabric || r.peer_node != peer_node);
+    }
+
+    pub fn remove_fabric(&mut self, fabric: FabricIndex) {
+        self.records.retain(|r| r.fabric != fabric);
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+}
+
+/// A session established through CASE.
+#[derive(Debug, Clone)]
+pub struct EstablishedSession {
+    pub params: SecureSessionParams,
+
+    /// Record to store for resuming this session later.
+    pub resumption: ResumptionRecord,
+}
+
+/// Reply of the responder to `Sigma1`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Sigma1Reply {
+    /// Full session establishment: payload of `CASE_Sigma2`.
+    Sigma2(Vec<u8>),
+
+    /// Session resumption: payload of `CASE_Sigma2_Resume`.
+    Sigma2Resume(Vec<u8>),
+}
+
+fn hkdf<const N: usize>(salt: &[u8], ikm: &[u8], info: &[u8]) -> [u8; N] {
+    let mut result = [0; N];
+    Hkdf::<Sha256>::new(Some(salt), ikm)
+        .expand(info, &mut result)
+        .expect("valid HKDF output length");
+    result
+}
+
+fn transcript_hash(messages: &[&[u8]]) -> [u8; 32] {
+    let mut hash = Sha256::new();
+    for message in messages {
+        hash.update(message);
+    }
+    hash.finalize().into()
+}
+
+fn seal(key: &SessionKey, nonce: &[u8; 13], plaintext: &[u8]) -> Result<Vec<u8>> {
+    let mut data = plaintext.to_vec();
+    let mic = Aes128Ccm::new(GenericArray::from_slice(key))
+        .encrypt_in_place_detached(GenericArray::from_slice(nonce), &[], &mut data)
+        .map_err(|_| anyhow!("Encryption failed"))?;
+    data.extend_from_slice(&mic);
+    Ok(data)
+}
+
+fn open(key: &SessionKey, nonce: &[u8; 13], data: &[u8]) -> Result<Vec<u8>> {
+    if data.len() < MIC_LENGTH {
+        return Err(anyhow!("Encrypted data too short"));
+    }
+
+    let (ciphertext, mic) = data.split_at(data.len() - MIC_LENGTH);
+    let mut plaintext = ciphertext.to_vec();
+    Aes128Ccm::new(GenericArray::from_slice(key))
+        .decrypt_in_place_detached(
+            GenericArray::from_slice(nonce),
+            &[],
+            &mut plaintext,
+            GenericArray::from_slice(mic),
+        )
+        .map_err(|_| anyhow!("Decryption failed"))?;
+    Ok(plaintext)
+}
+
+/// Salt for the resumption keys: `initiatorRandom || resumptionId`.
+fn resumption_salt(initiator_random: &[u8], resumption_id: &[u8]) -> Vec<u8> {
+    [initiator_random, resumption_id].concat()
+}
+
+fn resume_mic(shared_secret: &[u8], salt: &[u8], info: &[u8], nonce: &[u8; 13]) -> Result<Vec<u8>> {
+    seal(&hkdf(salt, shared_secret, info), nonce, &[])
+}
+
+fn session_keys(salt: &[u8], shared_secret: &[u8], info: &[u8], initiator: bool) -> SessionKeys {
+    let keys: [u8; 2 * KEY_LENGTH + ATTESTATION_CHALLENGE_LENGTH] = hkdf(salt, shared_secret, info);
+
+    SessionKeys::from_derived(
+        keys[..KEY_LENGTH].try_into().unwrap(),
+        keys[KEY_LENGTH..2 * KEY_LENGTH].try_into().unwrap(),
+        keys[2 * KEY_LENGTH..].try_into().unwrap(),
+        initiator,
+    )
+}
+
+fn ephemeral_key() -> (EphemeralSecret, Vec<u8>) {
+    let secret = EphemeralSecret::random(&mut rand::thread_rng());
+    let public = secret
+        .public_key()
+        .to_encoded_point(false)
+        .as_bytes()
+        .to_vec();
+    (secret, public)
+}
+
+fn ecdh(secret: &EphemeralSecret, peer_public: &[u8]) -> Result<[u8; SHARED_SECRET_LENGTH]> {
+    check_length("ephemeral public key", peer_public, POINT_LENGTH)?;
+    let peer_public = PublicKey::from_sec1_bytes(peer_public)
+        .map_err(|_| anyhow!("Invalid ephemeral public key"))?;
+
+    let shared = secret.diffie_hellman(&peer_public);
+    Ok((*shared.raw_secret_bytes()).into())
+}
+
+fn sign(fabric: &Fabric, tbs: &SigmaTbsData) -> Result<Vec<u8>> {
+    let signature: Signature = fabric.signing_key.sign(&encode(tbs)?);
+    Ok(signature.to_bytes().to_vec())
+}
+
+/// Validates the sender credentials of a decrypted `Sigma2`/`Sigma3` and
+/// checks its signature.
+fn verify_sender(
+    fabric: &Fabric,
+    validator: &dyn CertificateChainValidator,
+    tbe: &SigmaTbeData,
+    sender_eph_pub_key: &[u8],
+    receiver_eph_pub_key: &[u8],
+) -> Result<PeerIdentity> {
+    let identity = validator.validate(fabric, &tbe.sender_noc, tbe.sender_icac.as_deref())?;
+    if identity.fabric_id != fabric.fabric_id {
+        return Err(anyhow!(
+            "Peer is on fabric 0x{:016X}, expected 0x{:016X}",
+            identity.fabric_id,
+            fabric.fabric_id
+        ));
+    }
+
+    let tbs = encode(&SigmaTbsData {
+        sender_noc: tbe.sender_noc.clone(),
+        sender_icac: tbe.sender_icac.clone(),
+        sender_eph_pub_key: sender_eph_pub_key.to_vec(),
+        receiver_eph_pub_key: receiver_eph_pub_key.to_vec(),
+    })?;
+
+    check_length("signature", &tbe.signature, SIGNATURE_LENGTH)?;
+    let signature =
+        Signature::from_slice(&tbe.signature).map_err(|_| anyhow!("Invalid signature encoding"))?;
+    identity
+        .public_key
+        .verify(&tbs, &signature)
+        .map_err(|_| anyhow!("Invalid signature"))?;
+
+    Ok(identity)
+}
+
+fn session_params(
+    fabric: &Fabric,
+    local_session_id: u16,
+    peer_session_id: u16,
+    peer_node: NodeId,
+    keys: SessionKeys,
+    peer_mrp: Option<SessionParameters>,
+) -> Result<SecureSessionParams> {
+    Ok(SecureSessionParamsBuilder::default()
+        .kind(SecureSessionKind::Case)
+        .local_session_id(local_session_id)
+        .peer_session_id(peer_session_id)
+        .peer_node(peer_node)
+        .local_node(fabric.node_id)
+        .fabric(fabric.index)
+        .keys(keys)
+        .peer_mrp(peer_mrp.unwrap_or_default().mrp_config())
+        .build()?)
+}
+
+enum InitiatorState {
+    Idle,
+    AwaitingSigma2 {
+        sigma1: Vec<u8>,
+        initiator_random: Vec<u8>,
+        ephemeral: EphemeralSecret,
+        ephemeral_public: Vec<u8>,
+    },
+    AwaitingStatusReport {
+        session: EstablishedSession,
+    },
+    Finished,
+}
+
+/// Initiator side of CASE, opening a session to a node of its fabric.
+pub struct CaseInitiator {
+    fabric: Fabric,
+    peer_node: NodeId,
+    local_session_id: u16,
+    local_mrp: Option<SessionParameters>,
+    resumption: Option<ResumptionRecord>,
+    state: InitiatorState,
+}
+
+impl CaseInitiator {
+    pub fn new(fabric: Fabric, peer_node: NodeId, local_session_id: u16) -> Self {
+        CaseInitiator {
+            fabric,
+            peer_node,
+            local_session_id,
+            local_mrp: None,
+            resumption: None,
+            state: InitiatorState::Idle,
+        }
+    }
+
+    /// MRP parameters to advertise to the responder.
+    pub fn with_session_parameters(mut self, params: SessionParameters) -> Self {
+        self.local_mrp = Some(params);
+        self
+    }
+
+    /// Attempts to resume a previous session with the peer.
+    pub fn with_resumption(mut self, record: ResumptionRecord) -> Self {
+        self.resumption = Some(record);
+        self
+    }
+
+    /// Payload of the `CASE_Sigma1` that starts the exchange.
+    pub fn start(&mut self) -> Result<Vec<u8>> {
+        if !matches!(self.state, InitiatorState::Idle) {
+            return Err(anyhow!("CASE already started"));
+        }
+
+        let initiator_random = random();
+        let (ephemeral, ephemeral_public) = ephemeral_key();
+
+        let (resumption_id, initiator_resume_mic) = match &self.resumption {
+            Some(record) => (
+                Some(record.resumption_id.to_vec()),
+                Some(resume_mic(
+                    &record.shared_secret,
+                    &resumption_salt(&initiator_random, &record.resumption_id),
+                    b"Sigma1_Resume",
+                    SIGMA1_RESUME_NONCE,
+                )?),
+            ),
+            None => (None, None),
+        };
+
+        let sigma1 = encode(&Sigma1 {
+            destination_id: destination_id(
+                &initiator_random,
+                &self.fabric.root_public_key,
+                self.fabric.fabric_id,
+                self.peer_node,
+                &self.fabric.ipk,
+            )
+            .to_vec(),
+            initiator_random: initiator_random.clone(),
+            initiator_session_id: self.local_session_id,
+            initiator_eph_pub_key: ephemeral_public.clone(),
+            initiator_session_params: self.local_mrp,
+            resumption_id,
+            initiator_resume_mic,
+        })?;
+
+        self.state = InitiatorState::AwaitingSigma2 {
+            sigma1: sigma1.clone(),
+            initiator_random,
+            ephemeral,
+            ephemeral_public,
+        };
+        Ok(sigma1)
+    }
+
+    /// Processes `CASE_Sigma2` and returns the `CASE_Sigma3` payload.
+    ///
+    /// Fails unless the responder proves to be the expected node of the fabric.
+    pub fn on_sigma2(
+        &mut self,
+        payload: &[u8],
+        validator: &dyn CertificateChainValidator,
+    ) -> Result<Vec<u8>> {
+        let (sigma1, ephemeral, ephemeral_public) =
+            match std::mem::replace(&mut self.state, InitiatorState::Finished) {
+                InitiatorState::AwaitingSigma2 {
+                    sigma1,
+                    ephemeral,
+                    ephemeral_public,
+                    ..
+                } => (sigma1, ephemeral, ephemeral_public),
+                _ => return Err(anyhow!("Unexpected Sigma2")),
+            };
+
+        let sigma2: Sigma2 = decode(payload)?;
+        check_length("responder random", &sigma2.responder_random, RANDOM_LENGTH)?;
+        let shared_secret = ecdh(&ephemeral, &sigma2.responder_eph_pub_key)?;
+
+        let s2k = hkdf(
+            &[
+                &self.fabric.ipk[..],
+                &sigma2.responder_random,
+                &sigma2.responder_eph_pub_key,
+                &transcript_hash(&[&sigma1]),
+            ]
+            .concat(),
+            &shared_secret,
+            b"Sigma2",
+        );
+        let tbe2: SigmaTbeData = decode(&open(&s2k, SIGMA2_NONCE, &sigma2.encrypted2)?)?;
+
+        let resumption_id: ResumptionId = tbe2
+            .resumption_id
+            .as_deref()
+            .and_then(|id| id.try_into().ok())
+            .ok_or_else(|| anyhow!("Sigma2 without valid resumption id"))?;
+
+        let identity = verify_sender(
+            &self.fabric,
+            validator,
+            &tbe2,
+            &sigma2.responder_eph_pub_key,
+            &ephemeral_public,
+        )?;
+        if identity.node_id != self.peer_node {
+            return Err(anyhow!(
+                "Responder is node {:?}, expected {:?}",
+                identity.node_id,
+                self.peer_node
+            ));
+        }
+
+        let signature = sign(
+            &self.fabric,
+            &SigmaTbsData {
+                sender_noc: self.fabric.noc.clone(),
+                sender_icac: self.fabric.icac.clone(),
+                sender_eph_pub_key: ephemeral_public.clone(),
+                receiver_eph_pub_key: sigma2.responder_eph_pub_key.clone(),
+            },
+        )?;
+        let tbe3 = encode(&SigmaTbeData {
+            sender_noc: self.fabric.noc.clone(),
+            sender_icac: self.fabric.icac.clone(),
+            signature,
+            resumption_id: None,
+        })?;
+
+        let s3k = hkdf(
+            &[&self.fabric.ipk[..], &transcript_hash(&[&sigma1, payload])].concat(),
+            &shared_secret,
+            b"Sigma3",
+        );
+        let sigma3 = encode(&Sigma3 {
+            encrypted3: seal(&s3k, SIGMA3_NONCE, &tbe3)?,
+        })?;
+
+        let keys = session_keys(
+            &[
+                &self.fabric.ipk[..],
+                &transcript_hash(&[&sigma1, payload, &sigma3]),
+            ]
+            .concat(),
+            &shared_secret,
+            b"SessionKeys",
+            true,
+        );
+
+        self.state = InitiatorState::AwaitingStatusReport {
+            session: EstablishedSession {
+                params: session_params(
+                    &self.fabric,
+                    self.local_session_id,
+                    sigma2.responder_session_id,
+                    self.peer_node,
+                    keys,
+                    sigma2.responder_session_params,
+                )?,
+                resumption: ResumptionRecord {
+                    resumption_id,
+                    shared_secret,
+                    peer_node: self.peer_node,
+                    fabric: self.fabric.index,
+                },
+            },
+        };
+        Ok(sigma3)
+    }
+
+    /// Processes `CASE_Sigma2_Resume`.
+    ///
+    /// Returns the success report to send to the responder and the resumed
+    /// session.
+    pub fn on_sigma2_resume(
+        &mut self,
+        payload: &[u8],
+    ) -> Result<(StatusReport, EstablishedSession)> {
+        let initiator_random = match std::mem::replace(&mut self.state, InitiatorState::Finished) {
+            InitiatorState::AwaitingSigma2 {
+                initiator_random, ..
+            } => initiator_random,
+            _ => return Err(anyhow!("Unexpected Sigma2Resume")),
+        };
+        let record = self
+            .resumption
+            .as_ref()
+            .ok_or_else(|| anyhow!("Sigma2Resume without resumption attempt"))?;
+
+        let sigma2_resume: Sigma2Resume = decode(payload)?;
+        let resumption_id: ResumptionId = sigma2_resume
+            .resumption_id
+            .as_slice()
+            .try_into()
+            .map_err(|_| anyhow!("Invalid resumption id length"))?;
+
+        let salt = resumption_salt(&initiator_random, &resumption_id);
+        let expected_mic = resume_mic(
+            &record.shared_secret,
+            &salt,
+            b"Sigma2_Resume",
+            SIGMA2_RESUME_NONCE,
+        )?;
+        if expected_mic != sigma2_resume.sigma2_resume_mic {
+            return Err(anyhow!("Invalid Sigma2Resume MIC"));
+        }
+
+        let keys = session_keys(&salt, &record.shared_secret, b"SessionResumptionKeys", true);
+        let session = EstablishedSession {
+            params: session_params(
+                &self.fabric,
+                self.local_session_id,
+                sigma2_resume.responder_session_id,
+                record.peer_node,
+                keys,
+                sigma2_resume.responder_session_params,
+            )?,
+            resumption: ResumptionRecord {
+                resumption_id,
+                ..record.clone()
+            },
+        };
+
+        Ok((StatusReport::session_establishment_success(), session))
+    }
+
+    /// Processes the final `StatusReport` of the responder.
+    pub fn on_status_report(&mut self, report: &StatusReport) -> Result<EstablishedSession> {
+        match std::mem::replace(&mut self.state, InitiatorState::Finished) {
+            InitiatorState::AwaitingStatusReport { session }
+                if report.is_success()
+                    && report.secure_channel_code()
+                        == Some(SecureChannelCode::SessionEstablishmentSuccess) =>
+            {
+                Ok(session)
+            }
+            _ => Err(anyhow!("CASE failed: {}", report)),
+        }
+    }
+}
+
+/// Responder state between `Sigma2` and `Sigma3`.
+struct PendingSigma3 {
+    fabric: Fabric,
+    sigma1: Vec<u8>,
+    sigma2: Vec<u8>,
+    shared_secret: [u8; SHARED_SECRET_LENGTH],
+    initiator_eph_pub_key: Vec<u8>,
+    responder_eph_pub_key: Vec<u8>,
+    resumption_id: ResumptionId,
+    peer_session_id: u16,
+    peer_mrp: Option<SessionParameters>,
+}
+
+enum ResponderState {
+    AwaitingSigma1,
+    AwaitingSigma3(Box<PendingSigma3>),
+    AwaitingStatusReport { session: EstablishedSession },
+    Finished,
+}
+
+/// Responder side of CASE.
+pub struct CaseResponder {
+    local_session_id: u16,
+    local_mrp: Option<SessionParameters>,
+    state: ResponderState,
+}
+
+impl CaseResponder {
+    pub fn new(local_session_id: u16) -> Self {
+        CaseResponder {
+            local_session_id,
+            local_mrp: None,
+            state: ResponderState::AwaitingSigma1,
+        }
+    }
+
+    /// MRP parameters to advertise to the initiator.
+    pub fn with_session_parameters(mut self, params: SessionParameters) -> Self {
+        self.local_mrp = Some(params);
+        self
+    }
+
+    /// Processes `CASE_Sigma1`.
+    ///
+    /// The session is resumed if the initiator presents a valid resumption
+    /// record from `resumptions`, otherwise `fabrics` are searched for the
+    /// destination of the initiator.
+    pub fn on_sigma1(
+        &mut self,
+        payload: &[u8],
+        fabrics: &[Fabric],
+        resumptions: &ResumptionStore,
+    ) -> Result<Sigma1Reply> {
+        if !matches!(self.state, ResponderState::AwaitingSigma1) {
+            return Err(anyhow!("Unexpected Sigma1"));
+        }
+        self.state = ResponderState::Finished;
+
+        let sigma1: Sigma1 = decode(payload)?;
+        check_length("initiator random", &sigma1.initiator_random, RANDOM_LENGTH)?;
+        check_length(
+            "destination id",
+            &sigma1.destination_id,
+            DESTINATION_ID_LENGTH,
+        )?;
+
+        if let Some(reply) = self.try_resume(&sigma1, fabrics, resumptions)? {
+            return Ok(reply);
+        }
+
+        let fabric = fabrics
+            .iter()
+            .find(|f| {
+                destination_id(
+                    &sigma1.initiator_random,
+                    &f.root_public_key,
+                    f.fabric_id,
+                    f.node_id,
+                    &f.ipk,
+                ) == sigma1.destination_id.as_slice()
+            })
+            .ok_or_else(|| anyhow!("No fabric matches the Sigma1 destination id"))?;
+
+        let (ephemeral, ephemeral_public) = ephemeral_key();
+        let shared_secret = ecdh(&ephemeral, &sigma1.initiator_eph_pub_key)?;
+
+        let mut resumption_id = ResumptionId::default();
+        rand::thread_rng().fill_bytes(&mut resumption_id);
+
+        let signature = sign(
+            fabric,
+            &SigmaTbsData {
+                sender_noc: fabric.noc.clone(),
+                sender_icac: fabric.icac.clone(),
+                sender_eph_pub_key: ephemeral_public.clone(),
+                receiver_eph_pub_key: sigma1.initiator_eph_pub_key.clone(),
+            },
+        )?;
+        let tbe2 = encode(&SigmaTbeData {
+            sender_noc: fabric.noc.clone(),
+            sender_icac: fabric.icac.clone(),
+            signature,
+            resumption_id: Some(resumption_id.to_vec()),
+        })?;
+
+        let responder_random = random();
+        let s2k = hkdf(
+            &[
+                &fabric.ipk[..],
+                &responder_random,
+                &ephemeral_public,
+                &transcript_hash(&[payload]),
+            ]
+            .concat(),
+            &shared_secret,
+            b"Sigma2",
+        );
+
+        let sigma2 = encode(&Sigma2 {
+            responder_random,
+            responder_session_id: self.local_session_id,
+            responder_eph_pub_key: ephemeral_public.clone(),
+            encrypted2: seal(&s2k, SIGMA2_NONCE, &tbe2)?,
+            responder_session_params: self.local_mrp,
+        })?;
+
+        self.state = ResponderState::AwaitingSigma3(Box::new(PendingSigma3 {
+            fabric: fabric.clone(),
+            sigma1: payload.to_vec(),
+            sigma2: sigma2.clone(),
+            shared_secret,
+            initiator_eph_pub_key: sigma1.initiator_eph_pub_key,
+            responder_eph_pub_key: ephemeral_public,
+            resumption_id,
+            peer_session_id: sigma1.initiator_session_id,
+            peer_mrp: sigma1.initiator_session_params,
+        }));
+        Ok(Sigma1Reply::Sigma2(sigma2))
+    }
+
+    /// Replies with `Sigma2Resume` if `sigma1` resumes a known session.
+    fn try_resume(
+        &mut self,
+        sigma1: &Sigma1,
+        fabrics: &[Fabric],
+        resumptions: &ResumptionStore,
+    ) -> Result<Option<Sigma1Reply>> {
+        let (resumption_id, mic) = match (&sigma1.resumption_id, &sigma1.initiator_resume_mic) {
+            (Some(resumption_id), Some(mic)) => (resumption_id, mic),
+            _ => return Ok(None),
+        };
+
+        // unknown or invalid resumption attempts fall back to full CASE
+        let record = match resumptions.find_by_id(resumption_id) {
+            Some(record) => record,
+            None => return Ok(None),
+        };
+        let fabric = match fabrics.iter().find(|f| f.index == record.fabric) {
+            Some(fabric) => fabric,
+            None => return Ok(None),
+        };
+
+        let expected_mic = resume_mic(
+            &record.shared_secret,
+            &resumption_salt(&sigma1.initiator_random, resumption_id),
+            b"Sigma1_Resume",
+            SIGMA1_RESUME_NONCE,
+        )?;
+        if &expected_mic != mic {
+            return Ok(None);
+        }
+
+        let mut new_resumption_id = ResumptionId::default();
+        rand::thread_rng().fill_bytes(&mut new_resumption_id);
+
+        let salt = resumption_salt(&sigma1.initiator_random, &new_resumption_id);
+        let sigma2_resume = encode(&Sigma2Resume {
+            resumption_id: new_resumption_id.to_vec(),
+            sigma2_resume_mic: resume_mic(
+                &record.shared_secret,
+                &salt,
+                b"Sigma2_Resume",
+                SIGMA2_RESUME_NONCE,
+            )?,
+            responder_session_id: self.local_session_id,
+            responder_session_params: self.local_mrp,
+        })?;
+
+        let keys = session_keys(
+            &salt,
+            &record.shared_secret,
+            b"SessionResumptionKeys",
+            false,
+        );
+        self.state = ResponderState::AwaitingStatusReport {
+            session: EstablishedSession {
+                params: session_params(
+                    fabric,
+                    self.local_session_id,
+                    sigma1.initiator_session_id,
+                    record.peer_node,
+                    keys,
+                    sigma1.initiator_session_params,
+                )?,
+                resumption: ResumptionRecord {
+                    resumption_id: new_resumption_id,
+                    ..record.clone()
+                },
+            },
+        };
+
+        Ok(Some(Sigma1Reply::Sigma2Resume(sigma2_resume)))
+    }
+
+    /// Processes `CASE_Sigma3`.
+    ///
+    /// Returns the success report to send to the initiator and the
+    /// established session. Fails unless the initiator proves to be a node
+    /// of the fabric.
+    pub fn on_sigma3(
+        &mut self,
+        payload: &[u8],
+        validator: &dyn CertificateChainValidator,
+    ) -> Result<(StatusReport, EstablishedSession)> {
+        let PendingSigma3 {
+            fabric,
+            sigma1,
+            sigma2,
+            shared_secret,
+            initiator_eph_pub_key,
+            responder_eph_pub_key,
+            resumption_id,
+            peer_session_id,
+            peer_mrp,
+        } = match std::mem::replace(&mut self.state, ResponderState::Finished) {
+            ResponderState::AwaitingSigma3(pending) => *pending,
+            _ => return Err(anyhow!("Unexpected Sigma3")),
+        };
+
+        let sigma3: Sigma3 = decode(payload)?;
+        let s3k = hkdf(
+            &[&fabric.ipk[..], &transcript_hash(&[&sigma1, &sigma2])].concat(),
+            &shared_secret,
+            b"Sigma3",
+        );
+        let tbe3: SigmaTbeData = decode(&open(&s3k, SIGMA3_NONCE, &sigma3.encrypted3)?)?;
+
+        let identity = verify_sender(
+            &fabric,
+            validator,
+            &tbe3,
+            &initiator_eph_pub_key,
+            &responder_eph_pub_key,
+        )?;
+
+        let keys = session_keys(
+            &[
+                &fabric.ipk[..],
+                &transcript_hash(&[&sigma1, &sigma2, payload]),
+            ]
+            .concat(),
+            &shared_secret,
+            b"SessionKeys",
+            false,
+        );
+
+        let session = EstablishedSession {
+            params: session_params(
+                &fabric,
+                self.local_session_id,
+                peer_session_id,
+                identity.node_id,
+                keys,
+                peer_mrp,
+            )?,
+            resumption: ResumptionRecord {
+                resumption_id,
+                shared_secret,
+                peer_node: identity.node_id,
+                fabric: fabric.index,
+            },
+        };
+
+        Ok((StatusReport::session_establishment_success(), session))
+    }
+
+    /// Processes the final `StatusReport` of the initiator after `Sigma2Resume`.
+    pub fn on_status_report(&mut self, report: &StatusReport) -> Result<EstablishedSession> {
+        match std::mem::replace(&mut self.state, ResponderState::Finished) {
+            ResponderState::AwaitingStatusReport { session }
+                if report.is_success()
+                    && report.secure_channel_code()
+                        == Some(SecureChannelCode::SessionEstablishmentSuccess) =>
+            {
+                Ok(session)
+            }
+            _ => Err(anyhow!("CASE resumption failed: {}", report)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encryption::MessageCipher;
+    use crate::fabric::FabricBuilder;
+    use crate::packet::{HeaderBuilder, MessageDestination};
+    use crate::session::{SessionTable, SessionTableConfig};
+    use p256::ecdsa::{SigningKey, VerifyingKey};
+    use tlv_packed::testing::check_vectors;
+
+    const FABRIC_ID: u64 = 0xFAB0_0001;
+    const INITIATOR_NODE: NodeId = NodeId(0x1111);
+    const RESPONDER_NODE: NodeId = NodeId(0x2222);
+
+    /// Minimal stand-in for operational certificates:
+    /// `node id || fabric id || public key || root signature`.
+    fn test_certificate(
+        root: &SigningKey,
+        node_id: NodeId,
+        fabric_id: u64,
+        key: &SigningKey,
+    ) -> Vec<u8> {
+        let mut certificate = [
+            &node_id.0.to_le_bytes()[..],
+            &fabric_id.to_le_bytes(),
+            key.verifying_key().to_encoded_point(false).as_bytes(),
+        ]
+        .concat();
+        let signature: Signature = root.sign(&certificate);
+        certificate.extend_from_slice(&signature.to_bytes());
+        certificate
+    }
+
+    struct TestValidator;
+
+    impl CertificateChainValidator for TestValidator {
+        fn validate(
+            &self,
+            fabric: &Fabric,
+            noc: &[u8],
+            icac: Option<&[u8]>,
+        ) -> Result<PeerIdentity> {
+            if icac.is_some() || noc.len() != 16 + POINT_LENGTH + SIGNATURE_LENGTH {
+                return Err(anyhow!("Invalid test certificate"));
+            }
+
+            let (tbs, signature) = noc.split_at(16 + POINT_LENGTH);
+            VerifyingKey::from_sec1_bytes(&fabric.root_public_key)?
+                .verify(tbs, &Signature::from_slice(signature)?)
+                .map_err(|_| anyhow!("Certificate not signed by root"))?;
+
+            Ok(PeerIdentity {
+                node_id: NodeId(u64::from_le_bytes(tbs[..8].try_into()?)),
+                fabric_id: u64::from_le_bytes(tbs[8..16].try_into()?),
+                public_key: VerifyingKey::from_sec1_bytes(&tbs[16..])?,
+            })
+        }
+    }
+
+    fn fabric(root: &SigningKey, index: u8, fabric_id: u64, node_id: NodeId) -> Fabric {
+        let signing_key = SigningKey::random(&mut rand::thread_rng());
+        let root_public_key = root.verifying_key().to_encoded_point(false);
+
+        FabricBuilder::default()
+            .index(FabricIndex(index))
+            .fabric_id(fabric_id)
+            .node_id(node_id)
+            .root_certificate(Vec::new())
+            .root_public_key(root_public_key.as_bytes().try_into().unwrap())
+            .noc(test_certificate(root, node_id, fabric_id, &signing_key))
+            .ipk([0x42; 16])
+            .signing_key(signing_key)
+            .build()
+            .unwrap()
+    }
+
+    /// Initiator and responder fabrics sharing a root.
+    fn fabrics() -> (Fabric, Fabric) {
+        let root = SigningKey::random(&mut rand::thread_rng());
+        (
+            fabric(&root, 1, FABRIC_ID, INITIATOR_NODE),
+            fabric(&root, 2, FABRIC_ID, RESPONDER_NODE),
+        )
+    }
+
+    /// Runs full CASE, returning the initiator and responder sessions.
+    fn establish(
+        initiator_fabric: &Fabric,
+        responder_fabric: &Fabric,
+    ) -> (EstablishedSession, EstablishedSession) {
+        let mut initiator = CaseInitiator::new(initiator_fabric.clone(), RESPONDER_NODE, 0x1000);
+        let mut responder = CaseResponder::new(0x2000);
+
+        let sigma1 = initiator.start().unwrap();
+        let sigma2 = match responder
+            .on_sigma1(
+                &sigma1,
+                std::slice::from_ref(responder_fabric),
+                &ResumptionStore::new(4),
+            )
+            .unwrap()
+        {
+            Sigma1Reply::Sigma2(sigma2) => sigma2,
+            other => panic!("Unexpected reply {:?}", other),
+        };
+        let sigma3 = initiator.on_sigma2(&sigma2, &TestValidator).unwrap();
+        let (report, responder_session) = responder.on_sigma3(&sigma3, &TestValidator).unwrap();
+        let initiator_session = initiator.on_status_report(&report).unwrap();
+
+        (initiator_session, responder_session)
+    }
+
+    fn assert_matching_keys(initiator: &SecureSessionParams, responder: &SecureSessionParams) {
+        assert_eq!(initiator.keys.encrypt_key, responder.keys.decrypt_key);
+        assert_eq!(initiator.keys.decrypt_key, responder.keys.encrypt_key);
+        assert_eq!(
+            initiator.keys.attestation_challenge,
+            responder.keys.attestation_challenge
+        );
+        assert_ne!(initiator.keys.encrypt_key, initiator.keys.decrypt_key);
+    }
+
+    fn record(id: u8, fabric: u8, peer_node: u64) -> ResumptionRecord {
+        ResumptionRecord {
+            resumption_id: [id; RESUMPTION_ID_LENGTH],
+            shared_secret: [0; SHARED_SECRET_LENGTH],
+            peer_node: NodeId(peer_node),
+            fabric: FabricIndex(fabric),
+        }
+    }
+
+    #[test]
+    fn message_encoding() {
+        check_vectors::<Sigma3>(&["15 30 01 02 AB CD 18"]);
+        check_vectors::<SigmaTbeData>(&[
+            "15 30 01 01 01 30 03 01 03 18",
+            "15 30 01 01 01 30 02 01 02 30 03 01 03 30 04 01 04 18",
+        ]);
+        check_vectors::<Sigma2Resume>(&[
+            "15 30 01 01 01 30 02 01 02 25 03 34 12 18",
+            "15 30 01 01 01 30 02 01 02 25 03 34 12 35 04 26 01 88 13 00 00 18 18",
+        ]);
+    }
+
+    #[test]
+    fn end_to_end() {
+        let (initiator_fabric, responder_fabric) = fabrics();
+        let (initiator, responder) = establish(&initiator_fabric, &responder_fabric);
+
+        assert_matching_keys(&initiator.params, &responder.params);
+
+        assert_eq!(initiator.params.kind, SecureSessionKind::Case);
+        assert_eq!(initiator.params.local_session_id, 0x1000);
+        assert_eq!(initiator.params.peer_session_id, 0x2000);
+        assert_eq!(initiator.params.peer_node, RESPONDER_NODE);
+        assert_eq!(initiator.params.local_node, INITIATOR_NODE);
+        assert_eq!(initiator.params.fabric, FabricIndex(1));
+
+        assert_eq!(responder.params.peer_session_id, 0x1000);
+        assert_eq!(responder.params.peer_node, INITIATOR_NODE);
+        assert_eq!(responder.params.local_node, RESPONDER_NODE);
+        assert_eq!(responder.params.fabric, FabricIndex(2));
+
+        assert_eq!(
+            initiator.resumption.resumption_id,
+            responder.resumption.resumption_id
+        );
+        assert_eq!(
+            initiator.resumption.shared_secret,
+            responder.resumption.shared_secret
+        );
+        assert_eq!(initiator.resumption.peer_node, RESPONDER_NODE);
+        assert_eq!(responder.resumption.peer_node, INITIATOR_NODE);
+
+        // a message encrypted by the initiator is readable by the responder
+        let cipher = MessageCipher::new(&initiator.params.keys.encrypt_key);
+        let header = HeaderBuilder::default()
+            .session_id(initiator.params.peer_session_id)
+            .counter(1)
+            .destination(MessageDestination::None)
+            .source(None)
+            .build()
+            .unwrap();
+        let message = cipher
+            .encrypt(&header, None, INITIATOR_NODE, b"hello")
+            .unwrap();
+
+        let cipher = MessageCipher::new(&responder.params.keys.decrypt_key);
+        assert_eq!(
+            cipher.decrypt(&message, INITIATOR_NODE).unwrap().payload,
+            b"hello"
+        );
+
+        let mut table = SessionTable::new(SessionTableConfig::default());
+        table.add_secure(initiator.params).unwrap();
+        table.add_secure(responder.params).unwrap();
+    }
+
+    #[test]
+    fn resumption() {
+        let (initiator_fabric, responder_fabric) = fabrics();
+        let (first_initiator, first_responder) = establish(&initiator_fabric, &responder_fabric);
+
+        let mut resumptions = ResumptionStore::new(4);
+        resumptions.insert(first_responder.resumption.clone());
+
+        let mut initiator = CaseInitiator::new(initiator_fabric, RESPONDER_NODE, 0x1001)
+            .with_resumption(first_initiator.resumption.clone());
+        let mut responder = CaseResponder::new(0x2001);
+
+        let sigma1 = initiator.start().unwrap();
+        let sigma2_resume = match responder
+            .on_sigma1(&sigma1, &[responder_fabric], &resumptions)
+            .unwrap()
+        {
+            Sigma1Reply::Sigma2Resume(payload) => payload,
+            other => panic!("Unexpected reply {:?}", other),
+        };
+
+        let (report, initiator_session) = initiator.on_sigma2_resume(&sigma2_resume).unwrap();
+        let responder_session = responder.on_status_report(&report).unwrap();
+
+        assert_matching_keys(&initiator_session.params, &responder_session.params);
+        assert_ne!(
+            initiator_session.params.keys.encrypt_key,
+            first_initiator.params.keys.encrypt_key
+        );
+        assert_eq!(initiator_session.params.peer_session_id, 0x2001);
+        assert_eq!(responder_session.params.peer_session_id, 0x1001);
+        assert_eq!(initiator_session.params.peer_node, RESPONDER_NODE);
+        assert_eq!(responder_session.params.peer_node, INITIATOR_NODE);
+
+        // both sides move to a fresh resumption id for the same secret
+        assert_eq!(
+            initiator_session.resumption,
+            ResumptionRecord {
+                peer_node: RESPONDER_NODE,
+                fabric: FabricIndex(1),
+                ..responder_session.resumption.clone()
+            }
+        );
+        assert_ne!(
+            initiator_session.resumption.resumption_id,
+            first_initiator.resumption.resumption_id
+        );
+        assert_eq!(
+            initiator_session.resumption.shared_secret,
+            first_initiator.resumption.shared_secret
+        );
+    }
+
+    #[test]
+    fn resumption_falls_back_to_full_case() {
+        let (initiator_fabric, responder_fabric) = fabrics();
+        let (first_initiator, first_responder) = establish(&initiator_fabric, &responder_fabric);
+
+        // a forged secret makes the resumption MIC invalid
+        let mut resumptions = ResumptionStore::new(4);
+        resumptions.insert(ResumptionRecord {
+            shared_secret: [0; SHARED_SECRET_LENGTH],
+            ..first_responder.resumption
+        });
+
+        for resumptions in [&resumptions, &ResumptionStore::new(4)] {
+            let mut initiator =
+                CaseInitiator::new(initiator_fabric.clone(), RESPONDER_NODE, 0x1001)
+                    .with_resumption(first_initiator.resumption.clone());
+            let mut responder = CaseResponder::new(0x2001);
+
+            let sigma1 = initiator.start().unwrap();
+            let sigma2 = match responder
+                .on_sigma1(
+                    &sigma1,
+                    std::slice::from_ref(&responder_fabric),
+                    resumptions,
+                )
+                .unwrap()
+            {
+                Sigma1Reply::Sigma2(sigma2) => sigma2,
+                other => panic!("Unexpected reply {:?}", other),
+            };
+            let sigma3 = initiator.on_sigma2(&sigma2, &TestValidator).unwrap();
+            let (report, responder_session) = responder.on_sigma3(&sigma3, &TestValidator).unwrap();
+            let initiator_session = initiator.on_status_report(&report).unwrap();
+
+            assert_matching_keys(&initiator_session.params, &responder_session.params);
+        }
+    }
+
+    #[test]
+    fn unknown_destination() {
+        let (initiator_fabric, _) = fabrics();
+        let (_, other_root_fabric) = fabrics();
+
+        let mut initiator = CaseInitiator::new(initiator_fabric.clone(), RESPONDER_NODE, 0x1000);
+        let mut responder = CaseResponder::new(0x2000);
+        let sigma1 = initiator.start().unwrap();
+        assert!(responder
+            .on_sigma1(&sigma1, &[other_root_fabric], &ResumptionStore::new(4))
+            .is_err());
+
+        // same fabric, but addressed to another node
+        let mut initiator = CaseInitiator::new(initiator_fabric.clone(), NodeId(0x3333), 0x1000);
+        let mut responder = CaseResponder::new(0x2000);
+        let sigma1 = initiator.start().unwrap();
+        assert!(responder
+            .on_sigma1(&sigma1, &[initiator_fabric], &ResumptionStore::new(4))
+            .is_err());
+    }
+
+    /// Runs CASE up to `Sigma2` and returns whether the initiator accepts it.
+    fn initiator_accepts(initiator_fabric: &Fabric, responder_fabric: Fabric) -> bool {
+        let mut initiator = CaseInitiator::new(initiator_fabric.clone(), RESPONDER_NODE, 0x1000);
+        let mut responder = CaseResponder::new(0x2000);
+
+        let sigma1 = initiator.start().unwrap();
+        let sigma2 = match responder
+            .on_sigma1(&sigma1, &[responder_fabric], &ResumptionStore::new(4))
+            .unwrap()
+        {
+            Sigma1Reply::Sigma2(sigma2) => sigma2,
+            other => panic!("Unexpected reply {:?}", other),
+        };
+        initiator.on_sigma2(&sigma2, &TestValidator).is_ok()
+    }
+
+    #[test]
+    fn untrusted_responder() {
+        let root = SigningKey::random(&mut rand::thread_rng());
+        let initiator_fabric = fabric(&root, 1, FABRIC_ID, INITIATOR_NODE);
+        let responder_fabric = fabric(&root, 2, FABRIC_ID, RESPONDER_NODE);
+        assert!(initiator_accepts(
+            &initiator_fabric,
+            responder_fabric.clone()
+        ));
+
+        // the responder certificate is signed by another root
+        let other_root = SigningKey::random(&mut rand::thread_rng());
+        let impostor = Fabric {
+            noc: test_certificate(
+                &other_root,
+                RESPONDER_NODE,
+                FABRIC_ID,
+                &responder_fabric.signing_key,
+            ),
+            ..responder_fabric.clone()
+        };
+        assert!(!initiator_accepts(&initiator_fabric, impostor));
+
+        // the responder certificate is valid, but it signs with another key
+        let wrong_key = Fabric {
+            signing_key: SigningKey::random(&mut rand::thread_rng()),
+            ..responder_fabric
+        };
+        assert!(!initiator_accepts(&initiator_fabric, wrong_key));
+
+        // the responder certificate is for another fabric of the same root
+        let other_fabric = Fabric {
+            fabric_id: FABRIC_ID,
+            ..fabric(&root, 2, FABRIC_ID + 1, RESPONDER_NODE)
+        };
+        assert!(!initiator_accepts(&initiator_fabric, other_fabric));
+
+        // the responder certificate is for another node
+        let other_node = Fabric {
+            node_id: RESPONDER_NODE,
+            ..fabric(&root, 2, FABRIC_ID, NodeId(0x3333))
+        };
+        assert!(!initiator_accepts(&initiator_fabric, other_node));
+    }
+
+    #[test]
+    fn tampered_sigma3() {
+        let (initiator_fabric, responder_fabric) = fabrics();
+
+        let mut initiator = CaseInitiator::new(initiator_fabric, RESPONDER_NODE, 0x1000);
+        let mut responder = CaseResponder::new(0x2000);
+
+        let sigma1 = initiator.start().unwrap();
+        let sigma2 = match responder
+            .on_sigma1(&sigma1, &[responder_fabric], &ResumptionStore::new(4))
+            .unwrap()
+        {
+            Sigma1Reply::Sigma2(sigma2) => sigma2,
+            other => panic!("Unexpected reply {:?}", other),
+        };
+        let mut sigma3: Sigma3 =
+            decode(&initiator.on_sigma2(&sigma2, &TestValidator).unwrap()).unwrap();
+        sigma3.encrypted3[0] ^= 1;
+
+        assert!(responder
+            .on_sigma3(&encode(&sigma3).unwrap(), &TestValidator)
+            .is_err());
+    }
+
+    #[test]
+    fn unexpected_messages() {
+        let (initiator_fabric, responder_fabric) = fabrics();
+
+        let mut initiator = CaseInitiator::new(initiator_fabric, RESPONDER_NODE, 0x1000);
+        assert!(initiator.on_sigma2(&[], &TestValidator).is_err());
+        assert!(initiator.on_sigma2_resume(&[]).is_err());
+
+        let mut responder = CaseResponder::new(0x2000);
+        assert!(responder.on_sigma3(&[], &TestValidator).is_err());
+        assert!(responder
+            .on_status_report(&StatusReport::session_establishment_success())
+            .is_err());
+
+        // Sigma2Resume is only accepted when resumption was attempted
+        let mut initiator = CaseInitiator::new(responder_fabric, INITIATOR_NODE, 0x1000);
+        initiator.start().unwrap();
+        assert!(initiator.on_sigma2_resume(&[]).is_err());
+    }
+
+    #[test]
+    fn resumption_store() {
+        let mut store = ResumptionStore::new(2);
+        store.insert(record(1, 1, 0x10));
+        store.insert(record(2, 1, 0x20));
+        assert_eq!(store.len(), 2);
+
+        // a new record for the same peer replaces the previous one
+        store.insert(record(3, 1, 0x10));
+        assert_eq!(store.len(), 2);
+        assert!(store.find_by_id(&[1; RESUMPTION_ID_LENGTH]).is_none());
+        assert_eq!(
+            store.find_by_peer(FabricIndex(1), NodeId(0x10)),
+            Some(&record(3, 1, 0x10))
+        );
+
+        // the oldest record is evicted when full
+        store.insert(record(4, 2, 0x10));
+        assert!(store.find_by_peer(FabricIndex(1), NodeId(0x20)).is_none());
+        assert_eq!(
+            store.find_by_id(&[4; RESUMPTION_ID_LENGTH]),
+            Some(&record(4, 2, 0x10))
+        );
+
+        store.remove_fabric(FabricIndex(2));
+        assert_eq!(store.len(), 1);
+        store.remove_peer(FabricIndex(1), NodeId(0x10));
+        assert!(store.is_empty());
+    }
+}
@@ -0,0 +1,1038 @@
+//! Matter operational certificates.
+//!
+//! Certificates are exchanged in a compact TLV encoding ([Certificate]) that
+//! maps one to one to an X.509 DER certificate (see [crate::x509]).
+//! Signatures are always computed over the DER `TBSCertificate`.
+//!
+//! An operational chain is made of a node operational certificate (NOC),
+//! optionally an intermediate certificate (ICAC), and the root certificate
+//! (RCAC) of the fabric. [validate_chain] checks such a chain and returns the
+//! identity of the node.
+
+use anyhow::{anyhow, Result};
+use bitflags::bitflags;
+use matter_types::NodeId;
+use p256::ecdsa::signature::Verifier;
+use p256::ecdsa::{Signature, VerifyingKey};
+use streaming_iterator::StreamingIterator;
+use tlv_derive::{TlvEncodable, TlvMergeDecodable, TlvSchema};
+use tlv_packed::{impl_tlv_bitflags, DecodeEnd, DecodeError, TlvMergeDecodable};
+use tlv_stream::{Record, TagValue, Value};
+
+use crate::fabric::{CertificateChainValidator, Fabric, PeerIdentity};
+use crate::tlv::{decode, decode_tlv_list, encode_tlv_list};
+
+/// `ecdsa-with-SHA256`, the only signature algorithm of Matter certificates.
+pub const SIGNATURE_ALGORITHM_ECDSA_SHA256: u8 = 1;
+
+/// `id-ecPublicKey`, the only public key algorithm of Matter certificates.
+pub const PUBLIC_KEY_ALGORITHM_EC: u8 = 1;
+
+/// `prime256v1`, the only curve of Matter certificates.
+pub const CURVE_PRIME256V1: u8 = 1;
+
+/// Length of subject and authority key identifiers.
+pub const KEY_ID_LENGTH: usize = 20;
+
+/// Maximum length of certificate serial numbers.
+pub const MAX_SERIAL_NUMBER_LENGTH: usize = 20;
+
+/// Seconds from the Unix epoch to the Matter epoch (2000-01-01 00:00:00 UTC).
+pub const MATTER_EPOCH_UNIX_SECONDS: u64 = 946_684_800;
+
+/// Largest operational node id; higher ids are reserved for groups and
+/// temporary local ids.
+const MAX_OPERATIONAL_NODE_ID: u64 = 0xFFFF_FFEF_FFFF_FFFF;
+
+/// Returns true if `node_id` can identify a node in a NOC, i.e. it is
+/// neither the unspecified id 0 nor a group or temporary local id.
+pub fn is_operational_node_id(node_id: NodeId) -> bool {
+    (1..=MAX_OPERATIONAL_NODE_ID).contains(&node_id.0)
+}
+
+/// Returns true if `tag` is a valid CASE Authenticated Tag: its version,
+/// the lower 16 bits, must not be 0.
+pub fn is_valid_case_authenticated_tag(tag: u32) -> bool {
+    tag & 0xFFFF != 0
+}
+
+/// Maximum number of CASE Authenticated Tags in the subject of a NOC.
+pub const MAX_CASE_AUTHENTICATED_TAGS: usize = 3;
+
+/// Tag offset of text attributes encoded as `PrintableString` in X.509.
+const PRINTABLE_TAG_FLAG: u8 = 0x80;
+
+/// Standard X.509 attributes carried as strings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextAttribute {
+    CommonName = 1,
+    Surname = 2,
+    SerialNumber = 3,
+    CountryName = 4,
+    LocalityName = 5,
+    StateOrProvinceName = 6,
+    OrganizationName = 7,
+    OrganizationalUnitName = 8,
+    Title = 9,
+    Name = 10,
+    GivenName = 11,
+    Initials = 12,
+    GenerationQualifier = 13,
+    DnQualifier = 14,
+    Pseudonym = 15,
+    DomainComponent = 16,
+}
+
+impl TextAttribute {
+    pub const ALL: [TextAttribute; 16] = [
+        TextAttribute::CommonName,
+        TextAttribute::Surname,
+        TextAttribute::SerialNumber,
+        TextAttribute::CountryName,
+        TextAttribute::LocalityName,
+        TextAttribute::StateOrProvinceName,
+        TextAttribute::OrganizationName,
+        TextAttribute::OrganizationalUnitName,
+        TextAttribute::Title,
+        TextAttribute::Name,
+        TextAttribute::GivenName,
+        TextAttribute::Initials,
+        TextAttribute::GenerationQualifier,
+        TextAttribute::DnQualifier,
+        TextAttribute::Pseudonym,
+        TextAttribute::DomainComponent,
+    ];
+
+    pub fn from_tag(tag: u8) -> Option<Self> {
+        Self::ALL.iter().copied().find(|a| *a as u8 == tag)
+    }
+}
+
+/// A single attribute of a distinguished name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DnAttribute {
+    /// A standard attribute. `printable` attributes are `PrintableString`s
+    /// in X.509 (`IA5String` for domain components), others `UTF8String`s.
+    Text {
+        attribute: TextAttribute,
+        value: String,
+        printable: bool,
+    },
+    NodeId(NodeId),
+    FirmwareSigningId(u64),
+    IcacId(u64),
+    RcacId(u64),
+    FabricId(u64),
+    CaseAuthenticatedTag(u32),
+}
+
+impl DnAttribute {
+    /// Context tag of the attribute in the TLV encoding.
+    pub fn tag(&self) -> u8 {
+        match self {
+            DnAttribute::Text {
+                attribute,
+                printable: true,
+                ..
+            } if *attribute != TextAttribute::DomainComponent => {
+                *attribute as u8 | PRINTABLE_TAG_FLAG
+            }
+            DnAttribute::Text { attribute, .. } => *attribute as u8,
+            DnAttribute::NodeId(_) => 17,
+            DnAttribute::FirmwareSigningId(_) => 18,
+            DnAttribute::IcacId(_) => 19,
+            DnAttribute::RcacId(_) => 20,
+            DnAttribute::FabricId(_) => 21,
+            DnAttribute::CaseAuthenticatedTag(_) => 22,
+        }
+    }
+
+    fn from_record(tag: u8, value: Value<'_>) -> Result<Self, DecodeError> {
+        let number = || match value {
+            Value::Unsigned(n) => Ok(n),
+            _ => Err(DecodeError::InvalidData),
+        };
+
+        Ok(match tag {
+            17 => DnAttribute::NodeId(NodeId(number()?)),
+            18 => DnAttribute::FirmwareSigningId(number()?),
+            19 => DnAttribute::IcacId(number()?),
+            20 => DnAttribute::RcacId(number()?),
+            21 => DnAttribute::FabricId(number()?),
+            22 => DnAttribute::CaseAuthenticatedTag(
+                number()?.try_into().map_err(|_| DecodeError::OutOfRange)?,
+            ),
+            _ => {
+                let attribute = TextAttribute::from_tag(tag & !PRINTABLE_TAG_FLAG)
+                    .ok_or(DecodeError::UnknownTag)?;
+                let printable = tag & PRINTABLE_TAG_FLAG != 0;
+                if printable && attribute == TextAttribute::DomainComponent {
+                    return Err(DecodeError::UnknownTag);
+                }
+
+                let value = match value {
+                    Value::Utf8(data) => std::str::from_utf8(data)
+                        .map_err(|_| DecodeError::InvalidData)?
+                        .to_string(),
+                    _ => return Err(DecodeError::InvalidData),
+                };
+
+                DnAttribute::Text {
+                    attribute,
+                    value,
+                    printable,
+                }
+            }
+        })
+    }
+
+    fn value(&self) -> Value<'_> {
+        match self {
+            DnAttribute::Text { value, .. } => Value::Utf8(value.as_bytes()),
+            DnAttribute::NodeId(NodeId(n))
+            | DnAttribute::FirmwareSigningId(n)
+            | DnAttribute::IcacId(n)
+            | DnAttribute::RcacId(n)
+            | DnAttribute::FabricId(n) => Value::Unsigned(*n),
+            DnAttribute::CaseAuthenticatedTag(n) => Value::Unsigned((*n).into()),
+        }
+    }
+}
+
+/// TLV encoding of distinguished names: a list of context-tagged attributes.
+mod dn {
+    use super::*;
+    use tlv_packed::{EncodeError, TlvWriter, TypeSchema};
+
+    pub const SCHEMA: TypeSchema = TypeSchema::Any;
+
+    pub fn merge_decode<'a, S>(
+        value: &mut Vec<DnAttribute>,
+        source: &mut S,
+    ) -> Result<DecodeEnd, DecodeError>
+    where
+        S: StreamingIterator<Item = Record<'a>>,
+    {
+        value.clear();
+        decode_tlv_list(source, |tag, source| {
+            let record = source.get().ok_or(DecodeError::InvalidData)?;
+            value.push(DnAttribute::from_record(tag, record.value)?);
+            Ok(())
+        })
+    }
+
+    pub fn encode<W: TlvWriter>(
+        value: &[DnAttribute],
+        tag: TagValue,
+        writer: &mut W,
+    ) -> Result<(), EncodeError> {
+        encode_tlv_list(tag, writer, |writer| {
+            for attribute in value {
+                writer.write_record(Record {
+                    tag: TagValue::ContextSpecific {
+                        tag: attribute.tag().into(),
+                    },
+                    value: attribute.value(),
+                })?;
+            }
+            Ok(())
+        })
+    }
+}
+
+bitflags! {
+    /// Key usage extension bits.
+    #[derive(Default)]
+    pub struct KeyUsage: u16 {
+        const DIGITAL_SIGNATURE = 0x0001;
+        const NON_REPUDIATION = 0x0002;
+        const KEY_ENCIPHERMENT = 0x0004;
+        const DATA_ENCIPHERMENT = 0x0008;
+        const KEY_AGREEMENT = 0x0010;
+        const KEY_CERT_SIGN = 0x0020;
+        const CRL_SIGN = 0x0040;
+        const ENCIPHER_ONLY = 0x0080;
+        const DECIPHER_ONLY = 0x0100;
+    }
+}
+impl_tlv_bitflags!(KeyUsage: u16, reject_unknown_bits);
+
+/// Purposes of the extended key usage extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyPurpose {
+    ServerAuth = 1,
+    ClientAuth = 2,
+    CodeSigning = 3,
+    EmailProtection = 4,
+    TimeStamping = 5,
+    OcspSigning = 6,
+}
+
+impl KeyPurpose {
+    pub const ALL: [KeyPurpose; 6] = [
+        KeyPurpose::ServerAuth,
+        KeyPurpose::ClientAuth,
+        KeyPurpose::CodeSigning,
+        KeyPurpose::EmailProtection,
+        KeyPurpose::TimeStamping,
+        KeyPurpose::OcspSigning,
+    ];
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        Self::ALL.iter().copied().find(|p| *p as u8 == id)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+pub struct BasicConstraints {
+    #[tlv(tag = 1)]
+    pub is_ca: bool,
+
+    #[tlv(tag = 2)]
+    pub path_length: Option<u8>,
+}
+
+/// A certificate extension.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Extension {
+    BasicConstraints(BasicConstraints),
+    KeyUsage(KeyUsage),
+    ExtendedKeyUsage(Vec<KeyPurpose>),
+    SubjectKeyId(Vec<u8>),
+    AuthorityKeyId(Vec<u8>),
+
+    /// Any other extension, as its DER encoded `Extension`.
+    Future(Vec<u8>),
+}
+
+/// TLV encoding of extensions: a list of context-tagged extensions.
+mod extensions {
+    use super::*;
+    use tlv_packed::{decode_list, encode_list, EncodeError, TlvEncodable, TlvWriter, TypeSchema};
+
+    pub const SCHEMA: TypeSchema = TypeSchema::Any;
+
+    fn merge_decode_item<'a, S>(
+        value: &mut impl TlvMergeDecodable<'a, S>,
+        source: &mut S,
+    ) -> Result<(), DecodeError>
+    where
+        S: StreamingIterator<Item = Record<'a>>,
+    {
+        match value.merge_decode(source)? {
+            DecodeEnd::DataConsumed => Ok(()),
+            DecodeEnd::StreamFinished => Err(DecodeError::InvalidNesting),
+        }
+    }
+
+    pub fn merge_decode<'a, S>(
+        value: &mut Vec<Extension>,
+        source: &mut S,
+    ) -> Result<DecodeEnd, DecodeError>
+    where
+        S: StreamingIterator<Item = Record<'a>>,
+    {
+        value.clear();
+        decode_tlv_list(source, |tag, source| {
+            let extension = match tag {
+                1 => {
+                    let mut constraints = BasicConstraints::default();
+                    merge_decode_item(&mut constraints, source)?;
+                    Extension::BasicConstraints(constraints)
+                }
+                2 => {
+                    let mut usage = KeyUsage::default();
+                    merge_decode_item(&mut usage, source)?;
+                    Extension::KeyUsage(usage)
+                }
+                3 => {
+                    let mut ids: Vec<u8> = Vec::new();
+                    if decode_list(&mut ids, source, None)? != DecodeEnd::DataConsumed {
+                        return Err(DecodeError::InvalidNesting);
+                    }
+                    Extension::ExtendedKeyUsage(
+                        ids.into_iter()
+                            .map(|id| KeyPurpose::from_id(id).ok_or(DecodeError::OutOfRange))
+                            .collect::<Result<_, _>>()?,
+                    )
+                }
+                4..=6 => {
+                    let mut data: Vec<u8> = Vec::new();
+                    merge_decode_item(&mut data, source)?;
+                    match tag {
+                        4 => Extension::SubjectKeyId(data),
+                        5 => Extension::AuthorityKeyId(data),
+                        _ => Extension::Future(data),
+                    }
+                }
+                _ => return Err(DecodeError::UnknownTag),
+            };
+            value.push(extension);
+            Ok(())
+        })
+    }
+
+    pub fn encode<W: TlvWriter>(
+        value: &[Extension],
+        tag: TagValue,
+        writer: &mut W,
+    ) -> Result<(), EncodeError> {
+        encode_tlv_list(tag, writer, |writer| {
+            for extension in value {
+                let tag = |tag: u32| TagValue::ContextSpecific { tag };
+                match extension {
+                    Extension::BasicConstraints(constraints) => {
+                        constraints.encode(tag(1), writer)?
+                    }
+                    Extension::KeyUsage(usage) => usage.encode(tag(2), writer)?,
+                    Extension::ExtendedKeyUsage(purposes) => encode_list(
+                        &purposes.iter().map(|p| *p as u8).collect::<Vec<_>>(),
+                        tag(3),
+                        writer,
+                    )?,
+                    Extension::SubjectKeyId(id) => id.encode(tag(4), writer)?,
+                    Extension::AuthorityKeyId(id) => id.encode(tag(5), writer)?,
+                    Extension::Future(data) => data.encode(tag(6), writer)?,
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+/// A Matter certificate in its TLV encoding.
+///
+/// Times are in seconds since the Matter epoch (2000-01-01 00:00:00 UTC).
+#[derive(Debug, Default, Clone, PartialEq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+pub struct Certificate {
+    #[tlv(tag = 1, max_len = 20)]
+    pub serial_number: Vec<u8>,
+
+    #[tlv(tag = 2)]
+    pub signature_algorithm: u8,
+
+    #[tlv(tag = 3, with = dn)]
+    pub issuer: Vec<DnAttribute>,
+
+    #[tlv(tag = 4)]
+    pub not_before: u32,
+
+    /// `0` means that the certificate has no well-defined expiration.
+    #[tlv(tag = 5)]
+    pub not_after: u32,
+
+    #[tlv(tag = 6, with = dn)]
+    pub subject: Vec<DnAttribute>,
+
+    #[tlv(tag = 7)]
+    pub public_key_algorithm: u8,
+
+    #[tlv(tag = 8)]
+    pub curve_id: u8,
+
+    /// Uncompressed P-256 public key.
+    #[tlv(tag = 9)]
+    pub public_key: Vec<u8>,
+
+    #[tlv(tag = 10, with = extensions)]
+    pub extensions: Vec<Extension>,
+
+    /// Raw (`r || s`) ECDSA signature of the DER `TBSCertificate`.
+    #[tlv(tag = 11)]
+    pub signature: Vec<u8>,
+}
+
+/// Role of a certificate in an operational chain, given by its subject.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CertificateKind {
+    Root,
+    Intermediate,
+    Node,
+}
+
+impl Certificate {
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        decode(data)
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        crate::tlv::encode(self)
+    }
+
+    pub fn kind(&self) -> Option<CertificateKind> {
+        self.subject.iter().find_map(|a| match a {
+            DnAttribute::RcacId(_) => Some(CertificateKind::Root),
+            DnAttribute::IcacId(_) => Some(CertificateKind::Intermediate),
+            DnAttribute::NodeId(_) => Some(CertificateKind::Node),
+            _ => None,
+        })
+    }
+
+    pub fn node_id(&self) -> Option<NodeId> {
+        self.subject.iter().find_map(|a| match a {
+            DnAttribute::NodeId(id) => Some(*id),
+            _ => None,
+        })
+    }
+
+    pub fn fabric_id(&self) -> Option<u64> {
+        self.subject.iter().find_map(|a| match a {
+            DnAttribute::FabricId(id) => Some(*id),
+            _ => None,
+        })
+    }
+
+    /// CASE authenticated tags of the subject.
+    pub fn case_authenticated_tags(&self) -> impl Iterator<Item = u32> + '_ {
+        self.subject.iter().filter_map(|a| match a {
+            DnAttribute::CaseAuthenticatedTag(tag) => Some(*tag),
+            _ => None,
+        })
+    }
+
+    pub fn basic_constraints(&self) -> Option<&BasicConstraints> {
+        self.extensions.iter().find_map(|e| match e {
+            Extension::BasicConstraints(c) => Some(c),
+            _ => None,
+        })
+    }
+
+    pub fn key_usage(&self) -> Option<KeyUsage> {
+        self.extensions.iter().find_map(|e| match e {
+            Extension::KeyUsage(usage) => Some(*usage),
+            _ => None,
+        })
+    }
+
+    pub fn extended_key_usage(&self) -> Option<&[KeyPurpose]> {
+        self.extensions.iter().find_map(|e| match e {
+            Extension::ExtendedKeyUsage(purposes) => Some(purposes.as_slice()),
+            _ => None,
+        })
+    }
+
+    pub fn subject_key_id(&self) -> Option<&[u8]> {
+        self.extensions.iter().find_map(|e| match e {
+            Extension::SubjectKeyId(id) => Some(id.as_slice()),
+            _ => None,
+        })
+    }
+
+    pub fn authority_key_id(&self) -> Option<&[u8]> {
+        self.extensions.iter().find_map(|e| match e {
+            Extension::AuthorityKeyId(id) => Some(id.as_slice()),
+            _ => None,
+        })
+    }
+
+    pub fn is_ca(&self) -> bool {
+        self.basic_constraints().is_some_and(|c| c.is_ca)
+    }
+
+    pub fn verifying_key(&self) -> Result<VerifyingKey> {
+        if self.public_key_algorithm != PUBLIC_KEY_ALGORITHM_EC || self.curve_id != CURVE_PRIME256V1
+        {
+            return Err(anyhow!("Unsupported public key algorithm"));
+        }
+        VerifyingKey::from_sec1_bytes(&self.public_key).map_err(|_| anyhow!("Invalid public key"))
+    }
+
+    /// Checks the signature of the certificate against the key of its issuer.
+    pub fn verify_signature(&self, issuer_key: &VerifyingKey) -> Result<()> {
+        if self.signature_algorithm != SIGNATURE_ALGORITHM_ECDSA_SHA256 {
+            return Err(anyhow!("Unsupported signature algorithm"));
+        }
+
+        let signature = Signature::from_slice(&self.signature)
+            .map_err(|_| anyhow!("Invalid signature encoding"))?;
+        issuer_key
+            .verify(&self.tbs_der()?, &signature)
+            .map_err(|_| anyhow!("Invalid certificate signature"))
+    }
+
+    /// Checks that `time` (seconds since the Matter epoch) is within the
+    /// validity period.
+    pub fn is_valid_at(&self, time: u32) -> bool {
+        self.not_before <= time && (self.not_after == 0 || time <= self.not_after)
+    }
+}
+
+/// Checks that `certificate` is properly issued by `issuer`.
+fn check_issued_by(certificate: &Certificate, issuer: &Certificate) -> Result<()> {
+    if certificate.issuer != issuer.subject {
+        return Err(anyhow!(
+            "Issuer does not match the subject of the issuing certificate"
+        ));
+    }
+
+    match (certificate.authority_key_id(), issuer.subject_key_id()) {
+        (Some(authority), Some(subject)) if authority == subject => {}
+        _ => {
+            return Err(anyhow!(
+                "Authority key id does not match the issuing certificate"
+            ))
+        }
+    }
+
+    if !issuer.is_ca()
+        || !issuer
+            .key_usage()
+            .is_some_and(|usage| usage.contains(KeyUsage::KEY_CERT_SIGN))
+    {
+        return Err(anyhow!("Issuing certificate is not a CA"));
+    }
+
+    certificate.verify_signature(&issuer.verifying_key()?)
+}
+
+/// Checks the extensions and subject common to all certificates of a chain.
+fn check_certificate(
+    certificate: &Certificate,
+    kind: CertificateKind,
+    fabric_id: u64,
+    time: Option<u32>,
+) -> Result<()> {
+    if certificate.kind() != Some(kind) {
+        return Err(anyhow!("Expected {:?} certificate", kind));
+    }
+
+    if certificate.fabric_id().is_some_and(|id| id != fabric_id) {
+        return Err(anyhow!("Certificate chain spans multiple fabrics"));
+    }
+
+    if certificate.subject_key_id().map(<[u8]>::len) != Some(KEY_ID_LENGTH)
+        || certificate.authority_key_id().map(<[u8]>::len) != Some(KEY_ID_LENGTH)
+    {
+        return Err(anyhow!("Missing or invalid key identifiers"));
+    }
+
+    let ca = kind != CertificateKind::Node;
+    if certificate.basic_constraints().map(|c| c.is_ca) != Some(ca) {
+        return Err(anyhow!(
+            "Invalid basic constraints for {:?} certificate",
+            kind
+        ));
+    }
+
+    if let Some(time) = time {
+        if !certificate.is_valid_at(time) {
+            return Err(anyhow!(
+                "{:?} certificate is not valid at the current time",
+                kind
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Validates an operational certificate chain and returns the identity of
+/// the node it certifies.
+///
+/// `time` is the current time in seconds since the Matter epoch, used to
+/// check validity periods. Nodes without a trusted time source pass `None`.
+pub fn validate_chain(
+    noc: &Certificate,
+    icac: Option<&Certificate>,
+    rcac: &Certificate,
+    time: Option<u32>,
+) -> Result<PeerIdentity> {
+    let node_id = noc
+        .node_id()
+        .filter(|id| is_operational_node_id(*id))
+        .ok_or_else(|| anyhow!("NOC without operational node id"))?;
+    let fabric_id = noc
+        .fabric_id()
+        .filter(|id| *id != 0)
+        .ok_or_else(|| anyhow!("NOC without fabric id"))?;
+    if noc.case_authenticated_tags().count() > MAX_CASE_AUTHENTICATED_TAGS {
+        return Err(anyhow!(
+            "NOC with more than {} CASE Authenticated Tags",
+            MAX_CASE_AUTHENTICATED_TAGS
+        ));
+    }
+    if let Some(tag) = noc
+        .case_authenticated_tags()
+        .find(|tag| !is_valid_case_authenticated_tag(*tag))
+    {
+        return Err(anyhow!("CASE Authenticated Tag {:08X} has version 0", tag));
+    }
+
+    check_certificate(noc, CertificateKind::Node, fabric_id, time)?;
+    if !noc
+        .key_usage()
+        .is_some_and(|usage| usage.contains(KeyUsage::DIGITAL_SIGNATURE))
+    {
+        return Err(anyhow!("NOC key usage does not allow signatures"));
+    }
+    match noc.extended_key_usage() {
+        Some(purposes)
+            if purposes.contains(&KeyPurpose::ClientAuth)
+                && purposes.contains(&KeyPurpose::ServerAuth) => {}
+        _ => {
+            return Err(anyhow!(
+                "NOC extended key usage is not client and server auth"
+            ))
+        }
+    }
+
+    check_certificate(rcac, CertificateKind::Root, fabric_id, time)?;
+    if rcac.issuer != rcac.subject {
+        return Err(anyhow!("Root certificate is not self-signed"));
+    }
+    check_issued_by(rcac, rcac)?;
+
+    match icac {
+        Some(icac) => {
+            check_certificate(icac, CertificateKind::Intermediate, fabric_id, time)?;
+            if rcac
+                .basic_constraints()
+                .and_then(|c| c.path_length)
+                .is_some_and(|length| length < 1)
+            {
+                return Err(anyhow!("Root certificate does not allow intermediates"));
+            }
+            check_issued_by(icac, rcac)?;
+            check_issued_by(noc, icac)?;
+        }
+        None => check_issued_by(noc, rcac)?,
+    }
+
+    Ok(PeerIdentity {
+        node_id,
+        fabric_id,
+        public_key: noc.verifying_key()?,
+    })
+}
+
+/// Validates operational certificates against the root certificate of the
+/// fabric.
+#[derive(Debug, Default, Clone)]
+pub struct OperationalCertificateValidator {
+    /// Current time in seconds since the Matter epoch, if known.
+    pub time: Option<u32>,
+}
+
+impl CertificateChainValidator for OperationalCertificateValidator {
+    fn validate(&self, fabric: &Fabric, noc: &[u8], icac: Option<&[u8]>) -> Result<PeerIdentity> {
+        let rcac = Certificate::decode(&fabric.root_certificate)?;
+        if rcac.public_key != fabric.root_public_key {
+            return Err(anyhow!(
+                "Root certificate does not match the fabric root key"
+            ));
+        }
+
+        let icac = icac.map(Certificate::decode).transpose()?;
+        validate_chain(&Certificate::decode(noc)?, icac.as_ref(), &rcac, self.time)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::fabric::FabricBuilder;
+    use p256::ecdsa::signature::Signer;
+    use p256::ecdsa::SigningKey;
+    use sha2::{Digest, Sha256};
+    use tlv_packed::testing::check_vectors;
+
+    pub(crate) const FABRIC_ID: u64 = 0xFAB0_0000_0000_001D;
+    pub(crate) const NODE_ID: NodeId = NodeId(0xDEDE_DEDE_0000_0001);
+
+    fn public_key(key: &SigningKey) -> Vec<u8> {
+        key.verifying_key()
+            .to_encoded_point(false)
+            .as_bytes()
+            .to_vec()
+    }
+
+    fn key_id(key: &SigningKey) -> Vec<u8> {
+        Sha256::digest(public_key(key))[..KEY_ID_LENGTH].to_vec()
+    }
+
+    pub(crate) fn sign(certificate: &mut Certificate, key: &SigningKey) {
+        let signature: Signature = key.sign(&certificate.tbs_der().unwrap());
+        certificate.signature = signature.to_bytes().to_vec();
+    }
+
+    /// Issues a certificate for `subject_key`, self-signed without `issuer`.
+    fn issue(
+        subject: Vec<DnAttribute>,
+        subject_key: &SigningKey,
+        issuer: Option<(&Certificate, &SigningKey)>,
+        mut extensions: Vec<Extension>,
+    ) -> Certificate {
+        let (issuer_name, signer) = match issuer {
+            Some((certificate, key)) => (certificate.subject.clone(), key),
+            None => (subject.clone(), subject_key),
+        };
+        extensions.push(Extension::SubjectKeyId(key_id(subject_key)));
+        extensions.push(Extension::AuthorityKeyId(key_id(signer)));
+
+        let mut certificate = Certificate {
+            serial_number: vec![0x01, 0x23, 0x45],
+            signature_algorithm: SIGNATURE_ALGORITHM_ECDSA_SHA256,
+            issuer: issuer_name,
+            not_before: 0x2000_0000,
+            not_after: 0,
+            subject,
+            public_key_algorithm: PUBLIC_KEY_ALGORITHM_EC,
+            curve_id: CURVE_PRIME256V1,
+            public_key: public_key(subject_key),
+            extensions,
+            signature: Vec::new(),
+        };
+        sign(&mut certificate, signer);
+        certificate
+    }
+
+    fn ca_extensions() -> Vec<Extension> {
+        vec![
+            Extension::BasicConstraints(BasicConstraints {
+                is_ca: true,
+                path_length: None,
+            }),
+            Extension::KeyUsage(KeyUsage::KEY_CERT_SIGN | KeyUsage::CRL_SIGN),
+        ]
+    }
+
+    pub(crate) fn rcac(key: &SigningKey) -> Certificate {
+        issue(
+            vec![DnAttribute::RcacId(1), DnAttribute::FabricId(FABRIC_ID)],
+            key,
+            None,
+            ca_extensions(),
+        )
+    }
+
+    pub(crate) fn icac(key: &SigningKey, rcac: &Certificate, rcac_key: &SigningKey) -> Certificate {
+        issue(
+            vec![DnAttribute::IcacId(2)],
+            key,
+            Some((rcac, rcac_key)),
+            ca_extensions(),
+        )
+    }
+
+    pub(crate) fn noc(
+        key: &SigningKey,
+        issuer: &Certificate,
+        issuer_key: &SigningKey,
+    ) -> Certificate {
+        issue(
+            vec![
+                DnAttribute::NodeId(NODE_ID),
+                DnAttribute::FabricId(FABRIC_ID),
+                DnAttribute::CaseAuthenticatedTag(0xABCD_0002),
+            ],
+            key,
+            Some((issuer, issuer_key)),
+            vec![
+                Extension::BasicConstraints(BasicConstraints::default()),
+                Extension::KeyUsage(KeyUsage::DIGITAL_SIGNATURE),
+                Extension::ExtendedKeyUsage(vec![KeyPurpose::ClientAuth, KeyPurpose::ServerAuth]),
+            ],
+        )
+    }
+
+    fn random_key() -> SigningKey {
+        SigningKey::random(&mut rand::thread_rng())
+    }
+
+    #[test]
+    fn encoding() {
+        check_vectors::<BasicConstraints>(&["15 29 01 24 02 01 18", "15 28 01 18"]);
+
+        let certificate = Certificate {
+            serial_number: vec![0x01],
+            signature_algorithm: SIGNATURE_ALGORITHM_ECDSA_SHA256,
+            issuer: vec![DnAttribute::RcacId(1)],
+            not_before: 0x2000_0000,
+            not_after: 0,
+            subject: vec![
+                DnAttribute::Text {
+                    attribute: TextAttribute::CommonName,
+                    value: "A".into(),
+                    printable: false,
+                },
+                DnAttribute::Text {
+                    attribute: TextAttribute::CountryName,
+                    value: "B".into(),
+                    printable: true,
+                },
+                DnAttribute::CaseAuthenticatedTag(0x0001_0002),
+            ],
+            public_key_algorithm: PUBLIC_KEY_ALGORITHM_EC,
+            curve_id: CURVE_PRIME256V1,
+            public_key: vec![0x04],
+            extensions: vec![
+                Extension::BasicConstraints(BasicConstraints {
+                    is_ca: true,
+                    path_length: Some(1),
+                }),
+                Extension::KeyUsage(KeyUsage::KEY_CERT_SIGN | KeyUsage::CRL_SIGN),
+                Extension::ExtendedKeyUsage(vec![KeyPurpose::ServerAuth]),
+                Extension::SubjectKeyId(vec![0xAA]),
+                Extension::AuthorityKeyId(vec![0xBB]),
+                Extension::Future(vec![0x30, 0x00]),
+            ],
+            signature: vec![0xCC],
+        };
+
+        let data = tlv_packed::testing::hex_to_bytes(
+            "15
+             30 01 01 01
+             24 02 01
+             37 03 24 14 01 18
+             26 04 00 00 00 20
+             24 05 00
+             37 06 2C 01 01 41 2C 84 01 42 26 16 02 00 01 00 18
+             24 07 01
+             24 08 01
+             30 09 01 04
+             37 0A
+                35 01 29 01 24 02 01 18
+                24 02 60
+                36 03 04 01 18
+                30 04 01 AA
+                30 05 01 BB
+                30 06 02 30 00
+             18
+             30 0B 01 CC
+             18",
+        );
+
+        assert_eq!(certificate.encode().unwrap(), data);
+        assert_eq!(Certificate::decode(&data).unwrap(), certificate);
+
+        // printable domain components and unknown attributes do not exist
+        assert!(Certificate::decode(&tlv_packed::testing::hex_to_bytes(
+            "15 37 03 2C 90 01 41 18 18"
+        ))
+        .is_err());
+        assert!(Certificate::decode(&tlv_packed::testing::hex_to_bytes(
+            "15 37 03 24 17 01 18 18"
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn valid_chains() {
+        let (rcac_key, icac_key, noc_key) = (random_key(), random_key(), random_key());
+        let rcac = rcac(&rcac_key);
+        let icac = icac(&icac_key, &rcac, &rcac_key);
+
+        for (noc, icac) in [
+            (noc(&noc_key, &rcac, &rcac_key), None),
+            (noc(&noc_key, &icac, &icac_key), Some(&icac)),
+        ] {
+            let identity = validate_chain(&noc, icac, &rcac, Some(0x2000_0000)).unwrap();
+            assert_eq!(
+                identity,
+                PeerIdentity {
+                    node_id: NODE_ID,
+                    fabric_id: FABRIC_ID,
+                    public_key: *noc_key.verifying_key(),
+                }
+            );
+            assert_eq!(noc.kind(), Some(CertificateKind::Node));
+            assert_eq!(
+                noc.case_authenticated_tags().collect::<Vec<_>>(),
+                [0xABCD_0002]
+            );
+
+            // through the validator used by CASE
+            let fabric = FabricBuilder::default()
+                .index(matter_types::FabricIndex(1))
+                .fabric_id(FABRIC_ID)
+                .node_id(NodeId(1))
+                .root_certificate(rcac.encode().unwrap())
+                .root_public_key(rcac.public_key.as_slice().try_into().unwrap())
+                .noc(Vec::new())
+                .ipk([0; 16])
+                .signing_key(random_key())
+                .build()
+                .unwrap();
+            let icac = icac.map(|c| c.encode().unwrap());
+            assert_eq!(
+                OperationalCertificateValidator::default()
+                    .validate(&fabric, &noc.encode().unwrap(), icac.as_deref())
+                    .unwrap(),
+                identity
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_chains() {
+        let (rcac_key, icac_key, noc_key) = (random_key(), random_key(), random_key());
+        let rcac = rcac(&rcac_key);
+        let icac = icac(&icac_key, &rcac, &rcac_key);
+        let noc = noc(&noc_key, &rcac, &rcac_key);
+        assert!(validate_chain(&noc, None, &rcac, None).is_ok());
+
+        // issued by another root with the same subject
+        let other_key = random_key();
+        let other_noc = self::noc(&noc_key, &self::rcac(&other_key), &other_key);
+        assert!(validate_chain(&other_noc, None, &rcac, None).is_err());
+
+        // modified after signing
+        let mut tampered = noc.clone();
+        tampered.subject[0] = DnAttribute::NodeId(NodeId(2));
+        assert!(validate_chain(&tampered, None, &rcac, None).is_err());
+
+        // ICAC not part of the chain, or chain in the wrong order
+        assert!(validate_chain(&noc, Some(&icac), &rcac, None).is_err());
+        assert!(validate_chain(&icac, None, &rcac, None).is_err());
+        assert!(validate_chain(&rcac, None, &rcac, None).is_err());
+
+        // a root that does not allow intermediates
+        let mut strict_rcac = rcac.clone();
+        strict_rcac.extensions[0] = Extension::BasicConstraints(BasicConstraints {
+            is_ca: true,
+            path_length: Some(0),
+        });
+        sign(&mut strict_rcac, &rcac_key);
+        let icac = self::icac(&icac_key, &strict_rcac, &rcac_key);
+        let icac_noc = self::noc(&noc_key, &icac, &icac_key);
+        assert!(validate_chain(&icac_noc, Some(&icac), &strict_rcac, None).is_err());
+
+        // NOC of another fabric
+        let mut other_fabric = noc.clone();
+        other_fabric.subject[1] = DnAttribute::FabricId(FABRIC_ID + 1);
+        sign(&mut other_fabric, &rcac_key);
+        assert!(validate_chain(&other_fabric, None, &rcac, None).is_err());
+
+        // CASE Authenticated Tags: version 0, or more than three
+        let mut version_zero = noc.clone();
+        version_zero.subject[2] = DnAttribute::CaseAuthenticatedTag(0xABCD_0000);
+        sign(&mut version_zero, &rcac_key);
+        assert!(validate_chain(&version_zero, None, &rcac, None).is_err());
+
+        let mut many_tags = noc.clone();
+        many_tags
+            .subject
+            .extend((3..5).map(|tag| DnAttribute::CaseAuthenticatedTag(tag << 16 | 1)));
+        sign(&mut many_tags, &rcac_key);
+        assert!(validate_chain(&many_tags, None, &rcac, None).is_ok());
+        many_tags
+            .subject
+            .push(DnAttribute::CaseAuthenticatedTag(0x0005_0001));
+        sign(&mut many_tags, &rcac_key);
+        assert!(validate_chain(&many_tags, None, &rcac, None).is_err());
+
+        // NOC that is not usable for CASE
+        let mut server_only = noc.clone();
+        server_only.extensions[2] = Extension::ExtendedKeyUsage(vec![KeyPurpose::ServerAuth]);
+        sign(&mut server_only, &rcac_key);
+        assert!(validate_chain(&server_only, None, &rcac, None).is_err());
+
+        // validity period
+        let mut expiring = noc.clone();
+        expiring.not_after = 0x3000_0000;
+        sign(&mut expiring, &rcac_key);
+        assert!(validate_chain(&expiring, None, &rcac, Some(0x2FFF_FFFF)).is_ok());
+        assert!(validate_chain(&expiring, None, &rcac, Some(0x3000_0001)).is_err());
+        assert!(validate_chain(&expiring, None, &rcac, Some(0x1FFF_FFFF)).is_err());
+    }
+}
@@ -0,0 +1,526 @@
+//! Message counter handling: outbound counters and receive-side duplicate
+//! detection.
+//!
+//! Outbound counters start at a random value in `[1, 2^28]`. Secure unicast
+//! session counters must never roll over (the session has to be re-established
+//! instead), while the global unencrypted and group counters wrap around.
+//!
+//! Received counters are tracked in a [CounterWindow]: the largest counter
+//! seen so far plus a bitmap of the [MSG_COUNTER_WINDOW_SIZE] counters before it.
+//! Checking and committing are separate steps so that only messages that
+//! passed decryption and integrity checks advance the window.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use matter_types::NodeId;
+use rand::Rng;
+
+#[cfg(test)]
+use mock_instant::Instant;
+
+#[cfg(not(test))]
+use std::time::Instant;
+
+use crate::packet;
+
+/// Number of counters before the maximum one that are tracked for duplicates.
+pub const MSG_COUNTER_WINDOW_SIZE: u32 = 32;
+
+/// Upper bound (inclusive) of randomly initialized counters.
+const MAX_INITIAL_COUNTER: u32 = 1 << 28;
+
+/// Counters are compared modulo 2^32 when rollover is allowed: values up to
+/// half the range ahead of the maximum are considered newer.
+const ROLLOVER_HALF_RANGE: u32 = 1 << 31;
+
+/// Which counter rules apply to a message stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CounterKind {
+    /// Secure unicast sessions: no rollover, anything behind the window is rejected.
+    SecureUnicast,
+
+    /// Group messages: rollover allowed, anything behind the window is rejected.
+    Group,
+
+    /// Unencrypted messages: rollover allowed, messages behind the window
+    /// reset the window (e.g. the peer rebooted).
+    Unencrypted,
+}
+
+impl CounterKind {
+    fn allows_rollover(&self) -> bool {
+        !matches!(self, CounterKind::SecureUnicast)
+    }
+}
+
+/// Generates and advances the counter used for outgoing messages.
+///
+/// # Example
+///
+/// ```
+/// use matter_packets::counters::{CounterKind, OutboundCounter};
+///
+/// let mut counter = OutboundCounter::with_value(CounterKind::SecureUnicast, u32::MAX - 1);
+/// assert_eq!(counter.advance().unwrap(), u32::MAX - 1);
+/// assert_eq!(counter.advance().unwrap(), u32::MAX);
+/// assert!(counter.advance().is_err()); // session must be re-established
+///
+/// let mut counter = OutboundCounter::with_value(CounterKind::Unencrypted, u32::MAX);
+/// assert_eq!(counter.advance().unwrap(), u32::MAX);
+/// assert_eq!(counter.advance().unwrap(), 0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct OutboundCounter {
+    kind: CounterKind,
+    next: Option<u32>,
+}
+
+impl OutboundCounter {
+    /// Creates a counter starting at a random value in `[1, 2^28]`.
+    pub fn new(kind: CounterKind) -> Self {
+        Self::with_value(kind, rand::thread_rng().gen_range(1..=MAX_INITIAL_COUNTER))
+    }
+
+    /// Creates a counter whose next value is `value`.
+    pub fn with_value(kind: CounterKind, value: u32) -> Self {
+        OutboundCounter {
+            kind,
+            next: Some(value),
+        }
+    }
+
+    /// Returns the counter to use for the next message and advances.
+    ///
+    /// Fails once a counter that does not allow rollover is exhausted.
+    pub fn advance(&mut self) -> Result<u32> {
+        let value = self
+            .next
+            .ok_or_else(|| anyhow!("Message counter exhausted"))?;
+
+        self.next = match value.checked_add(1) {
+            Some(next) => Some(next),
+            None if self.kind.allows_rollover() => Some(0),
+            None => None,
+        };
+
+        Ok(value)
+    }
+
+    /// The value the next message will use, if any.
+    pub fn peek(&self) -> Option<u32> {
+        self.next
+    }
+}
+
+/// Result of checking a received message counter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CounterCheck {
+    /// Counter was not seen before, message may be processed.
+    New,
+
+    /// Counter was already received. The message must not be processed again,
+    /// though reliable messages should still be acknowledged.
+    Duplicate,
+
+    /// Counter is too old to be tracked by the window and must be dropped.
+    OutOfWindow,
+}
+
+/// Receive-side sliding window of message counters for one peer.
+///
+/// A new window trusts the first counter it commits.
+///
+/// # Example
+///
+/// ```
+/// use matter_packets::counters::{CounterCheck, CounterKind, CounterWindow};
+///
+/// let mut window = CounterWindow::new(CounterKind::SecureUnicast);
+/// assert_eq!(window.accept(100), CounterCheck::New);
+/// assert_eq!(window.accept(100), CounterCheck::Duplicate);
+/// assert_eq!(window.accept(90), CounterCheck::New);  // late but inside the window
+/// assert_eq!(window.accept(90), CounterCheck::Duplicate);
+/// assert_eq!(window.accept(50), CounterCheck::OutOfWindow);
+/// ```
+#[derive(Debug, Clone)]
+pub struct CounterWindow {
+    kind: CounterKind,
+
+    /// Largest counter received and a bitmap where bit `n` is set if
+    /// `max - n - 1` was received.
+    state: Option<(u32, u32)>,
+}
+
+impl CounterWindow {
+    pub fn new(kind: CounterKind) -> Self {
+        CounterWindow { kind, state: None }
+    }
+
+    /// Creates a window that treats `max_counter` as already received.
+    pub fn synchronized(kind: CounterKind, max_counter: u32) -> Self {
+        CounterWindow {
+            kind,
+            state: Some((max_counter, 0)),
+        }
+    }
+
+    /// Largest counter received so far.
+    pub fn max_counter(&self) -> Option<u32> {
+        self.state.map(|(max, _)| max)
+    }
+
+    /// Position of `counter` relative to the current maximum: `Ok(n)` for a
+    /// counter `n` ahead, `Err(n)` for a counter `n` behind (0 is the maximum).
+    fn position(&self, max: u32, counter: u32) -> core::result::Result<u32, u32> {
+        if self.kind.allows_rollover() {
+            let ahead = counter.wrapping_sub(max);
+            if ahead != 0 && ahead < ROLLOVER_HALF_RANGE {
+                Ok(ahead)
+            } else {
+                Err(max.wrapping_sub(counter))
+            }
+        } else if counter > max {
+            Ok(counter - max)
+        } else {
+            Err(max - counter)
+        }
+    }
+
+    /// Checks a received counter without modifying the window.
+    pub fn check(&self, counter: u32) -> CounterCheck {
+        let Some((max, bitmap)) = self.state else {
+            return CounterCheck::New;
+        };
+
+        match self.position(max, counter) {
+            Ok(_) => CounterCheck::New,
+            Err(0) => CounterCheck::Duplicate,
+            Err(behind) if behind <= MSG_COUNTER_WINDOW_SIZE => {
+                if bitmap & (1 << (behind - 1)) != 0 {
+                    CounterCheck::Duplicate
+                } else {
+                    CounterCheck::New
+                }
+            }
+            Err(_) if self.kind == CounterKind::Unencrypted => CounterCheck::New,
+            Err(_) => CounterCheck::OutOfWindow,
+        }
+    }
+
+    /// Records a counter as received. Should only be called for counters
+    /// that [CounterWindow::check] reported as [CounterCheck::New].
+    pub fn commit(&mut self, counter: u32) {
+        let Some((max, bitmap)) = self.state else {
+            self.state = Some((counter, 0));
+            return;
+        };
+
+        self.state = Some(match self.position(max, counter) {
+            Ok(ahead) => {
+                // the old maximum moves into the bitmap at position `ahead - 1`
+                let shifted = if ahead > MSG_COUNTER_WINDOW_SIZE {
+                    0
+                } else {
+                    ((bitmap as u64) << ahead) | (1u64 << (ahead - 1))
+                };
+                (counter, shifted as u32)
+            }
+            Err(behind) if behind <= MSG_COUNTER_WINDOW_SIZE => (max, bitmap | (1 << (behind - 1))),
+            Err(_) if self.kind == CounterKind::Unencrypted => (counter, 0),
+            Err(_) => (max, bitmap),
+        });
+    }
+
+    /// Checks a counter and records it if it is new.
+    pub fn accept(&mut self, counter: u32) -> CounterCheck {
+        let result = self.check(counter);
+        if result == CounterCheck::New {
+            self.commit(counter);
+        }
+        result
+    }
+}
+
+struct GroupPeer {
+    window: CounterWindow,
+    last_seen: Instant,
+}
+
+/// Counter windows for group message sources, keyed by source node id.
+///
+/// Group peers are trusted on first use. The table holds at most `capacity`
+/// peers, evicting the least recently seen one when full, and forgets peers
+/// not heard from within `idle_timeout`. Control and data messages use
+/// independent counters, so a table should be kept for each.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use matter_packets::counters::{CounterCheck, GroupPeerTable};
+/// use matter_packets::packet::{HeaderBuilder, MessageDestination, SecurityFlags};
+/// use matter_types::{GroupId, NodeId};
+///
+/// let mut table = GroupPeerTable::new(16, Duration::from_secs(600));
+/// let header = HeaderBuilder::default()
+///     .session_id(0x1234)
+///     .flags(SecurityFlags::SESSION_TYPE_BIT1)
+///     .counter(77)
+///     .source(Some(NodeId(5)))
+///     .destination(MessageDestination::Group(GroupId(1)))
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!(table.accept(&header).unwrap(), CounterCheck::New);
+/// assert_eq!(table.accept(&header).unwrap(), CounterCheck::Duplicate);
+/// ```
+pub struct GroupPeerTable {
+    peers: HashMap<NodeId, GroupPeer>,
+    capacity: usize,
+    idle_timeout: Duration,
+}
+
+impl GroupPeerTable {
+    pub fn new(capacity: usize, idle_timeout: Duration) -> Self {
+        GroupPeerTable {
+            peers: HashMap::new(),
+            capacity,
+            idle_timeout,
+        }
+    }
+
+    fn source(header: &packet::Header) -> Result<NodeId> {
+        header
+            .source
+            .ok_or_else(|| anyhow!("Group messages must contain a source node id"))
+    }
+
+    fn active_peer(&self, source: &NodeId) -> Option<&GroupPeer> {
+        self.peers
+            .get(source)
+            .filter(|peer| peer.last_seen.elapsed() <= self.idle_timeout)
+    }
+
+    /// Checks the counter of a group message header without recording it.
+    pub fn check(&self, header: &packet::Header) -> Result<CounterCheck> {
+        let source = Self::source(header)?;
+        Ok(match self.active_peer(&source) {
+            Some(peer) => peer.window.check(header.counter),
+            None => CounterCheck::New,
+        })
+    }
+
+    /// Records the counter of a group message that was successfully processed.
+    pub fn commit(&mut self, header: &packet::Header) -> Result<()> {
+        let source = Self::source(header)?;
+
+        if self.active_peer(&source).is_none() {
+            self.peers.remove(&source);
+            self.evict();
+        }
+
+        let peer = self.peers.entry(source).or_insert_with(|| GroupPeer {
+            window: CounterWindow::new(CounterKind::Group),
+            last_seen: Instant::now(),
+        });
+        peer.window.commit(header.counter);
+        peer.last_seen = Instant::now();
+
+        Ok(())
+    }
+
+    /// Checks a group message counter and records it if it is new.
+    pub fn accept(&mut self, header: &packet::Header) -> Result<CounterCheck> {
+        let result = self.check(header)?;
+        if result == CounterCheck::New {
+            self.commit(header)?;
+        }
+        Ok(result)
+    }
+
+    /// Number of tracked peers, including ones that may have timed out.
+    pub fn len(&self) -> usize {
+        self.peers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.peers.is_empty()
+    }
+
+    /// Makes room for one more peer: drops timed out peers and, if still
+    /// full, the least recently seen one.
+    fn evict(&mut self) {
+        let idle_timeout = self.idle_timeout;
+        self.peers
+            .retain(|_, peer| peer.last_seen.elapsed() <= idle_timeout);
+
+        if self.peers.len() >= self.capacity {
+            let oldest = self
+                .peers
+                .iter()
+                .min_by_key(|(_, peer)| peer.last_seen)
+                .map(|(id, _)| *id);
+            if let Some(id) = oldest {
+                self.peers.remove(&id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet::{HeaderBuilder, MessageDestination, SecurityFlags};
+    use matter_types::GroupId;
+    use mock_instant::MockClock;
+
+    fn group_header(source: u64, counter: u32) -> packet::Header {
+        HeaderBuilder::default()
+            .session_id(0x1234)
+            .flags(SecurityFlags::SESSION_TYPE_BIT1)
+            .counter(counter)
+            .source(Some(NodeId(source)))
+            .destination(MessageDestination::Group(GroupId(1)))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn random_initial_counter() {
+        for _ in 0..100 {
+            let value = OutboundCounter::new(CounterKind::SecureUnicast)
+                .peek()
+                .unwrap();
+            assert!((1..=MAX_INITIAL_COUNTER).contains(&value));
+        }
+    }
+
+    #[test]
+    fn window_edges() {
+        let mut window = CounterWindow::synchronized(CounterKind::SecureUnicast, 1000);
+
+        // exactly 32 behind is still tracked, 33 is not
+        assert_eq!(window.check(1000 - 32), CounterCheck::New);
+        assert_eq!(window.check(1000 - 33), CounterCheck::OutOfWindow);
+
+        assert_eq!(window.accept(968), CounterCheck::New);
+        assert_eq!(window.accept(968), CounterCheck::Duplicate);
+
+        // moving ahead by one keeps the old maximum as a duplicate
+        assert_eq!(window.accept(1001), CounterCheck::New);
+        assert_eq!(window.check(1000), CounterCheck::Duplicate);
+        assert_eq!(window.check(969), CounterCheck::New);
+        assert_eq!(window.check(968), CounterCheck::OutOfWindow);
+
+        // jumping far ahead clears the bitmap except the old maximum if in range
+        assert_eq!(window.accept(1033), CounterCheck::New);
+        assert_eq!(window.check(1001), CounterCheck::Duplicate);
+        assert_eq!(window.check(1002), CounterCheck::New);
+        assert_eq!(window.check(1000), CounterCheck::OutOfWindow);
+
+        assert_eq!(window.accept(5000), CounterCheck::New);
+        assert_eq!(window.check(4990), CounterCheck::New);
+        assert_eq!(window.max_counter(), Some(5000));
+    }
+
+    #[test]
+    fn unicast_does_not_roll_over() {
+        let mut window = CounterWindow::synchronized(CounterKind::SecureUnicast, u32::MAX);
+        assert_eq!(window.accept(0), CounterCheck::OutOfWindow);
+        assert_eq!(window.accept(u32::MAX - 3), CounterCheck::New);
+    }
+
+    #[test]
+    fn group_rolls_over() {
+        let mut window = CounterWindow::synchronized(CounterKind::Group, u32::MAX - 1);
+        assert_eq!(window.accept(2), CounterCheck::New);
+        assert_eq!(window.max_counter(), Some(2));
+        assert_eq!(window.check(u32::MAX - 1), CounterCheck::Duplicate);
+        assert_eq!(window.accept(u32::MAX), CounterCheck::New);
+        assert_eq!(window.accept(u32::MAX), CounterCheck::Duplicate);
+        assert_eq!(window.check(u32::MAX - 40), CounterCheck::OutOfWindow);
+
+        // more than half the range ahead counts as behind
+        assert_eq!(window.check(2 + (1 << 31) + 1), CounterCheck::OutOfWindow);
+    }
+
+    #[test]
+    fn unencrypted_resets_on_old_counters() {
+        let mut window = CounterWindow::synchronized(CounterKind::Unencrypted, 1000);
+        assert_eq!(window.accept(10), CounterCheck::New);
+        assert_eq!(window.max_counter(), Some(10));
+        assert_eq!(window.accept(10), CounterCheck::Duplicate);
+        assert_eq!(window.accept(1000), CounterCheck::New);
+    }
+
+    #[test]
+    fn check_does_not_modify() {
+        let window = CounterWindow::synchronized(CounterKind::SecureUnicast, 10);
+        assert_eq!(window.check(11), CounterCheck::New);
+        assert_eq!(window.check(11), CounterCheck::New);
+        assert_eq!(window.max_counter(), Some(10));
+    }
+
+    #[test]
+    fn group_peers_are_tracked_independently() {
+        let mut table = GroupPeerTable::new(4, Duration::from_secs(60));
+
+        assert_eq!(
+            table.accept(&group_header(1, 100)).unwrap(),
+            CounterCheck::New
+        );
+        assert_eq!(
+            table.accept(&group_header(2, 100)).unwrap(),
+            CounterCheck::New
+        );
+        assert_eq!(
+            table.accept(&group_header(1, 100)).unwrap(),
+            CounterCheck::Duplicate
+        );
+        assert_eq!(
+            table.accept(&group_header(1, 50)).unwrap(),
+            CounterCheck::OutOfWindow
+        );
+        assert_eq!(table.len(), 2);
+
+        let mut header = group_header(1, 1);
+        header.source = None;
+        assert!(table.accept(&header).is_err());
+    }
+
+    #[test]
+    fn group_peers_expire_and_are_evicted() {
+        let mut table = GroupPeerTable::new(2, Duration::from_secs(60));
+
+        table.accept(&group_header(1, 100)).unwrap();
+        MockClock::advance(Duration::from_secs(10));
+        table.accept(&group_header(2, 100)).unwrap();
+        MockClock::advance(Duration::from_secs(10));
+
+        // table is full: the least recently seen peer (1) is evicted
+        table.accept(&group_header(3, 100)).unwrap();
+        assert_eq!(table.len(), 2);
+        assert_eq!(
+            table.accept(&group_header(1, 100)).unwrap(),
+            CounterCheck::New
+        );
+        assert_eq!(
+            table.check(&group_header(3, 100)).unwrap(),
+            CounterCheck::Duplicate
+        );
+
+        // after the idle timeout, peers are trusted again on first use
+        MockClock::advance(Duration::from_secs(61));
+        assert_eq!(
+            table.check(&group_header(3, 100)).unwrap(),
+            CounterCheck::New
+        );
+        assert_eq!(
+            table.accept(&group_header(3, 20)).unwrap(),
+            CounterCheck::New
+        );
+        assert_eq!(table.len(), 1);
+    }
+}
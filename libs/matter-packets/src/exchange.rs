@@ -0,0 +1,675 @@
+//! Exchange management: conversations of request/response messages.
+//!
+//! Every message belongs to an exchange identified by the session it is sent
+//! on, its [ExchangeId] and whether this node initiated it. The
+//! [ExchangeManager] allocates ids for exchanges this node initiates, routes
+//! received messages to open exchanges and hands unsolicited messages to the
+//! [ProtocolHandler] registered for their protocol.
+//!
+//! The manager also owns the [MrpEngine] of each session so that acks are
+//! piggybacked on outgoing messages and exchanges are only dropped once their
+//! reliable messages were acknowledged.
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use matter_types::ExchangeId;
+use rand::Rng;
+
+use crate::mrp::{ExchangeKey, MrpAction, MrpConfig, MrpEngine};
+use crate::payload::{self, ExchangeFlags, ProtocolInfo, ProtocolOpCode};
+
+/// Identifies a session known to this node.
+///
+/// Handles are opaque to the exchange layer and are assigned by whatever
+/// keeps track of sessions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SessionHandle(pub u32);
+
+/// Identifies an exchange across all sessions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExchangeHandle {
+    pub session: SessionHandle,
+    pub exchange: ExchangeKey,
+}
+
+/// A protocol as used for handler registration: vendor id (0 for protocols
+/// defined by the specification) and protocol id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ProtocolId {
+    pub vendor_id: u16,
+    pub protocol: u16,
+}
+
+impl ProtocolId {
+    pub const SECURE_CHANNEL: ProtocolId = ProtocolId::standard(0);
+    pub const INTERACTION_MODEL: ProtocolId = ProtocolId::standard(1);
+    pub const BDX: ProtocolId = ProtocolId::standard(2);
+    pub const USER_DIRECTED_COMMISSIONING: ProtocolId = ProtocolId::standard(3);
+
+    pub const fn standard(protocol: u16) -> Self {
+        ProtocolId {
+            vendor_id: 0,
+            protocol,
+        }
+    }
+
+    /// Protocol an opcode belongs to.
+    ///
+    /// ```
+    /// use matter_packets::exchange::ProtocolId;
+    /// use matter_packets::payload::{ProtocolOpCode, SecureChannelOpcode};
+    ///
+    /// assert_eq!(
+    ///     ProtocolId::of(&ProtocolOpCode::SecureChannel(SecureChannelOpcode::PasePake1)),
+    ///     ProtocolId::SECURE_CHANNEL
+    /// );
+    /// assert_eq!(
+    ///     ProtocolId::of(&ProtocolOpCode::Vendor { vendor_id: 0xfff1, protocol: 7, opcode: 1 }),
+    ///     ProtocolId { vendor_id: 0xfff1, protocol: 7 }
+    /// );
+    /// ```
+    pub fn of(opcode: &ProtocolOpCode) -> Self {
+        match opcode {
+            ProtocolOpCode::Vendor {
+                vendor_id,
+                protocol,
+                ..
+            } => ProtocolId {
+                vendor_id: *vendor_id,
+                protocol: *protocol,
+            },
+            other => ProtocolId::standard(other.protocol_id()),
+        }
+    }
+}
+
+/// Handles messages that start a new exchange for a protocol.
+pub trait ProtocolHandler {
+    /// Called for a message from an initiator that does not belong to an
+    /// open exchange. The exchange is already open and may be used to reply.
+    ///
+    /// Returning an error closes the exchange.
+    fn on_unsolicited(
+        &mut self,
+        manager: &mut ExchangeManager,
+        exchange: ExchangeHandle,
+        header: &payload::Header,
+        payload: &[u8],
+    ) -> Result<()>;
+}
+
+/// How a received message was routed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Received {
+    /// Message belongs to an open exchange.
+    Exchange(ExchangeHandle),
+
+    /// Message opened a new exchange and was handed to its protocol handler.
+    Unsolicited(ExchangeHandle),
+
+    /// Message was already received before and must not be processed again.
+    Duplicate,
+
+    /// Message does not belong to any exchange (e.g. a late acknowledgement)
+    /// or no handler is registered for its protocol.
+    Unhandled,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExchangeState {
+    Open,
+
+    /// Closed by the application, waiting for outstanding acknowledgements.
+    Closing,
+}
+
+#[derive(Debug)]
+struct Exchange {
+    protocol: ProtocolId,
+    state: ExchangeState,
+}
+
+/// Tracks all exchanges of a node, see the [module documentation](self).
+pub struct ExchangeManager {
+    mrp_config: MrpConfig,
+    next_exchange_id: u16,
+    exchanges: HashMap<ExchangeHandle, Exchange>,
+    sessions: HashMap<SessionHandle, MrpEngine>,
+    handlers: HashMap<ProtocolId, Box<dyn ProtocolHandler>>,
+}
+
+impl ExchangeManager {
+    /// Creates a manager. Exchange ids are allocated starting at a random value.
+    pub fn new(mrp_config: MrpConfig) -> Self {
+        Self::with_initial_exchange_id(mrp_config, rand::thread_rng().gen())
+    }
+
+    pub fn with_initial_exchange_id(mrp_config: MrpConfig, exchange_id: u16) -> Self {
+        ExchangeManager {
+            mrp_config,
+            next_exchange_id: exchange_id,
+            exchanges: HashMap::new(),
+            sessions: HashMap::new(),
+            handlers: HashMap::new(),
+        }
+    }
+
+    /// Registers the handler for unsolicited messages of a protocol.
+    pub fn register_handler(
+        &mut self,
+        protocol: ProtocolId,
+        handler: Box<dyn ProtocolHandler>,
+    ) -> Result<()> {
+        if self.handlers.contains_key(&protocol) {
+            return Err(anyhow!(
+                "A handler for {:?} is already registered",
+                protocol
+            ));
+        }
+        self.handlers.insert(protocol, handler);
+        Ok(())
+    }
+
+    pub fn unregister_handler(&mut self, protocol: ProtocolId) -> Option<Box<dyn ProtocolHandler>> {
+        self.handlers.remove(&protocol)
+    }
+
+    /// Sets the retransmission parameters of a session's peer.
+    pub fn set_session_mrp_config(&mut self, session: SessionHandle, config: MrpConfig) {
+        self.mrp(session).set_config(config);
+    }
+
+    fn mrp(&mut self, session: SessionHandle) -> &mut MrpEngine {
+        let config = self.mrp_config;
+        self.sessions
+            .entry(session)
+            .or_insert_with(|| MrpEngine::new(config))
+    }
+
+    /// Opens a new exchange initiated by this node.
+    ///
+    /// Fails if every exchange id is in use by an initiated exchange of the
+    /// session.
+    pub fn initiate(
+        &mut self,
+        session: SessionHandle,
+        protocol: ProtocolId,
+    ) -> Result<ExchangeHandle> {
+        for _ in 0..=u16::MAX {
+            let handle = ExchangeHandle {
+                session,
+                exchange: ExchangeKey {
+                    id: ExchangeId(self.next_exchange_id),
+                    initiator: true,
+                },
+            };
+            self.next_exchange_id = self.next_exchange_id.wrapping_add(1);
+
+            if let Entry::Vacant(entry) = self.exchanges.entry(handle) {
+                entry.insert(Exchange {
+                    protocol,
+                    state: ExchangeState::Open,
+                });
+                // every exchange's session has an MRP engine, even before anything is sent
+                self.mrp(session);
+                return Ok(handle);
+            }
+        }
+
+        Err(anyhow!("No free exchange id on session {:?}", session))
+    }
+
+    /// Returns true if the exchange is open (i.e. not closed or closing).
+    pub fn is_open(&self, handle: ExchangeHandle) -> bool {
+        self.exchanges
+            .get(&handle)
+            .is_some_and(|exchange| exchange.state == ExchangeState::Open)
+    }
+
+    /// Number of tracked exchanges, including closing ones.
+    pub fn len(&self) -> usize {
+        self.exchanges.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.exchanges.is_empty()
+    }
+
+    /// Builds the payload header for a message sent on an open exchange,
+    /// piggybacking any pending acknowledgement.
+    pub fn prepare_send(
+        &mut self,
+        handle: ExchangeHandle,
+        opcode: ProtocolOpCode,
+        reliable: bool,
+    ) -> Result<payload::Header> {
+        let exchange = self
+            .exchanges
+            .get(&handle)
+            .filter(|exchange| exchange.state == ExchangeState::Open)
+            .ok_or_else(|| anyhow!("Exchange {:?} is not open", handle))?;
+
+        if ProtocolId::of(&opcode) != exchange.protocol {
+            return Err(anyhow!(
+                "Cannot send {:?} on an exchange for {:?}",
+                opcode,
+                exchange.protocol
+            ));
+        }
+
+        let mut flags = ExchangeFlags::empty();
+        flags.set(ExchangeFlags::INITIATOR, handle.exchange.initiator);
+        flags.set(ExchangeFlags::RELIABILITY, reliable);
+
+        let mut header = payload::Header {
+            flags,
+            protocol_opcode: opcode,
+            exchange: handle.exchange.id,
+            ack_counter: None,
+        };
+        self.mrp(handle.session)
+            .prepare_send(handle.exchange, &mut header);
+
+        Ok(header)
+    }
+
+    /// Records a reliable message that was sent, so it gets retransmitted
+    /// until acknowledged.
+    pub fn sent_reliable(
+        &mut self,
+        handle: ExchangeHandle,
+        counter: u32,
+        frame: Vec<u8>,
+    ) -> Result<()> {
+        self.mrp(handle.session)
+            .sent_reliable(handle.exchange, counter, frame)
+    }
+
+    /// Routes a received (decrypted) message.
+    ///
+    /// `duplicate` is the result of message counter checking: duplicates are
+    /// still acknowledged but not dispatched.
+    pub fn received(
+        &mut self,
+        session: SessionHandle,
+        counter: u32,
+        header: &payload::Header,
+        payload: &[u8],
+        duplicate: bool,
+    ) -> Result<Received> {
+        let handle = ExchangeHandle {
+            session,
+            exchange: ExchangeKey {
+                id: header.exchange,
+                // roles are reversed: the peer set INITIATOR if we did not initiate
+                initiator: !header.flags.contains(ExchangeFlags::INITIATOR),
+            },
+        };
+
+        self.mrp(session)
+            .received(handle.exchange, counter, header, duplicate);
+
+        if duplicate {
+            return Ok(Received::Duplicate);
+        }
+
+        if self.is_open(handle) {
+            return Ok(Received::Exchange(handle));
+        }
+
+        let unsolicited = header.flags.contains(ExchangeFlags::INITIATOR)
+            && !self.exchanges.contains_key(&handle)
+            && header.protocol_opcode
+                != ProtocolOpCode::SecureChannel(payload::SecureChannelOpcode::MrpStandaloneAck);
+        if !unsolicited {
+            return Ok(Received::Unhandled);
+        }
+
+        let protocol = ProtocolId::of(&header.protocol_opcode);
+        let Some(mut handler) = self.handlers.remove(&protocol) else {
+            return Ok(Received::Unhandled);
+        };
+
+        self.exchanges.insert(
+            handle,
+            Exchange {
+                protocol,
+                state: ExchangeState::Open,
+            },
+        );
+
+        let result = handler.on_unsolicited(self, handle, header, payload);
+        self.handlers.entry(protocol).or_insert(handler);
+
+        match result {
+            Ok(()) => Ok(Received::Unsolicited(handle)),
+            Err(err) => {
+                self.close(handle);
+                Err(err)
+            }
+        }
+    }
+
+    /// Closes an exchange. Pending acknowledgements are sent right away and
+    /// the exchange is dropped once its reliable messages are acknowledged
+    /// or failed.
+    pub fn close(&mut self, handle: ExchangeHandle) {
+        let Some(exchange) = self.exchanges.get_mut(&handle) else {
+            return;
+        };
+        exchange.state = ExchangeState::Closing;
+
+        let mrp = self
+            .sessions
+            .get_mut(&handle.session)
+            .expect("exchanges always have an MRP engine");
+        mrp.flush_ack(handle.exchange);
+        if !mrp.awaiting_ack(handle.exchange) {
+            self.exchanges.remove(&handle);
+        }
+    }
+
+    /// Drops all exchanges and reliability state of a session, e.g. when the
+    /// session is evicted.
+    pub fn remove_session(&mut self, session: SessionHandle) {
+        self.exchanges.retain(|handle, _| handle.session != session);
+        self.sessions.remove(&session);
+    }
+
+    /// Collects due retransmissions and acknowledgements of all sessions.
+    ///
+    /// Exchanges whose messages failed are closed.
+    pub fn poll(&mut self) -> Vec<(SessionHandle, MrpAction)> {
+        let mut actions = Vec::new();
+        for (session, mrp) in self.sessions.iter_mut() {
+            actions.extend(mrp.poll().into_iter().map(|action| (*session, action)));
+        }
+
+        for (session, action) in actions.iter() {
+            if let MrpAction::Failed { exchange, .. } = action {
+                self.exchanges.remove(&ExchangeHandle {
+                    session: *session,
+                    exchange: *exchange,
+                });
+            }
+        }
+
+        // closing exchanges are done once nothing awaits acknowledgement
+        let sessions = &self.sessions;
+        self.exchanges.retain(|handle, exchange| {
+            exchange.state == ExchangeState::Open
+                || sessions
+                    .get(&handle.session)
+                    .is_some_and(|mrp| mrp.awaiting_ack(handle.exchange))
+        });
+
+        actions
+    }
+
+    /// Time until [ExchangeManager::poll] has work to do, or `None` if idle.
+    pub fn next_timeout(&self) -> Option<std::time::Duration> {
+        self.sessions
+            .values()
+            .filter_map(|mrp| mrp.next_timeout())
+            .min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payload::{InteractionModelOpcode, SecureChannelOpcode};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    const SESSION: SessionHandle = SessionHandle(1);
+
+    fn peer_header(
+        flags: ExchangeFlags,
+        opcode: ProtocolOpCode,
+        exchange: u16,
+        ack_counter: Option<u32>,
+    ) -> payload::Header {
+        payload::Header {
+            flags,
+            protocol_opcode: opcode,
+            exchange: ExchangeId(exchange),
+            ack_counter,
+        }
+    }
+
+    const READ_REQUEST: ProtocolOpCode =
+        ProtocolOpCode::InteractionModel(InteractionModelOpcode::ReadRequest);
+    const REPORT_DATA: ProtocolOpCode =
+        ProtocolOpCode::InteractionModel(InteractionModelOpcode::ReportData);
+
+    /// Exchange, payload and piggybacked ack of each unsolicited message.
+    type Seen = Rc<RefCell<Vec<(ExchangeHandle, Vec<u8>, Option<u32>)>>>;
+
+    /// Responds to every request with a reliable ReportData and closes.
+    struct Responder {
+        seen: Seen,
+    }
+
+    impl ProtocolHandler for Responder {
+        fn on_unsolicited(
+            &mut self,
+            manager: &mut ExchangeManager,
+            exchange: ExchangeHandle,
+            _header: &payload::Header,
+            payload: &[u8],
+        ) -> Result<()> {
+            if payload.is_empty() {
+                self.seen.borrow_mut().push((exchange, vec![], None));
+                return Err(anyhow!("empty request"));
+            }
+
+            let header = manager.prepare_send(exchange, REPORT_DATA, true)?;
+            assert!(!header.flags.contains(ExchangeFlags::INITIATOR));
+            self.seen
+                .borrow_mut()
+                .push((exchange, payload.to_vec(), header.ack_counter));
+            manager.sent_reliable(exchange, 500, vec![0xaa])?;
+            manager.close(exchange);
+            Ok(())
+        }
+    }
+
+    fn manager_with_responder() -> (ExchangeManager, Seen) {
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let mut manager = ExchangeManager::with_initial_exchange_id(MrpConfig::default(), 100);
+        manager
+            .register_handler(
+                ProtocolId::INTERACTION_MODEL,
+                Box::new(Responder { seen: seen.clone() }),
+            )
+            .unwrap();
+        (manager, seen)
+    }
+
+    #[test]
+    fn initiator_exchanges() {
+        let mut manager = ExchangeManager::with_initial_exchange_id(MrpConfig::default(), u16::MAX);
+        let first = manager
+            .initiate(SESSION, ProtocolId::INTERACTION_MODEL)
+            .unwrap();
+        let second = manager
+            .initiate(SESSION, ProtocolId::INTERACTION_MODEL)
+            .unwrap();
+        assert_eq!(first.exchange.id, ExchangeId(u16::MAX));
+        assert_eq!(second.exchange.id, ExchangeId(0));
+        assert!(first.exchange.initiator);
+
+        let header = manager.prepare_send(first, READ_REQUEST, false).unwrap();
+        assert_eq!(header.flags, ExchangeFlags::INITIATOR);
+        assert_eq!(header.exchange, ExchangeId(u16::MAX));
+
+        // wrong protocol for the exchange
+        assert!(manager
+            .prepare_send(
+                first,
+                ProtocolOpCode::SecureChannel(SecureChannelOpcode::PasePake1),
+                false
+            )
+            .is_err());
+
+        // the response is routed back to the exchange
+        let response = peer_header(ExchangeFlags::empty(), REPORT_DATA, u16::MAX, None);
+        assert_eq!(
+            manager.received(SESSION, 1, &response, &[], false).unwrap(),
+            Received::Exchange(first)
+        );
+
+        // same id on another session or from an initiating peer is a different exchange
+        assert_eq!(
+            manager
+                .received(SessionHandle(2), 1, &response, &[], false)
+                .unwrap(),
+            Received::Unhandled
+        );
+
+        manager.close(first);
+        assert!(!manager.is_open(first));
+        assert!(manager.prepare_send(first, READ_REQUEST, false).is_err());
+        assert_eq!(manager.len(), 1);
+    }
+
+    #[test]
+    fn unsolicited_messages_are_dispatched() {
+        let (mut manager, seen) = manager_with_responder();
+
+        let request = peer_header(
+            ExchangeFlags::INITIATOR | ExchangeFlags::RELIABILITY,
+            READ_REQUEST,
+            7,
+            None,
+        );
+        let result = manager
+            .received(SESSION, 10, &request, &[1, 2], false)
+            .unwrap();
+
+        let Received::Unsolicited(handle) = result else {
+            panic!("unexpected {:?}", result);
+        };
+        assert_eq!(handle.exchange.id, ExchangeId(7));
+        assert!(!handle.exchange.initiator);
+        // the request's ack was piggybacked on the response
+        assert_eq!(seen.borrow().as_slice(), &[(handle, vec![1, 2], Some(10))]);
+
+        // closed by the handler but still awaiting the ack of its response
+        assert!(!manager.is_open(handle));
+        assert_eq!(manager.len(), 1);
+
+        // a retransmitted request is a duplicate, not a new exchange
+        assert_eq!(
+            manager
+                .received(SESSION, 10, &request, &[1, 2], true)
+                .unwrap(),
+            Received::Duplicate
+        );
+        assert_eq!(seen.borrow().len(), 1);
+
+        // the ack for the response completes the exchange
+        let ack = peer_header(
+            ExchangeFlags::INITIATOR | ExchangeFlags::ACKNOWLEDGEMENT,
+            ProtocolOpCode::SecureChannel(SecureChannelOpcode::MrpStandaloneAck),
+            7,
+            Some(500),
+        );
+        assert_eq!(
+            manager.received(SESSION, 11, &ack, &[], false).unwrap(),
+            Received::Unhandled
+        );
+        let actions = manager.poll();
+        assert!(actions
+            .iter()
+            .all(|(_, action)| matches!(action, MrpAction::SendStandaloneAck { .. })));
+        assert!(manager.is_empty());
+    }
+
+    #[test]
+    fn handler_errors_close_the_exchange() {
+        let (mut manager, _) = manager_with_responder();
+        let request = peer_header(ExchangeFlags::INITIATOR, READ_REQUEST, 7, None);
+        assert!(manager.received(SESSION, 1, &request, &[], false).is_err());
+        assert!(manager.is_empty());
+
+        // handler stays registered
+        assert!(manager.received(SESSION, 2, &request, &[1], false).is_ok());
+    }
+
+    #[test]
+    fn unknown_protocols_are_unhandled() {
+        let (mut manager, _) = manager_with_responder();
+        let request = peer_header(
+            ExchangeFlags::INITIATOR,
+            ProtocolOpCode::SecureChannel(SecureChannelOpcode::PbkdfParamRequest),
+            7,
+            None,
+        );
+        assert_eq!(
+            manager.received(SESSION, 1, &request, &[], false).unwrap(),
+            Received::Unhandled
+        );
+        assert!(manager.is_empty());
+
+        assert!(manager
+            .register_handler(
+                ProtocolId::INTERACTION_MODEL,
+                Box::new(Responder {
+                    seen: Default::default()
+                })
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn initiate_fails_when_all_ids_are_used() {
+        let mut manager = ExchangeManager::with_initial_exchange_id(MrpConfig::default(), 7);
+        for _ in 0..=u16::MAX {
+            manager.initiate(SESSION, ProtocolId::BDX).unwrap();
+        }
+        assert!(manager.initiate(SESSION, ProtocolId::BDX).is_err());
+
+        // other sessions have their own ids
+        let handle = manager.initiate(SessionHandle(2), ProtocolId::BDX).unwrap();
+        assert_eq!(handle.exchange.id, ExchangeId(7));
+
+        // closing an exchange makes its id available again
+        let closed = ExchangeHandle {
+            session: SESSION,
+            exchange: ExchangeKey {
+                id: ExchangeId(100),
+                initiator: true,
+            },
+        };
+        manager.close(closed);
+        assert_eq!(manager.initiate(SESSION, ProtocolId::BDX).unwrap(), closed);
+    }
+
+    #[test]
+    fn close_unused_exchange() {
+        let mut manager = ExchangeManager::new(MrpConfig::default());
+        let handle = manager
+            .initiate(SESSION, ProtocolId::INTERACTION_MODEL)
+            .unwrap();
+
+        manager.close(handle);
+        assert!(!manager.is_open(handle));
+        assert!(manager.is_empty());
+    }
+
+    #[test]
+    fn remove_session_drops_exchanges() {
+        let mut manager = ExchangeManager::with_initial_exchange_id(MrpConfig::default(), 1);
+        let handle = manager.initiate(SESSION, ProtocolId::BDX).unwrap();
+        manager.initiate(SessionHandle(2), ProtocolId::BDX).unwrap();
+        manager.sent_reliable(handle, 1, vec![]).unwrap();
+
+        manager.remove_session(SESSION);
+        assert_eq!(manager.len(), 1);
+        assert!(!manager.is_open(handle));
+    }
+}
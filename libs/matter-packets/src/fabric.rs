@@ -0,0 +1,119 @@
+//! Operational credentials of this node on a fabric.
+//!
+//! A [Fabric] holds everything needed to establish CASE sessions with other
+//! nodes of the same fabric: the trusted root, this node's certificate
+//! chain and signing key, and the identity protection key (IPK).
+//!
+//! Certificates are kept in their Matter TLV encoding. Validating a peer's
+//! chain is left to a [CertificateChainValidator] so that session
+//! establishment does not depend on a particular certificate implementation
+//! (see [crate::certificate::OperationalCertificateValidator]).
+
+use anyhow::Result;
+use derive_builder::Builder;
+use hkdf::Hkdf;
+use matter_types::{FabricIndex, NodeId};
+use p256::ecdsa::{SigningKey, VerifyingKey};
+use sha2::Sha256;
+
+use crate::spake2p::POINT_LENGTH;
+
+/// Length of the compressed fabric identifier.
+pub const COMPRESSED_FABRIC_ID_LENGTH: usize = 8;
+
+/// Length of the identity protection key.
+pub const IPK_LENGTH: usize = 16;
+
+/// Uncompressed P-256 public key, as carried in certificates.
+pub type PublicKeyBytes = [u8; POINT_LENGTH];
+
+/// Computes the compressed fabric identifier, used for operational discovery
+/// and to derive operational group keys.
+///
+/// ```
+/// use hex_literal::hex;
+/// use matter_packets::fabric::compressed_fabric_id;
+///
+/// let root_public_key = hex!(
+///     "044a9f42b1ca4840d37292bbc7f6a7e11e22200c976fc900dbc98a7a383a641cb8"
+///     "254a2e56d4e295a847943b4e3897c4a773e930277b4d9fbede8a052686bfacfa"
+/// );
+///
+/// assert_eq!(
+///     compressed_fabric_id(&root_public_key, 0x2906_C908_D115_D362),
+///     hex!("87e1b004e235a130")
+/// );
+/// ```
+pub fn compressed_fabric_id(
+    root_public_key: &PublicKeyBytes,
+    fabric_id: u64,
+) -> [u8; COMPRESSED_FABRIC_ID_LENGTH] {
+    let mut result = [0; COMPRESSED_FABRIC_ID_LENGTH];
+    Hkdf::<Sha256>::new(Some(&fabric_id.to_be_bytes()), &root_public_key[1..])
+        .expand(b"CompressedFabric", &mut result)
+        .expect("valid HKDF output length");
+    result
+}
+
+/// Derives the operational IPK from epoch key 0 of the IPK group key set.
+pub fn operational_ipk(
+    epoch_key: &[u8; IPK_LENGTH],
+    compressed_fabric_id: &[u8; COMPRESSED_FABRIC_ID_LENGTH],
+) -> [u8; IPK_LENGTH] {
+    let mut result = [0; IPK_LENGTH];
+    Hkdf::<Sha256>::new(Some(compressed_fabric_id), epoch_key)
+        .expand(b"GroupKey v1.0", &mut result)
+        .expect("valid HKDF output length");
+    result
+}
+
+/// Operational credentials of this node on one fabric.
+#[derive(Debug, Clone, Builder)]
+pub struct Fabric {
+    pub index: FabricIndex,
+    pub fabric_id: u64,
+    pub node_id: NodeId,
+
+    /// Trusted root certificate (RCAC) of the fabric.
+    pub root_certificate: Vec<u8>,
+
+    /// Public key of [Fabric::root_certificate].
+    pub root_public_key: PublicKeyBytes,
+
+    /// Node operational certificate of this node.
+    pub noc: Vec<u8>,
+
+    /// Intermediate certificate, if the NOC is not signed by the root.
+    #[builder(default)]
+    pub icac: Option<Vec<u8>>,
+
+    /// Operational IPK (see [operational_ipk]).
+    pub ipk: [u8; IPK_LENGTH],
+
+    /// Key matching the public key of [Fabric::noc].
+    pub signing_key: SigningKey,
+}
+
+impl Fabric {
+    pub fn compressed_fabric_id(&self) -> [u8; COMPRESSED_FABRIC_ID_LENGTH] {
+        compressed_fabric_id(&self.root_public_key, self.fabric_id)
+    }
+}
+
+/// Identity of a node, as proven by its operational certificate chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerIdentity {
+    pub node_id: NodeId,
+    pub fabric_id: u64,
+
+    /// Public key of the peer NOC, which signs its session establishment
+    /// messages.
+    pub public_key: VerifyingKey,
+}
+
+/// Validates operational certificate chains received during CASE.
+pub trait CertificateChainValidator {
+    /// Checks that `noc` (through `icac`, if present) chains up to the root
+    /// certificate of `fabric` and returns the identity it certifies.
+    fn validate(&self, fabric: &Fabric, noc: &[u8], icac: Option<&[u8]>) -> Result<PeerIdentity>;
+}
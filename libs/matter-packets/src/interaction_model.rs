@@ -0,0 +1,1013 @@
+//! Payloads of Interaction Model messages.
+//!
+//! Messages are TLV structures made of information blocks (IBs), named after
+//! the specification with an `Ib` suffix. Paths are encoded as TLV lists,
+//! everything else as structures. Attribute values, event data and command
+//! fields depend on the cluster and are kept encoded as [TlvData].
+//!
+//! ```
+//! use matter_packets::interaction_model::{
+//!     AttributePathIb, InteractionModelMessage, ReadRequest,
+//! };
+//!
+//! let request = ReadRequest {
+//!     attribute_requests: Some(vec![AttributePathIb {
+//!         endpoint: Some(1),
+//!         cluster: Some(0x0006),
+//!         attribute: Some(0x0000),
+//!         ..Default::default()
+//!     }]),
+//!     fabric_filtered: true,
+//!     ..Default::default()
+//! };
+//!
+//! let payload = request.to_payload().unwrap();
+//! assert_eq!(ReadRequest::from_payload(&payload).unwrap(), request);
+//! ```
+
+use anyhow::Result;
+use matter_types::NodeId;
+use streaming_iterator::StreamingIterator;
+use tlv_derive::{TlvEncodable, TlvMergeDecodable, TlvSchema};
+use tlv_packed::{
+    skip_element, DecodeEnd, DecodeError, EncodeError, ParserSource, TlvEncodable,
+    TlvMergeDecodable, TlvSchema, TlvWriter, TypeSchema, UnknownElements,
+};
+use tlv_stream::{ContainerType, Parser, Record, TagValue, Value};
+
+use crate::payload::InteractionModelOpcode;
+use crate::status_report::status_codes;
+use crate::tlv::{decode, decode_tlv_list, encode, encode_tlv_list};
+
+/// Interaction Model revision implemented by this crate (Matter 1.0).
+pub const INTERACTION_MODEL_REVISION: u8 = 1;
+
+status_codes! {
+    /// Interaction Model status codes, carried by [StatusIb] and
+    /// [StatusResponse].
+    #[derive(Default)]
+    StatusCode: u8 {
+        #[default]
+        Success = 0x00,
+        Failure = 0x01,
+        InvalidSubscription = 0x7D,
+        UnsupportedAccess = 0x7E,
+        UnsupportedEndpoint = 0x7F,
+        InvalidAction = 0x80,
+        UnsupportedCommand = 0x81,
+        InvalidCommand = 0x85,
+        UnsupportedAttribute = 0x86,
+        ConstraintError = 0x87,
+        UnsupportedWrite = 0x88,
+        ResourceExhausted = 0x89,
+        NotFound = 0x8B,
+        UnreportableAttribute = 0x8C,
+        InvalidDataType = 0x8D,
+        UnsupportedRead = 0x8F,
+        DataVersionMismatch = 0x92,
+        Timeout = 0x94,
+        Busy = 0x9C,
+        UnsupportedCluster = 0xC3,
+        NoUpstreamSubscription = 0xC5,
+        NeedsTimedInteraction = 0xC6,
+        UnsupportedEvent = 0xC7,
+        PathsExhausted = 0xC8,
+        TimedRequestMismatch = 0xC9,
+        FailsafeRequired = 0xCA,
+    }
+}
+
+impl<'a, S> TlvMergeDecodable<'a, S> for StatusCode
+where
+    S: StreamingIterator<Item = Record<'a>>,
+{
+    fn merge_decode(&mut self, source: &mut S) -> Result<DecodeEnd, DecodeError> {
+        let mut code = 0u8;
+        let decoded = code.merge_decode(source)?;
+        *self = code.into();
+        Ok(decoded)
+    }
+}
+
+impl TlvEncodable for StatusCode {
+    fn encode<W: TlvWriter>(&self, tag: TagValue, writer: &mut W) -> Result<(), EncodeError> {
+        u8::from(*self).encode(tag, writer)
+    }
+}
+
+impl TlvSchema for StatusCode {
+    const SCHEMA: TypeSchema = TypeSchema::Unsigned;
+}
+
+/// Revision of the Interaction Model used by the sender of a message.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+pub struct InteractionModelRevision(pub u8);
+
+impl Default for InteractionModelRevision {
+    fn default() -> Self {
+        InteractionModelRevision(INTERACTION_MODEL_REVISION)
+    }
+}
+
+/// A single TLV element kept in encoded form, like attribute values or
+/// command fields.
+///
+/// The element is stored with an anonymous tag and takes the tag of its
+/// field when encoded.
+///
+/// ```
+/// use matter_packets::interaction_model::TlvData;
+///
+/// let data = TlvData::new(&0x1234u16).unwrap();
+/// assert_eq!(data.as_bytes(), [0x05, 0x34, 0x12]);
+/// assert_eq!(data.decode::<u16>().unwrap(), 0x1234);
+/// ```
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TlvData(Vec<u8>);
+
+impl TlvData {
+    pub fn new<T: TlvEncodable>(value: &T) -> Result<Self> {
+        encode(value).map(TlvData)
+    }
+
+    pub fn decode<'a, T>(&'a self) -> Result<T>
+    where
+        T: TlvMergeDecodable<'a, ParserSource<'a>>,
+    {
+        decode(&self.0)
+    }
+
+    /// The encoded element, with an anonymous tag.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Builds an array out of `items`.
+    ///
+    /// ```
+    /// use matter_packets::interaction_model::TlvData;
+    ///
+    /// let items = [TlvData::new(&1u8).unwrap(), TlvData::new(&true).unwrap()];
+    /// let array = TlvData::array(&items);
+    /// assert_eq!(array.as_bytes(), [0x16, 0x04, 0x01, 0x09, 0x18]);
+    /// assert_eq!(array.array_items().unwrap(), items);
+    /// ```
+    pub fn array(items: &[TlvData]) -> Self {
+        let mut data = Vec::new();
+        data.write_record(Record {
+            tag: TagValue::Anonymous,
+            value: Value::ContainerStart(ContainerType::Array),
+        })
+        .expect("Vec writes never fail");
+        for item in items {
+            data.extend_from_slice(&item.0);
+        }
+        data.write_record(Record {
+            tag: TagValue::Anonymous,
+            value: Value::ContainerEnd,
+        })
+        .expect("Vec writes never fail");
+        TlvData(data)
+    }
+
+    /// Splits an array into its items. Returns `None` if the element is not
+    /// an array.
+    pub fn array_items(&self) -> Option<Vec<TlvData>> {
+        let mut records = Parser::new(&self.0);
+        if !matches!(
+            records.next(),
+            Some(Record {
+                value: Value::ContainerStart(ContainerType::Array),
+                ..
+            })
+        ) {
+            return None;
+        }
+
+        let mut items = Vec::new();
+        let mut item = Vec::new();
+        let mut depth = 0usize;
+        for record in records {
+            match record.value {
+                Value::ContainerEnd if depth == 0 => return Some(items),
+                Value::ContainerStart(_) => depth += 1,
+                Value::ContainerEnd => depth -= 1,
+                _ => {}
+            }
+
+            item.write_record(record).ok()?;
+            if depth == 0 {
+                items.push(TlvData(std::mem::take(&mut item)));
+            }
+        }
+
+        // truncated array
+        None
+    }
+}
+
+impl<'a, S> TlvMergeDecodable<'a, S> for TlvData
+where
+    S: StreamingIterator<Item = Record<'a>>,
+{
+    fn merge_decode(&mut self, source: &mut S) -> Result<DecodeEnd, DecodeError> {
+        let mut element = UnknownElements::default();
+        let decoded = element.merge_decode(source)?;
+
+        self.0.clear();
+        for (idx, record) in element.records().enumerate() {
+            let tag = match idx {
+                0 => TagValue::Anonymous,
+                _ => record.tag,
+            };
+            self.0
+                .write_record(Record { tag, ..record })
+                .map_err(|_| DecodeError::Internal)?;
+        }
+        Ok(decoded)
+    }
+}
+
+impl TlvEncodable for TlvData {
+    fn encode<W: TlvWriter>(&self, tag: TagValue, writer: &mut W) -> Result<(), EncodeError> {
+        for (idx, record) in Parser::new(&self.0).enumerate() {
+            let tag = match idx {
+                0 => tag,
+                _ => record.tag,
+            };
+            writer.write_record(Record { tag, ..record })?;
+        }
+        Ok(())
+    }
+}
+
+impl TlvSchema for TlvData {
+    const SCHEMA: TypeSchema = TypeSchema::Any;
+}
+
+fn context(tag: u8) -> TagValue {
+    TagValue::ContextSpecific { tag: tag.into() }
+}
+
+/// Decodes the current element into an optional field of a path.
+fn decode_optional<'a, S, T>(field: &mut Option<T>, source: &mut S) -> Result<(), DecodeError>
+where
+    S: StreamingIterator<Item = Record<'a>>,
+    T: TlvMergeDecodable<'a, S>,
+{
+    let mut value = T::default();
+    if value.merge_decode(source)? != DecodeEnd::DataConsumed {
+        return Err(DecodeError::InvalidNesting);
+    }
+    *field = Some(value);
+    Ok(())
+}
+
+/// Implements the TLV traits of a path, encoded as a TLV list of optional
+/// context-tagged fields.
+///
+/// Fields listed as `nullable` are `Option<Option<T>>`, with `Some(None)`
+/// encoded as null.
+macro_rules! tlv_path {
+    (
+        $name:ident {
+            $($tag:literal => $field:ident,)*
+            $(nullable $nullable_tag:literal => $nullable:ident,)*
+        }
+    ) => {
+        impl<'a, S> TlvMergeDecodable<'a, S> for $name
+        where
+            S: StreamingIterator<Item = Record<'a>>,
+        {
+            fn merge_decode(&mut self, source: &mut S) -> Result<DecodeEnd, DecodeError> {
+                decode_tlv_list(source, |tag, source| match tag {
+                    $($tag => decode_optional(&mut self.$field, source),)*
+                    $($nullable_tag => decode_optional(&mut self.$nullable, source),)*
+                    _ => skip_element(source).map(|_| ()),
+                })
+            }
+        }
+
+        impl TlvEncodable for $name {
+            fn encode<W: TlvWriter>(&self, tag: TagValue, writer: &mut W) -> Result<(), EncodeError> {
+                encode_tlv_list(tag, writer, |writer| {
+                    $(self.$field.encode(context($tag), writer)?;)*
+                    $(
+                        match self.$nullable {
+                            Some(None) => writer.write_record(Record {
+                                tag: context($nullable_tag),
+                                value: Value::Null,
+                            })?,
+                            Some(Some(ref value)) => value.encode(context($nullable_tag), writer)?,
+                            None => {}
+                        }
+                    )*
+                    Ok(())
+                })
+            }
+        }
+
+        impl TlvSchema for $name {
+            const SCHEMA: TypeSchema = TypeSchema::Any;
+        }
+    };
+}
+
+/// Path to attributes. Absent fields are wildcards.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct AttributePathIb {
+    pub enable_tag_compression: Option<bool>,
+    pub node: Option<NodeId>,
+    pub endpoint: Option<u16>,
+    pub cluster: Option<u32>,
+    pub attribute: Option<u32>,
+
+    /// Index in a list attribute. `Some(None)` (null) appends to the list
+    /// when writing.
+    pub list_index: Option<Option<u16>>,
+}
+
+tlv_path!(AttributePathIb {
+    0 => enable_tag_compression,
+    1 => node,
+    2 => endpoint,
+    3 => cluster,
+    4 => attribute,
+    nullable 5 => list_index,
+});
+
+/// Path to events. Absent fields are wildcards.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct EventPathIb {
+    pub node: Option<NodeId>,
+    pub endpoint: Option<u16>,
+    pub cluster: Option<u32>,
+    pub event: Option<u32>,
+    pub is_urgent: Option<bool>,
+}
+
+tlv_path!(EventPathIb {
+    0 => node,
+    1 => endpoint,
+    2 => cluster,
+    3 => event,
+    4 => is_urgent,
+});
+
+/// Path to a cluster instance.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ClusterPathIb {
+    pub node: Option<NodeId>,
+    pub endpoint: Option<u16>,
+    pub cluster: Option<u32>,
+}
+
+tlv_path!(ClusterPathIb {
+    0 => node,
+    1 => endpoint,
+    2 => cluster,
+});
+
+/// Path to a command. The endpoint is absent for group commands.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CommandPathIb {
+    pub endpoint: Option<u16>,
+    pub cluster: Option<u32>,
+    pub command: Option<u32>,
+}
+
+tlv_path!(CommandPathIb {
+    0 => endpoint,
+    1 => cluster,
+    2 => command,
+});
+
+/// Only report events with an event number of at least `event_min`.
+#[derive(Debug, Default, Clone, PartialEq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+pub struct EventFilterIb {
+    #[tlv(tag = 0)]
+    pub node: Option<NodeId>,
+
+    #[tlv(tag = 1)]
+    pub event_min: u64,
+}
+
+/// Skip attributes of a cluster whose data version is still `data_version`.
+#[derive(Debug, Default, Clone, PartialEq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+pub struct DataVersionFilterIb {
+    #[tlv(tag = 0)]
+    pub path: ClusterPathIb,
+
+    #[tlv(tag = 1)]
+    pub data_version: u32,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+pub struct StatusIb {
+    #[tlv(tag = 0)]
+    pub status: StatusCode,
+
+    /// Cluster specific status code.
+    #[tlv(tag = 1)]
+    pub cluster_status: Option<u8>,
+}
+
+impl StatusIb {
+    pub fn new(status: StatusCode) -> Self {
+        Self {
+            status,
+            cluster_status: None,
+        }
+    }
+}
+
+/// Value of an attribute, as reported or written.
+#[derive(Debug, Default, Clone, PartialEq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+pub struct AttributeDataIb {
+    #[tlv(tag = 0)]
+    pub data_version: Option<u32>,
+
+    #[tlv(tag = 1)]
+    pub path: AttributePathIb,
+
+    #[tlv(tag = 2)]
+    pub data: TlvData,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+pub struct AttributeStatusIb {
+    #[tlv(tag = 0)]
+    pub path: AttributePathIb,
+
+    #[tlv(tag = 1)]
+    pub status: StatusIb,
+}
+
+/// Either the value of an attribute or the reason it cannot be reported.
+/// Exactly one of the fields is present.
+#[derive(Debug, Default, Clone, PartialEq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+pub struct AttributeReportIb {
+    #[tlv(tag = 0)]
+    pub attribute_status: Option<AttributeStatusIb>,
+
+    #[tlv(tag = 1)]
+    pub attribute_data: Option<AttributeDataIb>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+pub struct EventDataIb {
+    #[tlv(tag = 0)]
+    pub path: EventPathIb,
+
+    #[tlv(tag = 1)]
+    pub event_number: u64,
+
+    /// 0 (debug), 1 (info) or 2 (critical).
+    #[tlv(tag = 2)]
+    pub priority: u8,
+
+    /// Milliseconds since the Unix epoch.
+    #[tlv(tag = 3)]
+    pub epoch_timestamp: Option<u64>,
+
+    /// Milliseconds since boot.
+    #[tlv(tag = 4)]
+    pub system_timestamp: Option<u64>,
+
+    /// Milliseconds since the epoch timestamp of the previous event.
+    #[tlv(tag = 5)]
+    pub delta_epoch_timestamp: Option<u64>,
+
+    /// Milliseconds since the system timestamp of the previous event.
+    #[tlv(tag = 6)]
+    pub delta_system_timestamp: Option<u64>,
+
+    #[tlv(tag = 7)]
+    pub data: TlvData,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+pub struct EventStatusIb {
+    #[tlv(tag = 0)]
+    pub path: EventPathIb,
+
+    #[tlv(tag = 1)]
+    pub status: StatusIb,
+}
+
+/// Either an event or the reason events cannot be reported. Exactly one of
+/// the fields is present.
+#[derive(Debug, Default, Clone, PartialEq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+pub struct EventReportIb {
+    #[tlv(tag = 0)]
+    pub event_status: Option<EventStatusIb>,
+
+    #[tlv(tag = 1)]
+    pub event_data: Option<EventDataIb>,
+}
+
+/// A command request or a command response with data.
+#[derive(Debug, Default, Clone, PartialEq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+pub struct CommandDataIb {
+    #[tlv(tag = 0)]
+    pub path: CommandPathIb,
+
+    /// Command fields, generally a structure.
+    #[tlv(tag = 1)]
+    pub fields: Option<TlvData>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+pub struct CommandStatusIb {
+    #[tlv(tag = 0)]
+    pub path: CommandPathIb,
+
+    #[tlv(tag = 1)]
+    pub status: StatusIb,
+}
+
+/// Either a response command or a status. Exactly one of the fields is
+/// present.
+#[derive(Debug, Default, Clone, PartialEq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+pub struct InvokeResponseIb {
+    #[tlv(tag = 0)]
+    pub command: Option<CommandDataIb>,
+
+    #[tlv(tag = 1)]
+    pub status: Option<CommandStatusIb>,
+}
+
+/// Payload of `StatusResponse`.
+#[derive(Debug, Default, Clone, PartialEq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+pub struct StatusResponse {
+    #[tlv(tag = 0)]
+    pub status: StatusCode,
+
+    #[tlv(tag = 255)]
+    pub interaction_model_revision: InteractionModelRevision,
+}
+
+/// Payload of `ReadRequest`.
+#[derive(Debug, Default, Clone, PartialEq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+pub struct ReadRequest {
+    #[tlv(tag = 0)]
+    pub attribute_requests: Option<Vec<AttributePathIb>>,
+
+    #[tlv(tag = 1)]
+    pub event_requests: Option<Vec<EventPathIb>>,
+
+    #[tlv(tag = 2)]
+    pub event_filters: Option<Vec<EventFilterIb>>,
+
+    /// Only report fabric-scoped data of the accessing fabric.
+    #[tlv(tag = 3)]
+    pub fabric_filtered: bool,
+
+    #[tlv(tag = 4)]
+    pub data_version_filters: Option<Vec<DataVersionFilterIb>>,
+
+    #[tlv(tag = 255)]
+    pub interaction_model_revision: InteractionModelRevision,
+}
+
+/// Payload of `SubscribeRequest`.
+#[derive(Debug, Default, Clone, PartialEq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+pub struct SubscribeRequest {
+    /// Keep existing subscriptions of the subscriber instead of replacing
+    /// them.
+    #[tlv(tag = 0)]
+    pub keep_subscriptions: bool,
+
+    /// Minimum interval between reports, in seconds.
+    #[tlv(tag = 1)]
+    pub min_interval_floor: u16,
+
+    /// Maximum interval between reports requested by the subscriber, in
+    /// seconds.
+    #[tlv(tag = 2)]
+    pub max_interval_ceiling: u16,
+
+    #[tlv(tag = 3)]
+    pub attribute_requests: Option<Vec<AttributePathIb>>,
+
+    #[tlv(tag = 4)]
+    pub event_requests: Option<Vec<EventPathIb>>,
+
+    #[tlv(tag = 5)]
+    pub event_filters: Option<Vec<EventFilterIb>>,
+
+    #[tlv(tag = 7)]
+    pub fabric_filtered: bool,
+
+    #[tlv(tag = 8)]
+    pub data_version_filters: Option<Vec<DataVersionFilterIb>>,
+
+    #[tlv(tag = 255)]
+    pub interaction_model_revision: InteractionModelRevision,
+}
+
+/// Payload of `SubscribeResponse`.
+#[derive(Debug, Default, Clone, PartialEq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+pub struct SubscribeResponse {
+    #[tlv(tag = 0)]
+    pub subscription_id: u32,
+
+    /// Maximum interval between reports chosen by the publisher, in seconds.
+    #[tlv(tag = 2)]
+    pub max_interval: u16,
+
+    #[tlv(tag = 255)]
+    pub interaction_model_revision: InteractionModelRevision,
+}
+
+/// Payload of `ReportData`.
+#[derive(Debug, Default, Clone, PartialEq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+pub struct ReportData {
+    /// Set for reports of a subscription.
+    #[tlv(tag = 0)]
+    pub subscription_id: Option<u32>,
+
+    #[tlv(tag = 1)]
+    pub attribute_reports: Option<Vec<AttributeReportIb>>,
+
+    #[tlv(tag = 2)]
+    pub event_reports: Option<Vec<EventReportIb>>,
+
+    /// More reports follow in further `ReportData` messages.
+    #[tlv(tag = 3)]
+    pub more_chunked_messages: Option<bool>,
+
+    /// The receiver does not answer with a `StatusResponse`.
+    #[tlv(tag = 4)]
+    pub suppress_response: Option<bool>,
+
+    #[tlv(tag = 255)]
+    pub interaction_model_revision: InteractionModelRevision,
+}
+
+/// Payload of `WriteRequest`.
+#[derive(Debug, Default, Clone, PartialEq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+pub struct WriteRequest {
+    #[tlv(tag = 0)]
+    pub suppress_response: Option<bool>,
+
+    /// The write is part of a timed interaction.
+    #[tlv(tag = 1)]
+    pub timed_request: bool,
+
+    #[tlv(tag = 2)]
+    pub write_requests: Vec<AttributeDataIb>,
+
+    #[tlv(tag = 3)]
+    pub more_chunked_messages: Option<bool>,
+
+    #[tlv(tag = 255)]
+    pub interaction_model_revision: InteractionModelRevision,
+}
+
+/// Payload of `WriteResponse`.
+#[derive(Debug, Default, Clone, PartialEq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+pub struct WriteResponse {
+    #[tlv(tag = 0)]
+    pub write_responses: Vec<AttributeStatusIb>,
+
+    #[tlv(tag = 255)]
+    pub interaction_model_revision: InteractionModelRevision,
+}
+
+/// Payload of `InvokeRequest`.
+#[derive(Debug, Default, Clone, PartialEq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+pub struct InvokeRequest {
+    #[tlv(tag = 0)]
+    pub suppress_response: bool,
+
+    /// The invoke is part of a timed interaction.
+    #[tlv(tag = 1)]
+    pub timed_request: bool,
+
+    #[tlv(tag = 2)]
+    pub invoke_requests: Vec<CommandDataIb>,
+
+    #[tlv(tag = 255)]
+    pub interaction_model_revision: InteractionModelRevision,
+}
+
+/// Payload of `InvokeResponse`.
+#[derive(Debug, Default, Clone, PartialEq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+pub struct InvokeResponse {
+    #[tlv(tag = 0)]
+    pub suppress_response: bool,
+
+    #[tlv(tag = 1)]
+    pub invoke_responses: Vec<InvokeResponseIb>,
+
+    #[tlv(tag = 2)]
+    pub more_chunked_messages: Option<bool>,
+
+    #[tlv(tag = 255)]
+    pub interaction_model_revision: InteractionModelRevision,
+}
+
+/// Payload of `TimedRequest`.
+#[derive(Debug, Default, Clone, PartialEq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+pub struct TimedRequest {
+    /// Time for the following write or invoke to arrive, in milliseconds.
+    #[tlv(tag = 0)]
+    pub timeout: u16,
+
+    #[tlv(tag = 255)]
+    pub interaction_model_revision: InteractionModelRevision,
+}
+
+/// Payload of an Interaction Model message.
+pub trait InteractionModelMessage:
+    TlvEncodable + for<'a> TlvMergeDecodable<'a, ParserSource<'a>>
+{
+    const OPCODE: InteractionModelOpcode;
+
+    fn to_payload(&self) -> Result<Vec<u8>> {
+        encode(self)
+    }
+
+    fn from_payload(payload: &[u8]) -> Result<Self> {
+        decode(payload)
+    }
+}
+
+macro_rules! interaction_model_messages {
+    ($($name:ident,)*) => {
+        $(
+            impl InteractionModelMessage for $name {
+                const OPCODE: InteractionModelOpcode = InteractionModelOpcode::$name;
+            }
+        )*
+    };
+}
+
+interaction_model_messages!(
+    StatusResponse,
+    ReadRequest,
+    SubscribeRequest,
+    SubscribeResponse,
+    ReportData,
+    WriteRequest,
+    WriteResponse,
+    InvokeRequest,
+    InvokeResponse,
+    TimedRequest,
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tlv_packed::testing::{check_vectors, hex_to_bytes};
+
+    fn attribute_path(endpoint: u16, cluster: u32, attribute: u32) -> AttributePathIb {
+        AttributePathIb {
+            endpoint: Some(endpoint),
+            cluster: Some(cluster),
+            attribute: Some(attribute),
+            ..Default::default()
+        }
+    }
+
+    fn command_path(endpoint: u16, cluster: u32, command: u32) -> CommandPathIb {
+        CommandPathIb {
+            endpoint: Some(endpoint),
+            cluster: Some(cluster),
+            command: Some(command),
+        }
+    }
+
+    fn assert_encoding<T: InteractionModelMessage + PartialEq + std::fmt::Debug>(
+        message: &T,
+        hex: &str,
+    ) {
+        let expected = hex_to_bytes(hex);
+        assert_eq!(message.to_payload().unwrap(), expected);
+        assert_eq!(&T::from_payload(&expected).unwrap(), message);
+    }
+
+    #[test]
+    fn paths() {
+        check_vectors::<AttributePathIb>(&[
+            "17 18",
+            "17 28 00 26 01 01 00 00 00 24 02 01 24 03 06 24 04 00 24 05 03 18",
+            "17 34 05 18",
+        ]);
+        check_vectors::<EventPathIb>(&["17 24 01 00 24 02 28 24 03 00 29 04 18"]);
+        check_vectors::<CommandPathIb>(&["17 24 00 01 24 01 06 24 02 02 18"]);
+        check_vectors::<ClusterPathIb>(&["17 24 01 01 24 02 06 18"]);
+
+        let path: AttributePathIb = decode(&hex_to_bytes("17 34 05 24 04 01 18")).unwrap();
+        assert_eq!(path.list_index, Some(None));
+        assert_eq!(path.attribute, Some(1));
+
+        // unknown tags are skipped, structures are not paths
+        let path: CommandPathIb =
+            decode(&hex_to_bytes("17 24 00 01 35 09 24 01 01 18 24 02 02 18")).unwrap();
+        assert_eq!(path.command, Some(2));
+        assert!(decode::<CommandPathIb>(&hex_to_bytes("15 24 00 01 18")).is_err());
+    }
+
+    #[test]
+    fn information_blocks() {
+        check_vectors::<StatusIb>(&["15 24 00 00 18", "15 24 00 87 24 01 02 18"]);
+        check_vectors::<DataVersionFilterIb>(&["15 37 00 24 01 01 24 02 06 18 24 01 03 18"]);
+        check_vectors::<EventFilterIb>(&["15 24 01 05 18"]);
+        check_vectors::<AttributeDataIb>(&[
+            "15 24 00 12 37 01 24 02 01 24 03 06 24 04 00 18 29 02 18",
+            "15 37 01 18 35 02 24 00 01 36 01 04 02 18 18 18",
+        ]);
+        check_vectors::<AttributeReportIb>(&["15 35 00 37 00 24 02 01 18 35 01 24 00 86 18 18 18"]);
+        check_vectors::<EventDataIb>(&[
+            "15 37 00 24 01 00 24 02 28 24 03 00 18 24 01 01 24 02 02 25 03 00 10 35 07 24 00 01 18 18",
+        ]);
+        check_vectors::<InvokeResponseIb>(&[
+            "15 35 00 37 00 24 00 01 24 01 06 24 02 02 18 18 18",
+            "15 35 01 37 00 24 00 01 24 01 06 24 02 02 18 35 01 24 00 00 18 18 18",
+        ]);
+
+        assert_eq!(StatusCode::from(0x87), StatusCode::ConstraintError);
+        assert_eq!(StatusCode::from(0x42), StatusCode::Other(0x42));
+        assert_eq!(u8::from(StatusCode::NeedsTimedInteraction), 0xC6);
+    }
+
+    #[test]
+    fn tlv_data() {
+        let data = TlvData::new(&vec![1u8, 2]).unwrap();
+        assert_eq!(data.as_bytes(), [0x10, 0x02, 0x01, 0x02]);
+
+        let mut encoded = Vec::new();
+        data.encode(context(2), &mut encoded).unwrap();
+        assert_eq!(encoded, [0x30, 0x02, 0x02, 0x01, 0x02]);
+
+        // decoding keeps nested tags and drops the outer one
+        let attribute: AttributeDataIb = decode(&hex_to_bytes(
+            "15 37 01 18 35 02 24 00 01 36 01 04 02 18 18 18",
+        ))
+        .unwrap();
+        assert_eq!(
+            attribute.data.as_bytes(),
+            hex_to_bytes("15 24 00 01 36 01 04 02 18 18")
+        );
+    }
+
+    #[test]
+    fn messages() {
+        assert_encoding(&StatusResponse::default(), "15 24 00 00 24 FF 01 18");
+        assert_encoding(
+            &TimedRequest {
+                timeout: 500,
+                ..Default::default()
+            },
+            "15 25 00 F4 01 24 FF 01 18",
+        );
+        assert_encoding(
+            &ReadRequest {
+                attribute_requests: Some(vec![attribute_path(1, 6, 0)]),
+                fabric_filtered: true,
+                ..Default::default()
+            },
+            "15 36 00 17 24 02 01 24 03 06 24 04 00 18 18 29 03 24 FF 01 18",
+        );
+        assert_encoding(
+            &SubscribeRequest {
+                max_interval_ceiling: 60,
+                attribute_requests: Some(vec![AttributePathIb::default()]),
+                event_requests: Some(vec![EventPathIb {
+                    endpoint: Some(0),
+                    is_urgent: Some(true),
+                    ..Default::default()
+                }]),
+                event_filters: Some(vec![EventFilterIb {
+                    node: None,
+                    event_min: 5,
+                }]),
+                fabric_filtered: true,
+                data_version_filters: Some(vec![DataVersionFilterIb {
+                    path: ClusterPathIb {
+                        node: None,
+                        endpoint: Some(1),
+                        cluster: Some(6),
+                    },
+                    data_version: 3,
+                }]),
+                ..Default::default()
+            },
+            "15 28 00 24 01 00 24 02 3C 36 03 17 18 18 36 04 17 24 01 00 29 04 18 18 \
+             36 05 15 24 01 05 18 18 29 07 36 08 15 37 00 24 01 01 24 02 06 18 24 01 03 18 18 \
+             24 FF 01 18",
+        );
+        assert_encoding(
+            &SubscribeResponse {
+                subscription_id: 0x1234_5678,
+                max_interval: 60,
+                ..Default::default()
+            },
+            "15 26 00 78 56 34 12 24 02 3C 24 FF 01 18",
+        );
+        assert_encoding(
+            &ReportData {
+                attribute_reports: Some(vec![AttributeReportIb {
+                    attribute_status: None,
+                    attribute_data: Some(AttributeDataIb {
+                        data_version: Some(0x12),
+                        path: attribute_path(1, 6, 0),
+                        data: TlvData::new(&true).unwrap(),
+                    }),
+                }]),
+                suppress_response: Some(true),
+                ..Default::default()
+            },
+            "15 36 01 15 35 01 24 00 12 37 01 24 02 01 24 03 06 24 04 00 18 29 02 18 18 18 \
+             29 04 24 FF 01 18",
+        );
+        assert_encoding(
+            &WriteRequest {
+                suppress_response: Some(false),
+                write_requests: vec![AttributeDataIb {
+                    data_version: None,
+                    path: AttributePathIb {
+                        list_index: Some(None),
+                        ..attribute_path(0, 0x1F, 0)
+                    },
+                    data: TlvData(hex_to_bytes("15 18")),
+                }],
+                ..Default::default()
+            },
+            "15 28 00 28 01 36 02 15 37 01 24 02 00 24 03 1F 24 04 00 34 05 18 35 02 18 18 18 \
+             24 FF 01 18",
+        );
+        assert_encoding(
+            &WriteResponse {
+                write_responses: vec![AttributeStatusIb {
+                    path: attribute_path(0, 0x1F, 0),
+                    status: StatusIb {
+                        status: StatusCode::ConstraintError,
+                        cluster_status: Some(2),
+                    },
+                }],
+                ..Default::default()
+            },
+            "15 36 00 15 37 00 24 02 00 24 03 1F 24 04 00 18 35 01 24 00 87 24 01 02 18 18 18 \
+             24 FF 01 18",
+        );
+        assert_encoding(
+            &InvokeRequest {
+                invoke_requests: vec![CommandDataIb {
+                    path: command_path(1, 6, 2),
+                    fields: Some(TlvData(hex_to_bytes("15 18"))),
+                }],
+                ..Default::default()
+            },
+            "15 28 00 28 01 36 02 15 37 00 24 00 01 24 01 06 24 02 02 18 35 01 18 18 18 \
+             24 FF 01 18",
+        );
+        assert_encoding(
+            &InvokeResponse {
+                invoke_responses: vec![InvokeResponseIb {
+                    command: None,
+                    status: Some(CommandStatusIb {
+                        path: command_path(1, 6, 2),
+                        status: StatusIb::new(StatusCode::Success),
+                    }),
+                }],
+                ..Default::default()
+            },
+            "15 28 00 36 01 15 35 01 37 00 24 00 01 24 01 06 24 02 02 18 35 01 24 00 00 18 18 \
+             18 18 24 FF 01 18",
+        );
+
+        // the revision is optional when decoding
+        let request = TimedRequest::from_payload(&hex_to_bytes("15 25 00 F4 01 18")).unwrap();
+        assert_eq!(
+            request.interaction_model_revision.0,
+            INTERACTION_MODEL_REVISION
+        );
+    }
+
+    #[test]
+    fn message_vectors() {
+        check_vectors::<ReadRequest>(&[
+            "15 36 00 17 24 02 01 24 03 06 24 04 00 18 18 29 03 24 FF 01 18",
+        ]);
+        check_vectors::<ReportData>(&[
+            "15 26 00 78 56 34 12 36 02 15 35 01 37 00 24 01 00 24 02 28 24 03 00 18 24 01 01 \
+             24 02 02 25 03 00 10 35 07 24 00 01 18 18 18 18 29 03 24 FF 01 18",
+        ]);
+        check_vectors::<InvokeRequest>(&[
+            "15 28 00 29 01 36 02 15 37 00 24 00 01 24 01 06 24 02 02 18 35 01 18 18 18 24 FF 0A 18",
+        ]);
+    }
+
+    #[test]
+    fn opcodes() {
+        assert_eq!(ReportData::OPCODE, InteractionModelOpcode::ReportData);
+        assert_eq!(TimedRequest::OPCODE, InteractionModelOpcode::TimedRequest);
+        assert_eq!(
+            InteractionModelOpcode::try_from(StatusResponse::OPCODE as u8),
+            Ok(InteractionModelOpcode::StatusResponse)
+        );
+    }
+}
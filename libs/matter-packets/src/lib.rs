@@ -1,6 +1,22 @@
-#![feature(slice_take)]
-
+pub mod ca;
+pub mod case;
+pub mod certificate;
+pub mod counters;
+pub mod encryption;
+pub mod exchange;
+pub mod fabric;
+pub mod interaction_model;
+pub mod message;
+pub mod mrp;
 pub mod packet;
+pub mod pase;
 pub mod payload;
+pub mod privacy;
 pub mod reader;
+pub mod report;
+pub mod session;
+pub mod spake2p;
+pub mod status_report;
+mod tlv;
 pub mod writer;
+pub mod x509;
@@ -0,0 +1,501 @@
+use anyhow::{anyhow, Result};
+
+use crate::packet::{self, SecurityFlags};
+use crate::payload;
+use crate::writer::LittleEndianWriter;
+
+/// Length of the Message Integrity Check appended to all secured messages.
+pub const MIC_LENGTH: usize = 16;
+
+/// Splits `count` bytes off the front of `buffer`, keeping the original lifetime.
+fn take_bytes<'a>(buffer: &mut &'a [u8], count: usize) -> Result<&'a [u8]> {
+    buffer.split_off(..count).ok_or_else(|| {
+        anyhow!(
+            "Insufficient data: need {} bytes, have {}",
+            count,
+            buffer.len()
+        )
+    })
+}
+
+/// Reads a u16-length prefixed block of bytes.
+fn take_extensions<'a>(buffer: &mut &'a [u8]) -> Result<&'a [u8]> {
+    let length = take_bytes(buffer, 2)?;
+    let length = u16::from_le_bytes([length[0], length[1]]) as usize;
+    take_bytes(buffer, length)
+}
+
+/// Writes a u16-length prefixed block of bytes.
+fn write_extensions(extensions: &[u8], writer: &mut impl LittleEndianWriter) -> Result<()> {
+    let length = u16::try_from(extensions.len())
+        .map_err(|_| anyhow!("Extensions too long: {} bytes", extensions.len()))?;
+    writer.write_le_u16(length)?;
+    writer.write(extensions)?;
+    Ok(())
+}
+
+impl packet::Header {
+    /// Returns true if messages with this header are secured, i.e. carry an
+    /// encrypted payload followed by a [MIC_LENGTH] integrity check.
+    ///
+    /// Only unicast messages on session 0 are unsecured.
+    ///
+    /// ```
+    /// use matter_packets::packet::{HeaderBuilder, SecurityFlags};
+    ///
+    /// let unsecured = HeaderBuilder::default().session_id(0).counter(1).build().unwrap();
+    /// assert!(!unsecured.is_secured());
+    ///
+    /// let secured = HeaderBuilder::default().session_id(12).counter(1).build().unwrap();
+    /// assert!(secured.is_secured());
+    ///
+    /// let group = HeaderBuilder::default()
+    ///     .session_id(0)
+    ///     .counter(1)
+    ///     .flags(SecurityFlags::SESSION_TYPE_BIT1)
+    ///     .build()
+    ///     .unwrap();
+    /// assert!(group.is_secured());
+    /// ```
+    pub fn is_secured(&self) -> bool {
+        self.session_id != 0 || (self.flags & SecurityFlags::SESSION_TYPE_MASK).bits() != 0
+    }
+}
+
+/// A complete message frame as sent over the wire.
+///
+/// The protocol section (payload header, secured extensions and application
+/// payload) is kept as raw bytes since for secured sessions it is encrypted.
+/// Use [ProtocolMessage::parse] on the (decrypted) payload to interpret it.
+///
+/// # Binary layout
+///
+/// | Size           | Description                                          |
+/// |----------------|------------------------------------------------------|
+/// | *              | Message header, see [packet::Header]                 |
+/// | `u16 + (len)`  | (Optional) message extensions                        |
+/// | *              | Payload (encrypted for secured messages)             |
+/// | `16 bytes`     | (Optional) Message Integrity Check (secured only)    |
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Message<'a> {
+    pub header: packet::Header,
+
+    /// Contents of the message extensions, without the length prefix.
+    pub message_extensions: Option<&'a [u8]>,
+
+    /// Protocol section of the message. Encrypted if the header is secured.
+    pub payload: &'a [u8],
+
+    /// Integrity check, present exactly when [packet::Header::is_secured].
+    pub mic: Option<&'a [u8; MIC_LENGTH]>,
+}
+
+impl<'a> Message<'a> {
+    /// Parses a complete message frame.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use matter_packets::message::*;
+    ///
+    /// let data: &[u8] = &[
+    ///   0x00,                   // flags: none set
+    ///   0x34, 0x12,             // session id: 0x1234
+    ///   0x20,                   // security flags: message extensions
+    ///   0x01, 0x00, 0x00, 0x00, // counter
+    ///   0x02, 0x00, 0xee, 0xff, // message extensions
+    ///   0xaa, 0xbb, 0xcc,       // encrypted payload
+    ///   0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, // MIC
+    /// ];
+    /// let message = Message::parse(data).unwrap();
+    ///
+    /// assert_eq!(message.header.session_id, 0x1234);
+    /// assert_eq!(message.message_extensions, Some([0xee, 0xff].as_slice()));
+    /// assert_eq!(message.payload, &[0xaa, 0xbb, 0xcc]);
+    /// assert_eq!(message.mic.unwrap()[15], 15);
+    ///
+    /// // secured messages must be long enough to hold a MIC
+    /// assert!(Message::parse(&data[..20]).is_err());
+    ///
+    /// let data: &[u8] = &[
+    ///   0x00,                   // flags: none set
+    ///   0x00, 0x00,             // session id: unsecured
+    ///   0x00,                   // security flags
+    ///   0x01, 0x00, 0x00, 0x00, // counter
+    ///   0xaa, 0xbb, 0xcc,       // payload
+    /// ];
+    /// let message = Message::parse(data).unwrap();
+    /// assert_eq!(message.message_extensions, None);
+    /// assert_eq!(message.payload, &[0xaa, 0xbb, 0xcc]);
+    /// assert_eq!(message.mic, None);
+    /// ```
+    pub fn parse(data: &'a [u8]) -> Result<Message<'a>> {
+        let mut buffer = data;
+        let header = packet::Header::parse(&mut buffer)?;
+
+        let message_extensions = if header.flags.contains(SecurityFlags::MESSAGE_EXTENSIONS) {
+            Some(take_extensions(&mut buffer)?)
+        } else {
+            None
+        };
+
+        let mic = if header.is_secured() {
+            let payload_length = buffer
+                .len()
+                .checked_sub(MIC_LENGTH)
+                .ok_or_else(|| anyhow!("Secured message too short to contain a MIC"))?;
+            let mic = &buffer[payload_length..];
+            buffer = &buffer[..payload_length];
+            Some(mic.try_into()?)
+        } else {
+            None
+        };
+
+        Ok(Message {
+            header,
+            message_extensions,
+            payload: buffer,
+            mic,
+        })
+    }
+
+    /// Serializes the complete message frame.
+    ///
+    /// The [SecurityFlags::MESSAGE_EXTENSIONS] flag is set based on
+    /// `message_extensions`. Fails if the MIC presence does not match
+    /// [packet::Header::is_secured].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use matter_packets::message::*;
+    /// use matter_packets::packet::HeaderBuilder;
+    /// use matter_packets::writer::SliceLittleEndianWriter;
+    ///
+    /// let message = Message {
+    ///     header: HeaderBuilder::default().session_id(0).counter(2).build().unwrap(),
+    ///     message_extensions: Some(&[0x11]),
+    ///     payload: &[1, 2, 3],
+    ///     mic: None,
+    /// };
+    ///
+    /// let mut buffer = [0u8; 16];
+    /// let cnt = {
+    ///    let mut writer = SliceLittleEndianWriter::new(buffer.as_mut_slice());
+    ///    message.write(&mut writer).unwrap();
+    ///    writer.written()
+    /// };
+    ///
+    /// assert_eq!(&buffer[..cnt], &[
+    ///   0x00,                   // flags: none
+    ///   0x00, 0x00,             // session id: unsecured
+    ///   0x20,                   // security flags: message extensions
+    ///   0x02, 0x00, 0x00, 0x00, // counter
+    ///   0x01, 0x00, 0x11,       // message extensions
+    ///   1, 2, 3,                // payload
+    /// ]);
+    ///
+    /// let parsed = Message::parse(&buffer[..cnt]).unwrap();
+    /// assert_eq!(parsed.message_extensions, message.message_extensions);
+    /// assert_eq!(parsed.payload, message.payload);
+    /// ```
+    pub fn write(&self, writer: &mut impl LittleEndianWriter) -> Result<()> {
+        if self.header.is_secured() != self.mic.is_some() {
+            return Err(anyhow!(
+                "MIC must be present exactly for secured messages (secured: {})",
+                self.header.is_secured()
+            ));
+        }
+
+        Self::write_header(&self.header, self.message_extensions, writer)?;
+        writer.write(self.payload)?;
+
+        if let Some(mic) = self.mic {
+            writer.write(mic.as_slice())?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the message header followed by the optional message extensions.
+    ///
+    /// These are the bytes preceding the payload, which secured messages use
+    /// as additional authenticated data.
+    pub fn write_header(
+        header: &packet::Header,
+        message_extensions: Option<&[u8]>,
+        writer: &mut impl LittleEndianWriter,
+    ) -> Result<()> {
+        let mut header = *header;
+        header.flags.set(
+            SecurityFlags::MESSAGE_EXTENSIONS,
+            message_extensions.is_some(),
+        );
+        header.write(writer)?;
+
+        if let Some(extensions) = message_extensions {
+            write_extensions(extensions, writer)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The protocol section of a message: payload header, optional secured
+/// extensions and the application payload.
+///
+/// # Binary layout
+///
+/// | Size           | Description                                |
+/// |----------------|--------------------------------------------|
+/// | *              | Payload header, see [payload::Header]      |
+/// | `u16 + (len)`  | (Optional) secured extensions              |
+/// | *              | Application payload                        |
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProtocolMessage<'a> {
+    pub header: payload::Header,
+
+    /// Contents of the secured extensions, without the length prefix.
+    pub secured_extensions: Option<&'a [u8]>,
+
+    pub payload: &'a [u8],
+}
+
+impl<'a> ProtocolMessage<'a> {
+    /// Parses an unencrypted protocol section.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use matter_packets::message::*;
+    /// use matter_packets::payload::*;
+    /// use matter_types::ExchangeId;
+    ///
+    /// let data: &[u8] = &[
+    ///    0x08,         // exchange flags: secured extensions
+    ///    0x22,         // Pake1 (for secure channel)
+    ///    0x12, 0x23,   // Exchange Id
+    ///    0x00, 0x00,   // secure channel protocol,
+    ///    0x01, 0x00, 0x33, // secured extensions
+    ///    0xab, 0xff, 0x12  // payload
+    /// ];
+    /// let message = ProtocolMessage::parse(data).unwrap();
+    ///
+    /// assert_eq!(message.header.exchange, ExchangeId(0x2312));
+    /// assert_eq!(message.secured_extensions, Some([0x33].as_slice()));
+    /// assert_eq!(message.payload, &[0xab, 0xff, 0x12]);
+    /// ```
+    pub fn parse(data: &'a [u8]) -> Result<ProtocolMessage<'a>> {
+        let mut buffer = data;
+        let header = payload::Header::parse(&mut buffer)?;
+
+        let secured_extensions = if header
+            .flags
+            .contains(payload::ExchangeFlags::SECURED_EXTENSIONS)
+        {
+            Some(take_extensions(&mut buffer)?)
+        } else {
+            None
+        };
+
+        Ok(ProtocolMessage {
+            header,
+            secured_extensions,
+            payload: buffer,
+        })
+    }
+
+    /// Serializes the protocol section.
+    ///
+    /// The [payload::ExchangeFlags::SECURED_EXTENSIONS] flag is set based on
+    /// `secured_extensions`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use matter_packets::message::*;
+    /// use matter_packets::payload::*;
+    /// use matter_packets::writer::SliceLittleEndianWriter;
+    /// use matter_types::ExchangeId;
+    ///
+    /// let message = ProtocolMessage {
+    ///     header: HeaderBuilder::default()
+    ///         .protocol_opcode(ProtocolOpCode::SecureChannel(SecureChannelOpcode::PasePake1))
+    ///         .exchange(ExchangeId(0x1234))
+    ///         .build()
+    ///         .unwrap(),
+    ///     secured_extensions: None,
+    ///     payload: &[0x15, 0x18],
+    /// };
+    ///
+    /// let mut buffer = [0u8; 16];
+    /// let cnt = {
+    ///    let mut writer = SliceLittleEndianWriter::new(buffer.as_mut_slice());
+    ///    message.write(&mut writer).unwrap();
+    ///    writer.written()
+    /// };
+    ///
+    /// assert_eq!(&buffer[..cnt], &[
+    ///   0x00,         // exchange flags
+    ///   0x22,         // Pake1
+    ///   0x34, 0x12,   // Exchange Id
+    ///   0x00, 0x00,   // secure channel protocol
+    ///   0x15, 0x18,   // payload
+    /// ]);
+    /// ```
+    pub fn write(&self, writer: &mut impl LittleEndianWriter) -> Result<()> {
+        let mut header = self.header;
+        header.flags.set(
+            payload::ExchangeFlags::SECURED_EXTENSIONS,
+            self.secured_extensions.is_some(),
+        );
+        header.write(writer)?;
+
+        if let Some(extensions) = self.secured_extensions {
+            write_extensions(extensions, writer)?;
+        }
+
+        writer.write(self.payload)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet::{HeaderBuilder, MessageDestination};
+    use crate::payload::{ExchangeFlags, ProtocolOpCode, SecureChannelOpcode};
+    use crate::writer::{SliceLittleEndianWriter, SpaceEstimator};
+    use matter_types::{ExchangeId, NodeId};
+
+    fn write_to_vec(write: impl Fn(&mut SliceLittleEndianWriter) -> Result<()>) -> Vec<u8> {
+        let mut buffer = [0u8; 256];
+        let cnt = {
+            let mut writer = SliceLittleEndianWriter::new(buffer.as_mut_slice());
+            write(&mut writer).unwrap();
+            writer.written()
+        };
+        buffer[..cnt].to_vec()
+    }
+
+    #[test]
+    fn secured_round_trip() {
+        let mic = [0xa5u8; MIC_LENGTH];
+        let message = Message {
+            header: HeaderBuilder::default()
+                .session_id(0x4321)
+                .counter(0x01020304)
+                .source(Some(NodeId(0x1122334455667788)))
+                .destination(MessageDestination::Node(NodeId(0x99)))
+                .build()
+                .unwrap(),
+            message_extensions: Some(&[1, 2, 3, 4]),
+            payload: &[0xde, 0xad, 0xbe, 0xef],
+            mic: Some(&mic),
+        };
+
+        let data = write_to_vec(|w| message.write(w));
+
+        let mut estimator = SpaceEstimator::default();
+        message.write(&mut estimator).unwrap();
+        assert_eq!(estimator.written(), data.len());
+
+        let parsed = Message::parse(&data).unwrap();
+        assert!(parsed
+            .header
+            .flags
+            .contains(SecurityFlags::MESSAGE_EXTENSIONS));
+        assert_eq!(parsed.message_extensions, message.message_extensions);
+        assert_eq!(parsed.payload, message.payload);
+        assert_eq!(parsed.mic, Some(&mic));
+    }
+
+    #[test]
+    fn mic_presence_is_validated() {
+        let unsecured = Message {
+            header: HeaderBuilder::default()
+                .session_id(0)
+                .counter(1)
+                .build()
+                .unwrap(),
+            message_extensions: None,
+            payload: &[],
+            mic: Some(&[0; MIC_LENGTH]),
+        };
+        assert!(unsecured.write(&mut SpaceEstimator::default()).is_err());
+
+        let secured = Message {
+            header: HeaderBuilder::default()
+                .session_id(1)
+                .counter(1)
+                .build()
+                .unwrap(),
+            mic: None,
+            ..unsecured
+        };
+        assert!(secured.write(&mut SpaceEstimator::default()).is_err());
+    }
+
+    #[test]
+    fn truncated_extensions_are_rejected() {
+        let data: &[u8] = &[
+            0x00, // flags
+            0x00, 0x00, // session id
+            0x20, // security flags: message extensions
+            0x01, 0x00, 0x00, 0x00, // counter
+            0x05, 0x00, 0x01, // extensions claim 5 bytes, only 1 present
+        ];
+        assert!(Message::parse(data).is_err());
+
+        let data: &[u8] = &[
+            0x08, // exchange flags: secured extensions
+            0x22, // Pake1
+            0x00, 0x00, // exchange
+            0x00, 0x00, // secure channel protocol
+            0x01, // truncated length
+        ];
+        assert!(ProtocolMessage::parse(data).is_err());
+    }
+
+    #[test]
+    fn unsecured_message_with_protocol_section() {
+        let protocol = ProtocolMessage {
+            header: payload::HeaderBuilder::default()
+                .flags(ExchangeFlags::INITIATOR | ExchangeFlags::RELIABILITY)
+                .protocol_opcode(ProtocolOpCode::SecureChannel(
+                    SecureChannelOpcode::PbkdfParamRequest,
+                ))
+                .exchange(ExchangeId(7))
+                .ack_counter(Some(0x55))
+                .build()
+                .unwrap(),
+            secured_extensions: Some(&[9, 9]),
+            payload: &[0x15, 0x18],
+        };
+        let protocol_data = write_to_vec(|w| protocol.write(w));
+
+        let message = Message {
+            header: HeaderBuilder::default()
+                .session_id(0)
+                .counter(42)
+                .build()
+                .unwrap(),
+            message_extensions: None,
+            payload: &protocol_data,
+            mic: None,
+        };
+        let data = write_to_vec(|w| message.write(w));
+
+        let parsed = Message::parse(&data).unwrap();
+        assert_eq!(parsed, message);
+
+        let parsed = ProtocolMessage::parse(parsed.payload).unwrap();
+        assert_eq!(parsed.secured_extensions, protocol.secured_extensions);
+        assert_eq!(parsed.payload, protocol.payload);
+        assert_eq!(parsed.header.ack_counter, Some(0x55));
+        assert!(parsed
+            .header
+            .flags
+            .contains(ExchangeFlags::SECURED_EXTENSIONS | ExchangeFlags::INITIATOR));
+    }
+}
@@ -0,0 +1,597 @@
+//! Message Reliability Protocol (MRP).
+//!
+//! [MrpEngine] tracks reliable messages of a single session that still need
+//! an acknowledgement and acknowledgements that still need to be sent. It does
+//! not perform any I/O: callers report sent and received messages and
+//! periodically [MrpEngine::poll] it for retransmissions and standalone acks,
+//! using [MrpEngine::next_timeout] to know when to poll again.
+//!
+//! Retransmission timeouts follow the specification:
+//!
+//! ```text
+//! t = i * MRP_BACKOFF_MARGIN * MRP_BACKOFF_BASE^max(0, n - MRP_BACKOFF_THRESHOLD)
+//!       * (1 + random(0, 1) * MRP_BACKOFF_JITTER)
+//! ```
+//!
+//! where `i` is the peer's active or idle interval and `n` the number of
+//! retransmissions already performed.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use matter_types::ExchangeId;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+#[cfg(test)]
+use mock_instant::Instant;
+
+#[cfg(not(test))]
+use std::time::Instant;
+
+use crate::payload::{self, ExchangeFlags, ProtocolOpCode, SecureChannelOpcode};
+
+/// Total number of transmissions (initial one included) before giving up.
+pub const MRP_MAX_TRANSMISSIONS: u32 = 5;
+
+/// Base of the exponential retransmission backoff.
+pub const MRP_BACKOFF_BASE: f64 = 1.6;
+
+/// Maximum random increase of a retransmission timeout.
+pub const MRP_BACKOFF_JITTER: f64 = 0.25;
+
+/// Margin applied on top of the peer's retransmission interval.
+pub const MRP_BACKOFF_MARGIN: f64 = 1.1;
+
+/// Number of retransmissions before the backoff starts growing.
+pub const MRP_BACKOFF_THRESHOLD: u32 = 1;
+
+/// Maximum delay before a pending acknowledgement is sent on its own.
+pub const MRP_STANDALONE_ACK_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Retransmission parameters advertised by a peer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MrpConfig {
+    /// Retransmission interval while the peer is idle (SESSION_IDLE_INTERVAL).
+    pub idle_interval: Duration,
+
+    /// Retransmission interval while the peer is active (SESSION_ACTIVE_INTERVAL).
+    pub active_interval: Duration,
+
+    /// How long a peer stays active after it was last heard from
+    /// (SESSION_ACTIVE_THRESHOLD).
+    pub active_threshold: Duration,
+
+    /// Total transmissions of a message before it is considered failed.
+    pub max_transmissions: u32,
+}
+
+impl Default for MrpConfig {
+    fn default() -> Self {
+        MrpConfig {
+            idle_interval: Duration::from_millis(500),
+            active_interval: Duration::from_millis(300),
+            active_threshold: Duration::from_millis(4000),
+            max_transmissions: MRP_MAX_TRANSMISSIONS,
+        }
+    }
+}
+
+/// Computes the time to wait for an acknowledgement.
+///
+/// `retransmissions` is the number of retransmissions already performed and
+/// `jitter` a random value in `[0, 1)`.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use matter_packets::mrp::retransmission_timeout;
+///
+/// let interval = Duration::from_millis(300);
+/// assert_eq!(retransmission_timeout(interval, 0, 0.0), Duration::from_millis(330));
+/// assert_eq!(retransmission_timeout(interval, 1, 0.0), Duration::from_millis(330));
+/// assert_eq!(retransmission_timeout(interval, 2, 0.0), Duration::from_millis(528));
+/// assert_eq!(retransmission_timeout(interval, 0, 1.0), Duration::from_micros(412_500));
+/// ```
+pub fn retransmission_timeout(interval: Duration, retransmissions: u32, jitter: f64) -> Duration {
+    let exponent = retransmissions.saturating_sub(MRP_BACKOFF_THRESHOLD);
+    let factor = MRP_BACKOFF_MARGIN
+        * MRP_BACKOFF_BASE.powi(exponent as i32)
+        * (1.0 + jitter * MRP_BACKOFF_JITTER);
+
+    // round to microseconds to avoid floating point noise
+    Duration::from_micros((interval.as_micros() as f64 * factor).round() as u64)
+}
+
+/// Identifies an exchange within a session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExchangeKey {
+    pub id: ExchangeId,
+
+    /// True if this node initiated the exchange.
+    pub initiator: bool,
+}
+
+/// Work requested by [MrpEngine::poll].
+#[derive(Debug, Clone, PartialEq)]
+pub enum MrpAction {
+    /// Send the given (already encoded) frame again.
+    Retransmit {
+        exchange: ExchangeKey,
+        frame: Vec<u8>,
+    },
+
+    /// Send a standalone acknowledgement, see [standalone_ack_header].
+    SendStandaloneAck {
+        exchange: ExchangeKey,
+        ack_counter: u32,
+    },
+
+    /// The message was not acknowledged after all transmissions.
+    Failed { exchange: ExchangeKey, counter: u32 },
+}
+
+/// Builds the payload header of a standalone acknowledgement.
+///
+/// ```
+/// use matter_packets::mrp::{standalone_ack_header, ExchangeKey};
+/// use matter_packets::payload::{ExchangeFlags, ProtocolOpCode, SecureChannelOpcode};
+/// use matter_types::ExchangeId;
+///
+/// let header = standalone_ack_header(ExchangeKey { id: ExchangeId(3), initiator: true }, 1234);
+/// assert_eq!(header.protocol_opcode, ProtocolOpCode::SecureChannel(SecureChannelOpcode::MrpStandaloneAck));
+/// assert_eq!(header.flags, ExchangeFlags::INITIATOR | ExchangeFlags::ACKNOWLEDGEMENT);
+/// assert_eq!(header.ack_counter, Some(1234));
+/// ```
+pub fn standalone_ack_header(exchange: ExchangeKey, ack_counter: u32) -> payload::Header {
+    let mut flags = ExchangeFlags::ACKNOWLEDGEMENT;
+    flags.set(ExchangeFlags::INITIATOR, exchange.initiator);
+
+    payload::Header {
+        flags,
+        protocol_opcode: ProtocolOpCode::SecureChannel(SecureChannelOpcode::MrpStandaloneAck),
+        exchange: exchange.id,
+        ack_counter: Some(ack_counter),
+    }
+}
+
+#[derive(Debug)]
+struct PendingRetransmission {
+    counter: u32,
+    frame: Vec<u8>,
+    transmissions: u32,
+    deadline: Instant,
+}
+
+#[derive(Debug)]
+struct PendingAck {
+    counter: u32,
+    deadline: Instant,
+}
+
+/// Reliability state of a single session, see the [module documentation](self).
+pub struct MrpEngine {
+    config: MrpConfig,
+    rng: StdRng,
+    last_peer_activity: Option<Instant>,
+    retransmissions: HashMap<ExchangeKey, PendingRetransmission>,
+    acks: HashMap<ExchangeKey, PendingAck>,
+
+    /// Acknowledgements that were replaced by newer ones and are due immediately.
+    flushed: Vec<(ExchangeKey, u32)>,
+}
+
+impl MrpEngine {
+    pub fn new(config: MrpConfig) -> Self {
+        Self::with_rng(config, StdRng::from_entropy())
+    }
+
+    /// Creates an engine with a seeded jitter source, for deterministic tests.
+    pub fn with_seed(config: MrpConfig, seed: u64) -> Self {
+        Self::with_rng(config, StdRng::seed_from_u64(seed))
+    }
+
+    fn with_rng(config: MrpConfig, rng: StdRng) -> Self {
+        MrpEngine {
+            config,
+            rng,
+            last_peer_activity: None,
+            retransmissions: HashMap::new(),
+            acks: HashMap::new(),
+            flushed: Vec::new(),
+        }
+    }
+
+    /// Peer parameters currently in use.
+    pub fn config(&self) -> &MrpConfig {
+        &self.config
+    }
+
+    /// Updates the peer parameters (e.g. once learned during session establishment).
+    pub fn set_config(&mut self, config: MrpConfig) {
+        self.config = config;
+    }
+
+    fn peer_interval(&self) -> Duration {
+        match self.last_peer_activity {
+            Some(time) if time.elapsed() < self.config.active_threshold => {
+                self.config.active_interval
+            }
+            _ => self.config.idle_interval,
+        }
+    }
+
+    fn backoff(&mut self, retransmissions: u32) -> Duration {
+        let jitter = self.rng.gen::<f64>();
+        retransmission_timeout(self.peer_interval(), retransmissions, jitter)
+    }
+
+    /// Prepares the payload header of an outgoing message on `exchange`:
+    /// a pending acknowledgement for the exchange is piggybacked on it.
+    pub fn prepare_send(&mut self, exchange: ExchangeKey, header: &mut payload::Header) {
+        if let Some(ack) = self.acks.remove(&exchange) {
+            header.ack_counter = Some(ack.counter);
+            header.flags.insert(ExchangeFlags::ACKNOWLEDGEMENT);
+        }
+    }
+
+    /// Records a message that was sent with [ExchangeFlags::RELIABILITY] so it
+    /// is retransmitted until acknowledged.
+    ///
+    /// Only a single message per exchange may await an acknowledgement.
+    pub fn sent_reliable(
+        &mut self,
+        exchange: ExchangeKey,
+        counter: u32,
+        frame: Vec<u8>,
+    ) -> Result<()> {
+        if self.retransmissions.contains_key(&exchange) {
+            return Err(anyhow!(
+                "Exchange {:?} already has a message awaiting acknowledgement",
+                exchange
+            ));
+        }
+
+        let deadline = Instant::now() + self.backoff(0);
+        self.retransmissions.insert(
+            exchange,
+            PendingRetransmission {
+                counter,
+                frame,
+                transmissions: 1,
+                deadline,
+            },
+        );
+        Ok(())
+    }
+
+    /// Processes the reliability fields of a received message.
+    ///
+    /// `duplicate` marks messages whose counter was already seen: they are
+    /// acknowledged right away but must not be processed again.
+    pub fn received(
+        &mut self,
+        exchange: ExchangeKey,
+        counter: u32,
+        header: &payload::Header,
+        duplicate: bool,
+    ) {
+        self.last_peer_activity = Some(Instant::now());
+
+        if let Some(ack_counter) = header.ack_counter {
+            if self
+                .retransmissions
+                .get(&exchange)
+                .is_some_and(|pending| pending.counter == ack_counter)
+            {
+                self.retransmissions.remove(&exchange);
+            }
+        }
+
+        if header.flags.contains(ExchangeFlags::RELIABILITY) {
+            let now = Instant::now();
+            let deadline = if duplicate {
+                now
+            } else {
+                now + MRP_STANDALONE_ACK_TIMEOUT
+            };
+
+            let previous = self.acks.insert(exchange, PendingAck { counter, deadline });
+
+            // only one acknowledgement can be pending per exchange, an older
+            // one is sent on its own right away
+            if let Some(previous) = previous.filter(|previous| previous.counter != counter) {
+                self.flushed.push((exchange, previous.counter));
+            }
+        }
+    }
+
+    /// Returns true if a message on `exchange` still awaits an acknowledgement.
+    pub fn awaiting_ack(&self, exchange: ExchangeKey) -> bool {
+        self.retransmissions.contains_key(&exchange)
+    }
+
+    /// Returns the acknowledgement that would be piggybacked on `exchange`.
+    pub fn pending_ack(&self, exchange: ExchangeKey) -> Option<u32> {
+        self.acks.get(&exchange).map(|ack| ack.counter)
+    }
+
+    /// Makes a pending acknowledgement for `exchange` due immediately,
+    /// e.g. because the exchange is closing.
+    pub fn flush_ack(&mut self, exchange: ExchangeKey) {
+        if let Some(ack) = self.acks.remove(&exchange) {
+            self.flushed.push((exchange, ack.counter));
+        }
+    }
+
+    /// Collects all work that is due.
+    pub fn poll(&mut self) -> Vec<MrpAction> {
+        let now = Instant::now();
+        let mut actions: Vec<MrpAction> = self
+            .flushed
+            .drain(..)
+            .map(|(exchange, ack_counter)| MrpAction::SendStandaloneAck {
+                exchange,
+                ack_counter,
+            })
+            .collect();
+
+        let due_acks: Vec<ExchangeKey> = self
+            .acks
+            .iter()
+            .filter(|(_, ack)| ack.deadline <= now)
+            .map(|(exchange, _)| *exchange)
+            .collect();
+        for exchange in due_acks {
+            let ack = self.acks.remove(&exchange).expect("key was just found");
+            actions.push(MrpAction::SendStandaloneAck {
+                exchange,
+                ack_counter: ack.counter,
+            });
+        }
+
+        let due: Vec<ExchangeKey> = self
+            .retransmissions
+            .iter()
+            .filter(|(_, pending)| pending.deadline <= now)
+            .map(|(exchange, _)| *exchange)
+            .collect();
+        for exchange in due {
+            let pending = self
+                .retransmissions
+                .get(&exchange)
+                .expect("key was just found");
+
+            if pending.transmissions >= self.config.max_transmissions {
+                let counter = pending.counter;
+                self.retransmissions.remove(&exchange);
+                actions.push(MrpAction::Failed { exchange, counter });
+                continue;
+            }
+
+            let retransmissions = pending.transmissions;
+            let backoff = self.backoff(retransmissions);
+            let pending = self
+                .retransmissions
+                .get_mut(&exchange)
+                .expect("key was just found");
+            pending.transmissions += 1;
+            pending.deadline = now + backoff;
+
+            actions.push(MrpAction::Retransmit {
+                exchange,
+                frame: pending.frame.clone(),
+            });
+        }
+
+        actions
+    }
+
+    /// Time until [MrpEngine::poll] has work to do, or `None` if idle.
+    pub fn next_timeout(&self) -> Option<Duration> {
+        if !self.flushed.is_empty() {
+            return Some(Duration::ZERO);
+        }
+
+        let now = Instant::now();
+        self.acks
+            .values()
+            .map(|ack| ack.deadline)
+            .chain(
+                self.retransmissions
+                    .values()
+                    .map(|pending| pending.deadline),
+            )
+            .min()
+            .map(|deadline| deadline.saturating_duration_since(now))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mock_instant::MockClock;
+
+    const EXCHANGE: ExchangeKey = ExchangeKey {
+        id: ExchangeId(10),
+        initiator: true,
+    };
+
+    fn header(flags: ExchangeFlags, ack_counter: Option<u32>) -> payload::Header {
+        payload::Header {
+            flags,
+            protocol_opcode: ProtocolOpCode::SecureChannel(SecureChannelOpcode::StatusReport),
+            exchange: EXCHANGE.id,
+            ack_counter,
+        }
+    }
+
+    fn config() -> MrpConfig {
+        MrpConfig {
+            idle_interval: Duration::from_millis(1000),
+            active_interval: Duration::from_millis(100),
+            active_threshold: Duration::from_millis(4000),
+            max_transmissions: 3,
+        }
+    }
+
+    #[test]
+    fn retransmits_with_backoff_until_failure() {
+        let mut engine = MrpEngine::with_seed(config(), 1);
+        engine.sent_reliable(EXCHANGE, 7, vec![1, 2, 3]).unwrap();
+        assert!(engine.awaiting_ack(EXCHANGE));
+
+        // peer is idle: first timeout is 1000ms * 1.1 with up to 25% jitter
+        let timeout = engine.next_timeout().unwrap();
+        assert!(timeout >= Duration::from_millis(1100));
+        assert!(timeout <= Duration::from_micros(1_375_000));
+
+        MockClock::advance(timeout - Duration::from_millis(1));
+        assert!(engine.poll().is_empty());
+
+        MockClock::advance(Duration::from_millis(1));
+        assert_eq!(
+            engine.poll(),
+            vec![MrpAction::Retransmit {
+                exchange: EXCHANGE,
+                frame: vec![1, 2, 3]
+            }]
+        );
+
+        // second retransmission: still below the backoff threshold
+        let timeout = engine.next_timeout().unwrap();
+        assert!(timeout >= Duration::from_millis(1100));
+        MockClock::advance(timeout);
+        assert_eq!(engine.poll().len(), 1);
+
+        // third timeout grows by the backoff base, then the message fails
+        let timeout = engine.next_timeout().unwrap();
+        assert!(timeout >= Duration::from_millis(1760));
+        MockClock::advance(timeout);
+        assert_eq!(
+            engine.poll(),
+            vec![MrpAction::Failed {
+                exchange: EXCHANGE,
+                counter: 7
+            }]
+        );
+        assert!(!engine.awaiting_ack(EXCHANGE));
+        assert_eq!(engine.next_timeout(), None);
+    }
+
+    #[test]
+    fn active_peer_uses_active_interval() {
+        let mut engine = MrpEngine::with_seed(config(), 2);
+        engine.received(EXCHANGE, 1, &header(ExchangeFlags::empty(), None), false);
+        engine.sent_reliable(EXCHANGE, 7, vec![]).unwrap();
+
+        let timeout = engine.next_timeout().unwrap();
+        assert!(timeout >= Duration::from_millis(110));
+        assert!(timeout <= Duration::from_micros(137_500));
+    }
+
+    #[test]
+    fn ack_stops_retransmission() {
+        let mut engine = MrpEngine::with_seed(config(), 3);
+        engine.sent_reliable(EXCHANGE, 7, vec![]).unwrap();
+        assert!(engine.sent_reliable(EXCHANGE, 8, vec![]).is_err());
+
+        // acks for other counters are ignored
+        engine.received(
+            EXCHANGE,
+            1,
+            &header(ExchangeFlags::ACKNOWLEDGEMENT, Some(6)),
+            false,
+        );
+        assert!(engine.awaiting_ack(EXCHANGE));
+
+        engine.received(
+            EXCHANGE,
+            2,
+            &header(ExchangeFlags::ACKNOWLEDGEMENT, Some(7)),
+            false,
+        );
+        assert!(!engine.awaiting_ack(EXCHANGE));
+        assert_eq!(engine.next_timeout(), None);
+    }
+
+    #[test]
+    fn acks_are_piggybacked() {
+        let mut engine = MrpEngine::with_seed(config(), 4);
+        engine.received(
+            EXCHANGE,
+            55,
+            &header(ExchangeFlags::RELIABILITY, None),
+            false,
+        );
+        assert_eq!(engine.pending_ack(EXCHANGE), Some(55));
+
+        let mut outgoing = header(ExchangeFlags::INITIATOR, None);
+        engine.prepare_send(EXCHANGE, &mut outgoing);
+        assert_eq!(outgoing.ack_counter, Some(55));
+        assert!(outgoing.flags.contains(ExchangeFlags::ACKNOWLEDGEMENT));
+
+        // nothing left to acknowledge on its own
+        assert_eq!(engine.pending_ack(EXCHANGE), None);
+        MockClock::advance(MRP_STANDALONE_ACK_TIMEOUT);
+        assert!(engine.poll().is_empty());
+    }
+
+    #[test]
+    fn standalone_ack_on_timeout() {
+        let mut engine = MrpEngine::with_seed(config(), 5);
+        engine.received(
+            EXCHANGE,
+            55,
+            &header(ExchangeFlags::RELIABILITY, None),
+            false,
+        );
+        assert_eq!(engine.next_timeout(), Some(MRP_STANDALONE_ACK_TIMEOUT));
+
+        MockClock::advance(MRP_STANDALONE_ACK_TIMEOUT);
+        assert_eq!(
+            engine.poll(),
+            vec![MrpAction::SendStandaloneAck {
+                exchange: EXCHANGE,
+                ack_counter: 55
+            }]
+        );
+        assert!(engine.poll().is_empty());
+    }
+
+    #[test]
+    fn duplicates_and_replaced_acks_are_sent_immediately() {
+        let mut engine = MrpEngine::with_seed(config(), 6);
+        engine.received(
+            EXCHANGE,
+            55,
+            &header(ExchangeFlags::RELIABILITY, None),
+            true,
+        );
+        assert_eq!(engine.next_timeout(), Some(Duration::ZERO));
+        assert_eq!(engine.poll().len(), 1);
+
+        engine.received(
+            EXCHANGE,
+            60,
+            &header(ExchangeFlags::RELIABILITY, None),
+            false,
+        );
+        engine.received(
+            EXCHANGE,
+            61,
+            &header(ExchangeFlags::RELIABILITY, None),
+            false,
+        );
+        assert_eq!(engine.pending_ack(EXCHANGE), Some(61));
+        assert_eq!(
+            engine.poll(),
+            vec![MrpAction::SendStandaloneAck {
+                exchange: EXCHANGE,
+                ack_counter: 60
+            }]
+        );
+    }
+}
@@ -5,19 +5,14 @@ use matter_types::{GroupId, NodeId};
 use super::reader::LittleEndianReader;
 use crate::writer::LittleEndianWriter;
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
 pub enum MessageDestination {
+    #[default]
     None,
     Node(NodeId),
     Group(GroupId),
 }
 
-impl Default for MessageDestination {
-    fn default() -> Self {
-        MessageDestination::None
-    }
-}
-
 // Mask and constant for messages version V1
 const FLAGS_VERSION_MASK: u8 = 0xF0;
 const FLAGS_VERSION_V1: u8 = 0x00;
@@ -30,6 +25,22 @@ const FLAGS_DESTINATION_MASK: u8 = 0x03;
 const FLAGS_DESTINATION_NODE: u8 = 0x01;
 const FLAGS_DESTINATION_GROUP: u8 = 0x02;
 
+/// Length of the counter, source and destination fields of an encoded header,
+/// based on its first (message flags) byte.
+pub(crate) fn counter_and_addresses_length(message_flags: u8) -> usize {
+    let source = if message_flags & FLAGS_SOURCE_NODE_ID_SET != 0 {
+        8
+    } else {
+        0
+    };
+    let destination = match message_flags & FLAGS_DESTINATION_MASK {
+        FLAGS_DESTINATION_NODE => 8,
+        FLAGS_DESTINATION_GROUP => 2,
+        _ => 0,
+    };
+    4 + source + destination
+}
+
 #[derive(Debug, PartialEq)]
 pub enum SessionType {
     Unicast,
@@ -83,8 +94,9 @@ impl SecurityFlags {
 /// | *              | Payload                                                        |
 /// | `16 bytes`     | (Optional) Message Integrity Check (for all except unecrypted) |
 ///
-#[derive(Debug, Default, PartialEq, Builder)]
+#[derive(Debug, Default, PartialEq, Clone, Copy, Builder)]
 pub struct Header {
+    #[builder(default)]
     pub flags: SecurityFlags,
     pub session_id: u16,
 
@@ -101,8 +113,11 @@ impl Header {
     ///
     /// NOTE:
     ///   [SecurityFlags::MESSAGE_EXTENSIONS] is NOT processed (i.e. the extensions
-    ///   are not skipped as part of parsing the header) and should be skipped or
-    ///   interpreted separately.
+    ///   are not skipped as part of parsing the header). Use
+    ///   [crate::message::Message::parse] to parse a complete message.
+    ///
+    ///   Messages with [SecurityFlags::PRIVACY] must be passed through
+    ///   [crate::privacy::PrivacyCipher::deobfuscate] before parsing.
     ///
     /// Examples:
     ///
@@ -262,7 +277,7 @@ impl Header {
             MessageDestination::None => {}
         };
 
-        // NOTE: this does NOT write the extensions
+        // NOTE: this does NOT write the extensions, see crate::message::Message
 
         Ok(())
     }
@@ -0,0 +1,763 @@
+//! Passcode-Authenticated Session Establishment (PASE).
+//!
+//! ```text
+//! initiator (commissioner)                 responder (commissionee)
+//!     PBKDFParamRequest   ---------------->
+//!                         <----------------   PBKDFParamResponse
+//!     Pake1 (pA)          ---------------->
+//!                         <----------------   Pake2 (pB, cB)
+//!     Pake3 (cA)          ---------------->
+//!                         <----------------   StatusReport
+//! ```
+//!
+//! [PaseInitiator] and [PaseResponder] only deal with message payloads:
+//! callers send them over an unsecured session using the matching
+//! [SecureChannelOpcode](crate::payload::SecureChannelOpcode) and, once
+//! established, add the resulting [SecureSessionParams] to their
+//! [SessionTable](crate::session::SessionTable).
+//!
+//! When the responder fails to process a message, it should reply with
+//! [StatusReport::invalid_parameter] and abandon the exchange.
+
+use anyhow::{anyhow, Result};
+use hkdf::Hkdf;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+use tlv_derive::{TlvEncodable, TlvMergeDecodable, TlvSchema};
+
+use crate::mrp::MrpConfig;
+use crate::session::{
+    SecureSessionKind, SecureSessionParams, SecureSessionParamsBuilder, SessionKeys,
+    ATTESTATION_CHALLENGE_LENGTH,
+};
+use crate::spake2p::{
+    PasscodeSecrets, Prover, Verifier, VerifierExchange, CONFIRMATION_LENGTH,
+    PBKDF2_MAX_ITERATIONS, PBKDF2_MAX_SALT_LENGTH, PBKDF2_MIN_ITERATIONS, PBKDF2_MIN_SALT_LENGTH,
+    POINT_LENGTH, SHARED_SECRET_LENGTH,
+};
+use crate::status_report::{SecureChannelCode, StatusReport};
+use crate::tlv::{decode, encode};
+
+/// Length of the random values exchanged in the PBKDF parameter messages.
+pub const RANDOM_LENGTH: usize = 32;
+
+/// Context prefix hashed together with the PBKDF parameter messages.
+const CONTEXT_PREFIX: &[u8] = b"CHIP PAKE V1 Commissioning";
+
+/// MRP parameters advertised during session establishment.
+#[derive(Debug, Default, Clone, Copy, PartialEq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+pub struct SessionParameters {
+    /// SESSION_IDLE_INTERVAL in milliseconds.
+    #[tlv(tag = 1)]
+    pub idle_interval: Option<u32>,
+
+    /// SESSION_ACTIVE_INTERVAL in milliseconds.
+    #[tlv(tag = 2)]
+    pub active_interval: Option<u32>,
+
+    /// SESSION_ACTIVE_THRESHOLD in milliseconds.
+    #[tlv(tag = 3)]
+    pub active_threshold: Option<u16>,
+}
+
+impl SessionParameters {
+    pub fn from_mrp(config: &MrpConfig) -> Self {
+        let millis = |value: Duration| value.as_millis().try_into().unwrap_or(u32::MAX);
+
+        SessionParameters {
+            idle_interval: Some(millis(config.idle_interval)),
+            active_interval: Some(millis(config.active_interval)),
+            active_threshold: Some(
+                millis(config.active_threshold)
+                    .try_into()
+                    .unwrap_or(u16::MAX),
+            ),
+        }
+    }
+
+    /// MRP configuration of the peer that sent these parameters. Missing
+    /// values use the defaults.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use matter_packets::mrp::MrpConfig;
+    /// use matter_packets::pase::SessionParameters;
+    ///
+    /// let params = SessionParameters { idle_interval: Some(5000), ..Default::default() };
+    /// let config = params.mrp_config();
+    ///
+    /// assert_eq!(config.idle_interval, Duration::from_millis(5000));
+    /// assert_eq!(config.active_interval, MrpConfig::default().active_interval);
+    /// ```
+    pub fn mrp_config(&self) -> MrpConfig {
+        let default = MrpConfig::default();
+        let millis = |value: Option<u32>, default: Duration| {
+            value.map_or(default, |ms| Duration::from_millis(ms as u64))
+        };
+
+        MrpConfig {
+            idle_interval: millis(self.idle_interval, default.idle_interval),
+            active_interval: millis(self.active_interval, default.active_interval),
+            active_threshold: millis(
+                self.active_threshold.map(u32::from),
+                default.active_threshold,
+            ),
+            ..default
+        }
+    }
+}
+
+/// Payload of `PBKDFParamRequest`.
+#[derive(Debug, Default, Clone, PartialEq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+pub struct PbkdfParamRequest {
+    #[tlv(tag = 1)]
+    pub initiator_random: Vec<u8>,
+
+    #[tlv(tag = 2)]
+    pub initiator_session_id: u16,
+
+    /// Always 0: the passcode of the commissioning window.
+    #[tlv(tag = 3)]
+    pub passcode_id: u16,
+
+    /// Set if the initiator already knows the PBKDF parameters, in which
+    /// case the responder omits them.
+    #[tlv(tag = 4)]
+    pub has_pbkdf_parameters: bool,
+
+    #[tlv(tag = 5)]
+    pub initiator_session_params: Option<SessionParameters>,
+}
+
+/// PBKDF2 parameters used to derive the SPAKE2+ secrets from the passcode.
+#[derive(Debug, Default, Clone, PartialEq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+pub struct PbkdfParameters {
+    #[tlv(tag = 1)]
+    pub iterations: u32,
+
+    #[tlv(tag = 2, max_len = 32)]
+    pub salt: Vec<u8>,
+}
+
+/// Payload of `PBKDFParamResponse`.
+#[derive(Debug, Default, Clone, PartialEq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+pub struct PbkdfParamResponse {
+    #[tlv(tag = 1)]
+    pub initiator_random: Vec<u8>,
+
+    #[tlv(tag = 2)]
+    pub responder_random: Vec<u8>,
+
+    #[tlv(tag = 3)]
+    pub responder_session_id: u16,
+
+    #[tlv(tag = 4)]
+    pub pbkdf_parameters: Option<PbkdfParameters>,
+
+    #[tlv(tag = 5)]
+    pub responder_session_params: Option<SessionParameters>,
+}
+
+/// Payload of `PASE_Pake1`.
+#[derive(Debug, Default, Clone, PartialEq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+pub struct Pake1 {
+    #[tlv(tag = 1)]
+    pub pa: Vec<u8>,
+}
+
+/// Payload of `PASE_Pake2`.
+#[derive(Debug, Default, Clone, PartialEq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+pub struct Pake2 {
+    #[tlv(tag = 1)]
+    pub pb: Vec<u8>,
+
+    #[tlv(tag = 2)]
+    pub cb: Vec<u8>,
+}
+
+/// Payload of `PASE_Pake3`.
+#[derive(Debug, Default, Clone, PartialEq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+pub struct Pake3 {
+    #[tlv(tag = 1)]
+    pub ca: Vec<u8>,
+}
+
+pub(crate) fn check_length(name: &str, value: &[u8], expected: usize) -> Result<()> {
+    if value.len() != expected {
+        return Err(anyhow!(
+            "Invalid {} length: {} (expected {})",
+            name,
+            value.len(),
+            expected
+        ));
+    }
+    Ok(())
+}
+
+pub(crate) fn random() -> Vec<u8> {
+    let mut result = vec![0; RANDOM_LENGTH];
+    rand::thread_rng().fill_bytes(&mut result);
+    result
+}
+
+/// Hash of the PBKDF parameter messages, binding them to the SPAKE2+ exchange.
+fn context(request: &[u8], response: &[u8]) -> [u8; 32] {
+    let mut hash = Sha256::new();
+    hash.update(CONTEXT_PREFIX);
+    hash.update(request);
+    hash.update(response);
+    hash.finalize().into()
+}
+
+/// Derives the session keys from the SPAKE2+ shared secret `Ke`.
+fn session_keys(shared_secret: &[u8; SHARED_SECRET_LENGTH], initiator: bool) -> SessionKeys {
+    let mut keys = [0; 32 + ATTESTATION_CHALLENGE_LENGTH];
+    Hkdf::<Sha256>::new(None, shared_secret)
+        .expand(b"SessionKeys", &mut keys)
+        .expect("valid HKDF output length");
+
+    SessionKeys::from_derived(
+        keys[..16].try_into().unwrap(),
+        keys[16..32].try_into().unwrap(),
+        keys[32..].try_into().unwrap(),
+        initiator,
+    )
+}
+
+fn session_params(
+    local_session_id: u16,
+    peer_session_id: u16,
+    keys: SessionKeys,
+    peer_mrp: Option<SessionParameters>,
+) -> Result<SecureSessionParams> {
+    Ok(SecureSessionParamsBuilder::default()
+        .kind(SecureSessionKind::Pase)
+        .local_session_id(local_session_id)
+        .peer_session_id(peer_session_id)
+        .keys(keys)
+        .peer_mrp(peer_mrp.unwrap_or_default().mrp_config())
+        .build()?)
+}
+
+enum InitiatorState {
+    Idle,
+    AwaitingPbkdfParamResponse {
+        request: Vec<u8>,
+        random: Vec<u8>,
+    },
+    AwaitingPake2 {
+        prover: Prover,
+        peer_session_id: u16,
+        peer_mrp: Option<SessionParameters>,
+    },
+    AwaitingStatusReport {
+        session: SecureSessionParams,
+    },
+    Finished,
+}
+
+/// Commissioner side of PASE.
+pub struct PaseInitiator {
+    passcode: u32,
+    local_session_id: u16,
+    local_mrp: Option<SessionParameters>,
+    pbkdf_parameters: Option<PbkdfParameters>,
+    state: InitiatorState,
+}
+
+impl PaseInitiator {
+    pub fn new(passcode: u32, local_session_id: u16) -> Self {
+        PaseInitiator {
+            passcode,
+            local_session_id,
+            local_mrp: None,
+            pbkdf_parameters: None,
+            state: InitiatorState::Idle,
+        }
+    }
+
+    /// MRP parameters to advertise to the responder.
+    pub fn with_session_parameters(mut self, params: SessionParameters) -> Self {
+        self.local_mrp = Some(params);
+        self
+    }
+
+    /// Known PBKDF parameters of the responder (e.g. from a previous
+    /// attempt), so that the responder does not need to send them again.
+    pub fn with_pbkdf_parameters(mut self, params: PbkdfParameters) -> Self {
+        self.pbkdf_parameters = Some(params);
+        self
+    }
+
+    /// Payload of the `PBKDFParamRequest` that starts the exchange.
+    pub fn start(&mut self) -> Result<Vec<u8>> {
+        if !matches!(self.state, InitiatorState::Idle) {
+            return Err(anyhow!("PASE already started"));
+        }
+
+        let random = random();
+        let request = encode(&PbkdfParamRequest {
+            initiator_random: random.clone(),
+            initiator_session_id: self.local_session_id,
+            passcode_id: 0,
+            has_pbkdf_parameters: self.pbkdf_parameters.is_some(),
+            initiator_session_params: self.local_mrp,
+        })?;
+
+        self.state = InitiatorState::AwaitingPbkdfParamResponse {
+            request: request.clone(),
+            random,
+        };
+        Ok(request)
+    }
+
+    /// Processes `PBKDFParamResponse` and returns the `PASE_Pake1` payload.
+    pub fn on_pbkdf_param_response(&mut self, payload: &[u8]) -> Result<Vec<u8>> {
+        let (request, random) = match std::mem::replace(&mut self.state, InitiatorState::Finished) {
+            InitiatorState::AwaitingPbkdfParamResponse { request, random } => (request, random),
+            _ => return Err(anyhow!("Unexpected PBKDFParamResponse")),
+        };
+
+        let response: PbkdfParamResponse = decode(payload)?;
+        if response.initiator_random != random {
+            return Err(anyhow!("PBKDFParamResponse for a different request"));
+        }
+        check_length(
+            "responder random",
+            &response.responder_random,
+            RANDOM_LENGTH,
+        )?;
+
+        let parameters = match (&self.pbkdf_parameters, &response.pbkdf_parameters) {
+            (_, Some(parameters)) => parameters,
+            (Some(parameters), None) => parameters,
+            (None, None) => return Err(anyhow!("PBKDFParamResponse without PBKDF parameters")),
+        };
+
+        let secrets =
+            PasscodeSecrets::derive(self.passcode, &parameters.salt, parameters.iterations)?;
+        let prover = Prover::new(secrets, &context(&request, payload));
+        let pake1 = encode(&Pake1 {
+            pa: prover.share().to_vec(),
+        })?;
+
+        self.state = InitiatorState::AwaitingPake2 {
+            prover,
+            peer_session_id: response.responder_session_id,
+            peer_mrp: response.responder_session_params,
+        };
+        Ok(pake1)
+    }
+
+    /// Processes `PASE_Pake2` and returns the `PASE_Pake3` payload.
+    ///
+    /// Fails if the responder does not know the passcode.
+    pub fn on_pake2(&mut self, payload: &[u8]) -> Result<Vec<u8>> {
+        let (prover, peer_session_id, peer_mrp) =
+            match std::mem::replace(&mut self.state, InitiatorState::Finished) {
+                InitiatorState::AwaitingPake2 {
+                    prover,
+                    peer_session_id,
+                    peer_mrp,
+                } => (prover, peer_session_id, peer_mrp),
+                _ => return Err(anyhow!("Unexpected Pake2")),
+            };
+
+        let pake2: Pake2 = decode(payload)?;
+        check_length("pB", &pake2.pb, POINT_LENGTH)?;
+        check_length("cB", &pake2.cb, CONFIRMATION_LENGTH)?;
+
+        let (ca, shared_secret) = prover.finish(&pake2.pb, &pake2.cb)?;
+
+        self.state = InitiatorState::AwaitingStatusReport {
+            session: session_params(
+                self.local_session_id,
+                peer_session_id,
+                session_keys(&shared_secret, true),
+                peer_mrp,
+            )?,
+        };
+        encode(&Pake3 { ca: ca.to_vec() })
+    }
+
+    /// Processes the final `StatusReport` of the responder.
+    ///
+    /// Returns the parameters of the established session on success.
+    pub fn on_status_report(&mut self, report: &StatusReport) -> Result<SecureSessionParams> {
+        let session = match std::mem::replace(&mut self.state, InitiatorState::Finished) {
+            InitiatorState::AwaitingStatusReport { session } => session,
+            _ => return Err(anyhow!("PASE failed: {}", report)),
+        };
+
+        match report.secure_channel_code() {
+            Some(SecureChannelCode::SessionEstablishmentSuccess) if report.is_success() => {
+                Ok(session)
+            }
+            _ => Err(anyhow!("PASE failed: {}", report)),
+        }
+    }
+}
+
+enum ResponderState {
+    AwaitingPbkdfParamRequest,
+    AwaitingPake1 {
+        context: [u8; 32],
+        peer_session_id: u16,
+        peer_mrp: Option<SessionParameters>,
+    },
+    AwaitingPake3 {
+        exchange: VerifierExchange,
+        peer_session_id: u16,
+        peer_mrp: Option<SessionParameters>,
+    },
+    Finished,
+}
+
+/// Commissionee side of PASE.
+pub struct PaseResponder {
+    verifier: Verifier,
+    parameters: PbkdfParameters,
+    local_session_id: u16,
+    local_mrp: Option<SessionParameters>,
+    state: ResponderState,
+}
+
+impl PaseResponder {
+    /// Creates a responder for the given verifier, which was derived with
+    /// the given salt and iteration count.
+    pub fn new(
+        verifier: Verifier,
+        salt: &[u8],
+        iterations: u32,
+        local_session_id: u16,
+    ) -> Result<Self> {
+        if !(PBKDF2_MIN_SALT_LENGTH..=PBKDF2_MAX_SALT_LENGTH).contains(&salt.len()) {
+            return Err(anyhow!("Invalid PBKDF2 salt length {}", salt.len()));
+        }
+
+        if !(PBKDF2_MIN_ITERATIONS..=PBKDF2_MAX_ITERATIONS).contains(&iterations) {
+            return Err(anyhow!("Invalid PBKDF2 iteration count {}", iterations));
+        }
+
+        Ok(PaseResponder {
+            verifier,
+            parameters: PbkdfParameters {
+                iterations,
+                salt: salt.to_vec(),
+            },
+            local_session_id,
+            local_mrp: None,
+            state: ResponderState::AwaitingPbkdfParamRequest,
+        })
+    }
+
+    /// MRP parameters to advertise to the initiator.
+    pub fn with_session_parameters(mut self, params: SessionParameters) -> Self {
+        self.local_mrp = Some(params);
+        self
+    }
+
+    /// Processes `PBKDFParamRequest` and returns the `PBKDFParamResponse` payload.
+    pub fn on_pbkdf_param_request(&mut self, payload: &[u8]) -> Result<Vec<u8>> {
+        if !matches!(self.state, ResponderState::AwaitingPbkdfParamRequest) {
+            return Err(anyhow!("Unexpected PBKDFParamRequest"));
+        }
+        self.state = ResponderState::Finished;
+
+        let request: PbkdfParamRequest = decode(payload)?;
+        check_length("initiator random", &request.initiator_random, RANDOM_LENGTH)?;
+        if request.passcode_id != 0 {
+            return Err(anyhow!("Unknown passcode id {}", request.passcode_id));
+        }
+
+        let response = encode(&PbkdfParamResponse {
+            initiator_random: request.initiator_random,
+            responder_random: random(),
+            responder_session_id: self.local_session_id,
+            pbkdf_parameters: (!request.has_pbkdf_parameters).then(|| self.parameters.clone()),
+            responder_session_params: self.local_mrp,
+        })?;
+
+        self.state = ResponderState::AwaitingPake1 {
+            context: context(payload, &response),
+            peer_session_id: request.initiator_session_id,
+            peer_mrp: request.initiator_session_params,
+        };
+        Ok(response)
+    }
+
+    /// Processes `PASE_Pake1` and returns the `PASE_Pake2` payload.
+    pub fn on_pake1(&mut self, payload: &[u8]) -> Result<Vec<u8>> {
+        let (context, peer_session_id, peer_mrp) =
+            match std::mem::replace(&mut self.state, ResponderState::Finished) {
+                ResponderState::AwaitingPake1 {
+                    context,
+                    peer_session_id,
+                    peer_mrp,
+                } => (context, peer_session_id, peer_mrp),
+                _ => return Err(anyhow!("Unexpected Pake1")),
+            };
+
+        let pake1: Pake1 = decode(payload)?;
+        check_length("pA", &pake1.pa, POINT_LENGTH)?;
+
+        let exchange = VerifierExchange::new(&self.verifier, &context, &pake1.pa)?;
+        let pake2 = encode(&Pake2 {
+            pb: exchange.share().to_vec(),
+            cb: exchange.confirmation().to_vec(),
+        })?;
+
+        self.state = ResponderState::AwaitingPake3 {
+            exchange,
+            peer_session_id,
+            peer_mrp,
+        };
+        Ok(pake2)
+    }
+
+    /// Processes `PASE_Pake3`.
+    ///
+    /// Returns the success report to send to the initiator and the
+    /// parameters of the established session. Fails if the initiator does
+    /// not know the passcode.
+    pub fn on_pake3(&mut self, payload: &[u8]) -> Result<(StatusReport, SecureSessionParams)> {
+        let (exchange, peer_session_id, peer_mrp) =
+            match std::mem::replace(&mut self.state, ResponderState::Finished) {
+                ResponderState::AwaitingPake3 {
+                    exchange,
+                    peer_session_id,
+                    peer_mrp,
+                } => (exchange, peer_session_id, peer_mrp),
+                _ => return Err(anyhow!("Unexpected Pake3")),
+            };
+
+        let pake3: Pake3 = decode(payload)?;
+        check_length("cA", &pake3.ca, CONFIRMATION_LENGTH)?;
+
+        let shared_secret = exchange.finish(&pake3.ca)?;
+        let session = session_params(
+            self.local_session_id,
+            peer_session_id,
+            session_keys(&shared_secret, false),
+            peer_mrp,
+        )?;
+
+        Ok((StatusReport::session_establishment_success(), session))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encryption::MessageCipher;
+    use crate::packet::{HeaderBuilder, MessageDestination};
+    use crate::session::{SessionTable, SessionTableConfig};
+    use hex_literal::hex;
+    use matter_types::NodeId;
+    use tlv_packed::testing::check_vectors;
+
+    const PASSCODE: u32 = 20202021;
+    const SALT: &[u8] = b"SPAKE2P Key Salt";
+    const ITERATIONS: u32 = 1000;
+
+    fn responder(passcode: u32) -> PaseResponder {
+        let verifier = PasscodeSecrets::derive(passcode, SALT, ITERATIONS)
+            .unwrap()
+            .verifier();
+        PaseResponder::new(verifier, SALT, ITERATIONS, 0x2000).unwrap()
+    }
+
+    #[test]
+    fn message_encoding() {
+        check_vectors::<SessionParameters>(&[
+            "15 26 01 88 13 00 00 24 02 2C 25 03 A0 0F 18",
+            "15 18",
+        ]);
+        check_vectors::<PbkdfParameters>(&[
+            "15 25 01 E8 03 30 02 10 53 50 41 4B 45 32 50 20 4B 65 79 20 53 61 6C 74 18",
+        ]);
+        check_vectors::<Pake3>(&["15 30 01 02 AB CD 18"]);
+    }
+
+    /// Known answer for the session key derivation, computed independently
+    /// with a plain Python HKDF-SHA256 from the `Ke` of the SPAKE2+ known
+    /// answer test.
+    #[test]
+    fn session_keys_known_answer() {
+        let keys = session_keys(&hex!("9cf009d4c2aee4751901affe122aaadf"), true);
+        assert_eq!(keys.encrypt_key, hex!("9dabe2db11b9e5b6aae3210ff41df364"));
+        assert_eq!(keys.decrypt_key, hex!("fd12d248da9f64055da00eb10c6045c9"));
+        assert_eq!(
+            keys.attestation_challenge,
+            hex!("8564866352856d5dd1e0f39029fb21d2")
+        );
+
+        let keys = session_keys(&hex!("9cf009d4c2aee4751901affe122aaadf"), false);
+        assert_eq!(keys.encrypt_key, hex!("fd12d248da9f64055da00eb10c6045c9"));
+        assert_eq!(keys.decrypt_key, hex!("9dabe2db11b9e5b6aae3210ff41df364"));
+    }
+
+    #[test]
+    fn end_to_end() {
+        let mut initiator =
+            PaseInitiator::new(PASSCODE, 0x1000).with_session_parameters(SessionParameters {
+                idle_interval: Some(5000),
+                ..Default::default()
+            });
+        let mut responder = responder(PASSCODE);
+
+        let request = initiator.start().unwrap();
+        let response = responder.on_pbkdf_param_request(&request).unwrap();
+        let pake1 = initiator.on_pbkdf_param_response(&response).unwrap();
+        let pake2 = responder.on_pake1(&pake1).unwrap();
+        let pake3 = initiator.on_pake2(&pake2).unwrap();
+        let (report, responder_session) = responder.on_pake3(&pake3).unwrap();
+
+        let mut data = Vec::new();
+        report.write(&mut data).unwrap();
+        let report = StatusReport::parse(&mut data.as_slice()).unwrap();
+        let initiator_session = initiator.on_status_report(&report).unwrap();
+
+        assert_eq!(initiator_session.kind, SecureSessionKind::Pase);
+        assert_eq!(initiator_session.local_session_id, 0x1000);
+        assert_eq!(initiator_session.peer_session_id, 0x2000);
+        assert_eq!(responder_session.local_session_id, 0x2000);
+        assert_eq!(responder_session.peer_session_id, 0x1000);
+        assert_eq!(
+            responder_session.peer_mrp.idle_interval,
+            Duration::from_millis(5000)
+        );
+        assert_eq!(initiator_session.peer_mrp, MrpConfig::default());
+
+        assert_eq!(
+            initiator_session.keys.encrypt_key,
+            responder_session.keys.decrypt_key
+        );
+        assert_eq!(
+            initiator_session.keys.decrypt_key,
+            responder_session.keys.encrypt_key
+        );
+        assert_eq!(
+            initiator_session.keys.attestation_challenge,
+            responder_session.keys.attestation_challenge
+        );
+
+        // a message encrypted by the initiator is readable by the responder
+        let cipher = MessageCipher::new(&initiator_session.keys.encrypt_key);
+        let header = HeaderBuilder::default()
+            .session_id(initiator_session.peer_session_id)
+            .counter(1)
+            .destination(MessageDestination::None)
+            .source(None)
+            .build()
+            .unwrap();
+        let message = cipher.encrypt(&header, None, NodeId(0), b"hello").unwrap();
+
+        let cipher = MessageCipher::new(&responder_session.keys.decrypt_key);
+        assert_eq!(
+            cipher.decrypt(&message, NodeId(0)).unwrap().payload,
+            b"hello"
+        );
+
+        // and both sessions can be added to session tables
+        let mut table = SessionTable::new(SessionTableConfig::default());
+        table.add_secure(initiator_session).unwrap();
+        table.add_secure(responder_session).unwrap();
+    }
+
+    #[test]
+    fn known_pbkdf_parameters() {
+        let mut initiator =
+            PaseInitiator::new(PASSCODE, 1).with_pbkdf_parameters(PbkdfParameters {
+                iterations: ITERATIONS,
+                salt: SALT.to_vec(),
+            });
+        let mut responder = responder(PASSCODE);
+
+        let request = initiator.start().unwrap();
+        let response = responder.on_pbkdf_param_request(&request).unwrap();
+        let decoded: PbkdfParamResponse = decode(&response).unwrap();
+        assert_eq!(decoded.pbkdf_parameters, None);
+
+        let pake1 = initiator.on_pbkdf_param_response(&response).unwrap();
+        let pake2 = responder.on_pake1(&pake1).unwrap();
+        let pake3 = initiator.on_pake2(&pake2).unwrap();
+        assert!(responder.on_pake3(&pake3).is_ok());
+    }
+
+    #[test]
+    fn wrong_passcode() {
+        let mut initiator = PaseInitiator::new(PASSCODE + 1, 1);
+        let mut responder = responder(PASSCODE);
+
+        let request = initiator.start().unwrap();
+        let response = responder.on_pbkdf_param_request(&request).unwrap();
+        let pake1 = initiator.on_pbkdf_param_response(&response).unwrap();
+        let pake2 = responder.on_pake1(&pake1).unwrap();
+
+        // initiator notices that the responder confirmation does not match
+        assert!(initiator.on_pake2(&pake2).is_err());
+        assert!(initiator
+            .on_status_report(&StatusReport::invalid_parameter())
+            .is_err());
+    }
+
+    #[test]
+    fn forged_confirmation() {
+        let mut initiator = PaseInitiator::new(PASSCODE, 1);
+        let mut responder = responder(PASSCODE);
+
+        let request = initiator.start().unwrap();
+        let response = responder.on_pbkdf_param_request(&request).unwrap();
+        let pake1 = initiator.on_pbkdf_param_response(&response).unwrap();
+        responder.on_pake1(&pake1).unwrap();
+
+        let pake3 = encode(&Pake3 {
+            ca: vec![0; CONFIRMATION_LENGTH],
+        })
+        .unwrap();
+        assert!(responder.on_pake3(&pake3).is_err());
+    }
+
+    #[test]
+    fn tampered_parameters() {
+        let mut initiator = PaseInitiator::new(PASSCODE, 1);
+        let mut responder = responder(PASSCODE);
+
+        let request = initiator.start().unwrap();
+        let response = responder.on_pbkdf_param_request(&request).unwrap();
+
+        // change the responder session id: contexts no longer match
+        let mut decoded: PbkdfParamResponse = decode(&response).unwrap();
+        decoded.responder_session_id += 1;
+        let response = encode(&decoded).unwrap();
+
+        let pake1 = initiator.on_pbkdf_param_response(&response).unwrap();
+        let pake2 = responder.on_pake1(&pake1).unwrap();
+        assert!(initiator.on_pake2(&pake2).is_err());
+    }
+
+    #[test]
+    fn unexpected_messages() {
+        let mut initiator = PaseInitiator::new(PASSCODE, 1);
+        let request = initiator.start().unwrap();
+        assert!(initiator.start().is_err());
+        assert!(initiator.on_pake2(&[]).is_err());
+
+        // an unexpected message aborts the exchange
+        assert!(initiator.on_pbkdf_param_response(&[]).is_err());
+
+        let mut responder = responder(PASSCODE);
+        assert!(responder.on_pake1(&[]).is_err());
+        assert!(responder.on_pbkdf_param_request(&request).is_err());
+
+        // responder rejects a response in place of a request
+        let response = self::responder(PASSCODE)
+            .on_pbkdf_param_request(&request)
+            .unwrap();
+        assert!(self::responder(PASSCODE)
+            .on_pbkdf_param_request(&response)
+            .is_err());
+    }
+}
@@ -299,7 +299,8 @@ impl Header {
     /// Parses a given buffer and interprets it as a MATTER message.
     ///
     /// It does NOT skip over secured extensions (but flag is parsed and can
-    /// be used as needed).
+    /// be used as needed). Use [crate::message::ProtocolMessage::parse] to
+    /// also handle extensions.
     ///
     /// Examples:
     ///
@@ -425,7 +426,7 @@ impl Header {
     /// ]);
     /// ```
     pub fn write(&self, writer: &mut impl LittleEndianWriter) -> Result<()> {
-        let mut flags = self.flags.clone();
+        let mut flags = self.flags;
         flags.set(
             ExchangeFlags::VENDOR,
             matches!(self.protocol_opcode, ProtocolOpCode::Vendor { .. }),
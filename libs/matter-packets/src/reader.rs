@@ -25,13 +25,14 @@ pub trait BytesSource {
 
 impl BytesSource for &[u8] {
     fn read(&mut self, count: usize) -> core::result::Result<&[u8], EndianReadError> {
-        self.take(..count).ok_or(EndianReadError::InsufficientData)
+        self.split_off(..count)
+            .ok_or(EndianReadError::InsufficientData)
     }
 }
 
 impl BytesSource for &mut [u8] {
     fn read(&mut self, count: usize) -> core::result::Result<&[u8], EndianReadError> {
-        match self.take_mut(..count) {
+        match self.split_off_mut(..count) {
             Some(data) => Ok(data),
             None => Err(EndianReadError::InsufficientData),
         }
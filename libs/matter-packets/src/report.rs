@@ -0,0 +1,604 @@
+//! Chunking of attribute reports.
+//!
+//! Reports that do not fit in a single message are split across several
+//! `ReportData` messages, all but the last one with `MoreChunkedMessages`
+//! set. List attributes too large for one message are sent as an empty list
+//! followed by one append operation (a null `ListIndex`) per item.
+//!
+//! [chunk_report] splits an [AttributeSnapshot] into such messages on the
+//! server side, and a [ReportAssembler] rebuilds the snapshot on the client
+//! side. [max_payload_size] gives the payload size that fits a given MTU.
+
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Result};
+
+use crate::interaction_model::{
+    AttributeDataIb, AttributePathIb, AttributeReportIb, AttributeStatusIb, ClusterPathIb,
+    DataVersionFilterIb, EventReportIb, ReportData, StatusIb, TlvData,
+};
+use crate::message::{Message, MIC_LENGTH};
+use crate::packet;
+use crate::payload;
+use crate::tlv::encode;
+use crate::writer::SpaceEstimator;
+
+/// An existing cluster on an endpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ConcreteClusterPath {
+    pub endpoint: u16,
+    pub cluster: u32,
+}
+
+/// An existing attribute on an endpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ConcreteAttributePath {
+    pub endpoint: u16,
+    pub cluster: u32,
+    pub attribute: u32,
+}
+
+impl ConcreteAttributePath {
+    /// Converts a path without wildcards.
+    pub fn from_path(path: &AttributePathIb) -> Option<Self> {
+        Some(Self {
+            endpoint: path.endpoint?,
+            cluster: path.cluster?,
+            attribute: path.attribute?,
+        })
+    }
+
+    pub fn to_path(self) -> AttributePathIb {
+        AttributePathIb {
+            endpoint: Some(self.endpoint),
+            cluster: Some(self.cluster),
+            attribute: Some(self.attribute),
+            ..Default::default()
+        }
+    }
+
+    pub fn cluster_path(self) -> ConcreteClusterPath {
+        ConcreteClusterPath {
+            endpoint: self.endpoint,
+            cluster: self.cluster,
+        }
+    }
+}
+
+/// Reported state of an attribute.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttributeValue {
+    Data(TlvData),
+
+    /// The attribute could not be reported.
+    Status(StatusIb),
+}
+
+/// Attribute values and data versions of a node, as seen by a report.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct AttributeSnapshot {
+    pub attributes: BTreeMap<ConcreteAttributePath, AttributeValue>,
+    pub data_versions: BTreeMap<ConcreteClusterPath, u32>,
+}
+
+impl AttributeSnapshot {
+    /// Filters for the clusters with a known data version, so that a
+    /// following read or subscription skips unchanged clusters.
+    pub fn data_version_filters(&self) -> Vec<DataVersionFilterIb> {
+        self.data_versions
+            .iter()
+            .map(|(path, version)| DataVersionFilterIb {
+                path: ClusterPathIb {
+                    node: None,
+                    endpoint: Some(path.endpoint),
+                    cluster: Some(path.cluster),
+                },
+                data_version: *version,
+            })
+            .collect()
+    }
+}
+
+/// Combines chunked `ReportData` messages into an [AttributeSnapshot].
+///
+/// The same assembler can be used for all reports of a subscription: every
+/// report updates the snapshot built by the previous ones.
+#[derive(Debug, Default)]
+pub struct ReportAssembler {
+    snapshot: AttributeSnapshot,
+    events: Vec<EventReportIb>,
+    subscription_id: Option<u32>,
+
+    /// A chunked report is in progress.
+    pending: bool,
+}
+
+impl ReportAssembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Applies a received report. Returns `true` once the last chunk of the
+    /// report was received.
+    pub fn add(&mut self, report: &ReportData) -> Result<bool> {
+        match (self.subscription_id, report.subscription_id) {
+            (None, id) => self.subscription_id = id,
+            (Some(expected), Some(id)) if expected == id => {}
+            _ => return Err(anyhow!("Report for another subscription")),
+        }
+
+        for attribute_report in report.attribute_reports.iter().flatten() {
+            match attribute_report {
+                AttributeReportIb {
+                    attribute_status: Some(status),
+                    attribute_data: None,
+                } => self.add_status(status)?,
+                AttributeReportIb {
+                    attribute_status: None,
+                    attribute_data: Some(data),
+                } => self.add_data(data)?,
+                _ => {
+                    return Err(anyhow!(
+                        "Attribute report without exactly one status or data"
+                    ))
+                }
+            }
+        }
+
+        self.events
+            .extend(report.event_reports.iter().flatten().cloned());
+
+        self.pending = report.more_chunked_messages == Some(true);
+        Ok(!self.pending)
+    }
+
+    fn add_status(&mut self, status: &AttributeStatusIb) -> Result<()> {
+        let path = ConcreteAttributePath::from_path(&status.path)
+            .ok_or_else(|| anyhow!("Attribute status without concrete path"))?;
+        self.snapshot
+            .attributes
+            .insert(path, AttributeValue::Status(status.status.clone()));
+        Ok(())
+    }
+
+    fn add_data(&mut self, data: &AttributeDataIb) -> Result<()> {
+        let path = ConcreteAttributePath::from_path(&data.path)
+            .ok_or_else(|| anyhow!("Attribute data without concrete path"))?;
+
+        match data.path.list_index {
+            None => {
+                self.snapshot
+                    .attributes
+                    .insert(path, AttributeValue::Data(data.data.clone()));
+            }
+            Some(None) => {
+                if let Some(version) = data.data_version {
+                    if self.snapshot.data_versions.get(&path.cluster_path()) != Some(&version) {
+                        return Err(anyhow!(
+                            "Data version changed while appending to {:?}",
+                            path
+                        ));
+                    }
+                }
+
+                let list = match self.snapshot.attributes.get_mut(&path) {
+                    Some(AttributeValue::Data(list)) => list,
+                    _ => return Err(anyhow!("Append to unknown list {:?}", path)),
+                };
+                let mut items = list
+                    .array_items()
+                    .ok_or_else(|| anyhow!("Append to {:?}, which is not a list", path))?;
+                items.push(data.data.clone());
+                *list = TlvData::array(&items);
+            }
+            Some(Some(index)) => {
+                return Err(anyhow!(
+                    "Unsupported list index {} in report of {:?}",
+                    index,
+                    path
+                ))
+            }
+        }
+
+        if let Some(version) = data.data_version {
+            self.snapshot
+                .data_versions
+                .insert(path.cluster_path(), version);
+        }
+        Ok(())
+    }
+
+    /// A chunked report was started but its last chunk was not received yet.
+    pub fn is_pending(&self) -> bool {
+        self.pending
+    }
+
+    pub fn subscription_id(&self) -> Option<u32> {
+        self.subscription_id
+    }
+
+    pub fn snapshot(&self) -> &AttributeSnapshot {
+        &self.snapshot
+    }
+
+    pub fn into_snapshot(self) -> AttributeSnapshot {
+        self.snapshot
+    }
+
+    /// Returns the event reports received so far.
+    pub fn take_events(&mut self) -> Vec<EventReportIb> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+/// Attribute reports of one attribute: the full value if it fits in a
+/// message, otherwise an empty list followed by one append per item.
+fn attribute_reports(
+    path: ConcreteAttributePath,
+    value: &AttributeValue,
+    data_version: Option<u32>,
+    fits: impl Fn(&AttributeReportIb) -> Result<bool>,
+) -> Result<Vec<AttributeReportIb>> {
+    let data = |path: AttributePathIb, data: TlvData| AttributeReportIb {
+        attribute_status: None,
+        attribute_data: Some(AttributeDataIb {
+            data_version,
+            path,
+            data,
+        }),
+    };
+
+    let report = match value {
+        AttributeValue::Status(status) => AttributeReportIb {
+            attribute_status: Some(AttributeStatusIb {
+                path: path.to_path(),
+                status: status.clone(),
+            }),
+            attribute_data: None,
+        },
+        AttributeValue::Data(value) => data(path.to_path(), value.clone()),
+    };
+
+    if fits(&report)? {
+        return Ok(vec![report]);
+    }
+
+    let items = match value {
+        AttributeValue::Data(value) => value.array_items(),
+        AttributeValue::Status(_) => None,
+    }
+    .ok_or_else(|| anyhow!("Attribute {:?} does not fit in a report", path))?;
+
+    let mut reports = vec![data(path.to_path(), TlvData::array(&[]))];
+    for item in items {
+        reports.push(data(
+            AttributePathIb {
+                list_index: Some(None),
+                ..path.to_path()
+            },
+            item,
+        ));
+    }
+    Ok(reports)
+}
+
+/// Space left for the Interaction Model payload of a message sent with
+/// `header` and `protocol_header` in a packet of at most `mtu` bytes.
+///
+/// Accounts for the message header, the protocol header and, for secured
+/// sessions, the message integrity check. `protocol_header` should carry an
+/// acknowledgement counter if one may be piggybacked on the message.
+pub fn max_payload_size(
+    mtu: usize,
+    header: &packet::Header,
+    protocol_header: &payload::Header,
+) -> Result<usize> {
+    let mut estimator = SpaceEstimator::default();
+    Message::write_header(header, None, &mut estimator)?;
+    protocol_header.write(&mut estimator)?;
+
+    let mic = match header.is_secured() {
+        true => MIC_LENGTH,
+        false => 0,
+    };
+
+    mtu.checked_sub(estimator.written() + mic)
+        .ok_or_else(|| anyhow!("MTU {} is too small for the message headers", mtu))
+}
+
+/// Splits `snapshot` into `ReportData` messages whose payload is at most
+/// `max_payload_size` bytes.
+///
+/// `max_payload_size` is the space left for the Interaction Model payload
+/// once message and protocol headers and the message integrity check are
+/// accounted for, see [max_payload_size].
+pub fn chunk_report(
+    snapshot: &AttributeSnapshot,
+    subscription_id: Option<u32>,
+    max_payload_size: usize,
+) -> Result<Vec<ReportData>> {
+    let message = |attribute_reports: Vec<AttributeReportIb>, more_chunked_messages| ReportData {
+        subscription_id,
+        attribute_reports: Some(attribute_reports),
+        more_chunked_messages,
+        ..Default::default()
+    };
+
+    // every report is an array element, so message sizes add up
+    let empty_size = encode(&message(Vec::new(), Some(true)))?.len();
+    if empty_size > max_payload_size {
+        return Err(anyhow!("Payload size too small for any report"));
+    }
+    let available = max_payload_size - empty_size;
+    let fits = |report: &AttributeReportIb| Ok(encode(report)?.len() <= available);
+
+    let mut messages = Vec::new();
+    let mut current = Vec::new();
+    let mut current_size = 0;
+
+    for (path, value) in &snapshot.attributes {
+        let data_version = snapshot.data_versions.get(&path.cluster_path()).copied();
+
+        for report in attribute_reports(*path, value, data_version, fits)? {
+            let size = encode(&report)?.len();
+            if size > available {
+                return Err(anyhow!(
+                    "List item of attribute {:?} does not fit in a report",
+                    path
+                ));
+            }
+
+            if current_size + size > available {
+                messages.push(message(std::mem::take(&mut current), Some(true)));
+                current_size = 0;
+            }
+            current.push(report);
+            current_size += size;
+        }
+    }
+
+    messages.push(message(current, None));
+    Ok(messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encryption::MessageCipher;
+    use crate::interaction_model::{InteractionModelMessage, StatusCode};
+    use crate::message::ProtocolMessage;
+    use crate::packet::{HeaderBuilder, MessageDestination};
+    use crate::payload::{ExchangeFlags, InteractionModelOpcode, ProtocolOpCode};
+    use matter_types::{ExchangeId, NodeId};
+    use tlv_packed::testing::hex_to_bytes;
+
+    fn path(endpoint: u16, cluster: u32, attribute: u32) -> ConcreteAttributePath {
+        ConcreteAttributePath {
+            endpoint,
+            cluster,
+            attribute,
+        }
+    }
+
+    /// A snapshot with small values, a status and a list of `items` strings.
+    fn snapshot(items: usize) -> AttributeSnapshot {
+        let list: Vec<_> = (0..items)
+            .map(|i| TlvData::new(&format!("item {:04}", i)).unwrap())
+            .collect();
+
+        let mut snapshot = AttributeSnapshot::default();
+        snapshot.attributes.insert(
+            path(0, 0x28, 1),
+            AttributeValue::Data(TlvData::new(&"vendor").unwrap()),
+        );
+        snapshot.attributes.insert(
+            path(0, 0x28, 0x99),
+            AttributeValue::Status(StatusIb::new(StatusCode::UnsupportedAttribute)),
+        );
+        snapshot.attributes.insert(
+            path(1, 0x1D, 0),
+            AttributeValue::Data(TlvData::array(&list)),
+        );
+        snapshot.attributes.insert(
+            path(1, 0x06, 0),
+            AttributeValue::Data(TlvData::new(&true).unwrap()),
+        );
+        snapshot
+            .data_versions
+            .insert(path(0, 0x28, 0).cluster_path(), 0x1000);
+        snapshot
+            .data_versions
+            .insert(path(1, 0x1D, 0).cluster_path(), 0x2000);
+        snapshot
+    }
+
+    fn reassemble(messages: &[ReportData]) -> AttributeSnapshot {
+        let mut assembler = ReportAssembler::new();
+        for (idx, message) in messages.iter().enumerate() {
+            // through the wire format
+            let message = ReportData::from_payload(&message.to_payload().unwrap()).unwrap();
+            assert_eq!(assembler.add(&message).unwrap(), idx == messages.len() - 1);
+        }
+        assert!(!assembler.is_pending());
+        assembler.into_snapshot()
+    }
+
+    #[test]
+    fn single_message() {
+        let snapshot = snapshot(3);
+        let messages = chunk_report(&snapshot, Some(7), 1024).unwrap();
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].more_chunked_messages, None);
+        assert_eq!(messages[0].subscription_id, Some(7));
+        assert_eq!(messages[0].attribute_reports.as_ref().unwrap().len(), 4);
+        assert_eq!(reassemble(&messages), snapshot);
+    }
+
+    #[test]
+    fn chunked_lists() {
+        let snapshot = snapshot(40);
+
+        for max_payload_size in [96, 128, 200, 400] {
+            let messages = chunk_report(&snapshot, None, max_payload_size).unwrap();
+            assert!(messages.len() > 1);
+
+            for (idx, message) in messages.iter().enumerate() {
+                assert!(message.to_payload().unwrap().len() <= max_payload_size);
+                let last = idx == messages.len() - 1;
+                assert_eq!(message.more_chunked_messages, (!last).then_some(true));
+            }
+
+            let appends = messages
+                .iter()
+                .flat_map(|m| m.attribute_reports.iter().flatten())
+                .filter_map(|r| r.attribute_data.as_ref())
+                .filter(|d| d.path.list_index == Some(None))
+                .count();
+            assert_eq!(appends, 40);
+
+            assert_eq!(reassemble(&messages), snapshot);
+        }
+
+        assert!(chunk_report(&snapshot, None, 40).is_err());
+    }
+
+    #[test]
+    fn empty_snapshot() {
+        let messages = chunk_report(&AttributeSnapshot::default(), None, 64).unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(reassemble(&messages), AttributeSnapshot::default());
+    }
+
+    #[test]
+    fn subscription_updates() {
+        let mut assembler = ReportAssembler::new();
+        for message in chunk_report(&snapshot(10), Some(3), 128).unwrap() {
+            assembler.add(&message).unwrap();
+        }
+
+        // a later report replaces the list and bumps the data version
+        let update = ReportData::from_payload(&hex_to_bytes(
+            "15 25 00 03 00 36 01 15 35 01 25 00 01 20 37 01 24 02 01 24 03 1D 24 04 00 18 \
+             36 02 0C 01 61 18 18 18 18 24 FF 01 18",
+        ))
+        .unwrap();
+        assert!(assembler.add(&update).unwrap());
+
+        let snapshot = assembler.snapshot();
+        assert_eq!(
+            snapshot.attributes[&path(1, 0x1D, 0)],
+            AttributeValue::Data(TlvData::array(&[TlvData::new(&"a").unwrap()]))
+        );
+        assert_eq!(
+            snapshot.data_version_filters()[1],
+            DataVersionFilterIb {
+                path: ClusterPathIb {
+                    node: None,
+                    endpoint: Some(1),
+                    cluster: Some(0x1D),
+                },
+                data_version: 0x2001,
+            }
+        );
+
+        let other_subscription = ReportData {
+            subscription_id: Some(4),
+            ..Default::default()
+        };
+        assert!(assembler.add(&other_subscription).is_err());
+    }
+
+    #[test]
+    fn invalid_reports() {
+        let append = |data_version| ReportData {
+            attribute_reports: Some(vec![AttributeReportIb {
+                attribute_status: None,
+                attribute_data: Some(AttributeDataIb {
+                    data_version,
+                    path: AttributePathIb {
+                        list_index: Some(None),
+                        ..path(1, 0x1D, 0).to_path()
+                    },
+                    data: TlvData::new(&1u8).unwrap(),
+                }),
+            }]),
+            ..Default::default()
+        };
+
+        // append without a list
+        assert!(ReportAssembler::new().add(&append(None)).is_err());
+
+        let mut assembler = ReportAssembler::new();
+        let list = chunk_report(&snapshot(1), None, 1024).unwrap();
+        assembler.add(&list[0]).unwrap();
+        assert!(assembler.add(&append(Some(0x2000))).is_ok());
+        assert!(assembler.add(&append(Some(0x2001))).is_err());
+
+        // wildcard paths are not reported
+        let wildcard = ReportData {
+            attribute_reports: Some(vec![AttributeReportIb {
+                attribute_status: None,
+                attribute_data: Some(AttributeDataIb::default()),
+            }]),
+            ..Default::default()
+        };
+        assert!(ReportAssembler::new().add(&wildcard).is_err());
+        assert!(ReportAssembler::new()
+            .add(&ReportData {
+                attribute_reports: Some(vec![AttributeReportIb::default()]),
+                ..Default::default()
+            })
+            .is_err());
+    }
+
+    #[test]
+    fn chunks_fit_the_mtu() {
+        const MTU: usize = 256;
+
+        let header = HeaderBuilder::default()
+            .session_id(0x1234)
+            .counter(1)
+            .source(Some(NodeId(0x1122_3344_5566_7788)))
+            .destination(MessageDestination::Node(NodeId(0x99)))
+            .build()
+            .unwrap();
+        let protocol_header = payload::HeaderBuilder::default()
+            .flags(ExchangeFlags::ACKNOWLEDGEMENT | ExchangeFlags::RELIABILITY)
+            .protocol_opcode(ProtocolOpCode::InteractionModel(
+                InteractionModelOpcode::ReportData,
+            ))
+            .exchange(ExchangeId(0x4321))
+            .ack_counter(Some(0x0102_0304))
+            .build()
+            .unwrap();
+
+        // 24 bytes message header, 10 bytes protocol header, 16 bytes MIC
+        let payload_size = max_payload_size(MTU, &header, &protocol_header).unwrap();
+        assert_eq!(payload_size, MTU - 24 - 10 - 16);
+
+        let cipher = MessageCipher::new(&[0x5a; 16]);
+        let messages = chunk_report(&snapshot(40), None, payload_size).unwrap();
+        assert!(messages.len() > 1);
+
+        for message in &messages {
+            let payload = message.to_payload().unwrap();
+            let mut plaintext = Vec::new();
+            ProtocolMessage {
+                header: protocol_header,
+                secured_extensions: None,
+                payload: &payload,
+            }
+            .write(&mut plaintext)
+            .unwrap();
+
+            let data = cipher
+                .encrypt(&header, None, NodeId(0x1122_3344_5566_7788), &plaintext)
+                .unwrap();
+            assert!(data.len() <= MTU);
+            assert_eq!(data.len(), MTU - payload_size + payload.len());
+        }
+
+        assert!(max_payload_size(40, &header, &protocol_header).is_err());
+    }
+}
@@ -0,0 +1,608 @@
+//! Session table: the unsecured sessions used for session establishment and
+//! the secure (PASE and CASE) sessions established through them.
+//!
+//! Received messages are matched to a session through their
+//! [packet::Header]: session id 0 selects the unsecured session of the peer,
+//! any other id the secure session with that local session id.
+//!
+//! The table is bounded. When full, the least recently active session of
+//! the same kind is evicted, and sessions idle for longer than the configured
+//! timeout are dropped by [SessionTable::expire]. Removed sessions are
+//! reported through [SessionTable::take_removed] so that dependent state
+//! (e.g. exchanges) can be cleaned up.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use derive_builder::Builder;
+use matter_types::{FabricIndex, NodeId};
+use rand::Rng;
+
+#[cfg(test)]
+use mock_instant::Instant;
+
+#[cfg(not(test))]
+use std::time::Instant;
+
+use crate::counters::{CounterKind, CounterWindow, OutboundCounter};
+use crate::encryption::SessionKey;
+use crate::exchange::SessionHandle;
+use crate::mrp::MrpConfig;
+use crate::packet::{self, MessageDestination, SessionType};
+
+/// Length of the attestation challenge derived alongside session keys.
+pub const ATTESTATION_CHALLENGE_LENGTH: usize = 16;
+
+/// How a secure session was established.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecureSessionKind {
+    /// Passcode-authenticated session, used for commissioning.
+    Pase,
+
+    /// Certificate-authenticated session between operational nodes.
+    Case,
+}
+
+/// Keys of a secure session, from the point of view of this node.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SessionKeys {
+    pub encrypt_key: SessionKey,
+    pub decrypt_key: SessionKey,
+    pub attestation_challenge: [u8; ATTESTATION_CHALLENGE_LENGTH],
+}
+
+impl SessionKeys {
+    /// Assigns the initiator-to-responder and responder-to-initiator keys
+    /// derived by session establishment based on this node's role.
+    pub fn from_derived(
+        i2r_key: SessionKey,
+        r2i_key: SessionKey,
+        attestation_challenge: [u8; ATTESTATION_CHALLENGE_LENGTH],
+        initiator: bool,
+    ) -> Self {
+        let (encrypt_key, decrypt_key) = if initiator {
+            (i2r_key, r2i_key)
+        } else {
+            (r2i_key, i2r_key)
+        };
+
+        SessionKeys {
+            encrypt_key,
+            decrypt_key,
+            attestation_challenge,
+        }
+    }
+}
+
+impl std::fmt::Debug for SessionKeys {
+    // keys are intentionally not printed
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SessionKeys { .. }")
+    }
+}
+
+/// Parameters of a newly established secure session.
+#[derive(Debug, Clone, Builder)]
+pub struct SecureSessionParams {
+    pub kind: SecureSessionKind,
+    pub local_session_id: u16,
+    pub peer_session_id: u16,
+
+    /// Operational node id of the peer (unspecified for PASE).
+    #[builder(default)]
+    pub peer_node: NodeId,
+
+    /// Operational node id of this node on the fabric (unspecified for PASE).
+    #[builder(default)]
+    pub local_node: NodeId,
+
+    #[builder(default)]
+    pub fabric: FabricIndex,
+
+    pub keys: SessionKeys,
+
+    /// Retransmission parameters advertised by the peer.
+    #[builder(default)]
+    pub peer_mrp: MrpConfig,
+}
+
+/// An established PASE or CASE session.
+#[derive(Debug)]
+pub struct SecureSession {
+    pub params: SecureSessionParams,
+    pub outbound: OutboundCounter,
+    pub inbound: CounterWindow,
+    last_activity: Instant,
+}
+
+/// A session without encryption, used to establish secure sessions.
+///
+/// Unsecured sessions share a single global outbound counter, see
+/// [SessionTable::next_counter].
+#[derive(Debug)]
+pub struct UnsecuredSession {
+    /// Ephemeral node id of the initiator of session establishment.
+    pub ephemeral_node: NodeId,
+    pub inbound: CounterWindow,
+    pub peer_mrp: MrpConfig,
+    last_activity: Instant,
+}
+
+#[derive(Debug)]
+pub enum Session {
+    Unsecured(UnsecuredSession),
+    Secure(SecureSession),
+}
+
+impl Session {
+    fn last_activity(&self) -> Instant {
+        match self {
+            Session::Unsecured(session) => session.last_activity,
+            Session::Secure(session) => session.last_activity,
+        }
+    }
+
+    fn touch(&mut self) {
+        let now = Instant::now();
+        match self {
+            Session::Unsecured(session) => session.last_activity = now,
+            Session::Secure(session) => session.last_activity = now,
+        }
+    }
+
+    /// Receive-side counter window of the session.
+    pub fn inbound(&mut self) -> &mut CounterWindow {
+        match self {
+            Session::Unsecured(session) => &mut session.inbound,
+            Session::Secure(session) => &mut session.inbound,
+        }
+    }
+
+    /// Retransmission parameters of the peer.
+    pub fn peer_mrp(&self) -> MrpConfig {
+        match self {
+            Session::Unsecured(session) => session.peer_mrp,
+            Session::Secure(session) => session.params.peer_mrp,
+        }
+    }
+
+    pub fn as_secure(&self) -> Option<&SecureSession> {
+        match self {
+            Session::Secure(session) => Some(session),
+            Session::Unsecured(_) => None,
+        }
+    }
+}
+
+/// Limits of a [SessionTable].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SessionTableConfig {
+    pub max_secure_sessions: usize,
+    pub max_unsecured_sessions: usize,
+
+    /// Sessions without activity for this long are dropped by [SessionTable::expire].
+    pub idle_timeout: Duration,
+}
+
+impl Default for SessionTableConfig {
+    fn default() -> Self {
+        SessionTableConfig {
+            max_secure_sessions: 16,
+            max_unsecured_sessions: 4,
+            idle_timeout: Duration::from_secs(60 * 60),
+        }
+    }
+}
+
+/// Tracks all sessions of a node, see the [module documentation](self).
+pub struct SessionTable {
+    config: SessionTableConfig,
+    next_handle: u32,
+    sessions: HashMap<SessionHandle, Session>,
+    by_local_session_id: HashMap<u16, SessionHandle>,
+    by_ephemeral_node: HashMap<NodeId, SessionHandle>,
+    unencrypted_counter: OutboundCounter,
+    removed: Vec<SessionHandle>,
+}
+
+impl SessionTable {
+    pub fn new(config: SessionTableConfig) -> Self {
+        SessionTable {
+            config,
+            next_handle: 1,
+            sessions: HashMap::new(),
+            by_local_session_id: HashMap::new(),
+            by_ephemeral_node: HashMap::new(),
+            unencrypted_counter: OutboundCounter::new(CounterKind::Unencrypted),
+            removed: Vec::new(),
+        }
+    }
+
+    fn new_handle(&mut self) -> SessionHandle {
+        let handle = SessionHandle(self.next_handle);
+        self.next_handle = self.next_handle.wrapping_add(1);
+        handle
+    }
+
+    /// Picks a random, non-zero local session id not used by any session.
+    pub fn allocate_session_id(&self) -> Result<u16> {
+        if self.by_local_session_id.len() >= u16::MAX as usize {
+            return Err(anyhow!("No free session ids"));
+        }
+
+        let mut rng = rand::thread_rng();
+        loop {
+            let id = rng.gen_range(1..=u16::MAX);
+            if !self.by_local_session_id.contains_key(&id) {
+                return Ok(id);
+            }
+        }
+    }
+
+    /// Returns the unsecured session for an ephemeral initiator node id,
+    /// creating it if needed.
+    pub fn unsecured_session(&mut self, ephemeral_node: NodeId) -> SessionHandle {
+        if let Some(handle) = self.by_ephemeral_node.get(&ephemeral_node) {
+            return *handle;
+        }
+
+        if self.by_ephemeral_node.len() >= self.config.max_unsecured_sessions {
+            self.evict(false);
+        }
+
+        let handle = self.new_handle();
+        self.sessions.insert(
+            handle,
+            Session::Unsecured(UnsecuredSession {
+                ephemeral_node,
+                inbound: CounterWindow::new(CounterKind::Unencrypted),
+                peer_mrp: MrpConfig::default(),
+                last_activity: Instant::now(),
+            }),
+        );
+        self.by_ephemeral_node.insert(ephemeral_node, handle);
+        handle
+    }
+
+    /// Adds a newly established secure session, evicting the least recently
+    /// active secure session if the table is full.
+    pub fn add_secure(&mut self, params: SecureSessionParams) -> Result<SessionHandle> {
+        if params.local_session_id == 0 {
+            return Err(anyhow!("Session id 0 is reserved for unsecured sessions"));
+        }
+        if self
+            .by_local_session_id
+            .contains_key(&params.local_session_id)
+        {
+            return Err(anyhow!(
+                "Local session id {} is already in use",
+                params.local_session_id
+            ));
+        }
+
+        if self.by_local_session_id.len() >= self.config.max_secure_sessions {
+            self.evict(true);
+        }
+
+        let handle = self.new_handle();
+        self.by_local_session_id
+            .insert(params.local_session_id, handle);
+        self.sessions.insert(
+            handle,
+            Session::Secure(SecureSession {
+                params,
+                outbound: OutboundCounter::new(CounterKind::SecureUnicast),
+                inbound: CounterWindow::new(CounterKind::SecureUnicast),
+                last_activity: Instant::now(),
+            }),
+        );
+        Ok(handle)
+    }
+
+    /// Selects the session a received message belongs to.
+    ///
+    /// Unsecured messages are matched by the initiator's ephemeral node id:
+    /// the source node id of messages from the initiator or the destination
+    /// node id of messages to it. `from_initiator` is the initiator flag of the
+    /// (unencrypted) exchange header. An unsecured session is only created for
+    /// messages from an initiator that carry a source node id, never for
+    /// responses. Group messages are not handled by this table.
+    pub fn session_for_header(
+        &mut self,
+        header: &packet::Header,
+        from_initiator: bool,
+    ) -> Option<SessionHandle> {
+        if header.flags.session_type().ok()? != SessionType::Unicast {
+            return None;
+        }
+
+        if header.session_id != 0 {
+            return self.by_local_session_id.get(&header.session_id).copied();
+        }
+
+        match (from_initiator, header.source, header.destination) {
+            (true, Some(source), _) => Some(self.unsecured_session(source)),
+            (false, _, MessageDestination::Node(destination)) => {
+                self.by_ephemeral_node.get(&destination).copied()
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the counter to use for the next message sent on a session.
+    pub fn next_counter(&mut self, handle: SessionHandle) -> Result<u32> {
+        match self.sessions.get_mut(&handle) {
+            Some(Session::Secure(session)) => session.outbound.advance(),
+            Some(Session::Unsecured(_)) => self.unencrypted_counter.advance(),
+            None => Err(anyhow!("Unknown session {:?}", handle)),
+        }
+    }
+
+    pub fn get(&self, handle: SessionHandle) -> Option<&Session> {
+        self.sessions.get(&handle)
+    }
+
+    pub fn get_mut(&mut self, handle: SessionHandle) -> Option<&mut Session> {
+        self.sessions.get_mut(&handle)
+    }
+
+    /// Marks a session as active, e.g. when a message was sent or received.
+    pub fn touch(&mut self, handle: SessionHandle) {
+        if let Some(session) = self.sessions.get_mut(&handle) {
+            session.touch();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
+    }
+
+    /// Removes a session.
+    pub fn remove(&mut self, handle: SessionHandle) -> Option<Session> {
+        let session = self.sessions.remove(&handle)?;
+        match &session {
+            Session::Secure(secure) => {
+                self.by_local_session_id
+                    .remove(&secure.params.local_session_id);
+            }
+            Session::Unsecured(unsecured) => {
+                self.by_ephemeral_node.remove(&unsecured.ephemeral_node);
+            }
+        }
+        self.removed.push(handle);
+        Some(session)
+    }
+
+    /// Removes all secure sessions matching a predicate.
+    fn remove_secure_where(&mut self, predicate: impl Fn(&SecureSessionParams) -> bool) {
+        let handles: Vec<SessionHandle> = self
+            .sessions
+            .iter()
+            .filter_map(|(handle, session)| match session {
+                Session::Secure(secure) if predicate(&secure.params) => Some(*handle),
+                _ => None,
+            })
+            .collect();
+
+        for handle in handles {
+            self.remove(handle);
+        }
+    }
+
+    /// Removes all sessions on a fabric, e.g. when the fabric is removed.
+    pub fn remove_fabric(&mut self, fabric: FabricIndex) {
+        self.remove_secure_where(|params| {
+            params.kind == SecureSessionKind::Case && params.fabric == fabric
+        });
+    }
+
+    /// Removes all CASE sessions with a peer on a fabric.
+    pub fn remove_peer(&mut self, fabric: FabricIndex, peer_node: NodeId) {
+        self.remove_secure_where(|params| {
+            params.kind == SecureSessionKind::Case
+                && params.fabric == fabric
+                && params.peer_node == peer_node
+        });
+    }
+
+    /// Drops sessions idle for longer than the configured timeout.
+    pub fn expire(&mut self) {
+        let idle_timeout = self.config.idle_timeout;
+        let expired: Vec<SessionHandle> = self
+            .sessions
+            .iter()
+            .filter(|(_, session)| session.last_activity().elapsed() > idle_timeout)
+            .map(|(handle, _)| *handle)
+            .collect();
+
+        for handle in expired {
+            self.remove(handle);
+        }
+    }
+
+    /// Returns the sessions removed (explicitly, by eviction or by expiry)
+    /// since the last call.
+    pub fn take_removed(&mut self) -> Vec<SessionHandle> {
+        std::mem::take(&mut self.removed)
+    }
+
+    /// Removes the least recently active secure or unsecured session.
+    fn evict(&mut self, secure: bool) {
+        let oldest = self
+            .sessions
+            .iter()
+            .filter(|(_, session)| matches!(session, Session::Secure(_)) == secure)
+            .min_by_key(|(_, session)| session.last_activity())
+            .map(|(handle, _)| *handle);
+
+        if let Some(handle) = oldest {
+            self.remove(handle);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet::{HeaderBuilder, SecurityFlags};
+    use matter_types::GroupId;
+    use mock_instant::MockClock;
+
+    fn keys() -> SessionKeys {
+        SessionKeys::from_derived([1; 16], [2; 16], [3; 16], true)
+    }
+
+    fn case_session(local_session_id: u16, fabric: u8, peer: u64) -> SecureSessionParams {
+        SecureSessionParamsBuilder::default()
+            .kind(SecureSessionKind::Case)
+            .local_session_id(local_session_id)
+            .peer_session_id(local_session_id + 1000)
+            .peer_node(NodeId(peer))
+            .fabric(FabricIndex(fabric))
+            .keys(keys())
+            .build()
+            .unwrap()
+    }
+
+    fn header(session_id: u16) -> packet::Header {
+        HeaderBuilder::default()
+            .session_id(session_id)
+            .counter(1)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn keys_follow_role() {
+        let initiator = SessionKeys::from_derived([1; 16], [2; 16], [3; 16], true);
+        let responder = SessionKeys::from_derived([1; 16], [2; 16], [3; 16], false);
+        assert_eq!(initiator.encrypt_key, responder.decrypt_key);
+        assert_eq!(initiator.decrypt_key, responder.encrypt_key);
+        assert_eq!(format!("{:?}", initiator), "SessionKeys { .. }");
+    }
+
+    #[test]
+    fn sessions_are_selected_by_header() {
+        let mut table = SessionTable::new(SessionTableConfig::default());
+        let secure = table.add_secure(case_session(10, 1, 5)).unwrap();
+
+        assert_eq!(table.session_for_header(&header(10), true), Some(secure));
+        assert_eq!(table.session_for_header(&header(11), true), None);
+
+        // unsecured: matched by source, or destination for replies
+        let mut request = header(0);
+        request.source = Some(NodeId(0xabc));
+        let unsecured = table.session_for_header(&request, true).unwrap();
+        assert_ne!(unsecured, secure);
+
+        let mut reply = header(0);
+        reply.destination = MessageDestination::Node(NodeId(0xabc));
+        assert_eq!(table.session_for_header(&reply, false), Some(unsecured));
+
+        // responses and messages without a source never create sessions
+        reply.destination = MessageDestination::Node(NodeId(0xdef));
+        assert_eq!(table.session_for_header(&reply, false), None);
+        request.source = Some(NodeId(0xdef));
+        assert_eq!(table.session_for_header(&request, false), None);
+        assert_eq!(table.session_for_header(&header(0), true), None);
+        assert_eq!(table.len(), 2);
+
+        // group messages are not unicast sessions
+        let mut group = header(10);
+        group.flags = SecurityFlags::SESSION_TYPE_BIT1;
+        group.destination = MessageDestination::Group(GroupId(1));
+        assert_eq!(table.session_for_header(&group, true), None);
+
+        assert!(table.get(secure).unwrap().as_secure().is_some());
+        assert!(table.get(unsecured).unwrap().as_secure().is_none());
+    }
+
+    #[test]
+    fn session_ids() {
+        let mut table = SessionTable::new(SessionTableConfig::default());
+        let id = table.allocate_session_id().unwrap();
+        assert_ne!(id, 0);
+
+        table.add_secure(case_session(id, 1, 5)).unwrap();
+        assert!(table.add_secure(case_session(id, 1, 6)).is_err());
+        assert!(table.add_secure(case_session(0, 1, 6)).is_err());
+        assert_ne!(table.allocate_session_id().unwrap(), id);
+    }
+
+    #[test]
+    fn counters() {
+        let mut table = SessionTable::new(SessionTableConfig::default());
+        let secure = table.add_secure(case_session(10, 1, 5)).unwrap();
+        let a = table.unsecured_session(NodeId(1));
+        let b = table.unsecured_session(NodeId(2));
+
+        // unsecured sessions share the global counter
+        let first = table.next_counter(a).unwrap();
+        assert_eq!(table.next_counter(b).unwrap(), first.wrapping_add(1));
+
+        let secure_first = table.next_counter(secure).unwrap();
+        assert_eq!(table.next_counter(secure).unwrap(), secure_first + 1);
+
+        assert!(table.next_counter(SessionHandle(999)).is_err());
+    }
+
+    #[test]
+    fn eviction_of_least_recently_active() {
+        let mut table = SessionTable::new(SessionTableConfig {
+            max_secure_sessions: 2,
+            max_unsecured_sessions: 1,
+            idle_timeout: Duration::from_secs(100),
+        });
+
+        let first = table.add_secure(case_session(1, 1, 5)).unwrap();
+        MockClock::advance(Duration::from_secs(1));
+        let second = table.add_secure(case_session(2, 1, 6)).unwrap();
+        MockClock::advance(Duration::from_secs(1));
+        table.touch(first);
+
+        let third = table.add_secure(case_session(3, 1, 7)).unwrap();
+        assert_eq!(table.take_removed(), vec![second]);
+        assert!(table.get(first).is_some());
+        assert!(table.get(third).is_some());
+
+        // unsecured sessions are limited independently
+        let a = table.unsecured_session(NodeId(1));
+        let b = table.unsecured_session(NodeId(2));
+        assert_eq!(table.take_removed(), vec![a]);
+        assert_eq!(table.len(), 3);
+        assert_eq!(table.unsecured_session(NodeId(2)), b);
+    }
+
+    #[test]
+    fn expiry_and_fabric_removal() {
+        let mut table = SessionTable::new(SessionTableConfig {
+            idle_timeout: Duration::from_secs(10),
+            ..Default::default()
+        });
+
+        let idle = table.add_secure(case_session(1, 1, 5)).unwrap();
+        let active = table.add_secure(case_session(2, 1, 6)).unwrap();
+        let other_fabric = table.add_secure(case_session(3, 2, 6)).unwrap();
+
+        MockClock::advance(Duration::from_secs(8));
+        table.touch(active);
+        table.touch(other_fabric);
+        MockClock::advance(Duration::from_secs(8));
+
+        table.expire();
+        assert_eq!(table.take_removed(), vec![idle]);
+        assert_eq!(table.session_for_header(&header(1), true), None);
+
+        table.remove_peer(FabricIndex(1), NodeId(6));
+        assert_eq!(table.take_removed(), vec![active]);
+
+        table.remove_fabric(FabricIndex(2));
+        assert_eq!(table.take_removed(), vec![other_fabric]);
+        assert!(table.is_empty());
+    }
+}
@@ -0,0 +1,616 @@
+//! SPAKE2+ over P-256 with SHA-256, HKDF and HMAC, as used by PASE.
+//!
+//! The prover (commissioner) knows the passcode and derives `w0` and `w1`
+//! from it through PBKDF2. The verifier (commissionee) only stores `w0` and
+//! `L = w1 * P` (see [Verifier]), so the passcode itself does not need to be
+//! kept on the device.
+//!
+//! ```text
+//! prover:   X = x * P + w0 * M      Z = x * (Y - w0 * N)    V = w1 * (Y - w0 * N)
+//! verifier: Y = y * P + w0 * N      Z = y * (X - w0 * M)    V = y * L
+//! ```
+//!
+//! Both sides hash the transcript `TT` into `Ka || Ke`, derive the
+//! confirmation keys `KcA || KcB` from `Ka` and exchange
+//! `cA = HMAC(KcA, Y)` and `cB = HMAC(KcB, X)`. `Ke` is the shared secret
+//! from which session keys are derived.
+
+use anyhow::{anyhow, Result};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use p256::elliptic_curve::ops::Reduce;
+use p256::elliptic_curve::sec1::{FromEncodedPoint, ToEncodedPoint};
+use p256::elliptic_curve::Field;
+use p256::{AffinePoint, EncodedPoint, ProjectivePoint, Scalar, U256};
+use sha2::{Digest, Sha256};
+
+/// Length of an uncompressed P-256 point.
+pub const POINT_LENGTH: usize = 65;
+
+/// Length of a P-256 scalar.
+pub const SCALAR_LENGTH: usize = 32;
+
+/// Length of each of `w0s` and `w1s` as output by PBKDF2.
+pub const W_LENGTH: usize = SCALAR_LENGTH + 8;
+
+/// Length of a serialized [Verifier]: `w0 || L`.
+pub const VERIFIER_LENGTH: usize = SCALAR_LENGTH + POINT_LENGTH;
+
+/// Length of the key confirmation values `cA` and `cB`.
+pub const CONFIRMATION_LENGTH: usize = 32;
+
+/// Length of the shared secret `Ke`.
+pub const SHARED_SECRET_LENGTH: usize = 16;
+
+pub const PBKDF2_MIN_ITERATIONS: u32 = 1000;
+pub const PBKDF2_MAX_ITERATIONS: u32 = 100_000;
+pub const PBKDF2_MIN_SALT_LENGTH: usize = 16;
+pub const PBKDF2_MAX_SALT_LENGTH: usize = 32;
+
+/// Point `M` of the SPAKE2+ P-256 cipher suite (compressed).
+const M: [u8; 33] = [
+    0x02, 0x88, 0x6e, 0x2f, 0x97, 0xac, 0xe4, 0x6e, 0x55, 0xba, 0x9d, 0xd7, 0x24, 0x25, 0x79, 0xf2,
+    0x99, 0x3b, 0x64, 0xe1, 0x6e, 0xf3, 0xdc, 0xab, 0x95, 0xaf, 0xd4, 0x97, 0x33, 0x3d, 0x8f, 0xa1,
+    0x2f,
+];
+
+/// Point `N` of the SPAKE2+ P-256 cipher suite (compressed).
+const N: [u8; 33] = [
+    0x03, 0xd8, 0xbb, 0xd6, 0xc6, 0x39, 0xc6, 0x29, 0x37, 0xb0, 0x4d, 0x99, 0x7f, 0x38, 0xc3, 0x77,
+    0x07, 0x19, 0xc6, 0x29, 0xd7, 0x01, 0x4d, 0x49, 0xa2, 0x4b, 0x4f, 0x98, 0xba, 0xa1, 0x29, 0x2b,
+    0x49,
+];
+
+/// The shared secret `Ke` established by a successful exchange.
+pub type SharedSecret = [u8; SHARED_SECRET_LENGTH];
+
+fn decode_point(data: &[u8]) -> Result<ProjectivePoint> {
+    let encoded = EncodedPoint::from_bytes(data).map_err(|_| anyhow!("Invalid point encoding"))?;
+    let point = Option::<AffinePoint>::from(AffinePoint::from_encoded_point(&encoded))
+        .ok_or_else(|| anyhow!("Point is not on the P-256 curve"))?;
+
+    let point = ProjectivePoint::from(point);
+    if point == ProjectivePoint::IDENTITY {
+        return Err(anyhow!("Point is the identity element"));
+    }
+    Ok(point)
+}
+
+fn encode_point(point: &ProjectivePoint) -> [u8; POINT_LENGTH] {
+    let mut result = [0; POINT_LENGTH];
+    result.copy_from_slice(point.to_affine().to_encoded_point(false).as_bytes());
+    result
+}
+
+fn point_m() -> ProjectivePoint {
+    decode_point(&M).expect("M is a valid point")
+}
+
+fn point_n() -> ProjectivePoint {
+    decode_point(&N).expect("N is a valid point")
+}
+
+/// Reduces a big endian `w0s`/`w1s` value modulo the curve order.
+fn reduce_w(data: &[u8; W_LENGTH]) -> Scalar {
+    let (high, low) = data.split_at(W_LENGTH - SCALAR_LENGTH);
+
+    // 2^256 mod n
+    let shift = <Scalar as Reduce<U256>>::reduce(U256::MAX) + Scalar::ONE;
+    let high = Scalar::from(u64::from_be_bytes(high.try_into().unwrap()));
+    let low = <Scalar as Reduce<U256>>::reduce(U256::from_be_slice(low));
+
+    high * shift + low
+}
+
+/// Passcode derived secrets `w0` and `w1`, known to the prover.
+#[derive(Clone)]
+pub struct PasscodeSecrets {
+    w0: Scalar,
+    w1: Scalar,
+}
+
+impl PasscodeSecrets {
+    /// Derives `w0` and `w1` from a setup passcode through PBKDF2-HMAC-SHA256.
+    ///
+    /// The passcode is used as its 4 byte little endian encoding.
+    pub fn derive(passcode: u32, salt: &[u8], iterations: u32) -> Result<Self> {
+        if !(PBKDF2_MIN_SALT_LENGTH..=PBKDF2_MAX_SALT_LENGTH).contains(&salt.len()) {
+            return Err(anyhow!("Invalid PBKDF2 salt length {}", salt.len()));
+        }
+
+        if !(PBKDF2_MIN_ITERATIONS..=PBKDF2_MAX_ITERATIONS).contains(&iterations) {
+            return Err(anyhow!("Invalid PBKDF2 iteration count {}", iterations));
+        }
+
+        let mut ws = [0; 2 * W_LENGTH];
+        pbkdf2::pbkdf2_hmac::<Sha256>(&passcode.to_le_bytes(), salt, iterations, &mut ws);
+
+        let (w0s, w1s) = ws.split_at(W_LENGTH);
+        Ok(PasscodeSecrets {
+            w0: reduce_w(w0s.try_into().unwrap()),
+            w1: reduce_w(w1s.try_into().unwrap()),
+        })
+    }
+
+    /// The verifier to provision on the device for this passcode.
+    pub fn verifier(&self) -> Verifier {
+        Verifier {
+            w0: self.w0,
+            l: ProjectivePoint::GENERATOR * self.w1,
+        }
+    }
+}
+
+impl std::fmt::Debug for PasscodeSecrets {
+    // secrets are intentionally not printed
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("PasscodeSecrets { .. }")
+    }
+}
+
+/// Verification data `w0` and `L`, stored by the verifier.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Verifier {
+    w0: Scalar,
+    l: ProjectivePoint,
+}
+
+impl Verifier {
+    /// Parses a serialized verifier: `w0` (big endian) followed by the
+    /// uncompressed point `L`.
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        if data.len() != VERIFIER_LENGTH {
+            return Err(anyhow!("Invalid verifier length {}", data.len()));
+        }
+
+        let (w0, l) = data.split_at(SCALAR_LENGTH);
+        let w0 = <Scalar as Reduce<U256>>::reduce(U256::from_be_slice(w0));
+
+        Ok(Verifier {
+            w0,
+            l: decode_point(l)?,
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; VERIFIER_LENGTH] {
+        let mut result = [0; VERIFIER_LENGTH];
+        result[..SCALAR_LENGTH].copy_from_slice(&self.w0.to_bytes());
+        result[SCALAR_LENGTH..].copy_from_slice(&encode_point(&self.l));
+        result
+    }
+}
+
+impl std::fmt::Debug for Verifier {
+    // secrets are intentionally not printed
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Verifier { .. }")
+    }
+}
+
+/// Keys derived from the transcript.
+struct TranscriptKeys {
+    kc_a: [u8; 16],
+    kc_b: [u8; 16],
+    ke: SharedSecret,
+}
+
+impl TranscriptKeys {
+    fn derive(
+        context: &[u8],
+        x: &[u8; POINT_LENGTH],
+        y: &[u8; POINT_LENGTH],
+        z: &ProjectivePoint,
+        v: &ProjectivePoint,
+        w0: &Scalar,
+    ) -> Self {
+        let mut hash = Sha256::new();
+        let mut add = |data: &[u8]| {
+            hash.update((data.len() as u64).to_le_bytes());
+            hash.update(data);
+        };
+
+        add(context);
+        add(&[]); // prover identity
+        add(&[]); // verifier identity
+        add(&encode_point(&point_m()));
+        add(&encode_point(&point_n()));
+        add(x);
+        add(y);
+        add(&encode_point(z));
+        add(&encode_point(v));
+        add(&w0.to_bytes());
+
+        let hash = hash.finalize();
+        let (ka, ke) = hash.split_at(hash.len() / 2);
+
+        let mut kc = [0; 32];
+        Hkdf::<Sha256>::new(None, ka)
+            .expand(b"ConfirmationKeys", &mut kc)
+            .expect("valid HKDF output length");
+
+        TranscriptKeys {
+            kc_a: kc[..16].try_into().unwrap(),
+            kc_b: kc[16..].try_into().unwrap(),
+            ke: ke.try_into().unwrap(),
+        }
+    }
+}
+
+fn confirmation(key: &[u8], share: &[u8]) -> [u8; CONFIRMATION_LENGTH] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(share);
+    mac.finalize().into_bytes().into()
+}
+
+fn verify_confirmation(key: &[u8], share: &[u8], expected: &[u8]) -> Result<()> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(share);
+    mac.verify_slice(expected)
+        .map_err(|_| anyhow!("Key confirmation failed"))
+}
+
+/// Prover side of the exchange (PASE initiator).
+pub struct Prover {
+    secrets: PasscodeSecrets,
+    context: Vec<u8>,
+    x: Scalar,
+    share: [u8; POINT_LENGTH],
+}
+
+impl Prover {
+    /// Starts an exchange bound to `context` with a random ephemeral scalar.
+    pub fn new(secrets: PasscodeSecrets, context: &[u8]) -> Self {
+        Self::with_scalar(secrets, context, Scalar::random(&mut rand::thread_rng()))
+    }
+
+    fn with_scalar(secrets: PasscodeSecrets, context: &[u8], x: Scalar) -> Self {
+        let share = ProjectivePoint::GENERATOR * x + point_m() * secrets.w0;
+
+        Prover {
+            share: encode_point(&share),
+            secrets,
+            context: context.to_vec(),
+            x,
+        }
+    }
+
+    /// The share `pA` (`X`) to send to the verifier.
+    pub fn share(&self) -> &[u8; POINT_LENGTH] {
+        &self.share
+    }
+
+    /// Processes the verifier share `pB` and confirmation `cB`.
+    ///
+    /// Returns the confirmation `cA` to send to the verifier and the shared secret.
+    pub fn finish(
+        self,
+        verifier_share: &[u8],
+        verifier_confirmation: &[u8],
+    ) -> Result<([u8; CONFIRMATION_LENGTH], SharedSecret)> {
+        let y = decode_point(verifier_share)?;
+        let y_bytes = encode_point(&y);
+
+        let unblinded = y - point_n() * self.secrets.w0;
+        let z = unblinded * self.x;
+        let v = unblinded * self.secrets.w1;
+
+        let keys = TranscriptKeys::derive(
+            &self.context,
+            &self.share,
+            &y_bytes,
+            &z,
+            &v,
+            &self.secrets.w0,
+        );
+        verify_confirmation(&keys.kc_b, &self.share, verifier_confirmation)?;
+
+        Ok((confirmation(&keys.kc_a, &y_bytes), keys.ke))
+    }
+}
+
+/// Verifier side of the exchange (PASE responder).
+pub struct VerifierExchange {
+    share: [u8; POINT_LENGTH],
+    confirmation: [u8; CONFIRMATION_LENGTH],
+    kc_a: [u8; 16],
+    ke: SharedSecret,
+}
+
+impl VerifierExchange {
+    /// Processes the prover share `pA` with a random ephemeral scalar.
+    pub fn new(verifier: &Verifier, context: &[u8], prover_share: &[u8]) -> Result<Self> {
+        Self::with_scalar(
+            verifier,
+            context,
+            prover_share,
+            Scalar::random(&mut rand::thread_rng()),
+        )
+    }
+
+    fn with_scalar(
+        verifier: &Verifier,
+        context: &[u8],
+        prover_share: &[u8],
+        y: Scalar,
+    ) -> Result<Self> {
+        let x = decode_point(prover_share)?;
+        let x_bytes = encode_point(&x);
+
+        let share = encode_point(&(ProjectivePoint::GENERATOR * y + point_n() * verifier.w0));
+        let z = (x - point_m() * verifier.w0) * y;
+        let v = verifier.l * y;
+
+        let keys = TranscriptKeys::derive(context, &x_bytes, &share, &z, &v, &verifier.w0);
+
+        Ok(VerifierExchange {
+            share,
+            confirmation: confirmation(&keys.kc_b, &x_bytes),
+            kc_a: keys.kc_a,
+            ke: keys.ke,
+        })
+    }
+
+    /// The share `pB` (`Y`) to send to the prover.
+    pub fn share(&self) -> &[u8; POINT_LENGTH] {
+        &self.share
+    }
+
+    /// The confirmation `cB` to send to the prover.
+    pub fn confirmation(&self) -> &[u8; CONFIRMATION_LENGTH] {
+        &self.confirmation
+    }
+
+    /// Checks the prover confirmation `cA` and returns the shared secret.
+    pub fn finish(self, prover_confirmation: &[u8]) -> Result<SharedSecret> {
+        verify_confirmation(&self.kc_a, &self.share, prover_confirmation)?;
+        Ok(self.ke)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hex_literal::hex;
+
+    const SALT: &[u8] = b"SPAKE2P Key Salt";
+
+    #[test]
+    fn suite_points_are_valid() {
+        assert_ne!(point_m(), point_n());
+        assert_eq!(encode_point(&point_m())[0], 0x04);
+    }
+
+    #[test]
+    fn pbkdf2_sha256() {
+        // RFC 7914, section 11
+        let mut output = [0; 64];
+        pbkdf2::pbkdf2_hmac::<Sha256>(b"passwd", b"salt", 1, &mut output);
+        assert_eq!(
+            output,
+            hex!(
+                "55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc"
+                "49ca9cccf179b645991664b39d77ef317c71b845b1e30bd509112041d3a19783"
+            )
+        );
+    }
+
+    #[test]
+    fn derive_verifier() {
+        // Well known test verifier for passcode 20202021
+        let secrets = PasscodeSecrets::derive(20202021, SALT, 1000).unwrap();
+        assert_eq!(
+            secrets.verifier().to_bytes(),
+            hex!(
+                "b96170aae803346884724fe9a3b287c30330c2a660375d17bb205a8cf1aecb35"
+                "0457f8ab79ee253ab6a8e46bb09e543ae422736de501e3db37d441fe344920d0"
+                "9548e4c18240630c4ff4913c53513839b7c07fcc0627a1b8573a149fcd1fa466"
+                "cf"
+            )
+        );
+
+        let verifier = secrets.verifier();
+        assert_eq!(
+            Verifier::from_bytes(&verifier.to_bytes()).unwrap(),
+            verifier
+        );
+    }
+
+    #[test]
+    fn derive_rejects_invalid_parameters() {
+        assert!(PasscodeSecrets::derive(1, &[0; 15], 1000).is_err());
+        assert!(PasscodeSecrets::derive(1, &[0; 33], 1000).is_err());
+        assert!(PasscodeSecrets::derive(1, SALT, 999).is_err());
+        assert!(PasscodeSecrets::derive(1, SALT, 100_001).is_err());
+    }
+
+    #[test]
+    fn reduce_wide_values() {
+        let mut data = [0xff; W_LENGTH];
+        data[..8].copy_from_slice(&[0; 8]);
+        assert_eq!(reduce_w(&data), <Scalar as Reduce<U256>>::reduce(U256::MAX));
+
+        let mut one_shifted = [0; W_LENGTH];
+        one_shifted[7] = 1;
+        assert_eq!(
+            reduce_w(&one_shifted),
+            <Scalar as Reduce<U256>>::reduce(U256::MAX) + Scalar::ONE
+        );
+    }
+
+    #[test]
+    fn exchange() {
+        let secrets = PasscodeSecrets::derive(20202021, SALT, 1000).unwrap();
+        let verifier = secrets.verifier();
+        let context = [0x42; 32];
+
+        let prover = Prover::new(secrets, &context);
+        let exchange = VerifierExchange::new(&verifier, &context, prover.share()).unwrap();
+
+        let (prover_confirmation, prover_secret) = prover
+            .finish(exchange.share(), exchange.confirmation())
+            .unwrap();
+        let verifier_secret = exchange.finish(&prover_confirmation).unwrap();
+
+        assert_eq!(prover_secret, verifier_secret);
+    }
+
+    /// The published test vector of RFC 9383, Appendix C (P256-SHA256).
+    ///
+    /// Only the values that do not depend on the key schedule are checked:
+    /// the RFC derives its keys from the full transcript hash, while Matter
+    /// uses the schedule of draft-bar-cfrg-spake2plus-01.
+    #[test]
+    fn rfc9383_shares() {
+        let scalar = |bytes: [u8; SCALAR_LENGTH]| {
+            <Scalar as Reduce<U256>>::reduce(U256::from_be_slice(&bytes))
+        };
+        let w0 = hex!("bb8e1bbcf3c48f62c08db243652ae55d3e5586053fca77102994f23ad95491b3");
+        let secrets = PasscodeSecrets {
+            w0: scalar(w0),
+            w1: scalar(hex!(
+                "7e945f34d78785b8a3ef44d0df5a1a97d6b3b460409a345ca7830387a74b1dba"
+            )),
+        };
+        let x = scalar(hex!(
+            "d1232c8e8693d02368976c174e2088851b8365d0d79a9eee709c6a05a2fad539"
+        ));
+        let y = scalar(hex!(
+            "717a72348a182085109c8d3917d6c43d59b224dc6a7fc4f0483232fa6516d8b3"
+        ));
+
+        let verifier = secrets.verifier();
+        let l = hex!(
+            "04eb7c9db3d9a9eb1f8adab81b5794c1f13ae3e225efbe91ea487425854c7fc0"
+            "0f00bfedcbd09b2400142d40a14f2064ef31dfaa903b91d1faea7093d835966e"
+            "fd"
+        );
+        assert_eq!(verifier.to_bytes()[..SCALAR_LENGTH], w0);
+        assert_eq!(verifier.to_bytes()[SCALAR_LENGTH..], l);
+
+        let prover = Prover::with_scalar(secrets, &[], x);
+        assert_eq!(
+            prover.share(),
+            &hex!(
+                "04ef3bd051bf78a2234ec0df197f7828060fe9856503579bb1733009042c15c0"
+                "c1de127727f418b5966afadfdd95a6e4591d171056b333dab97a79c7193e3417"
+                "27"
+            )
+        );
+
+        let exchange = VerifierExchange::with_scalar(&verifier, &[], prover.share(), y).unwrap();
+        assert_eq!(
+            exchange.share(),
+            &hex!(
+                "04c0f65da0d11927bdf5d560c69e1d7d939a05b0e88291887d679fcadea75810"
+                "fb5cc1ca7494db39e82ff2f50665255d76173e09986ab46742c798a9a68437b0"
+                "48"
+            )
+        );
+    }
+
+    /// Known answer for a complete exchange with fixed ephemeral scalars.
+    ///
+    /// The expected values were computed independently of this crate, by a
+    /// plain Python implementation of the SPAKE2+ P-256 suite (which also
+    /// reproduces the verifier of [derive_verifier]). The confirmation and
+    /// session keys are not yet checked against a published vector.
+    #[test]
+    fn exchange_known_answer() {
+        let scalar = |bytes: [u8; SCALAR_LENGTH]| {
+            <Scalar as Reduce<U256>>::reduce(U256::from_be_slice(&bytes))
+        };
+        let x = scalar(hex!(
+            "57d26906780681e07444b3128e6fd504487f76a56db675dc828145a6c078a017"
+        ));
+        let y = scalar(hex!(
+            "e0d057cfdb4f794ad861191b172ee9aafc46b5bd00ad1db44a45321201783ac8"
+        ));
+        let context: Vec<u8> = (0..32).collect();
+
+        let secrets = PasscodeSecrets::derive(20202021, SALT, 1000).unwrap();
+        let verifier = secrets.verifier();
+
+        let prover = Prover::with_scalar(secrets, &context, x);
+        assert_eq!(
+            prover.share(),
+            &hex!(
+                "04f29e4bbd426418a4ca3ea64aa4228ba679e83ede02dc654be166be08f9ee24"
+                "55ad70b9101b747bcbd9cf3918359b457d16ac4d4ffb5c4921d9a9a478e86a3c"
+                "42"
+            )
+        );
+
+        let exchange =
+            VerifierExchange::with_scalar(&verifier, &context, prover.share(), y).unwrap();
+        assert_eq!(
+            exchange.share(),
+            &hex!(
+                "044e55e3ffd8cbd612718111be01d4f79cf424298270939e22addb38346c7dd6"
+                "28e2023f0fb4254072de22f5059129eb17999a8b51bab38fa9a6cf66be97ef98"
+                "e6"
+            )
+        );
+        assert_eq!(
+            exchange.confirmation(),
+            &hex!("0cc79407291b040bced2141ba5fb3aa3f4169af8d25079053f46b819d9a103b1")
+        );
+
+        let (prover_confirmation, prover_secret) = prover
+            .finish(exchange.share(), exchange.confirmation())
+            .unwrap();
+        assert_eq!(
+            prover_confirmation,
+            hex!("8f73cc48bb56433b7ab4e02388c36ea3ef1cd04003507752bcd7d6d5645ae1cf")
+        );
+        assert_eq!(prover_secret, hex!("9cf009d4c2aee4751901affe122aaadf"));
+        assert_eq!(
+            exchange.finish(&prover_confirmation).unwrap(),
+            hex!("9cf009d4c2aee4751901affe122aaadf")
+        );
+    }
+
+    #[test]
+    fn wrong_passcode_fails_confirmation() {
+        let verifier = PasscodeSecrets::derive(20202021, SALT, 1000)
+            .unwrap()
+            .verifier();
+        let secrets = PasscodeSecrets::derive(20202022, SALT, 1000).unwrap();
+        let context = [0x42; 32];
+
+        let prover = Prover::with_scalar(secrets, &context, Scalar::from(1234u64));
+        let exchange = VerifierExchange::with_scalar(
+            &verifier,
+            &context,
+            prover.share(),
+            Scalar::from(5678u64),
+        )
+        .unwrap();
+
+        assert!(prover
+            .finish(exchange.share(), exchange.confirmation())
+            .is_err());
+    }
+
+    #[test]
+    fn context_mismatch_fails_confirmation() {
+        let secrets = PasscodeSecrets::derive(20202021, SALT, 1000).unwrap();
+        let verifier = secrets.verifier();
+
+        let prover = Prover::new(secrets, &[1; 32]);
+        let exchange = VerifierExchange::new(&verifier, &[2; 32], prover.share()).unwrap();
+
+        assert!(prover
+            .finish(exchange.share(), exchange.confirmation())
+            .is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_shares() {
+        let verifier = PasscodeSecrets::derive(20202021, SALT, 1000)
+            .unwrap()
+            .verifier();
+
+        assert!(VerifierExchange::new(&verifier, &[], &[0x04; POINT_LENGTH]).is_err());
+        assert!(VerifierExchange::new(&verifier, &[], &[0x00]).is_err());
+    }
+}
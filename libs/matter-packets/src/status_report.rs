@@ -0,0 +1,365 @@
+//! StatusReport messages of the secure channel protocol.
+//!
+//! A [StatusReport] carries a [GeneralCode] common to all protocols, the id
+//! of the protocol the report is about and a code specific to that protocol,
+//! optionally followed by protocol specific data. Codes of the secure channel
+//! protocol are available as [SecureChannelCode]; codes of other protocols
+//! are kept as raw values.
+//!
+//! Unknown codes are preserved (as the `Other` variant of the code enums),
+//! so reports from newer peers can still be parsed and forwarded.
+
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+use anyhow::Result;
+
+use crate::exchange::ProtocolId;
+use crate::reader::LittleEndianReader;
+use crate::writer::LittleEndianWriter;
+
+/// Defines an open enumeration over an integer code: known values get a
+/// named variant, anything else is kept in `Other`.
+macro_rules! status_codes {
+    (
+        $(#[$meta:meta])*
+        $name:ident: $repr:ty {
+            $($(#[$vmeta:meta])* $variant:ident = $value:expr,)*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum $name {
+            $($(#[$vmeta])* $variant,)*
+            Other($repr),
+        }
+
+        impl From<$repr> for $name {
+            fn from(value: $repr) -> Self {
+                match value {
+                    $($value => $name::$variant,)*
+                    other => $name::Other(other),
+                }
+            }
+        }
+
+        impl From<$name> for $repr {
+            fn from(value: $name) -> $repr {
+                match value {
+                    $($name::$variant => $value,)*
+                    $name::Other(other) => other,
+                }
+            }
+        }
+    };
+}
+
+pub(crate) use status_codes;
+
+status_codes! {
+    /// General status codes, common to all protocols.
+    GeneralCode: u16 {
+        Success = 0,
+        Failure = 1,
+        BadPrecondition = 2,
+        OutOfRange = 3,
+        BadRequest = 4,
+        Unsupported = 5,
+        Unexpected = 6,
+        ResourceExhausted = 7,
+        Busy = 8,
+        Timeout = 9,
+        Continue = 10,
+        Aborted = 11,
+        InvalidArgument = 12,
+        NotFound = 13,
+        AlreadyExists = 14,
+        PermissionDenied = 15,
+        DataLoss = 16,
+    }
+}
+
+status_codes! {
+    /// Protocol specific status codes of the secure channel protocol.
+    SecureChannelCode: u16 {
+        SessionEstablishmentSuccess = 0,
+        NoSharedTrustRoots = 1,
+        InvalidParameter = 2,
+        CloseSession = 3,
+        /// Protocol data contains the minimum wait time (`u16`, milliseconds).
+        Busy = 4,
+    }
+}
+
+/// Payload of a `StatusReport` message.
+///
+/// # Binary layout
+///
+/// | Size    | Description                                       |
+/// |---------|---------------------------------------------------|
+/// | `u16`   | General code                                      |
+/// | `u32`   | Protocol id: vendor id (high 16 bits), protocol   |
+/// | `u16`   | Protocol specific code                            |
+/// | *       | (Optional) protocol specific data                 |
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusReport {
+    pub general_code: GeneralCode,
+    pub protocol: ProtocolId,
+    pub protocol_code: u16,
+    pub protocol_data: Vec<u8>,
+}
+
+impl StatusReport {
+    /// Creates a secure channel status report without protocol data.
+    pub fn secure_channel(general_code: GeneralCode, code: SecureChannelCode) -> Self {
+        StatusReport {
+            general_code,
+            protocol: ProtocolId::SECURE_CHANNEL,
+            protocol_code: code.into(),
+            protocol_data: Vec::new(),
+        }
+    }
+
+    /// Report sent by the responder once session establishment completed.
+    pub fn session_establishment_success() -> Self {
+        Self::secure_channel(
+            GeneralCode::Success,
+            SecureChannelCode::SessionEstablishmentSuccess,
+        )
+    }
+
+    /// Report sent when a session establishment message could not be processed.
+    pub fn invalid_parameter() -> Self {
+        Self::secure_channel(GeneralCode::Failure, SecureChannelCode::InvalidParameter)
+    }
+
+    /// Report asking the peer to retry session establishment after `wait_time`.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use matter_packets::status_report::StatusReport;
+    ///
+    /// let report = StatusReport::busy(Duration::from_millis(1500));
+    /// assert_eq!(report.busy_wait_time(), Some(Duration::from_millis(1500)));
+    /// assert_eq!(report.to_string(), "Busy: SecureChannel/Busy (retry after 1500ms)");
+    /// ```
+    pub fn busy(wait_time: Duration) -> Self {
+        let millis = u16::try_from(wait_time.as_millis()).unwrap_or(u16::MAX);
+        StatusReport {
+            protocol_data: millis.to_le_bytes().to_vec(),
+            ..Self::secure_channel(GeneralCode::Busy, SecureChannelCode::Busy)
+        }
+    }
+
+    /// Protocol code as a secure channel code, if the report is for the
+    /// secure channel protocol.
+    pub fn secure_channel_code(&self) -> Option<SecureChannelCode> {
+        (self.protocol == ProtocolId::SECURE_CHANNEL).then(|| self.protocol_code.into())
+    }
+
+    /// Minimum wait time of a secure channel busy report.
+    pub fn busy_wait_time(&self) -> Option<Duration> {
+        if self.secure_channel_code()? != SecureChannelCode::Busy {
+            return None;
+        }
+        let data: [u8; 2] = self.protocol_data.get(..2)?.try_into().ok()?;
+        Some(Duration::from_millis(u16::from_le_bytes(data) as u64))
+    }
+
+    /// Returns true for reports with a success general code.
+    pub fn is_success(&self) -> bool {
+        self.general_code == GeneralCode::Success
+    }
+
+    /// Parses a status report payload.
+    ///
+    /// ```
+    /// use matter_packets::exchange::ProtocolId;
+    /// use matter_packets::status_report::*;
+    ///
+    /// let mut data: &[u8] = &[
+    ///     0x01, 0x00,             // general code: failure
+    ///     0x00, 0x00, 0x00, 0x00, // protocol: secure channel
+    ///     0x02, 0x00,             // invalid parameter
+    /// ];
+    /// let report = StatusReport::parse(&mut data).unwrap();
+    ///
+    /// assert_eq!(report.general_code, GeneralCode::Failure);
+    /// assert_eq!(report.protocol, ProtocolId::SECURE_CHANNEL);
+    /// assert_eq!(report.secure_channel_code(), Some(SecureChannelCode::InvalidParameter));
+    /// assert!(report.protocol_data.is_empty());
+    /// assert_eq!(report.to_string(), "Failure: SecureChannel/InvalidParameter");
+    ///
+    /// // too short
+    /// let mut data: &[u8] = &[0x01, 0x00, 0x00];
+    /// assert!(StatusReport::parse(&mut data).is_err());
+    /// ```
+    pub fn parse(buffer: &mut &[u8]) -> Result<StatusReport> {
+        let general_code = GeneralCode::from(buffer.read_le_u16()?);
+        let protocol = buffer.read_le_u32()?;
+        let protocol_code = buffer.read_le_u16()?;
+        let protocol_data = std::mem::take(buffer).to_vec();
+
+        Ok(StatusReport {
+            general_code,
+            protocol: ProtocolId {
+                vendor_id: (protocol >> 16) as u16,
+                protocol: protocol as u16,
+            },
+            protocol_code,
+            protocol_data,
+        })
+    }
+
+    /// Writes a status report payload.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use matter_packets::status_report::StatusReport;
+    ///
+    /// let mut data = Vec::new();
+    /// StatusReport::busy(Duration::from_millis(0x1234)).write(&mut data).unwrap();
+    /// assert_eq!(data, &[
+    ///     0x08, 0x00,             // general code: busy
+    ///     0x00, 0x00, 0x00, 0x00, // protocol: secure channel
+    ///     0x04, 0x00,             // busy
+    ///     0x34, 0x12,             // minimum wait time
+    /// ]);
+    /// ```
+    pub fn write(&self, writer: &mut impl LittleEndianWriter) -> Result<()> {
+        writer.write_le_u16(self.general_code.into())?;
+        writer.write_le_u32(
+            ((self.protocol.vendor_id as u32) << 16) | self.protocol.protocol as u32,
+        )?;
+        writer.write_le_u16(self.protocol_code)?;
+        writer.write(&self.protocol_data)?;
+        Ok(())
+    }
+}
+
+impl Display for GeneralCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GeneralCode::Other(code) => write!(f, "GeneralCode(0x{:04X})", code),
+            known => write!(f, "{:?}", known),
+        }
+    }
+}
+
+impl Display for StatusReport {
+    /// Renders as `<general code>: <protocol>/<protocol code>`, followed by
+    /// any protocol data.
+    ///
+    /// ```
+    /// use matter_packets::exchange::ProtocolId;
+    /// use matter_packets::status_report::*;
+    ///
+    /// let report = StatusReport {
+    ///     general_code: GeneralCode::from(0x99),
+    ///     protocol: ProtocolId { vendor_id: 0xfff1, protocol: 2 },
+    ///     protocol_code: 7,
+    ///     protocol_data: vec![0xab, 0xcd],
+    /// };
+    /// assert_eq!(
+    ///     report.to_string(),
+    ///     "GeneralCode(0x0099): Protocol(0xFFF1:0x0002)/0x0007 (data: abcd)"
+    /// );
+    /// ```
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: ", self.general_code)?;
+
+        match self.protocol {
+            ProtocolId::SECURE_CHANNEL => write!(f, "SecureChannel/")?,
+            ProtocolId::INTERACTION_MODEL => write!(f, "InteractionModel/")?,
+            ProtocolId::BDX => write!(f, "Bdx/")?,
+            ProtocolId::USER_DIRECTED_COMMISSIONING => write!(f, "UserDirectedCommissioning/")?,
+            ProtocolId {
+                vendor_id,
+                protocol,
+            } => write!(f, "Protocol(0x{:04X}:0x{:04X})/", vendor_id, protocol)?,
+        }
+
+        match self.secure_channel_code() {
+            Some(SecureChannelCode::Other(_)) | None => write!(f, "0x{:04X}", self.protocol_code)?,
+            Some(code) => write!(f, "{:?}", code)?,
+        }
+
+        if let Some(wait_time) = self.busy_wait_time() {
+            return write!(f, " (retry after {}ms)", wait_time.as_millis());
+        }
+
+        if !self.protocol_data.is_empty() {
+            write!(f, " (data: ")?;
+            for byte in &self.protocol_data {
+                write!(f, "{:02x}", byte)?;
+            }
+            write!(f, ")")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_round_trip() {
+        for value in 0..=20u16 {
+            assert_eq!(u16::from(GeneralCode::from(value)), value);
+            assert_eq!(u16::from(SecureChannelCode::from(value)), value);
+        }
+        assert_eq!(GeneralCode::from(8), GeneralCode::Busy);
+        assert_eq!(GeneralCode::from(17), GeneralCode::Other(17));
+        assert_eq!(SecureChannelCode::from(3), SecureChannelCode::CloseSession);
+    }
+
+    #[test]
+    fn report_round_trip() {
+        let reports = [
+            StatusReport::session_establishment_success(),
+            StatusReport::busy(Duration::from_millis(250)),
+            StatusReport {
+                general_code: GeneralCode::Other(0x1234),
+                protocol: ProtocolId {
+                    vendor_id: 0xfff1,
+                    protocol: 0x8000,
+                },
+                protocol_code: 0xabcd,
+                protocol_data: vec![1, 2, 3],
+            },
+        ];
+
+        for report in reports {
+            let mut data = Vec::new();
+            report.write(&mut data).unwrap();
+            let mut buffer = data.as_slice();
+            assert_eq!(StatusReport::parse(&mut buffer).unwrap(), report);
+            assert!(buffer.is_empty());
+        }
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(
+            StatusReport::session_establishment_success().to_string(),
+            "Success: SecureChannel/SessionEstablishmentSuccess"
+        );
+        assert_eq!(
+            StatusReport::secure_channel(GeneralCode::Failure, SecureChannelCode::Other(9))
+                .to_string(),
+            "Failure: SecureChannel/0x0009"
+        );
+
+        let report = StatusReport {
+            general_code: GeneralCode::Failure,
+            protocol: ProtocolId::INTERACTION_MODEL,
+            protocol_code: 0x7e,
+            protocol_data: vec![],
+        };
+        assert_eq!(report.to_string(), "Failure: InteractionModel/0x007E");
+        assert_eq!(report.busy_wait_time(), None);
+        assert!(!report.is_success());
+    }
+}
@@ -0,0 +1,72 @@
+//! TLV helpers shared by the message types of this crate.
+
+use anyhow::{anyhow, Result};
+use streaming_iterator::StreamingIterator;
+use tlv_packed::{DecodeEnd, DecodeError, EncodeError, TlvWriter};
+use tlv_stream::{ContainerType, Record, TagValue, Value};
+
+/// Encodes a value as an anonymous TLV element.
+pub(crate) fn encode<T: tlv_packed::TlvEncodable>(value: &T) -> Result<Vec<u8>> {
+    tlv_packed::encode_to_vec(value).map_err(|e| anyhow!("Failed to encode TLV: {:?}", e))
+}
+
+/// Decodes a value from a complete TLV element.
+pub(crate) fn decode<'a, T>(data: &'a [u8]) -> Result<T>
+where
+    T: tlv_packed::TlvMergeDecodable<'a, tlv_packed::ParserSource<'a>>,
+{
+    tlv_packed::decode_from_bytes(data).map_err(|e| anyhow!("Failed to decode TLV: {:?}", e))
+}
+
+/// Writes the start of a TLV list, calls `content` and writes the list end.
+pub(crate) fn encode_tlv_list<W: TlvWriter>(
+    tag: TagValue,
+    writer: &mut W,
+    content: impl FnOnce(&mut W) -> Result<(), EncodeError>,
+) -> Result<(), EncodeError> {
+    writer.write_record(Record {
+        tag,
+        value: Value::ContainerStart(ContainerType::List),
+    })?;
+    content(writer)?;
+    writer.write_record(Record {
+        tag: TagValue::Anonymous,
+        value: Value::ContainerEnd,
+    })
+}
+
+/// Calls `item` for every element of the current TLV list, with the context
+/// tag of the element.
+pub(crate) fn decode_tlv_list<'a, S>(
+    source: &mut S,
+    mut item: impl FnMut(u8, &mut S) -> Result<(), DecodeError>,
+) -> Result<DecodeEnd, DecodeError>
+where
+    S: StreamingIterator<Item = Record<'a>>,
+{
+    if !matches!(
+        source.get(),
+        Some(Record {
+            value: Value::ContainerStart(ContainerType::List),
+            ..
+        })
+    ) {
+        return Err(DecodeError::InvalidData);
+    }
+
+    loop {
+        let tag = match source.next() {
+            None => return Ok(DecodeEnd::StreamFinished),
+            Some(Record {
+                value: Value::ContainerEnd,
+                ..
+            }) => return Ok(DecodeEnd::DataConsumed),
+            Some(Record {
+                tag: TagValue::ContextSpecific { tag },
+                ..
+            }) => u8::try_from(*tag).map_err(|_| DecodeError::UnknownTag)?,
+            Some(_) => return Err(DecodeError::InvalidData),
+        };
+        item(tag, source)?;
+    }
+}
@@ -140,6 +140,24 @@ impl LittleEndianWriter for SpaceEstimator {
     }
 }
 
+/// Appends written data to a growable vector.
+///
+/// # Example
+///
+/// ```
+/// use matter_packets::writer::LittleEndianWriter;
+///
+/// let mut buffer = vec![0xff];
+/// assert!(buffer.write_le_u16(0x1234).is_ok());
+/// assert_eq!(buffer, &[0xff, 0x34, 0x12]);
+/// ```
+impl LittleEndianWriter for Vec<u8> {
+    fn write(&mut self, data: &[u8]) -> core::result::Result<(), EndianWriteError> {
+        self.extend_from_slice(data);
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
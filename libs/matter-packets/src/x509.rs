@@ -0,0 +1,1030 @@
+//! Conversion of Matter certificates to and from X.509 DER.
+//!
+//! The TLV form of a certificate only holds what cannot be inferred, so the
+//! conversion is fully determined: every TLV certificate has exactly one DER
+//! encoding, and a DER certificate is only accepted if converting it back
+//! gives the exact same bytes. This keeps signatures, computed over the DER
+//! `TBSCertificate`, valid in both forms.
+
+use anyhow::{anyhow, Result};
+use matter_types::NodeId;
+use p256::ecdsa::Signature;
+
+use crate::certificate::{
+    BasicConstraints, Certificate, DnAttribute, Extension, KeyPurpose, KeyUsage, TextAttribute,
+    CURVE_PRIME256V1, MATTER_EPOCH_UNIX_SECONDS, PUBLIC_KEY_ALGORITHM_EC,
+    SIGNATURE_ALGORITHM_ECDSA_SHA256,
+};
+
+const BOOLEAN: u8 = 0x01;
+const INTEGER: u8 = 0x02;
+const BIT_STRING: u8 = 0x03;
+const OCTET_STRING: u8 = 0x04;
+const OBJECT_IDENTIFIER: u8 = 0x06;
+const UTF8_STRING: u8 = 0x0C;
+const PRINTABLE_STRING: u8 = 0x13;
+const IA5_STRING: u8 = 0x16;
+const UTC_TIME: u8 = 0x17;
+const GENERALIZED_TIME: u8 = 0x18;
+const SEQUENCE: u8 = 0x30;
+const SET: u8 = 0x31;
+const VERSION: u8 = 0xA0; // [0] EXPLICIT
+const EXTENSIONS: u8 = 0xA3; // [3] EXPLICIT
+const KEY_IDENTIFIER: u8 = 0x80; // [0] IMPLICIT
+
+const X509_V3: u8 = 2;
+
+const OID_ECDSA_WITH_SHA256: &[u8] = &[0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x04, 0x03, 0x02];
+const OID_EC_PUBLIC_KEY: &[u8] = &[0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x02, 0x01];
+const OID_PRIME256V1: &[u8] = &[0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x03, 0x01, 0x07];
+
+const OID_BASIC_CONSTRAINTS: &[u8] = &[0x55, 0x1D, 0x13];
+const OID_KEY_USAGE: &[u8] = &[0x55, 0x1D, 0x0F];
+const OID_EXTENDED_KEY_USAGE: &[u8] = &[0x55, 0x1D, 0x25];
+const OID_SUBJECT_KEY_ID: &[u8] = &[0x55, 0x1D, 0x0E];
+const OID_AUTHORITY_KEY_ID: &[u8] = &[0x55, 0x1D, 0x23];
+
+/// `1.3.6.1.5.5.7.3`, followed by the key purpose.
+const OID_KEY_PURPOSE_PREFIX: &[u8] = &[0x2B, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03];
+
+/// `1.3.6.1.4.1.37244.1`, followed by the Matter DN attribute.
+const OID_MATTER_DN_PREFIX: &[u8] = &[0x2B, 0x06, 0x01, 0x04, 0x01, 0x82, 0xA2, 0x7C, 0x01];
+
+/// `GeneralizedTime` of certificates without well-defined expiration.
+const NO_EXPIRATION: &[u8] = b"99991231235959Z";
+
+/// Encodes a DER element.
+fn der(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut result = vec![tag];
+    let length = content.len();
+    if length < 0x80 {
+        result.push(length as u8);
+    } else {
+        let bytes = length.to_be_bytes();
+        let skip = bytes.iter().take_while(|b| **b == 0).count();
+        result.push(0x80 | (bytes.len() - skip) as u8);
+        result.extend_from_slice(&bytes[skip..]);
+    }
+    result.extend_from_slice(content);
+    result
+}
+
+fn der_unsigned(value: u64) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let skip = bytes.iter().take_while(|b| **b == 0).count().min(7);
+    let mut content = bytes[skip..].to_vec();
+    if content[0] & 0x80 != 0 {
+        content.insert(0, 0);
+    }
+    der(INTEGER, &content)
+}
+
+/// Reads consecutive DER elements.
+struct DerReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> DerReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        DerReader { data }
+    }
+
+    fn peek_tag(&self) -> Option<u8> {
+        self.data.first().copied()
+    }
+
+    /// Reads the next element, returning its tag, content and full encoding.
+    fn read_any(&mut self) -> Result<(u8, &'a [u8], &'a [u8])> {
+        let truncated = || anyhow!("Truncated DER element");
+
+        let (&tag, rest) = self.data.split_first().ok_or_else(truncated)?;
+        let (&first, mut rest) = rest.split_first().ok_or_else(truncated)?;
+
+        let length = match first {
+            0..=0x7F => first as usize,
+            0x81..=0x84 => {
+                let count = (first & 0x7F) as usize;
+                if rest.len() < count {
+                    return Err(truncated());
+                }
+                let (bytes, remaining) = rest.split_at(count);
+                rest = remaining;
+                bytes.iter().fold(0, |length, b| length << 8 | *b as usize)
+            }
+            _ => return Err(anyhow!("Unsupported DER length encoding")),
+        };
+
+        if rest.len() < length {
+            return Err(truncated());
+        }
+
+        let header_length = self.data.len() - rest.len();
+        let (raw, remaining) = self.data.split_at(header_length + length);
+        self.data = remaining;
+        Ok((tag, &raw[header_length..], raw))
+    }
+
+    fn read(&mut self, expected: u8) -> Result<&'a [u8]> {
+        match self.read_any()? {
+            (tag, content, _) if tag == expected => Ok(content),
+            (tag, _, _) => Err(anyhow!(
+                "Unexpected DER tag 0x{:02X} (expected 0x{:02X})",
+                tag,
+                expected
+            )),
+        }
+    }
+
+    fn read_optional(&mut self, tag: u8) -> Result<Option<&'a [u8]>> {
+        match self.peek_tag() {
+            Some(t) if t == tag => self.read(tag).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    fn finish(&self) -> Result<()> {
+        match self.data.is_empty() {
+            true => Ok(()),
+            false => Err(anyhow!("Unexpected trailing DER data")),
+        }
+    }
+}
+
+/// Splits seconds since the Unix epoch into `(year, month, day, hour,
+/// minute, second)`.
+fn civil_time(unix_seconds: u64) -> (u64, u64, u64, u64, u64, u64) {
+    let days = (unix_seconds / 86400) as i64;
+    let seconds = unix_seconds % 86400;
+
+    // days to civil date in the proleptic Gregorian calendar, with years
+    // starting in March so leap days are last
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    (
+        year as u64,
+        month as u64,
+        day as u64,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+    )
+}
+
+/// Inverse of [civil_time], or `None` if the result does not fit.
+fn unix_seconds(
+    year: i64,
+    month: i64,
+    day: i64,
+    hour: i64,
+    minute: i64,
+    second: i64,
+) -> Option<i64> {
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let shifted_month = (month + 9) % 12;
+    let day_of_year = (153 * shifted_month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era
+        .checked_mul(146_097)?
+        .checked_add(day_of_era)?
+        .checked_sub(719_468)?;
+
+    days.checked_mul(86400)?
+        .checked_add(hour * 3600 + minute * 60 + second)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Encodes a time as `UTCTime` until 2049 and `GeneralizedTime` afterwards.
+fn time(matter_seconds: u32) -> Vec<u8> {
+    let (year, month, day, hour, minute, second) =
+        civil_time(MATTER_EPOCH_UNIX_SECONDS + u64::from(matter_seconds));
+    let time = format!(
+        "{:02}{:02}{:02}{:02}{:02}Z",
+        month, day, hour, minute, second
+    );
+
+    match year {
+        ..=2049 => der(UTC_TIME, format!("{:02}{}", year % 100, time).as_bytes()),
+        _ => der(GENERALIZED_TIME, format!("{:04}{}", year, time).as_bytes()),
+    }
+}
+
+fn parse_time(reader: &mut DerReader<'_>) -> Result<u32> {
+    let invalid = || anyhow!("Invalid certificate time");
+
+    let text = match reader.read_any()? {
+        (GENERALIZED_TIME, NO_EXPIRATION, _) => return Ok(0),
+        (UTC_TIME, text, _) if text.len() == 13 => text,
+        (GENERALIZED_TIME, text, _) if text.len() == 15 => text,
+        _ => return Err(invalid()),
+    };
+
+    let (digits, zone) = text.split_at(text.len() - 1);
+    if zone != b"Z" || !digits.iter().all(u8::is_ascii_digit) {
+        return Err(invalid());
+    }
+    let number = |digits: &[u8]| {
+        digits
+            .iter()
+            .fold(0, |value, digit| value * 10 + i64::from(digit - b'0'))
+    };
+
+    let (year, rest) = match digits.len() {
+        12 => match number(&digits[..2]) {
+            year @ 0..=49 => (2000 + year, &digits[2..]),
+            year => (1900 + year, &digits[2..]),
+        },
+        _ => (number(&digits[..4]), &digits[4..]),
+    };
+    let field = |index: usize| number(&rest[index..index + 2]);
+    let (month, day, hour, minute, second) = (field(0), field(2), field(4), field(6), field(8));
+
+    if !(1..=12).contains(&month)
+        || !(1..=days_in_month(year, month)).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 59
+    {
+        return Err(invalid());
+    }
+    if year < 2000 {
+        return Err(anyhow!(
+            "Certificate time before 2000-01-01 is not supported"
+        ));
+    }
+
+    unix_seconds(year, month, day, hour, minute, second)
+        .and_then(|seconds| seconds.checked_sub(MATTER_EPOCH_UNIX_SECONDS as i64))
+        .and_then(|seconds| u32::try_from(seconds).ok())
+        .ok_or_else(invalid)
+}
+
+fn text_attribute_oid(attribute: TextAttribute) -> Vec<u8> {
+    match attribute {
+        TextAttribute::DomainComponent => {
+            // 0.9.2342.19200300.100.1.25
+            vec![0x09, 0x92, 0x26, 0x89, 0x93, 0xF2, 0x2C, 0x64, 0x01, 0x19]
+        }
+        _ => {
+            // 2.5.4.x
+            let id = match attribute {
+                TextAttribute::CommonName => 3,
+                TextAttribute::Surname => 4,
+                TextAttribute::SerialNumber => 5,
+                TextAttribute::CountryName => 6,
+                TextAttribute::LocalityName => 7,
+                TextAttribute::StateOrProvinceName => 8,
+                TextAttribute::OrganizationName => 10,
+                TextAttribute::OrganizationalUnitName => 11,
+                TextAttribute::Title => 12,
+                TextAttribute::Name => 41,
+                TextAttribute::GivenName => 42,
+                TextAttribute::Initials => 43,
+                TextAttribute::GenerationQualifier => 44,
+                TextAttribute::DnQualifier => 46,
+                TextAttribute::Pseudonym => 65,
+                TextAttribute::DomainComponent => unreachable!(),
+            };
+            vec![0x55, 0x04, id]
+        }
+    }
+}
+
+/// Matter specific attributes are upper case hex strings.
+fn matter_attribute(tag: u8, value: u64, digits: usize) -> (Vec<u8>, Vec<u8>) {
+    (
+        [OID_MATTER_DN_PREFIX, &[tag - 16]].concat(),
+        der(UTF8_STRING, format!("{:0digits$X}", value).as_bytes()),
+    )
+}
+
+fn name(attributes: &[DnAttribute]) -> Vec<u8> {
+    let mut content = Vec::new();
+
+    for attribute in attributes {
+        let tag = attribute.tag();
+        let (oid, value) = match attribute {
+            DnAttribute::Text {
+                attribute,
+                value,
+                printable,
+            } => {
+                let string_type = match (attribute, printable) {
+                    (TextAttribute::DomainComponent, _) => IA5_STRING,
+                    (_, true) => PRINTABLE_STRING,
+                    (_, false) => UTF8_STRING,
+                };
+                (
+                    text_attribute_oid(*attribute),
+                    der(string_type, value.as_bytes()),
+                )
+            }
+            DnAttribute::NodeId(NodeId(value))
+            | DnAttribute::FirmwareSigningId(value)
+            | DnAttribute::IcacId(value)
+            | DnAttribute::RcacId(value)
+            | DnAttribute::FabricId(value) => matter_attribute(tag, *value, 16),
+            DnAttribute::CaseAuthenticatedTag(value) => matter_attribute(tag, (*value).into(), 8),
+        };
+
+        let attribute = der(SEQUENCE, &[der(OBJECT_IDENTIFIER, &oid), value].concat());
+        content.extend(der(SET, &attribute));
+    }
+
+    der(SEQUENCE, &content)
+}
+
+fn parse_name(data: &[u8]) -> Result<Vec<DnAttribute>> {
+    let mut result = Vec::new();
+    let mut reader = DerReader::new(data);
+
+    while reader.peek_tag().is_some() {
+        // only single-valued relative distinguished names
+        let mut set = DerReader::new(reader.read(SET)?);
+        let mut attribute = DerReader::new(set.read(SEQUENCE)?);
+        set.finish()?;
+
+        let oid = attribute.read(OBJECT_IDENTIFIER)?;
+        let (string_type, value, _) = attribute.read_any()?;
+        attribute.finish()?;
+        let value = std::str::from_utf8(value)?;
+
+        if let Some(&[id]) = oid.strip_prefix(OID_MATTER_DN_PREFIX) {
+            let digits = if id == 6 { 8 } else { 16 };
+            if string_type != UTF8_STRING
+                || value.len() != digits
+                || !value
+                    .bytes()
+                    .all(|c| c.is_ascii_digit() || c.is_ascii_uppercase())
+            {
+                return Err(anyhow!("Invalid Matter DN attribute value `{}`", value));
+            }
+            let value = u64::from_str_radix(value, 16)?;
+
+            result.push(match id {
+                1 => DnAttribute::NodeId(NodeId(value)),
+                2 => DnAttribute::FirmwareSigningId(value),
+                3 => DnAttribute::IcacId(value),
+                4 => DnAttribute::RcacId(value),
+                5 => DnAttribute::FabricId(value),
+                6 => DnAttribute::CaseAuthenticatedTag(value as u32),
+                _ => return Err(anyhow!("Unsupported Matter DN attribute {}", id)),
+            });
+            continue;
+        }
+
+        let attribute = TextAttribute::ALL
+            .iter()
+            .copied()
+            .find(|a| text_attribute_oid(*a) == oid)
+            .ok_or_else(|| anyhow!("Unsupported DN attribute {:02X?}", oid))?;
+
+        let printable = match (attribute, string_type) {
+            (TextAttribute::DomainComponent, IA5_STRING) => false,
+            (TextAttribute::DomainComponent, _) => {
+                return Err(anyhow!("Domain component must be an IA5String"))
+            }
+            (_, UTF8_STRING) => false,
+            (_, PRINTABLE_STRING) => true,
+            _ => return Err(anyhow!("Unsupported string type 0x{:02X}", string_type)),
+        };
+
+        result.push(DnAttribute::Text {
+            attribute,
+            value: value.to_string(),
+            printable,
+        });
+    }
+
+    Ok(result)
+}
+
+fn key_usage_bits(usage: KeyUsage) -> Vec<u8> {
+    // bit 0 (digital signature) is the most significant bit of the string
+    let bits = usage.bits();
+    let bytes = [
+        (bits as u8).reverse_bits(),
+        ((bits >> 8) as u8).reverse_bits(),
+    ];
+    let length = if bytes[1] != 0 { 2 } else { 1 };
+    let unused = match bytes[length - 1] {
+        0 => 0,
+        last => last.trailing_zeros() as u8,
+    };
+
+    der(BIT_STRING, &[&[unused], &bytes[..length]].concat())
+}
+
+fn parse_key_usage(data: &[u8]) -> Result<KeyUsage> {
+    let content = DerReader::new(data).read(BIT_STRING)?;
+    let bits = match content {
+        [_, first] => u16::from(first.reverse_bits()),
+        [_, first, second] => {
+            u16::from(first.reverse_bits()) | u16::from(second.reverse_bits()) << 8
+        }
+        _ => return Err(anyhow!("Invalid key usage")),
+    };
+    KeyUsage::from_bits(bits).ok_or_else(|| anyhow!("Unsupported key usage bits"))
+}
+
+fn extension(oid: &[u8], critical: bool, value: &[u8]) -> Vec<u8> {
+    let critical = match critical {
+        true => der(BOOLEAN, &[0xFF]),
+        false => Vec::new(),
+    };
+    der(
+        SEQUENCE,
+        &[
+            der(OBJECT_IDENTIFIER, oid),
+            critical,
+            der(OCTET_STRING, value),
+        ]
+        .concat(),
+    )
+}
+
+fn extension_der(extension_value: &Extension) -> Vec<u8> {
+    match extension_value {
+        Extension::BasicConstraints(constraints) => {
+            let mut content = Vec::new();
+            if constraints.is_ca {
+                content.extend(der(BOOLEAN, &[0xFF]));
+            }
+            if let Some(length) = constraints.path_length {
+                content.extend(der_unsigned(length.into()));
+            }
+            extension(OID_BASIC_CONSTRAINTS, true, &der(SEQUENCE, &content))
+        }
+        Extension::KeyUsage(usage) => extension(OID_KEY_USAGE, true, &key_usage_bits(*usage)),
+        Extension::ExtendedKeyUsage(purposes) => {
+            let content: Vec<u8> = purposes
+                .iter()
+                .flat_map(|p| {
+                    der(
+                        OBJECT_IDENTIFIER,
+                        &[OID_KEY_PURPOSE_PREFIX, &[key_purpose_id(*p)]].concat(),
+                    )
+                })
+                .collect();
+            extension(OID_EXTENDED_KEY_USAGE, true, &der(SEQUENCE, &content))
+        }
+        Extension::SubjectKeyId(id) => extension(OID_SUBJECT_KEY_ID, false, &der(OCTET_STRING, id)),
+        Extension::AuthorityKeyId(id) => extension(
+            OID_AUTHORITY_KEY_ID,
+            false,
+            &der(SEQUENCE, &der(KEY_IDENTIFIER, id)),
+        ),
+        Extension::Future(data) => data.clone(),
+    }
+}
+
+/// Last component of the `id-kp` OID of a key purpose.
+fn key_purpose_id(purpose: KeyPurpose) -> u8 {
+    match purpose {
+        KeyPurpose::ServerAuth => 1,
+        KeyPurpose::ClientAuth => 2,
+        KeyPurpose::CodeSigning => 3,
+        KeyPurpose::EmailProtection => 4,
+        KeyPurpose::TimeStamping => 8,
+        KeyPurpose::OcspSigning => 9,
+    }
+}
+
+fn parse_extension(raw: &[u8], content: &[u8]) -> Result<Extension> {
+    let mut reader = DerReader::new(content);
+    let oid = reader.read(OBJECT_IDENTIFIER)?;
+    reader.read_optional(BOOLEAN)?;
+    let value = reader.read(OCTET_STRING)?;
+    reader.finish()?;
+
+    Ok(match oid {
+        OID_BASIC_CONSTRAINTS => {
+            let mut reader = DerReader::new(DerReader::new(value).read(SEQUENCE)?);
+            let is_ca = reader.read_optional(BOOLEAN)? == Some(&[0xFF]);
+            let path_length = match reader.read_optional(INTEGER)? {
+                Some([length]) => Some(*length),
+                Some([0, length]) => Some(*length),
+                Some(_) => return Err(anyhow!("Unsupported path length constraint")),
+                None => None,
+            };
+            reader.finish()?;
+            Extension::BasicConstraints(BasicConstraints { is_ca, path_length })
+        }
+        OID_KEY_USAGE => Extension::KeyUsage(parse_key_usage(value)?),
+        OID_EXTENDED_KEY_USAGE => {
+            let mut reader = DerReader::new(DerReader::new(value).read(SEQUENCE)?);
+            let mut purposes = Vec::new();
+            while reader.peek_tag().is_some() {
+                let oid = reader.read(OBJECT_IDENTIFIER)?;
+                let purpose = KeyPurpose::ALL
+                    .iter()
+                    .copied()
+                    .find(|p| {
+                        oid.strip_prefix(OID_KEY_PURPOSE_PREFIX) == Some(&[key_purpose_id(*p)])
+                    })
+                    .ok_or_else(|| anyhow!("Unsupported key purpose {:02X?}", oid))?;
+                purposes.push(purpose);
+            }
+            Extension::ExtendedKeyUsage(purposes)
+        }
+        OID_SUBJECT_KEY_ID => {
+            Extension::SubjectKeyId(DerReader::new(value).read(OCTET_STRING)?.to_vec())
+        }
+        OID_AUTHORITY_KEY_ID => {
+            let mut reader = DerReader::new(DerReader::new(value).read(SEQUENCE)?);
+            let id = reader.read(KEY_IDENTIFIER)?.to_vec();
+            reader.finish()?;
+            Extension::AuthorityKeyId(id)
+        }
+        _ => Extension::Future(raw.to_vec()),
+    })
+}
+
+fn algorithm_identifier() -> Vec<u8> {
+    der(SEQUENCE, &der(OBJECT_IDENTIFIER, OID_ECDSA_WITH_SHA256))
+}
+
+fn public_key_algorithm() -> Vec<u8> {
+    der(
+        SEQUENCE,
+        &[
+            der(OBJECT_IDENTIFIER, OID_EC_PUBLIC_KEY),
+            der(OBJECT_IDENTIFIER, OID_PRIME256V1),
+        ]
+        .concat(),
+    )
+}
+
+impl Certificate {
+    /// DER encoding of the X.509 `TBSCertificate`, which is what the
+    /// certificate signature covers.
+    pub fn tbs_der(&self) -> Result<Vec<u8>> {
+        if self.signature_algorithm != SIGNATURE_ALGORITHM_ECDSA_SHA256
+            || self.public_key_algorithm != PUBLIC_KEY_ALGORITHM_EC
+            || self.curve_id != CURVE_PRIME256V1
+        {
+            return Err(anyhow!("Unsupported certificate algorithms"));
+        }
+
+        let extensions: Vec<u8> = self.extensions.iter().flat_map(extension_der).collect();
+
+        Ok(der(
+            SEQUENCE,
+            &[
+                der(VERSION, &der_unsigned(X509_V3.into())),
+                der(INTEGER, &self.serial_number),
+                algorithm_identifier(),
+                name(&self.issuer),
+                der(
+                    SEQUENCE,
+                    &[time(self.not_before), self.not_after_der()].concat(),
+                ),
+                name(&self.subject),
+                der(
+                    SEQUENCE,
+                    &[
+                        public_key_algorithm(),
+                        der(BIT_STRING, &[&[0], self.public_key.as_slice()].concat()),
+                    ]
+                    .concat(),
+                ),
+                der(EXTENSIONS, &der(SEQUENCE, &extensions)),
+            ]
+            .concat(),
+        ))
+    }
+
+    fn not_after_der(&self) -> Vec<u8> {
+        match self.not_after {
+            0 => der(GENERALIZED_TIME, NO_EXPIRATION),
+            not_after => time(not_after),
+        }
+    }
+
+    /// Converts the certificate to X.509 DER.
+    pub fn to_der(&self) -> Result<Vec<u8>> {
+        let signature = Signature::from_slice(&self.signature)
+            .map_err(|_| anyhow!("Invalid signature encoding"))?;
+
+        Ok(der(
+            SEQUENCE,
+            &[
+                self.tbs_der()?,
+                algorithm_identifier(),
+                der(BIT_STRING, &[&[0], signature.to_der().as_bytes()].concat()),
+            ]
+            .concat(),
+        ))
+    }
+
+    /// Converts an X.509 DER certificate.
+    ///
+    /// Fails unless the certificate uses only what Matter certificates can
+    /// represent.
+    pub fn from_der(data: &[u8]) -> Result<Self> {
+        let mut reader = DerReader::new(data);
+        let mut certificate = DerReader::new(reader.read(SEQUENCE)?);
+        reader.finish()?;
+
+        let mut tbs = DerReader::new(certificate.read(SEQUENCE)?);
+        if certificate.read_any()?.2 != algorithm_identifier() {
+            return Err(anyhow!("Unsupported signature algorithm"));
+        }
+        let signature = match certificate.read(BIT_STRING)? {
+            [0, signature @ ..] => Signature::from_der(signature)
+                .map_err(|_| anyhow!("Invalid signature"))?
+                .to_bytes()
+                .to_vec(),
+            _ => return Err(anyhow!("Invalid signature")),
+        };
+        certificate.finish()?;
+
+        if tbs.read(VERSION)? != der_unsigned(X509_V3.into()) {
+            return Err(anyhow!("Unsupported certificate version"));
+        }
+        let serial_number = tbs.read(INTEGER)?.to_vec();
+        if tbs.read_any()?.2 != algorithm_identifier() {
+            return Err(anyhow!("Unsupported signature algorithm"));
+        }
+        let issuer = parse_name(tbs.read(SEQUENCE)?)?;
+
+        let mut validity = DerReader::new(tbs.read(SEQUENCE)?);
+        let not_before = parse_time(&mut validity)?;
+        let not_after = parse_time(&mut validity)?;
+        validity.finish()?;
+
+        let subject = parse_name(tbs.read(SEQUENCE)?)?;
+
+        let mut public_key_info = DerReader::new(tbs.read(SEQUENCE)?);
+        if public_key_info.read_any()?.2 != public_key_algorithm() {
+            return Err(anyhow!("Unsupported public key algorithm"));
+        }
+        let public_key = match public_key_info.read(BIT_STRING)? {
+            [0, key @ ..] => key.to_vec(),
+            _ => return Err(anyhow!("Invalid public key")),
+        };
+        public_key_info.finish()?;
+
+        let mut extensions = Vec::new();
+        let mut extension_reader =
+            DerReader::new(DerReader::new(tbs.read(EXTENSIONS)?).read(SEQUENCE)?);
+        while extension_reader.peek_tag().is_some() {
+            let (tag, content, raw) = extension_reader.read_any()?;
+            if tag != SEQUENCE {
+                return Err(anyhow!("Invalid extension"));
+            }
+            extensions.push(parse_extension(raw, content)?);
+        }
+        tbs.finish()?;
+
+        let result = Certificate {
+            serial_number,
+            signature_algorithm: SIGNATURE_ALGORITHM_ECDSA_SHA256,
+            issuer,
+            not_before,
+            not_after,
+            subject,
+            public_key_algorithm: PUBLIC_KEY_ALGORITHM_EC,
+            curve_id: CURVE_PRIME256V1,
+            public_key,
+            extensions,
+            signature,
+        };
+
+        if result.to_der()? != data {
+            return Err(anyhow!(
+                "Certificate cannot be represented as a Matter certificate"
+            ));
+        }
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::certificate::tests::{icac, noc, rcac, sign};
+    use crate::certificate::validate_chain;
+    use hex_literal::hex;
+    use p256::ecdsa::SigningKey;
+
+    // A root and node certificate pair created outside of this crate: the DER
+    // forms were issued by the Python `cryptography` package and the TLV forms
+    // written by hand from the Matter certificate schema.
+    const ROOT_TLV: [u8; 252] = hex!(
+        "153001081f2e3d4c5b6a79882402013703271401000000cacacaca182604ef17"
+        "1b2726056eb5b94c3706271401000000cacacaca182407012408013009410402"
+        "17e617f0b6443928278f96999e69a23a4f2c152bdf6d6cdf66e5b80282d4ed19"
+        "4a7debcb97712d2dda3ca85aa8765a56f45fc758599652f2897c65306e579437"
+        "0a350129011824026030041433ca6bc5e920e6bec2a16ce07a70e8282bcd68d1"
+        "30051433ca6bc5e920e6bec2a16ce07a70e8282bcd68d118300b40cd81c45ee7"
+        "f66dba1314f74054b856d9aaab9c6783d455eb6a3c45e1556c8d113fc0d4f8ab"
+        "6b7b393a42f4d1a05ec2fc8e5f1d7188fe59ccd7b496050750ca0018"
+    );
+    const ROOT_DER: [u8; 417] = hex!(
+        "3082019d30820143a00302010202081f2e3d4c5b6a7988300a06082a8648ce3d"
+        "04030230223120301e060a2b0601040182a27c01040c10434143414341434130"
+        "30303030303031301e170d3230313031353134323334335a170d343031303135"
+        "3134323334325a30223120301e060a2b0601040182a27c01040c104341434143"
+        "41434130303030303030313059301306072a8648ce3d020106082a8648ce3d03"
+        "0107034200040217e617f0b6443928278f96999e69a23a4f2c152bdf6d6cdf66"
+        "e5b80282d4ed194a7debcb97712d2dda3ca85aa8765a56f45fc758599652f289"
+        "7c65306e5794a3633061300f0603551d130101ff040530030101ff300e060355"
+        "1d0f0101ff040403020106301d0603551d0e0416041433ca6bc5e920e6bec2a1"
+        "6ce07a70e8282bcd68d1301f0603551d2304183016801433ca6bc5e920e6bec2"
+        "a16ce07a70e8282bcd68d1300a06082a8648ce3d0403020348003045022100cd"
+        "81c45ee7f66dba1314f74054b856d9aaab9c6783d455eb6a3c45e1556c8d1102"
+        "203fc0d4f8ab6b7b393a42f4d1a05ec2fc8e5f1d7188fe59ccd7b496050750ca"
+        "00"
+    );
+    const NOC_TLV: [u8; 269] = hex!(
+        "153001082a3b4c5d6e7f80912402013703271401000000cacacaca182604ef17"
+        "1b2726056eb5b94c3706271101000100dededede27151d0000000000b0fa1824"
+        "070124080130094104d65a93977caa3d1b081852ff57a79e465f1660577304ba"
+        "ead505dd3a48589cf350185e895372df6221ea3a137557e473fddb6755f05bd5"
+        "07c3c533fce9c91285370a3501280118240201360304020401183004140b6993"
+        "f12d0755df63a9622afee00aac88cb69db30051433ca6bc5e920e6bec2a16ce0"
+        "7a70e8282bcd68d118300b40d3a5ec00fa219218c2da50f8c935b1ef090d01a7"
+        "b7a0aab7b84c5b07dda885288ede08cb5cef2a392085961f1aca8af0f8434ced"
+        "d4439535de1c13bd3f3d0b7418"
+    );
+    const NOC_DER: [u8; 485] = hex!(
+        "308201e130820186a00302010202082a3b4c5d6e7f8091300a06082a8648ce3d"
+        "04030230223120301e060a2b0601040182a27c01040c10434143414341434130"
+        "30303030303031301e170d3230313031353134323334335a170d343031303135"
+        "3134323334325a30443120301e060a2b0601040182a27c01010c104445444544"
+        "45444530303031303030313120301e060a2b0601040182a27c01050c10464142"
+        "303030303030303030303031443059301306072a8648ce3d020106082a8648ce"
+        "3d03010703420004d65a93977caa3d1b081852ff57a79e465f1660577304baea"
+        "d505dd3a48589cf350185e895372df6221ea3a137557e473fddb6755f05bd507"
+        "c3c533fce9c91285a38183308180300c0603551d130101ff04023000300e0603"
+        "551d0f0101ff04040302078030200603551d250101ff0416301406082b060105"
+        "0507030206082b06010505070301301d0603551d0e041604140b6993f12d0755"
+        "df63a9622afee00aac88cb69db301f0603551d2304183016801433ca6bc5e920"
+        "e6bec2a16ce07a70e8282bcd68d1300a06082a8648ce3d040302034900304602"
+        "2100d3a5ec00fa219218c2da50f8c935b1ef090d01a7b7a0aab7b84c5b07dda8"
+        "85280221008ede08cb5cef2a392085961f1aca8af0f8434cedd4439535de1c13"
+        "bd3f3d0b74"
+    );
+
+    fn random_key() -> SigningKey {
+        SigningKey::random(&mut rand::thread_rng())
+    }
+
+    #[test]
+    fn times() {
+        assert_eq!(time(0), der(UTC_TIME, b"000101000000Z"));
+        assert_eq!(time(0x2000_0000), der(UTC_TIME, b"170104184832Z"));
+        // 2050-01-01T00:00:00Z
+        assert_eq!(
+            time(1_577_923_200),
+            der(GENERALIZED_TIME, b"20500101000000Z")
+        );
+        assert_eq!(time(u32::MAX), der(GENERALIZED_TIME, b"21360207062815Z"));
+
+        for seconds in [
+            0,
+            59,
+            86_399,
+            5_097_600,
+            0x2000_0000,
+            1_577_923_200,
+            u32::MAX,
+        ] {
+            let encoded = time(seconds);
+            assert_eq!(parse_time(&mut DerReader::new(&encoded)).unwrap(), seconds);
+        }
+
+        assert_eq!(
+            parse_time(&mut DerReader::new(&der(GENERALIZED_TIME, NO_EXPIRATION))).unwrap(),
+            0
+        );
+        // before the Matter epoch
+        assert!(parse_time(&mut DerReader::new(&der(UTC_TIME, b"991231235959Z"))).is_err());
+        let parse = |tag, text: &[u8]| parse_time(&mut DerReader::new(&der(tag, text)));
+        assert!(parse(UTC_TIME, b"501015142343Z").is_err());
+        assert!(parse(GENERALIZED_TIME, b"19691231235959Z").is_err());
+
+        // malformed and out of range fields
+        assert!(parse(UTC_TIME, b"2001010000Z").is_err());
+        for invalid in [
+            b"001301000000Z",
+            b"000001000000Z",
+            b"000100000000Z",
+            b"010229000000Z",
+            b"000431000000Z",
+            b"000101240000Z",
+            b"000101006000Z",
+            b"000101000060Z",
+            b"00010100000+Z",
+            b"000101000000+",
+        ] {
+            assert!(parse(UTC_TIME, invalid).is_err());
+        }
+        // 2000 is a leap year, 2100 is not
+        assert!(parse(UTC_TIME, b"000229000000Z").is_ok());
+        assert!(parse(GENERALIZED_TIME, b"21000229000000Z").is_err());
+    }
+
+    #[test]
+    fn encoding() {
+        assert_eq!(der(OCTET_STRING, &[0xAB]), [0x04, 0x01, 0xAB]);
+        assert_eq!(der(OCTET_STRING, &[0; 0x80])[..3], [0x04, 0x81, 0x80]);
+        assert_eq!(
+            der(OCTET_STRING, &[0; 0x100])[..4],
+            [0x04, 0x82, 0x01, 0x00]
+        );
+
+        assert_eq!(der_unsigned(0), [0x02, 0x01, 0x00]);
+        assert_eq!(der_unsigned(0x7F), [0x02, 0x01, 0x7F]);
+        assert_eq!(der_unsigned(0x80), [0x02, 0x02, 0x00, 0x80]);
+
+        assert_eq!(
+            key_usage_bits(KeyUsage::DIGITAL_SIGNATURE),
+            hex!("03 02 07 80")
+        );
+        assert_eq!(
+            key_usage_bits(KeyUsage::KEY_CERT_SIGN | KeyUsage::CRL_SIGN),
+            hex!("03 02 01 06")
+        );
+        assert_eq!(
+            key_usage_bits(KeyUsage::DIGITAL_SIGNATURE | KeyUsage::DECIPHER_ONLY),
+            hex!("03 03 07 80 80")
+        );
+        for usage in [
+            KeyUsage::DIGITAL_SIGNATURE,
+            KeyUsage::KEY_CERT_SIGN | KeyUsage::CRL_SIGN,
+            KeyUsage::all(),
+        ] {
+            assert_eq!(parse_key_usage(&key_usage_bits(usage)).unwrap(), usage);
+        }
+
+        let name = name(&[
+            DnAttribute::NodeId(NodeId(0xDEDE_DEDE_0001_0001)),
+            DnAttribute::Text {
+                attribute: TextAttribute::DomainComponent,
+                value: "example".into(),
+                printable: false,
+            },
+        ]);
+        assert_eq!(
+            name,
+            [
+                &hex!("30 3B")[..],
+                &hex!("31 20 30 1E 06 0A 2B 06 01 04 01 82 A2 7C 01 01 0C 10"),
+                b"DEDEDEDE00010001",
+                &hex!("31 17 30 15 06 0A 09 92 26 89 93 F2 2C 64 01 19 16 07"),
+                b"example",
+            ]
+            .concat()
+        );
+        assert_eq!(
+            parse_name(DerReader::new(&name).read(SEQUENCE).unwrap()).unwrap(),
+            [
+                DnAttribute::NodeId(NodeId(0xDEDE_DEDE_0001_0001)),
+                DnAttribute::Text {
+                    attribute: TextAttribute::DomainComponent,
+                    value: "example".into(),
+                    printable: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let (rcac_key, icac_key, noc_key) = (random_key(), random_key(), random_key());
+        let rcac = rcac(&rcac_key);
+        let icac = icac(&icac_key, &rcac, &rcac_key);
+        let mut noc = noc(&noc_key, &icac, &icac_key);
+
+        // unknown extensions are carried as they are
+        noc.extensions.push(Extension::Future(extension(
+            &[0x2A, 0x03],
+            false,
+            &der(UTF8_STRING, b"future"),
+        )));
+        noc.not_after = 1_577_923_200;
+        sign(&mut noc, &icac_key);
+
+        for certificate in [rcac, icac, noc] {
+            let encoded = certificate.to_der().unwrap();
+            assert_eq!(Certificate::from_der(&encoded).unwrap(), certificate);
+
+            // the DER signature is the converted TLV signature
+            let mut reader = DerReader::new(&encoded);
+            let mut content = DerReader::new(reader.read(SEQUENCE).unwrap());
+            assert_eq!(
+                content.read_any().unwrap().2,
+                certificate.tbs_der().unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn independent_certificates() {
+        let rcac = Certificate::decode(&ROOT_TLV).unwrap();
+        let noc = Certificate::decode(&NOC_TLV).unwrap();
+
+        for (certificate, tlv, der) in [
+            (&rcac, &ROOT_TLV[..], &ROOT_DER[..]),
+            (&noc, &NOC_TLV, &NOC_DER),
+        ] {
+            assert_eq!(certificate.encode().unwrap(), tlv);
+            assert_eq!(certificate.to_der().unwrap(), der);
+            assert_eq!(&Certificate::from_der(der).unwrap(), certificate);
+        }
+
+        // 2020-10-16, a day after the start of the validity period
+        let identity = validate_chain(&noc, None, &rcac, Some(656_173_423)).unwrap();
+        assert_eq!(identity.node_id, NodeId(0xDEDE_DEDE_0001_0001));
+        assert_eq!(identity.fabric_id, 0xFAB0_0000_0000_001D);
+        assert_eq!(
+            identity.public_key.to_encoded_point(false).as_bytes(),
+            noc.public_key
+        );
+    }
+
+    #[test]
+    fn certificate_dated_before_2000() {
+        // the root certificate, valid from 1950-10-15T14:23:43Z
+        let before = der(UTC_TIME, b"201015142343Z");
+        let after = der(UTC_TIME, b"501015142343Z");
+        let position = ROOT_DER
+            .windows(before.len())
+            .position(|w| w == before)
+            .unwrap();
+        let mut encoded = ROOT_DER.to_vec();
+        encoded[position..position + after.len()].copy_from_slice(&after);
+
+        assert!(Certificate::from_der(&encoded).is_err());
+    }
+
+    #[test]
+    fn unsupported_certificates() {
+        let key = random_key();
+        let certificate = rcac(&key);
+        let encoded = certificate.to_der().unwrap();
+        assert!(Certificate::from_der(&encoded).is_ok());
+
+        // trailing data
+        assert!(Certificate::from_der(&[encoded.as_slice(), &[0]].concat()).is_err());
+
+        // truncated
+        assert!(Certificate::from_der(&encoded[..encoded.len() - 1]).is_err());
+
+        // ecdsa-with-SHA384
+        let position = encoded
+            .windows(OID_ECDSA_WITH_SHA256.len())
+            .position(|w| w == OID_ECDSA_WITH_SHA256)
+            .unwrap();
+        let mut sha384 = encoded.clone();
+        sha384[position + OID_ECDSA_WITH_SHA256.len() - 1] = 0x03;
+        assert!(Certificate::from_der(&sha384).is_err());
+
+        // extensions that do not convert back to the same DER: an explicit
+        // default `critical` flag, and a non-critical basic constraints
+        for non_canonical in [
+            der(
+                SEQUENCE,
+                &[
+                    der(OBJECT_IDENTIFIER, OID_SUBJECT_KEY_ID),
+                    der(BOOLEAN, &[0x00]),
+                    der(OCTET_STRING, &der(OCTET_STRING, &[0xAB; 20])),
+                ]
+                .concat(),
+            ),
+            extension(OID_BASIC_CONSTRAINTS, false, &der(SEQUENCE, &[])),
+        ] {
+            let mut certificate = certificate.clone();
+            certificate
+                .extensions
+                .push(Extension::Future(non_canonical));
+            sign(&mut certificate, &key);
+            assert!(Certificate::from_der(&certificate.to_der().unwrap()).is_err());
+        }
+    }
+}
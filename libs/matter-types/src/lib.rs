@@ -1,20 +1,50 @@
 use core::fmt::Debug;
+use tlv_derive::{TlvEncodable, TlvMergeDecodable, TlvSchema};
+use tlv_packed::FabricIndexValue;
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(
+    Debug, Default, Copy, Clone, PartialEq, Eq, Hash, TlvMergeDecodable, TlvEncodable, TlvSchema,
+)]
 pub struct NodeId(pub u64);
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(
+    Debug, Default, Copy, Clone, PartialEq, Eq, Hash, TlvMergeDecodable, TlvEncodable, TlvSchema,
+)]
 pub struct GroupId(pub u16);
 
-#[derive(Clone, Copy, PartialEq, PartialOrd)]
+#[derive(
+    Default, Clone, Copy, PartialEq, PartialOrd, TlvMergeDecodable, TlvEncodable, TlvSchema,
+)]
 pub struct VendorId(pub u16);
 
-#[derive(Clone, Copy, PartialEq, PartialOrd)]
+#[derive(
+    Default, Clone, Copy, PartialEq, PartialOrd, TlvMergeDecodable, TlvEncodable, TlvSchema,
+)]
 pub struct ProductId(pub u16);
 
-#[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd)]
 pub struct ExchangeId(pub u16);
 
+/// Index of a fabric on a node, carried at context tag 254 by fabric-scoped structures.
+#[derive(
+    Debug, Default, Copy, Clone, PartialEq, Eq, TlvMergeDecodable, TlvEncodable, TlvSchema,
+)]
+pub struct FabricIndex(pub u8);
+
+impl FabricIndex {
+    /// Fabric index 0 is reserved to indicate the absence of a fabric.
+    pub const NONE: FabricIndex = FabricIndex(0);
+}
+
+impl FabricIndexValue for FabricIndex {
+    fn fabric_index(&self) -> Option<u8> {
+        match *self {
+            FabricIndex::NONE => None,
+            FabricIndex(index) => Some(index),
+        }
+    }
+}
+
 impl Debug for ProductId {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_fmt(format_args!("ProductId(0x{:X})", self.0))
@@ -26,3 +56,31 @@ impl Debug for VendorId {
         f.write_fmt(format_args!("VendorId(0x{:X})", self.0))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use streaming_iterator::StreamingIterator;
+    use tlv_packed::{TlvEncodable, TlvMergeDecodable};
+    use tlv_stream::{Record, TagValue, Value};
+
+    #[test]
+    fn ids_are_transparent() {
+        let mut data = Vec::new();
+        VendorId(0xFFF1)
+            .encode(TagValue::ContextSpecific { tag: 1 }, &mut data)
+            .unwrap();
+        assert_eq!(data, [0x25, 0x01, 0xF1, 0xFF]);
+
+        let records = [Record {
+            tag: TagValue::ContextSpecific { tag: 1 },
+            value: Value::Unsigned(0x1122334455),
+        }];
+        let mut source = streaming_iterator::convert(records.iter().copied());
+        source.next();
+
+        let mut node_id = NodeId::default();
+        node_id.merge_decode(&mut source).unwrap();
+        assert_eq!(node_id, NodeId(0x1122334455));
+    }
+}
@@ -0,0 +1,189 @@
+use quote::quote;
+use syn::{parse_quote, DeriveInput, Generics};
+
+use crate::{
+    newtype_field, option_inner_type, parse_struct_fields, FieldKind, ParsedTag, StructFieldInfo,
+    StructOptions,
+};
+
+impl StructFieldInfo {
+    /// Encodes `value` (a reference to the field value, without any `Option`).
+    fn encode_value(
+        &self,
+        value: proc_macro2::TokenStream,
+        tag: &ParsedTag,
+    ) -> proc_macro2::TokenStream {
+        match self.list_item_type() {
+            Some(_) => quote! { ::tlv_packed::encode_list(#value, #tag, writer)? },
+            None => quote! { ::tlv_packed::TlvEncodable::encode(#value, #tag, writer)? },
+        }
+    }
+
+    /// Encodes the field (if it is encoded at all) into `writer`.
+    ///
+    /// Fabric-sensitive fields are only encoded if `__tlv_include_sensitive` is set.
+    fn encode_statement(&self) -> Option<proc_macro2::TokenStream> {
+        let statement = self.encode_field_statement()?;
+
+        if !self.fabric_sensitive {
+            return Some(statement);
+        }
+
+        Some(quote! {
+            if __tlv_include_sensitive {
+                #statement
+            }
+        })
+    }
+
+    fn encode_field_statement(&self) -> Option<proc_macro2::TokenStream> {
+        let ident = &self.ident;
+
+        match (&self.kind, &self.with) {
+            (FieldKind::Tagged(tag, _), Some(with)) => Some(quote! {
+                #with::encode(&self.#ident, #tag, writer)?;
+            }),
+            (FieldKind::Tagged(tag, _), None) if self.nullable => {
+                let encode = self.encode_value(quote! { value }, tag);
+                Some(quote! {
+                    match self.#ident {
+                        ::core::option::Option::Some(ref value) => #encode,
+                        ::core::option::Option::None => writer.write_record(::tlv_stream::Record {
+                            tag: #tag,
+                            value: ::tlv_stream::Value::Null,
+                        })?,
+                    }
+                })
+            }
+            (FieldKind::Tagged(tag, _), None) if self.list_item_type().is_some() => {
+                let encode = self.encode_value(quote! { value }, tag);
+                match option_inner_type(&self.ty) {
+                    Some(_) => Some(quote! {
+                        if let ::core::option::Option::Some(ref value) = self.#ident {
+                            #encode;
+                        }
+                    }),
+                    None => Some(quote! {
+                        let value = &self.#ident;
+                        #encode;
+                    }),
+                }
+            }
+            (FieldKind::Tagged(tag, _), None) => Some(quote! {
+                ::tlv_packed::TlvEncodable::encode(&self.#ident, #tag, writer)?;
+            }),
+            (FieldKind::Unknown, _) => Some(quote! {
+                ::tlv_packed::TlvEncodable::encode(&self.#ident, ::tlv_stream::TagValue::Anonymous, writer)?;
+            }),
+            (FieldKind::Skipped, _) => None,
+        }
+    }
+}
+
+/// Adds a `TlvEncodable` bound to every type parameter of the structure.
+fn encode_impl_generics(generics: &Generics) -> Generics {
+    let mut result = generics.clone();
+
+    let type_params: Vec<_> = generics.type_params().map(|t| t.ident.clone()).collect();
+    let where_clause = result.make_where_clause();
+    for ident in type_params {
+        where_clause.predicates.push(parse_quote! {
+            #ident: ::tlv_packed::TlvEncodable
+        });
+    }
+
+    result
+}
+
+pub fn expand_tlv_encodable(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+
+    let generics = encode_impl_generics(&input.generics);
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, type_generics, _) = input.generics.split_for_impl();
+
+    if newtype_field(&input)?.is_some() {
+        return Ok(quote! {
+            impl #impl_generics ::tlv_packed::TlvEncodable for #name #type_generics
+            #where_clause
+            {
+                fn encode<__TlvWriter: ::tlv_packed::TlvWriter>(
+                    &self,
+                    tag: ::tlv_stream::TagValue,
+                    writer: &mut __TlvWriter,
+                ) -> ::core::result::Result<(), ::tlv_packed::EncodeError> {
+                    ::tlv_packed::TlvEncodable::encode(&self.0, tag, writer)
+                }
+            }
+        });
+    }
+
+    // validates structure attributes, even if they only apply to decoding
+    let options = StructOptions::from_attributes(&input.attrs)?;
+
+    let fields = parse_struct_fields(&input)?;
+    let fabric_index = options.fabric_index_field(name, &fields)?;
+    let fields_encode: Vec<_> = fields.iter().filter_map(|f| f.encode_statement()).collect();
+
+    let encode_body = quote! {
+        writer.write_record(::tlv_stream::Record {
+            tag,
+            value: ::tlv_stream::Value::ContainerStart(::tlv_stream::ContainerType::Structure),
+        })?;
+
+        #(#fields_encode)*
+
+        writer.write_record(::tlv_stream::Record {
+            tag: ::tlv_stream::TagValue::Anonymous,
+            value: ::tlv_stream::Value::ContainerEnd,
+        })
+    };
+
+    let fabric_scoped_impl = fabric_index.map(|field| {
+        let ident = &field.ident;
+        quote! {
+            impl #impl_generics ::tlv_packed::FabricScoped for #name #type_generics
+            #where_clause
+            {
+                fn fabric_index(&self) -> ::core::option::Option<u8> {
+                    ::tlv_packed::FabricIndexValue::fabric_index(&self.#ident)
+                }
+
+                fn encode_for_fabric<__TlvWriter: ::tlv_packed::TlvWriter>(
+                    &self,
+                    tag: ::tlv_stream::TagValue,
+                    accessing_fabric: u8,
+                    writer: &mut __TlvWriter,
+                ) -> ::core::result::Result<(), ::tlv_packed::EncodeError> {
+                    let __tlv_include_sensitive =
+                        ::tlv_packed::FabricScoped::fabric_index(self) == ::core::option::Option::Some(accessing_fabric);
+
+                    #encode_body
+                }
+            }
+        }
+    });
+
+    // plain encoding includes all fields, fabric-sensitive or not
+    let include_sensitive = fabric_index.map(|_| {
+        quote! { let __tlv_include_sensitive = true; }
+    });
+
+    Ok(quote! {
+        impl #impl_generics ::tlv_packed::TlvEncodable for #name #type_generics
+        #where_clause
+        {
+            fn encode<__TlvWriter: ::tlv_packed::TlvWriter>(
+                &self,
+                tag: ::tlv_stream::TagValue,
+                writer: &mut __TlvWriter,
+            ) -> ::core::result::Result<(), ::tlv_packed::EncodeError> {
+                #include_sensitive
+
+                #encode_body
+            }
+        }
+
+        #fabric_scoped_impl
+    })
+}
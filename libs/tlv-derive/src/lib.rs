@@ -1,13 +1,24 @@
+use core::fmt::Display;
 use lazy_static::lazy_static;
 use proc_macro::TokenStream;
-use proc_macro2::TokenTree;
-use quote::quote;
+use proc_macro2::Span;
+use quote::{quote, ToTokens};
 use regex::{Match, Regex};
 use streaming_iterator::{convert, StreamingIterator};
-use syn::{parse_macro_input, DeriveInput, ExprLit, Fields, Data, Ident, Attribute, Path};
+use syn::ext::IdentExt;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{
+    parse_macro_input, parse_quote, Attribute, Data, DataStruct, DeriveInput, Expr, ExprLit, Field,
+    Fields, GenericArgument, GenericParam, Generics, Ident, Lifetime, LifetimeDef, Lit, LitInt,
+    LitStr, Meta, MetaNameValue, Path, PathArguments, Token, Type, TypePath,
+};
 use tlv_packed::{DecodeEnd, DecodeError, TlvDecodable, TlvMergeDecodable};
 use tlv_stream::{ContainerType, Record, Value};
 
+mod encode;
+mod schema;
+
 #[derive(Debug, Copy, Clone, Default, PartialEq)]
 struct ChildStructure {
     some_unsigned: Option<u32>, // tag: 1
@@ -220,15 +231,73 @@ fn parse_u16_match(m: Option<Match>) -> anyhow::Result<u16> {
     Ok(value)
 }
 
+/// A tag value parsed from a tag string at macro expansion time.
+///
+/// Converts into a [::tlv_stream::TagValue] expression via [ToTokens].
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum ParsedTag {
+    Anonymous,
+    ContextSpecific(u8),
+    Implicit(u32),
+    Full {
+        vendor_id: u16,
+        profile_id: u16,
+        tag: u32,
+    },
+}
+
+impl ToTokens for ParsedTag {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        tokens.extend(match *self {
+            ParsedTag::Anonymous => quote! {
+                ::tlv_stream::TagValue::Anonymous
+            },
+            ParsedTag::ContextSpecific(tag) => {
+                let tag = tag as u32;
+                quote! {
+                    ::tlv_stream::TagValue::ContextSpecific { tag: #tag }
+                }
+            }
+            ParsedTag::Implicit(tag) => quote! {
+                ::tlv_stream::TagValue::Implicit { tag: #tag }
+            },
+            ParsedTag::Full {
+                vendor_id,
+                profile_id,
+                tag,
+            } => quote! {
+                ::tlv_stream::TagValue::Full { vendor_id: #vendor_id, profile_id: #profile_id, tag: #tag }
+            },
+        });
+    }
+}
+
+impl Display for ParsedTag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParsedTag::Anonymous => write!(f, "anonymous"),
+            ParsedTag::ContextSpecific(tag) => write!(f, "context:{}", tag),
+            ParsedTag::Implicit(tag) => write!(f, "implicit:{}", tag),
+            ParsedTag::Full {
+                vendor_id,
+                profile_id,
+                tag,
+            } => write!(f, "full:{}-{}-{}", vendor_id, profile_id, tag),
+        }
+    }
+}
+
 /// Parses a string tag value into an underlying
 /// [::tlvstream::TagValue] that can be used for macro generation
 ///
 /// Valid syntax examples:
 ///   - "context: 123"
-///   - "context: 0xabc"
+///   - "context: 0xab"
 ///   - "CONTEXt: 22"
 ///
-fn parse_tag_value(tag: &str) -> Result<proc_macro2::TokenStream, anyhow::Error> {
+/// Context specific tags are encoded on a single byte, so they
+/// are limited to 255.
+fn parse_tag_value(tag: &str) -> Result<ParsedTag, anyhow::Error> {
     lazy_static! {
         static ref RE_CONTEXT: Regex =
             Regex::new(r"^(?i)context:\s*(\d+|0x[[:xdigit:]]+)$").unwrap();
@@ -241,48 +310,52 @@ fn parse_tag_value(tag: &str) -> Result<proc_macro2::TokenStream, anyhow::Error>
     }
 
     if tag.eq_ignore_ascii_case("anonymous") {
-        return Ok(quote! {
-            ::tlv_stream::TagValue::Anonymous
-        }
-        .into());
+        return Ok(ParsedTag::Anonymous);
     }
 
     if let Some(captures) = RE_CONTEXT.captures(tag) {
         let tag = parse_u32_match(captures.get(1))?;
+        let tag = u8::try_from(tag).map_err(|_| {
+            anyhow::anyhow!(
+                "Context tag {} does not fit in one byte (maximum is 255)",
+                tag
+            )
+        })?;
 
-        return Ok(quote! {
-            ::tlv_stream::TagValue::ContextSpecific { tag: #tag}
-        }
-        .into());
+        return Ok(ParsedTag::ContextSpecific(tag));
     }
 
     if let Some(captures) = RE_IMPLICIT.captures(tag) {
-        let tag = parse_u32_match(captures.get(1))?;
-
-        return Ok(quote! {
-            ::tlv_stream::TagValue::Implicit { tag: #tag}
-        }
-        .into());
+        return Ok(ParsedTag::Implicit(parse_u32_match(captures.get(1))?));
     }
+
     if let Some(captures) = RE_FULL.captures(tag) {
         let tag = parse_u32_match(captures.get(3))?;
 
         if captures.get(1).is_some() {
-            let vendor_id = parse_u16_match(captures.get(1))?;
-            let profile_id = parse_u16_match(captures.get(2))?;
-
-            return Ok(quote! {
-                ::tlv_stream::TagValue::Full { vendor_id: #vendor_id, profile_id: #profile_id, tag: #tag}
-            }.into());
+            return Ok(ParsedTag::Full {
+                vendor_id: parse_u16_match(captures.get(1))?,
+                profile_id: parse_u16_match(captures.get(2))?,
+                tag,
+            });
         } else {
-            return Ok(quote! {
-                ::tlv_stream::TagValue::Full { vendor_id: 0, profile_id: 0, tag: #tag}
-            }
-            .into());
+            return Ok(ParsedTag::Full {
+                vendor_id: 0,
+                profile_id: 0,
+                tag,
+            });
         }
     }
 
-    return Err(anyhow::anyhow!("Invalid tag syntax: '{}'", tag));
+    Err(anyhow::anyhow!(
+        "Invalid tag syntax: '{}'. Expected something like \"context: 1\", \"implicit: 0x1234\", \"full: 1-2-3\" or \"anonymous\"",
+        tag
+    ))
+}
+
+/// Parses a string literal containing a tag value, reporting errors at the literal location.
+fn parse_tag_literal(lit: &LitStr) -> syn::Result<ParsedTag> {
+    parse_tag_value(&lit.value()).map_err(|e| syn::Error::new(lit.span(), e))
 }
 
 /// Converts strings from tag value.
@@ -309,8 +382,8 @@ fn parse_tag_value(tag: &str) -> Result<proc_macro2::TokenStream, anyhow::Error>
 /// );
 ///
 /// assert_eq!(
-///     into_parsed_tag_value!("context: 0xabcd"),
-///     TagValue::ContextSpecific { tag: 0xabcd }
+///     into_parsed_tag_value!("context: 0xab"),
+///     TagValue::ContextSpecific { tag: 0xab }
 /// );
 ///
 /// assert_eq!(
@@ -339,7 +412,7 @@ fn parse_tag_value(tag: &str) -> Result<proc_macro2::TokenStream, anyhow::Error>
 /// );
 /// ```
 ///
-/// It invalid values are passed, the parsing will panic
+/// If invalid values are passed, a compile error is reported at the literal location
 ///
 /// ```compile_fail
 /// use tlv_derive::into_parsed_tag_value;
@@ -351,113 +424,1217 @@ fn parse_tag_value(tag: &str) -> Result<proc_macro2::TokenStream, anyhow::Error>
 /// into_parsed_tag_value!("context: notahexvalue");
 /// ```
 ///
+/// Context specific tags must fit in a single byte:
+///
+/// ```compile_fail
+/// use tlv_derive::into_parsed_tag_value;
+/// into_parsed_tag_value!("context: 256");
+/// ```
+///
 #[proc_macro]
 pub fn into_parsed_tag_value(input: TokenStream) -> TokenStream {
-    let item: ExprLit = syn::parse(input).unwrap();
+    let lit = parse_macro_input!(input as LitStr);
 
-    match item.lit {
-        syn::Lit::Str(s) => parse_tag_value(s.value().as_str()).unwrap().into(),
-        _ => panic!("Need a string literal to parse"),
+    match parse_tag_literal(&lit) {
+        Ok(tag) => tag.into_token_stream().into(),
+        Err(e) => e.to_compile_error().into(),
     }
 }
 
-fn extract_tag_value(attrs: impl IntoIterator<Item=Attribute>) -> proc_macro2::TokenStream {
-    
-    for a in attrs {
-        if !a.path.is_ident("tlv_tag") {
-            continue
-        }
-        
-        let mut iter = a.tokens.into_iter();
-        let v = match iter.next() {
-            Some(value) => value,
-            None => continue,
+/// A single entry inside a `#[tlv(...)]` attribute.
+///
+/// Entries are either flags (like `unknown`) or `name = value` pairs
+/// (like `unknown_tags = "reject"`).
+struct TlvAttributeEntry {
+    name: Ident,
+    value: Option<Expr>,
+}
+
+impl Parse for TlvAttributeEntry {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name = Ident::parse_any(input)?;
+        let value = if input.peek(Token![=]) {
+            input.parse::<Token![=]>()?;
+            Some(input.parse()?)
+        } else {
+            None
         };
 
-        
-        if !matches!(v, TokenTree::Punct(ref p) if p.as_char() == '=') {
-            continue;
+        Ok(Self { name, value })
+    }
+}
+
+/// Extracts all the entries of `#[tlv(...)]` attributes
+fn tlv_attribute_entries<'a>(
+    attrs: impl IntoIterator<Item = &'a Attribute>,
+) -> syn::Result<Vec<TlvAttributeEntry>> {
+    let mut result = Vec::new();
+
+    for a in attrs.into_iter().filter(|a| a.path.is_ident("tlv")) {
+        result.extend(
+            a.parse_args_with(Punctuated::<TlvAttributeEntry, Token![,]>::parse_terminated)?,
+        );
+    }
+
+    Ok(result)
+}
+
+/// Interprets an attribute value as a word, accepting both
+/// `name = "word"` and `name = word`.
+fn attribute_word(value: &Option<Expr>) -> Option<String> {
+    match value {
+        Some(Expr::Lit(ExprLit {
+            lit: syn::Lit::Str(s),
+            ..
+        })) => Some(s.value()),
+        Some(Expr::Path(p)) => p.path.get_ident().map(|i| i.to_string()),
+        _ => None,
+    }
+}
+
+/// How a derived decoder handles elements whose tag does not match any field.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum UnknownTagPolicy {
+    /// Skip over unknown elements (including entire containers)
+    Ignore,
+
+    /// Store unknown elements in the field marked as `#[tlv(unknown)]`
+    Collect,
+
+    /// Fail decoding with `DecodeError::UnknownTag`
+    Reject,
+}
+
+#[derive(Debug)]
+struct StructOptions {
+    unknown_tags: UnknownTagPolicy,
+
+    /// The structure belongs to a fabric, identified by its fabric index field
+    fabric_scoped: bool,
+}
+
+impl StructOptions {
+    fn from_attributes(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut result = Self {
+            unknown_tags: UnknownTagPolicy::Ignore,
+            fabric_scoped: false,
+        };
+
+        for entry in tlv_attribute_entries(attrs)? {
+            if entry.name == "fabric_scoped" {
+                if let Some(ref value) = entry.value {
+                    return Err(syn::Error::new_spanned(
+                        value,
+                        "`fabric_scoped` does not take a value",
+                    ));
+                }
+                result.fabric_scoped = true;
+                continue;
+            }
+
+            if entry.name != "unknown_tags" {
+                return Err(syn::Error::new(
+                    entry.name.span(),
+                    format!("Unknown structure attribute `{}`", entry.name),
+                ));
+            }
+
+            result.unknown_tags = match attribute_word(&entry.value).as_deref() {
+                Some("ignore") => UnknownTagPolicy::Ignore,
+                Some("collect") => UnknownTagPolicy::Collect,
+                Some("reject") => UnknownTagPolicy::Reject,
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        entry
+                            .value
+                            .as_ref()
+                            .map_or(&entry.name as &dyn ToTokens, |v| v),
+                        "`unknown_tags` must be one of \"ignore\", \"collect\" or \"reject\"",
+                    ))
+                }
+            };
         }
-        let v = match iter.next() {
-            Some(value) => value,
-            None => continue,
+
+        Ok(result)
+    }
+
+    /// Validates the fabric scoping of the structure fields.
+    ///
+    /// Returns the fabric index field of fabric-scoped structures.
+    fn fabric_index_field<'a>(
+        &self,
+        name: &Ident,
+        fields: &'a [StructFieldInfo],
+    ) -> syn::Result<Option<&'a StructFieldInfo>> {
+        if !self.fabric_scoped {
+            return match fields.iter().find(|f| f.fabric_index || f.fabric_sensitive) {
+                Some(f) => Err(syn::Error::new(
+                    f.ident.span(),
+                    "`fabric_index` and `fabric_sensitive` fields require `#[tlv(fabric_scoped)]` on the structure",
+                )),
+                None => Ok(None),
+            };
+        }
+
+        match fields.iter().find(|f| f.fabric_index) {
+            Some(f) => Ok(Some(f)),
+            None => Err(syn::Error::new_spanned(
+                name,
+                "Fabric-scoped structures require a `#[tlv(fabric_index)]` field (tag `context:254`)",
+            )),
+        }
+    }
+}
+
+/// Finds the tag of a field given in the `#[tlv_tag = "..."]` shorthand form.
+fn extract_tag_value(attrs: &[Attribute]) -> syn::Result<Option<(ParsedTag, Span)>> {
+    let mut result = None;
+
+    for a in attrs.iter().filter(|a| a.path.is_ident("tlv_tag")) {
+        let lit = match a.parse_meta()? {
+            Meta::NameValue(MetaNameValue {
+                lit: Lit::Str(lit), ..
+            }) => lit,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    a,
+                    "Expected a tag string like `#[tlv_tag = \"context:1\"]`",
+                ))
+            }
         };
 
-        if let TokenTree::Literal(ref l) = v {
-            let tag: String = l.to_string().parse().unwrap();
-            
-            // Tag includes quotes, like "\"context: 1\""
+        if result.is_some() {
+            return Err(syn::Error::new_spanned(a, "Duplicate `tlv_tag` attribute"));
+        }
+
+        result = Some((parse_tag_literal(&lit)?, lit.span()));
+    }
 
-            return parse_tag_value(&tag[1..tag.len()-1]).unwrap()
+    Ok(result)
+}
+
+/// Parses the value of a `tag = ...` attribute entry.
+///
+/// Accepts either a tag string (like `tag = "full: 1-2-3"`) or an
+/// integer, which is a shorthand for a context tag (`tag = 1`).
+fn parse_tag_expr(expr: &Expr) -> syn::Result<ParsedTag> {
+    match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Str(lit), ..
+        }) => parse_tag_literal(lit),
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(lit), ..
+        }) => match lit.base10_parse::<u8>() {
+            Ok(tag) => Ok(ParsedTag::ContextSpecific(tag)),
+            Err(_) => Err(syn::Error::new(
+                lit.span(),
+                format!(
+                    "Context tag {} does not fit in one byte (maximum is 255)",
+                    lit.base10_digits()
+                ),
+            )),
+        },
+        _ => Err(syn::Error::new_spanned(
+            expr,
+            "Expected a tag like `tag = 1` or `tag = \"context:1\"`",
+        )),
+    }
+}
+
+/// Parses a non-negative integer attribute value, like `max_len = 32`.
+fn parse_usize_expr(expr: &Expr) -> syn::Result<LitInt> {
+    match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(lit), ..
+        }) => {
+            lit.base10_parse::<usize>()?;
+            Ok(LitInt::new(lit.base10_digits(), lit.span()))
         }
+        _ => Err(syn::Error::new_spanned(expr, "Expected an integer")),
+    }
+}
+
+/// Parses the value of a `with = ...` attribute entry, given either as a
+/// path or as a string containing a path.
+fn parse_with_path(expr: &Expr) -> syn::Result<Path> {
+    match expr {
+        Expr::Path(p) => Ok(p.path.clone()),
+        Expr::Lit(ExprLit {
+            lit: Lit::Str(lit), ..
+        }) => lit.parse(),
+        _ => Err(syn::Error::new_spanned(
+            expr,
+            "Expected a module path like `with = my_module`",
+        )),
+    }
+}
+
+#[derive(Debug)]
+enum FieldKind {
+    /// A field decoded from elements with the given tag
+    Tagged(ParsedTag, Span),
+
+    /// A field collecting all unknown elements
+    Unknown,
+
+    /// A field that is neither decoded nor encoded
+    Skipped,
+}
+
+/// Returns `T` if the given type is `Name<T>`, like `Option<T>` or `Vec<T>`.
+///
+/// Detection is syntactic, so it only works for types spelled with the given
+/// name (optionally with a path prefix like `std::option::`).
+fn generic_inner_type<'a>(ty: &'a Type, name: &str) -> Option<&'a Type> {
+    let path = match ty {
+        Type::Path(TypePath { qself: None, path }) => path,
+        _ => return None,
+    };
+
+    let segment = path.segments.last()?;
+    if segment.ident != name {
+        return None;
+    }
+
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) if args.args.len() == 1 => match args.args.first() {
+            Some(GenericArgument::Type(inner)) => Some(inner),
+            _ => None,
+        },
+        _ => None,
     }
+}
+
+/// Returns `T` if the given type is `Option<T>`.
+fn option_inner_type(ty: &Type) -> Option<&Type> {
+    generic_inner_type(ty, "Option")
+}
+
+/// Returns `T` if the given type is a list spelled as `Vec<T>`.
+///
+/// `Vec<u8>` is an octet string and not a list.
+fn list_inner_type(ty: &Type) -> Option<&Type> {
+    generic_inner_type(ty, "Vec").filter(
+        |inner| !matches!(inner, Type::Path(TypePath { qself: None, path }) if path.is_ident("u8")),
+    )
+}
 
-    
-    panic!("Missing tag value. Please add an attribute like `#[tlv_tag=\"context:1\"]`")
+/// Constraints on field values, validated while decoding.
+#[derive(Debug, Default)]
+struct FieldConstraints {
+    /// Maximum length of strings and octet strings
+    max_len: Option<LitInt>,
+
+    /// Allowed range of numeric values
+    range: Option<Expr>,
+
+    /// Maximum number of entries in a list
+    max_entries: Option<LitInt>,
 }
 
 #[derive(Debug)]
 struct StructFieldInfo {
     ident: Ident,
-    tag_value: proc_macro2::TokenStream,
+    ty: Type,
+    kind: FieldKind,
+
+    /// Value of the field in the generated `Default` implementation
+    default: Option<Expr>,
+
+    /// Module providing `merge_decode` and `encode` functions for the field
+    with: Option<Path>,
+
+    /// `None` values are encoded as null instead of being omitted
+    nullable: bool,
+
+    constraints: FieldConstraints,
+
+    /// The fabric index of a fabric-scoped structure, at tag `context:254`
+    fabric_index: bool,
+
+    /// Only encoded for the fabric a fabric-scoped structure belongs to
+    fabric_sensitive: bool,
 }
 
-impl StructFieldInfo
-{
-    pub fn decode_match(&self) -> proc_macro2::TokenStream {
-       let tag = self.tag_value.clone();
-       let ident = self.ident.clone();
-       
-       // TODO: we may want to detect Option and have default code
-       //       logic for that.
-       quote!{
-           #tag => {
-                self.#ident.merge_decode(source)?
-           }
-       }
+impl StructFieldInfo {
+    /// Type of the field value, without any `Option`.
+    fn value_type(&self) -> &Type {
+        option_inner_type(&self.ty).unwrap_or(&self.ty)
+    }
+
+    /// Element type if the field value is a list.
+    fn list_item_type(&self) -> Option<&Type> {
+        match self.with {
+            Some(_) => None,
+            None => list_inner_type(self.value_type()),
+        }
+    }
+
+    /// Decodes the current element into `target` (a `&mut` to the field value).
+    fn decode_value(&self, target: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        if self.list_item_type().is_none() {
+            return quote! {
+                ::tlv_packed::TlvMergeDecodable::merge_decode(#target, source)?
+            };
+        }
+
+        let max_entries = match self.constraints.max_entries {
+            Some(ref max) => quote! { ::core::option::Option::Some(#max) },
+            None => quote! { ::core::option::Option::None },
+        };
+
+        quote! {
+            ::tlv_packed::decode_list(#target, source, #max_entries)?
+        }
+    }
+
+    /// Validates the constraints of the decoded `value` (a reference to the field value).
+    fn validate_value(&self, value: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        let max_len = self.constraints.max_len.as_ref().map(|max| {
+            quote! { ::tlv_packed::check_max_len(#value, #max)?; }
+        });
+        let range = self.constraints.range.as_ref().map(|range| {
+            quote! { ::tlv_packed::check_range(#value, #range)?; }
+        });
+
+        quote! {
+            #max_len
+            #range
+        }
+    }
+
+    pub fn decode_match(&self) -> Option<proc_macro2::TokenStream> {
+        let ident = &self.ident;
+
+        let tag = match self.kind {
+            FieldKind::Tagged(ref tag, _) => tag,
+            FieldKind::Unknown | FieldKind::Skipped => return None,
+        };
+
+        if let Some(ref with) = self.with {
+            let validate = self.validate_value(quote! { &self.#ident });
+            return Some(quote! {
+                #tag => {
+                    let decoded = #with::merge_decode(&mut self.#ident, source)?;
+                    #validate
+                    decoded
+                }
+            });
+        }
+
+        if option_inner_type(&self.ty).is_none() {
+            let decode = self.decode_value(quote! { &mut self.#ident });
+            let validate = self.validate_value(quote! { &self.#ident });
+            return Some(quote! {
+                #tag => {
+                    let decoded = #decode;
+                    #validate
+                    decoded
+                }
+            });
+        }
+
+        let decode = self.decode_value(quote! { &mut value });
+        let validate = self.validate_value(quote! { &value });
+
+        // Optional values are set to None for explicit nulls. Otherwise the
+        // inner value is merge-decoded (starting from default if not yet set),
+        // which allows optional structures and borrowed values.
+        Some(quote! {
+            #tag => {
+                match ::streaming_iterator::StreamingIterator::get(source) {
+                    ::core::option::Option::Some(::tlv_stream::Record {
+                        tag: _,
+                        value: ::tlv_stream::Value::Null,
+                    }) => {
+                        self.#ident = ::core::option::Option::None;
+                        ::tlv_packed::DecodeEnd::DataConsumed
+                    }
+                    _ => {
+                        let mut value = self.#ident.take().unwrap_or_default();
+                        let decoded = #decode;
+                        #validate
+                        self.#ident = ::core::option::Option::Some(value);
+                        decoded
+                    }
+                }
+            }
+        })
+    }
+
+    /// Initializer of the field in the generated `Default` implementation.
+    pub fn default_initializer(&self) -> proc_macro2::TokenStream {
+        let ident = &self.ident;
+        match self.default {
+            Some(ref default) => quote! { #ident: #default },
+            None => quote! { #ident: ::core::default::Default::default() },
+        }
+    }
+}
+
+impl TryFrom<syn::Field> for StructFieldInfo {
+    type Error = syn::Error;
+
+    fn try_from(f: syn::Field) -> syn::Result<Self> {
+        let ident = f
+            .ident
+            .ok_or_else(|| syn::Error::new_spanned(&f.ty, "Fields need to be named"))?;
+
+        let mut tag = extract_tag_value(&f.attrs)?;
+        let mut is_unknown = false;
+        let mut is_skipped = false;
+        let mut default = None;
+        let mut with = None;
+        let mut nullable = false;
+        let mut constraints = FieldConstraints::default();
+        let mut fabric_index = false;
+        let mut fabric_sensitive = false;
+
+        for entry in tlv_attribute_entries(&f.attrs)? {
+            let name = entry.name.to_string();
+            match (name.as_str(), &entry.value) {
+                ("unknown", None) => is_unknown = true,
+                ("skip", None) => is_skipped = true,
+                ("nullable", None) => nullable = true,
+                ("fabric_index", None) => fabric_index = true,
+                ("fabric_sensitive", None) => fabric_sensitive = true,
+                ("default", None) => {
+                    default = Some(parse_quote!(::core::default::Default::default()))
+                }
+                ("default", Some(value)) => default = Some(value.clone()),
+                ("tag", Some(value)) => {
+                    if tag.is_some() {
+                        return Err(syn::Error::new(
+                            entry.name.span(),
+                            format!("Duplicate tag for `{}`", ident),
+                        ));
+                    }
+                    tag = Some((parse_tag_expr(value)?, entry.name.span()));
+                }
+                ("with", Some(value)) => with = Some(parse_with_path(value)?),
+                ("max_len", Some(value)) => constraints.max_len = Some(parse_usize_expr(value)?),
+                ("max_entries", Some(value)) => {
+                    constraints.max_entries = Some(parse_usize_expr(value)?)
+                }
+                ("range", Some(value)) => constraints.range = Some(value.clone()),
+                (
+                    "unknown" | "skip" | "nullable" | "fabric_index" | "fabric_sensitive",
+                    Some(value),
+                ) => {
+                    return Err(syn::Error::new_spanned(
+                        value,
+                        format!("`{}` does not take a value", name),
+                    ))
+                }
+                ("tag" | "with" | "max_len" | "max_entries" | "range", None) => {
+                    return Err(syn::Error::new(
+                        entry.name.span(),
+                        format!("`{}` requires a value", name),
+                    ))
+                }
+                _ => {
+                    return Err(syn::Error::new(
+                        entry.name.span(),
+                        format!("Unknown field attribute `{}`", entry.name),
+                    ))
+                }
+            }
+        }
+
+        if fabric_index {
+            match tag {
+                Some((ParsedTag::ContextSpecific(254), _)) => {}
+                Some((_, span)) => {
+                    return Err(syn::Error::new(
+                        span,
+                        "`fabric_index` fields always use tag `context:254`",
+                    ))
+                }
+                None => tag = Some((ParsedTag::ContextSpecific(254), ident.span())),
+            }
+        }
+
+        if fabric_index && fabric_sensitive {
+            return Err(syn::Error::new(
+                ident.span(),
+                "The `fabric_index` field cannot be `fabric_sensitive`",
+            ));
+        }
+
+        let kind = match (is_unknown, is_skipped, tag) {
+            (false, false, Some((tag, span))) => FieldKind::Tagged(tag, span),
+            (true, false, None) => FieldKind::Unknown,
+            (false, true, None) => FieldKind::Skipped,
+            (true, true, _) => {
+                return Err(syn::Error::new(
+                    ident.span(),
+                    "`unknown` and `skip` cannot be used together",
+                ))
+            }
+            (_, _, Some((_, span))) => {
+                return Err(syn::Error::new(
+                    span,
+                    "`#[tlv(unknown)]` and `#[tlv(skip)]` fields cannot have a tag",
+                ))
+            }
+            (false, false, None) => {
+                return Err(syn::Error::new(
+                    ident.span(),
+                    format!(
+                        "Missing tag value for `{}`. Please add an attribute like `#[tlv(tag = 1)]` or `#[tlv_tag=\"context:1\"]`",
+                        ident
+                    ),
+                ))
+            }
+        };
+
+        if !matches!(kind, FieldKind::Tagged(..)) && (default.is_some() || with.is_some()) {
+            return Err(syn::Error::new(
+                ident.span(),
+                "`default` and `with` are only supported on tagged fields",
+            ));
+        }
+
+        if nullable && (with.is_some() || option_inner_type(&f.ty).is_none()) {
+            return Err(syn::Error::new(
+                ident.span(),
+                "`nullable` is only supported on `Option<T>` fields without `with`",
+            ));
+        }
+
+        let is_list =
+            with.is_none() && list_inner_type(option_inner_type(&f.ty).unwrap_or(&f.ty)).is_some();
+        let has_constraints = constraints.max_len.is_some()
+            || constraints.range.is_some()
+            || constraints.max_entries.is_some();
+
+        if has_constraints && !matches!(kind, FieldKind::Tagged(..)) {
+            return Err(syn::Error::new(
+                ident.span(),
+                "Constraints are only supported on tagged fields",
+            ));
+        }
+
+        if is_list && (constraints.max_len.is_some() || constraints.range.is_some()) {
+            return Err(syn::Error::new(
+                ident.span(),
+                "List fields only support the `max_entries` constraint",
+            ));
+        }
+
+        if let (false, Some(max_entries)) = (is_list, &constraints.max_entries) {
+            return Err(syn::Error::new(
+                max_entries.span(),
+                "`max_entries` is only supported on list (`Vec<T>`) fields",
+            ));
+        }
+
+        Ok(Self {
+            ident,
+            ty: f.ty,
+            kind,
+            default,
+            with,
+            nullable,
+            constraints,
+            fabric_index,
+            fabric_sensitive,
+        })
+    }
+}
+
+/// Returns the wrapped field if the structure is a newtype (a tuple structure
+/// with exactly one field).
+///
+/// Newtypes are transparent: they decode and encode exactly like the value they
+/// wrap, so they take no `tlv` attributes.
+fn newtype_field(input: &DeriveInput) -> syn::Result<Option<&Field>> {
+    let field = match input.data {
+        Data::Struct(DataStruct {
+            fields: Fields::Unnamed(ref fields),
+            ..
+        }) if fields.unnamed.len() == 1 => &fields.unnamed[0],
+        _ => return Ok(None),
+    };
+
+    let attribute = input
+        .attrs
+        .iter()
+        .chain(field.attrs.iter())
+        .find(|a| a.path.is_ident("tlv") || a.path.is_ident("tlv_tag"));
+
+    match attribute {
+        Some(a) => Err(syn::Error::new_spanned(
+            a,
+            "Newtype structures are transparent and do not support `tlv` attributes",
+        )),
+        None => Ok(Some(field)),
+    }
+}
+
+/// Parses all fields of a structure, reporting all field errors at once.
+///
+/// Also validates that tags are unique within the structure.
+fn parse_struct_fields(input: &DeriveInput) -> syn::Result<Vec<StructFieldInfo>> {
+    let fields = match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => fields.named.clone(),
+            Fields::Unnamed(ref fields) => {
+                return Err(syn::Error::new_spanned(
+                    fields,
+                    "Tuple structures are only supported as single field newtypes, fields need to be named",
+                ))
+            }
+            Fields::Unit => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "Unit structures are not supported, fields need to be named",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "Derive only supported for structures",
+            ))
+        }
+    };
+
+    let mut errors: Option<syn::Error> = None;
+    let mut push_error = |e: syn::Error| match errors {
+        Some(ref mut errors) => errors.combine(e),
+        None => errors = Some(e),
+    };
+
+    let mut result: Vec<StructFieldInfo> = Vec::new();
+    for field in fields {
+        let field = match StructFieldInfo::try_from(field) {
+            Ok(field) => field,
+            Err(e) => {
+                push_error(e);
+                continue;
+            }
+        };
+
+        if let FieldKind::Tagged(tag, span) = field.kind {
+            let existing = result
+                .iter()
+                .find(|f| matches!(f.kind, FieldKind::Tagged(other, _) if other == tag));
+
+            if let Some(existing) = existing {
+                push_error(syn::Error::new(
+                    span,
+                    format!(
+                        "Duplicate tag `{}`: already used by field `{}`",
+                        tag, existing.ident
+                    ),
+                ));
+            }
+        }
+
+        result.push(field);
+    }
+
+    match errors {
+        Some(errors) => Err(errors),
+        None => Ok(result),
     }
 }
 
-        
-impl From<syn::Field> for StructFieldInfo {
-    fn from(f: syn::Field) -> Self {
-        Self { 
-            ident: f.ident.unwrap(), 
-            tag_value: extract_tag_value(f.attrs),
+/// Builds the generics of a `TlvMergeDecodable` implementation for a
+/// (possibly generic) structure.
+///
+/// The first lifetime of the structure (if any) is used as the lifetime of the
+/// decoded data, so that borrowed fields (like `&'a str` or `&'a [u8]`) point
+/// directly into the decoded buffer. A new lifetime is added for structures
+/// without lifetimes.
+///
+/// Returns the implementation generics and the data lifetime.
+fn decode_impl_generics(generics: &Generics) -> (Generics, Lifetime) {
+    let mut result = generics.clone();
+
+    let lifetime = match generics.lifetimes().next() {
+        Some(def) => def.lifetime.clone(),
+        None => {
+            let lifetime = Lifetime::new("'__tlv", proc_macro2::Span::call_site());
+            result.params.insert(
+                0,
+                GenericParam::Lifetime(LifetimeDef::new(lifetime.clone())),
+            );
+            lifetime
         }
+    };
+
+    let type_params: Vec<_> = generics.type_params().map(|t| t.ident.clone()).collect();
+
+    result.params.push(parse_quote!(__TlvSource));
+
+    let where_clause = result.make_where_clause();
+    where_clause.predicates.push(parse_quote! {
+        __TlvSource: ::streaming_iterator::StreamingIterator<Item = ::tlv_stream::Record<#lifetime>>
+    });
+    for ident in type_params {
+        where_clause.predicates.push(parse_quote! {
+            #ident: ::tlv_packed::TlvMergeDecodable<#lifetime, __TlvSource>
+        });
     }
+
+    (result, lifetime)
 }
 
-#[proc_macro_derive(TlvMergeDecodable, attributes(tlv_tag))]
+/// Derives [tlv_packed::TlvMergeDecodable] for a structure with named fields.
+///
+/// Fields are configured with `#[tlv(...)]` attributes:
+///
+///   - `tag = ...`: the tag of the field, either as a string (see [into_parsed_tag_value]
+///     for the tag syntax) or as an integer for context tags. `#[tlv_tag = "..."]`
+///     is a shorthand for `#[tlv(tag = "...")]`.
+///   - `default = expr`: initial value of the field. Structures with `default`
+///     fields get a generated `Default` implementation (so they must not also
+///     derive `Default`) in which other fields use `Default::default()`. Like
+///     every field, absent elements leave the current value unchanged. A plain
+///     `default` uses `Default::default()`.
+///   - `skip`: the field is neither decoded nor encoded and needs no tag.
+///   - `nullable`: for `Option<T>` fields, `None` is encoded as null instead of
+///     being omitted (null is always decoded as `None`).
+///   - `with = module`: decode and encode the field using `module::merge_decode`
+///     and `module::encode`, which have the same signatures as
+///     [tlv_packed::TlvMergeDecodable::merge_decode] and [tlv_packed::TlvEncodable::encode]
+///     with the field value as first argument.
+///   - `max_len = N`: decoding fails with `DecodeError::TooLong` if the string
+///     or octet string value is longer than `N` bytes.
+///   - `range = a..=b`: decoding fails with `DecodeError::OutOfRange` if the
+///     value is outside of the given range (any range expression works).
+///   - `max_entries = N`: for list fields, decoding fails with
+///     `DecodeError::TooManyEntries` if the list has more than `N` entries.
+///   - `fabric_index`, `fabric_sensitive`: fields of fabric-scoped structures,
+///     see [macro@TlvEncodable].
+///
+/// Every field that is not skipped needs a tag.
+///
+/// Newtype structures (tuple structures with a single field) are transparent
+/// and decode exactly like the value they wrap:
+///
+/// ```
+/// use tlv_derive::TlvMergeDecodable;
+///
+/// #[derive(Debug, Default, TlvMergeDecodable)]
+/// struct EndpointId(u16);
+/// ```
+///
+/// ```
+/// use std::time::Duration;
+/// use tlv_derive::TlvMergeDecodable;
+///
+/// mod seconds {
+///     use std::time::Duration;
+///     use streaming_iterator::StreamingIterator;
+///     use tlv_packed::{DecodeEnd, DecodeError, TlvMergeDecodable};
+///     use tlv_stream::Record;
+///
+///     pub fn merge_decode<'a, S>(value: &mut Duration, source: &mut S) -> Result<DecodeEnd, DecodeError>
+///     where
+///         S: StreamingIterator<Item = Record<'a>>,
+///     {
+///         let mut seconds = 0u32;
+///         let decoded = seconds.merge_decode(source)?;
+///         *value = Duration::from_secs(seconds.into());
+///         Ok(decoded)
+///     }
+/// }
+///
+/// #[derive(Debug, TlvMergeDecodable)]
+/// struct Settings {
+///     #[tlv(tag = 1, default = 10)]
+///     retries: u8,
+///
+///     #[tlv(tag = "context:2", with = seconds)]
+///     timeout: Duration,
+///
+///     #[tlv(skip)]
+///     dirty: bool,
+/// }
+/// ```
+///
+/// Generic structures are supported. The first lifetime of the structure is
+/// used as the lifetime of the decoded data, so borrowed fields decode without
+/// copying:
+///
+/// ```
+/// use tlv_derive::TlvMergeDecodable;
+///
+/// #[derive(Debug, Default, TlvMergeDecodable)]
+/// struct Borrowed<'a> {
+///     #[tlv_tag = "context:1"]
+///     name: &'a str,
+///
+///     #[tlv_tag = "context:2"]
+///     data: Option<&'a [u8]>,
+/// }
+/// ```
+///
+/// Fields of type `Option<T>` are set to `None` when the element is null and
+/// otherwise decoded as `T`.
+///
+/// Fields of type `Vec<T>` (except `Vec<u8>`, which is an octet string) are
+/// lists, decoded from TLV arrays or lists and encoded as arrays. Constraints
+/// on optional fields are only checked if the value is present:
+///
+/// ```
+/// use tlv_derive::TlvMergeDecodable;
+///
+/// #[derive(Debug, Default, TlvMergeDecodable)]
+/// struct Group {
+///     #[tlv(tag = 1, range = 1..=254)]
+///     endpoint: u16,
+///
+///     #[tlv(tag = 2, max_len = 16)]
+///     name: Option<String>,
+///
+///     #[tlv(tag = 3, max_entries = 4)]
+///     members: Vec<u64>,
+/// }
+/// ```
+///
+/// Elements with tags that match no field are handled according to the
+/// structure-level `#[tlv(unknown_tags = "...")]` attribute:
+///
+///   - `"ignore"` (default): unknown elements are skipped. Unknown containers
+///     are skipped in full.
+///   - `"collect"`: unknown elements are appended to the field marked with
+///     `#[tlv(unknown)]`, generally of type [tlv_packed::UnknownElements].
+///   - `"reject"`: decoding fails with `DecodeError::UnknownTag`.
+///
+/// ```
+/// use tlv_derive::TlvMergeDecodable;
+/// use tlv_packed::UnknownElements;
+///
+/// #[derive(Debug, Default, TlvMergeDecodable)]
+/// #[tlv(unknown_tags = "collect")]
+/// struct PassThrough {
+///     #[tlv_tag = "context:1"]
+///     known: u32,
+///
+///     #[tlv(unknown)]
+///     other: UnknownElements,
+/// }
+/// ```
+///
+/// Invalid attributes are reported as compile errors on the offending field
+/// or attribute. This includes invalid tag syntax:
+///
+/// ```compile_fail
+/// use tlv_derive::TlvMergeDecodable;
+///
+/// #[derive(Debug, Default, TlvMergeDecodable)]
+/// struct Misspelled {
+///     #[tlv_tag = "contxt:1"]
+///     value: u32,
+/// }
+/// ```
+///
+/// missing tags:
+///
+/// ```compile_fail
+/// use tlv_derive::TlvMergeDecodable;
+///
+/// #[derive(Debug, Default, TlvMergeDecodable)]
+/// struct Untagged {
+///     value: u32,
+/// }
+/// ```
+///
+/// tags used by more than one field:
+///
+/// ```compile_fail
+/// use tlv_derive::TlvMergeDecodable;
+///
+/// #[derive(Debug, Default, TlvMergeDecodable)]
+/// struct Duplicate {
+///     #[tlv_tag = "context:1"]
+///     first: u32,
+///
+///     #[tlv_tag = "context:0x01"]
+///     second: u32,
+/// }
+/// ```
+///
+/// fabric-scoped structures without a fabric index field:
+///
+/// ```compile_fail
+/// use tlv_derive::TlvMergeDecodable;
+///
+/// #[derive(Debug, Default, TlvMergeDecodable)]
+/// #[tlv(fabric_scoped)]
+/// struct MissingFabricIndex {
+///     #[tlv(tag = 1, fabric_sensitive)]
+///     value: u32,
+/// }
+/// ```
+///
+/// constraints that do not apply to the field type:
+///
+/// ```compile_fail
+/// use tlv_derive::TlvMergeDecodable;
+///
+/// #[derive(Debug, Default, TlvMergeDecodable)]
+/// struct NotAList {
+///     #[tlv(tag = 1, max_entries = 4)]
+///     value: u32,
+/// }
+/// ```
+///
+/// context tags that do not fit in one byte:
+///
+/// ```compile_fail
+/// use tlv_derive::TlvMergeDecodable;
+///
+/// #[derive(Debug, Default, TlvMergeDecodable)]
+/// struct TooLarge {
+///     #[tlv_tag = "context:256"]
+///     value: u32,
+/// }
+/// ```
+///
+/// and tuple structures with more than one field:
+///
+/// ```compile_fail
+/// use tlv_derive::TlvMergeDecodable;
+///
+/// #[derive(Debug, Default, TlvMergeDecodable)]
+/// struct Tuple(u32, u32);
+/// ```
+#[proc_macro_derive(TlvMergeDecodable, attributes(tlv_tag, tlv))]
 pub fn derive_tlv_mergedecodable(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
-    let name = input.ident;
-    
-    let fields = match input.data {
-        Data::Struct(ref data) => {
-            match data.fields {
-                Fields::Named(ref fields) => {
-                    fields.named.clone()
-                },
-                _ => panic!("Fields need to be named in structure"),
+    match expand_tlv_mergedecodable(input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// Derives [tlv_packed::TlvEncodable] for a structure with named fields.
+///
+/// The structure is encoded as a TLV structure using the same field attributes
+/// as [macro@TlvMergeDecodable]. `None` optional fields are omitted (or encoded
+/// as null for `#[tlv(nullable)]` fields), skipped fields are not encoded and
+/// collected unknown elements are re-emitted after all known fields.
+///
+/// ```
+/// use tlv_derive::TlvEncodable;
+/// use tlv_packed::TlvEncodable;
+/// use tlv_stream::TagValue;
+///
+/// #[derive(TlvEncodable)]
+/// struct Point {
+///     #[tlv(tag = 1)]
+///     x: u8,
+///
+///     #[tlv(tag = 2)]
+///     y: Option<u8>,
+/// }
+///
+/// let mut data = Vec::new();
+/// Point { x: 1, y: None }.encode(TagValue::Anonymous, &mut data).unwrap();
+///
+/// assert_eq!(data, [
+///     0x15,             // anonymous structure
+///     0x24, 0x01, 0x01, // context 1: unsigned 1
+///     0x18,             // end of structure
+/// ]);
+/// ```
+///
+/// Structures marked with `#[tlv(fabric_scoped)]` also implement
+/// [tlv_packed::FabricScoped]. They need exactly one `#[tlv(fabric_index)]`
+/// field, which always uses tag `context:254` and whose type implements
+/// [tlv_packed::FabricIndexValue]. Fields marked as `#[tlv(fabric_sensitive)]`
+/// are omitted by `encode_for_fabric` unless the accessing fabric is the one
+/// the structure belongs to:
+///
+/// ```
+/// use tlv_derive::TlvEncodable;
+/// use tlv_packed::FabricScoped;
+/// use tlv_stream::TagValue;
+///
+/// #[derive(TlvEncodable)]
+/// #[tlv(fabric_scoped)]
+/// struct GroupKeyMap {
+///     #[tlv(tag = 1)]
+///     group_id: u16,
+///
+///     #[tlv(tag = 2, fabric_sensitive)]
+///     key_set_id: u16,
+///
+///     #[tlv(fabric_index)]
+///     fabric_index: u8,
+/// }
+///
+/// let entry = GroupKeyMap { group_id: 1, key_set_id: 2, fabric_index: 3 };
+///
+/// let mut data = Vec::new();
+/// entry.encode_for_fabric(TagValue::Anonymous, 4, &mut data).unwrap();
+///
+/// assert_eq!(data, [
+///     0x15,             // anonymous structure
+///     0x24, 0x01, 0x01, // context 1: unsigned 1
+///     0x24, 0xFE, 0x03, // context 254: fabric index 3
+///     0x18,             // end of structure
+/// ]);
+/// ```
+#[proc_macro_derive(TlvEncodable, attributes(tlv_tag, tlv))]
+pub fn derive_tlv_encodable(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match encode::expand_tlv_encodable(input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// Derives [tlv_packed::TlvSchema] for a structure with named fields.
+///
+/// The schema lists all fields that are not skipped, using the same field
+/// attributes as [macro@TlvMergeDecodable]. Field types need to implement
+/// [tlv_packed::TlvSchema] as well, and modules used via `with = module` need
+/// to provide a `SCHEMA` constant.
+///
+/// `Option<T>` fields are reported as optional, unless they are marked as
+/// `#[tlv(nullable)]`, in which case `None` is encoded as null instead of
+/// being omitted.
+///
+/// ```
+/// use tlv_derive::TlvSchema;
+/// use tlv_packed::{TlvSchema, TypeSchema};
+/// use tlv_stream::TagValue;
+///
+/// #[derive(TlvSchema)]
+/// struct Device {
+///     #[tlv(tag = 1)]
+///     name: String,
+///
+///     #[tlv(tag = 2, nullable)]
+///     location: Option<String>,
+/// }
+///
+/// let schema = match Device::SCHEMA {
+///     TypeSchema::Structure(schema) => schema,
+///     _ => unreachable!(),
+/// };
+///
+/// let field = schema.field_by_tag(TagValue::ContextSpecific { tag: 2 }).unwrap();
+/// assert_eq!(field.name, "location");
+/// assert_eq!(field.rust_type, "Option<String>");
+/// assert!(field.nullable);
+/// ```
+#[proc_macro_derive(TlvSchema, attributes(tlv_tag, tlv))]
+pub fn derive_tlv_schema(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match schema::expand_tlv_schema(input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// Implements `Default` for a structure, using the `default` values of its fields.
+fn default_impl(input: &DeriveInput, fields: &[StructFieldInfo]) -> proc_macro2::TokenStream {
+    let name = &input.ident;
+
+    let mut generics = input.generics.clone();
+    let type_params: Vec<_> = generics.type_params().map(|p| p.ident.clone()).collect();
+    let where_clause = generics.make_where_clause();
+    for ident in type_params {
+        where_clause.predicates.push(parse_quote! {
+            #ident: ::core::default::Default
+        });
+    }
+    let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+
+    let initializers = fields.iter().map(|f| f.default_initializer());
+
+    quote! {
+        impl #impl_generics ::core::default::Default for #name #type_generics
+        #where_clause
+        {
+            fn default() -> Self {
+                Self {
+                    #(#initializers, )*
+                }
             }
+        }
+    }
+}
+
+fn expand_tlv_mergedecodable(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+
+    let (decode_generics, lifetime) = decode_impl_generics(&input.generics);
+    let (impl_generics, _, where_clause) = decode_generics.split_for_impl();
+    let (_, type_generics, _) = input.generics.split_for_impl();
+
+    if newtype_field(&input)?.is_some() {
+        return Ok(quote! {
+            impl #impl_generics ::tlv_packed::TlvMergeDecodable<#lifetime, __TlvSource> for #name #type_generics
+            #where_clause
+            {
+                fn merge_decode(&mut self, source: &mut __TlvSource) -> ::core::result::Result<::tlv_packed::DecodeEnd, ::tlv_packed::DecodeError> {
+                    ::tlv_packed::TlvMergeDecodable::merge_decode(&mut self.0, source)
+                }
+            }
+        });
+    }
+
+    let options = StructOptions::from_attributes(&input.attrs)?;
+
+    let fields = parse_struct_fields(&input)?;
+    options.fabric_index_field(name, &fields)?;
+
+    let fields_decode: Vec<_> = fields.iter().filter_map(|f| f.decode_match()).collect();
+    let default_impl = fields
+        .iter()
+        .any(|f| f.default.is_some())
+        .then(|| default_impl(&input, &fields));
+
+    let unknown_fields: Vec<_> = fields
+        .iter()
+        .filter(|f| matches!(f.kind, FieldKind::Unknown))
+        .map(|f| &f.ident)
+        .collect();
+
+    let unknown_decode = match (options.unknown_tags, unknown_fields.as_slice()) {
+        (UnknownTagPolicy::Ignore, []) => quote! {
+            ::tlv_packed::skip_element(source)?
         },
-        _ => panic!("Derive only supported for structures"),
+        (UnknownTagPolicy::Reject, []) => quote! {
+            return ::core::result::Result::Err(::tlv_packed::DecodeError::UnknownTag)
+        },
+        (UnknownTagPolicy::Collect, [ident]) => quote! {
+            ::tlv_packed::TlvMergeDecodable::merge_decode(&mut self.#ident, source)?
+        },
+        (UnknownTagPolicy::Collect, _) => {
+            return Err(syn::Error::new_spanned(
+                name,
+                "`unknown_tags = \"collect\"` requires exactly one `#[tlv(unknown)]` field",
+            ))
+        }
+        (_, [ident, ..]) => {
+            return Err(syn::Error::new_spanned(
+                ident,
+                "`#[tlv(unknown)]` fields require `#[tlv(unknown_tags = \"collect\")]`",
+            ))
+        }
     };
-    
-    let fields_decode: Vec<_> = fields.into_iter().map(|item| {
-        StructFieldInfo::from(item).decode_match()
-    }).collect();
-    
-    quote! {
-        impl<'a, Source> ::tlv_packed::TlvMergeDecodable<'a, Source> for #name
-        where
-            Source: ::streaming_iterator::StreamingIterator<Item = ::tlv_stream::Record<'a>>,
+
+    Ok(quote! {
+        #default_impl
+
+        impl #impl_generics ::tlv_packed::TlvMergeDecodable<#lifetime, __TlvSource> for #name #type_generics
+        #where_clause
         {
-            fn merge_decode(&mut self, source: &mut Source) -> ::core::result::Result<::tlv_packed::DecodeEnd, ::tlv_packed::DecodeError> {
+            fn merge_decode(&mut self, source: &mut __TlvSource) -> ::core::result::Result<::tlv_packed::DecodeEnd, ::tlv_packed::DecodeError> {
                 if !std::matches!(
-                    source.get(),
+                    ::streaming_iterator::StreamingIterator::get(source),
                     ::core::option::Option::Some(::tlv_stream::Record {
                         tag: _,
                         value: ::tlv_stream::Value::ContainerStart(::tlv_stream::ContainerType::Structure)
@@ -465,22 +1642,24 @@ pub fn derive_tlv_mergedecodable(input: TokenStream) -> TokenStream {
                 ) {
                     return ::core::result::Result::Err(::tlv_packed::DecodeError::InvalidData);
                 }
-                
+
                 loop {
-                    let record = source.next();
+                    let record = ::streaming_iterator::StreamingIterator::next(source);
 
                     let record = match record {
                         ::core::option::Option::None => return ::core::result::Result::Ok(::tlv_packed::DecodeEnd::StreamFinished),
                         ::core::option::Option::Some(::tlv_stream::Record {
                             tag: _,
                             value: ::tlv_stream::Value::ContainerEnd,
-                        }) => return ::core::result::Result::Ok(::tlv_packed::DecodeEnd::DataConsumed),
+                        }) => {
+                            return ::core::result::Result::Ok(::tlv_packed::DecodeEnd::DataConsumed);
+                        }
                         ::core::option::Option::Some(value) => value,
                     };
 
                     let decoded = match record.tag {
                         #(#fields_decode, )*
-                        _ => ::tlv_packed::DecodeEnd::DataConsumed, // TODO: log here?
+                        _ => #unknown_decode,
                     };
 
                     if decoded != ::tlv_packed::DecodeEnd::DataConsumed {
@@ -489,7 +1668,7 @@ pub fn derive_tlv_mergedecodable(input: TokenStream) -> TokenStream {
                 }
             }
         }
-    }.into()
+    })
 }
 
 #[cfg(test)]
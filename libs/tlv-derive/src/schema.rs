@@ -0,0 +1,123 @@
+use quote::{quote, ToTokens};
+use syn::{parse_quote, DeriveInput, Generics, Type};
+
+use crate::{
+    newtype_field, option_inner_type, parse_struct_fields, FieldKind, StructFieldInfo,
+    StructOptions,
+};
+
+/// Formats a type the way it is generally written in code.
+///
+/// Token streams print spaces between all tokens (like `Option < u32 >`), so
+/// spaces are only kept between words.
+fn type_name(ty: &Type) -> String {
+    let tokens = ty.to_token_stream().to_string();
+    let chars: Vec<char> = tokens.chars().collect();
+    let is_word = |c: Option<&char>| matches!(c, Some(c) if c.is_alphanumeric() || *c == '_');
+
+    chars
+        .iter()
+        .enumerate()
+        .filter(|(idx, c)| {
+            **c != ' ' || (is_word(chars.get(idx.wrapping_sub(1))) && is_word(chars.get(idx + 1)))
+        })
+        .map(|(_, c)| c)
+        .collect()
+}
+
+impl StructFieldInfo {
+    /// Builds the `FieldSchema` describing the field.
+    fn schema_entry(&self) -> Option<proc_macro2::TokenStream> {
+        let name = self.ident.to_string();
+        let rust_type = type_name(&self.ty);
+
+        let tag = match self.kind {
+            FieldKind::Tagged(ref tag, _) => quote! { ::core::option::Option::Some(#tag) },
+            FieldKind::Unknown => quote! { ::core::option::Option::None },
+            FieldKind::Skipped => return None,
+        };
+
+        let optional = option_inner_type(&self.ty).is_some() && !self.nullable;
+        let nullable = self.nullable;
+
+        let schema = match (&self.with, self.list_item_type()) {
+            (Some(with), _) => quote! { #with::SCHEMA },
+            (None, Some(item)) => quote! {
+                ::tlv_packed::TypeSchema::Array(&<#item as ::tlv_packed::TlvSchema>::SCHEMA)
+            },
+            (None, None) => {
+                let ty = self.value_type();
+                quote! { <#ty as ::tlv_packed::TlvSchema>::SCHEMA }
+            }
+        };
+
+        Some(quote! {
+            ::tlv_packed::FieldSchema {
+                name: #name,
+                tag: #tag,
+                rust_type: #rust_type,
+                optional: #optional,
+                nullable: #nullable,
+                schema: #schema,
+            }
+        })
+    }
+}
+
+/// Adds a `TlvSchema` bound to every type parameter of the structure.
+fn schema_impl_generics(generics: &Generics) -> Generics {
+    let mut result = generics.clone();
+
+    let type_params: Vec<_> = generics.type_params().map(|t| t.ident.clone()).collect();
+    let where_clause = result.make_where_clause();
+    for ident in type_params {
+        where_clause.predicates.push(parse_quote! {
+            #ident: ::tlv_packed::TlvSchema
+        });
+    }
+
+    result
+}
+
+pub fn expand_tlv_schema(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+
+    let generics = schema_impl_generics(&input.generics);
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, type_generics, _) = input.generics.split_for_impl();
+
+    if let Some(field) = newtype_field(&input)? {
+        let ty = &field.ty;
+        return Ok(quote! {
+            impl #impl_generics ::tlv_packed::TlvSchema for #name #type_generics
+            #where_clause
+            {
+                const SCHEMA: ::tlv_packed::TypeSchema = <#ty as ::tlv_packed::TlvSchema>::SCHEMA;
+            }
+        });
+    }
+
+    // validates structure attributes, even if they only apply to decoding
+    let options = StructOptions::from_attributes(&input.attrs)?;
+
+    let fields = parse_struct_fields(&input)?;
+    options.fabric_index_field(name, &fields)?;
+
+    let fields_schema: Vec<_> = fields.iter().filter_map(|f| f.schema_entry()).collect();
+    let struct_name = name.to_string();
+
+    Ok(quote! {
+        impl #impl_generics ::tlv_packed::TlvSchema for #name #type_generics
+        #where_clause
+        {
+            const SCHEMA: ::tlv_packed::TypeSchema = ::tlv_packed::TypeSchema::Structure(
+                &::tlv_packed::StructSchema {
+                    name: #struct_name,
+                    fields: &[
+                        #(#fields_schema),*
+                    ],
+                }
+            );
+        }
+    })
+}
@@ -0,0 +1,99 @@
+#[macro_use]
+extern crate tlv_derive;
+
+use tlv_packed::{
+    decode_from_bytes, encode_to_vec, DecodeError, StructSchema, TlvSchema, TypeSchema,
+};
+
+#[derive(Debug, Clone, Default, PartialEq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+struct Group {
+    #[tlv(tag = 1, range = 1..=254)]
+    endpoint: u16,
+
+    #[tlv(tag = 2, max_len = 4)]
+    name: Option<String>,
+
+    #[tlv(tag = 3, max_entries = 2)]
+    members: Vec<u64>,
+
+    #[tlv(tag = 4)]
+    labels: Option<Vec<String>>,
+}
+
+fn valid_group() -> Group {
+    Group {
+        endpoint: 1,
+        name: Some("abcd".into()),
+        members: vec![1, 2],
+        labels: Some(vec!["x".into()]),
+    }
+}
+
+#[test]
+fn test_valid_round_trip() {
+    let group = valid_group();
+    let data = encode_to_vec(&group).unwrap();
+    assert_eq!(decode_from_bytes::<Group>(&data), Ok(group));
+
+    let empty = Group {
+        endpoint: 254,
+        ..Default::default()
+    };
+    let data = encode_to_vec(&empty).unwrap();
+    assert_eq!(decode_from_bytes::<Group>(&data), Ok(empty));
+}
+
+#[test]
+fn test_out_of_range() {
+    for endpoint in [0, 255] {
+        let group = Group {
+            endpoint,
+            ..valid_group()
+        };
+        let data = encode_to_vec(&group).unwrap();
+        assert_eq!(
+            decode_from_bytes::<Group>(&data),
+            Err(DecodeError::OutOfRange)
+        );
+    }
+}
+
+#[test]
+fn test_too_long() {
+    let group = Group {
+        name: Some("abcde".into()),
+        ..valid_group()
+    };
+    let data = encode_to_vec(&group).unwrap();
+    assert_eq!(decode_from_bytes::<Group>(&data), Err(DecodeError::TooLong));
+}
+
+#[test]
+fn test_too_many_entries() {
+    let group = Group {
+        members: vec![1, 2, 3],
+        ..valid_group()
+    };
+    let data = encode_to_vec(&group).unwrap();
+    assert_eq!(
+        decode_from_bytes::<Group>(&data),
+        Err(DecodeError::TooManyEntries)
+    );
+}
+
+#[test]
+fn test_list_schema() {
+    let schema: &StructSchema = match Group::SCHEMA {
+        TypeSchema::Structure(s) => s,
+        _ => panic!("Expected a structure schema"),
+    };
+
+    assert_eq!(
+        schema.field_by_name("members").unwrap().schema,
+        TypeSchema::Array(&TypeSchema::Unsigned)
+    );
+    assert_eq!(
+        schema.field_by_name("labels").unwrap().schema,
+        TypeSchema::Array(&TypeSchema::Utf8)
+    );
+}
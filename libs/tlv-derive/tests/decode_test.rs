@@ -1,8 +1,8 @@
 #[macro_use]
 extern crate tlv_derive;
 
-use streaming_iterator::{convert, StreamingIterator};
-use tlv_packed::{DecodeEnd, DecodeError, TlvDecodable, TlvMergeDecodable};
+use streaming_iterator::StreamingIterator;
+use tlv_packed::{DecodeError, TlvMergeDecodable, UnknownElements};
 use tlv_stream::{ContainerType, Record, TagValue, Value};
 
 #[derive(Debug, Copy, Clone, Default, PartialEq, TlvMergeDecodable)]
@@ -49,3 +49,246 @@ fn test_simple_decode() {
     assert_eq!(s.some_unsigned, Some(123));
     assert_eq!(s.some_signed, -2);
 }
+
+#[derive(Debug, Default, PartialEq, TlvMergeDecodable)]
+#[tlv(unknown_tags = "reject")]
+struct StrictStructure {
+    #[tlv_tag = "context:1"]
+    value: u8,
+}
+
+#[derive(Debug, Default, PartialEq, TlvMergeDecodable)]
+#[tlv(unknown_tags = "collect")]
+struct CollectingStructure {
+    #[tlv_tag = "context:1"]
+    value: u8,
+
+    #[tlv(unknown)]
+    unknown: UnknownElements,
+}
+
+/// A structure containing an unknown container (tag 3) whose content
+/// looks like known fields.
+fn records_with_unknown_container() -> [Record<'static>; 7] {
+    [
+        Record {
+            tag: TagValue::Anonymous,
+            value: Value::ContainerStart(ContainerType::Structure),
+        },
+        Record {
+            tag: TagValue::ContextSpecific { tag: 3 },
+            value: Value::ContainerStart(ContainerType::Structure),
+        },
+        Record {
+            tag: TagValue::ContextSpecific { tag: 1 },
+            value: Value::Unsigned(99),
+        },
+        Record {
+            tag: TagValue::Anonymous,
+            value: Value::ContainerEnd,
+        },
+        Record {
+            tag: TagValue::ContextSpecific { tag: 1 },
+            value: Value::Unsigned(10),
+        },
+        Record {
+            tag: TagValue::ContextSpecific { tag: 4 },
+            value: Value::Bool(true),
+        },
+        Record {
+            tag: TagValue::Anonymous,
+            value: Value::ContainerEnd,
+        },
+    ]
+}
+
+#[test]
+fn test_unknown_container_skipped() {
+    let records = records_with_unknown_container();
+    let mut streamer = streaming_iterator::convert(records.iter().copied());
+    streamer.next();
+
+    let mut s = ChildStructure::default();
+    s.merge_decode(&mut streamer).unwrap();
+
+    // content of the unknown container is NOT interpreted as a field
+    assert_eq!(s.some_unsigned, Some(10));
+    assert_eq!(streamer.next(), None);
+}
+
+#[test]
+fn test_unknown_tags_rejected() {
+    let records = records_with_unknown_container();
+    let mut streamer = streaming_iterator::convert(records.iter().copied());
+    streamer.next();
+
+    let mut s = StrictStructure::default();
+    assert_eq!(s.merge_decode(&mut streamer), Err(DecodeError::UnknownTag));
+
+    let records = [records[0], records[4], records[6]];
+    let mut streamer = streaming_iterator::convert(records.iter().copied());
+    streamer.next();
+
+    let mut s = StrictStructure::default();
+    s.merge_decode(&mut streamer).unwrap();
+    assert_eq!(s, StrictStructure { value: 10 });
+}
+
+#[test]
+fn test_unknown_tags_collected() {
+    let records = records_with_unknown_container();
+    let mut streamer = streaming_iterator::convert(records.iter().copied());
+    streamer.next();
+
+    let mut s = CollectingStructure::default();
+    s.merge_decode(&mut streamer).unwrap();
+
+    assert_eq!(s.value, 10);
+
+    let mut expected = records[1..4].to_vec();
+    expected.push(records[5]);
+    assert_eq!(s.unknown.records().collect::<Vec<_>>(), expected);
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, TlvMergeDecodable)]
+struct TopStructure<'a> {
+    #[tlv_tag = "context:1"]
+    some_nr: Option<u32>,
+
+    #[tlv_tag = "context:2"]
+    some_str: &'a str,
+
+    #[tlv_tag = "context:3"]
+    some_bytes: &'a [u8],
+
+    #[tlv_tag = "context:4"]
+    child: ChildStructure,
+
+    #[tlv_tag = "context:5"]
+    child2: Option<ChildStructure>,
+}
+
+#[derive(Debug, Default, PartialEq, TlvMergeDecodable)]
+struct GenericStructure<T>
+where
+    T: Default,
+{
+    #[tlv_tag = "context:1"]
+    value: T,
+
+    #[tlv_tag = "context:2"]
+    optional: Option<T>,
+}
+
+#[test]
+fn test_borrowed_decode() {
+    let data = [
+        0x15, // anonymous structure
+        0x24, 0x01, 0x7B, // context 1: unsigned 123
+        0x2C, 0x02, 0x03, 0x41, 0x42, 0x43, // context 2: "ABC"
+        0x30, 0x03, 0x02, 0x01, 0x02, // context 3: bytes [1, 2]
+        0x35, 0x05, // context 5: structure
+        0x24, 0x01, 0x05, //    context 1: unsigned 5
+        0x20, 0x02, 0xFE, //    context 2: signed -2
+        0x18, // end of child
+        0x18, // end of top structure
+    ];
+
+    let mut streamer = streaming_iterator::convert(tlv_stream::Parser::new(&data));
+    streamer.next();
+
+    let mut s = TopStructure::default();
+    s.merge_decode(&mut streamer).unwrap();
+
+    assert_eq!(s.some_nr, Some(123));
+    assert_eq!(s.some_str, "ABC");
+    assert_eq!(s.some_bytes, &[1, 2]);
+    assert_eq!(s.child, ChildStructure::default());
+    assert_eq!(
+        s.child2,
+        Some(ChildStructure {
+            some_unsigned: Some(5),
+            some_signed: -2,
+        })
+    );
+
+    // borrowed values point into the original buffer
+    assert!(data.as_ptr_range().contains(&s.some_str.as_ptr()));
+    assert!(data.as_ptr_range().contains(&s.some_bytes.as_ptr()));
+}
+
+#[test]
+fn test_optional_null_decode() {
+    let records = [
+        Record {
+            tag: TagValue::Anonymous,
+            value: Value::ContainerStart(ContainerType::Structure),
+        },
+        Record {
+            tag: TagValue::ContextSpecific { tag: 1 },
+            value: Value::Null,
+        },
+        Record {
+            tag: TagValue::ContextSpecific { tag: 5 },
+            value: Value::Null,
+        },
+        Record {
+            tag: TagValue::Anonymous,
+            value: Value::ContainerEnd,
+        },
+    ];
+
+    let mut s = TopStructure {
+        some_nr: Some(1),
+        child2: Some(ChildStructure::default()),
+        ..Default::default()
+    };
+
+    let mut streamer = streaming_iterator::convert(records.iter().copied());
+    streamer.next();
+    s.merge_decode(&mut streamer).unwrap();
+
+    assert_eq!(s.some_nr, None);
+    assert_eq!(s.child2, None);
+}
+
+#[test]
+fn test_generic_decode() {
+    let records = [
+        Record {
+            tag: TagValue::Anonymous,
+            value: Value::ContainerStart(ContainerType::Structure),
+        },
+        Record {
+            tag: TagValue::ContextSpecific { tag: 1 },
+            value: Value::Unsigned(1000),
+        },
+        Record {
+            tag: TagValue::ContextSpecific { tag: 2 },
+            value: Value::Unsigned(2000),
+        },
+        Record {
+            tag: TagValue::Anonymous,
+            value: Value::ContainerEnd,
+        },
+    ];
+
+    let mut streamer = streaming_iterator::convert(records.iter().copied());
+    streamer.next();
+
+    let mut s = GenericStructure::<u16>::default();
+    s.merge_decode(&mut streamer).unwrap();
+    assert_eq!(
+        s,
+        GenericStructure {
+            value: 1000,
+            optional: Some(2000)
+        }
+    );
+
+    let mut streamer = streaming_iterator::convert(records.iter().copied());
+    streamer.next();
+
+    let mut s = GenericStructure::<u8>::default();
+    assert_eq!(s.merge_decode(&mut streamer), Err(DecodeError::InvalidData));
+}
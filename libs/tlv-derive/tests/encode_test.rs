@@ -0,0 +1,258 @@
+#[macro_use]
+extern crate tlv_derive;
+
+use std::time::Duration;
+
+use streaming_iterator::StreamingIterator;
+use tlv_packed::{DecodeError, TlvEncodable, TlvMergeDecodable, UnknownElements};
+use tlv_stream::{Parser, TagValue};
+
+/// Encodes durations as a number of seconds.
+mod seconds {
+    use std::time::Duration;
+
+    use streaming_iterator::StreamingIterator;
+    use tlv_packed::{
+        DecodeEnd, DecodeError, EncodeError, TlvEncodable, TlvMergeDecodable, TlvWriter,
+    };
+    use tlv_stream::{Record, TagValue};
+
+    pub fn merge_decode<'a, S>(
+        value: &mut Duration,
+        source: &mut S,
+    ) -> Result<DecodeEnd, DecodeError>
+    where
+        S: StreamingIterator<Item = Record<'a>>,
+    {
+        let mut seconds = 0u32;
+        let decoded = seconds.merge_decode(source)?;
+        *value = Duration::from_secs(seconds.into());
+        Ok(decoded)
+    }
+
+    pub fn encode<W: TlvWriter>(
+        value: &Duration,
+        tag: TagValue,
+        writer: &mut W,
+    ) -> Result<(), EncodeError> {
+        u32::try_from(value.as_secs())
+            .map_err(|_| EncodeError::InvalidData)?
+            .encode(tag, writer)
+    }
+}
+
+#[derive(Debug, Default, PartialEq, TlvMergeDecodable, TlvEncodable)]
+struct Child {
+    #[tlv(tag = 1)]
+    value: Option<u16>,
+}
+
+#[derive(Debug, PartialEq, TlvMergeDecodable, TlvEncodable)]
+#[tlv(unknown_tags = "collect")]
+struct Settings {
+    #[tlv_tag = "context:1"]
+    name: String,
+
+    #[tlv(tag = 2, default = 3)]
+    retries: u8,
+
+    #[tlv(tag = "context:3", with = seconds)]
+    timeout: Duration,
+
+    #[tlv(tag = 4)]
+    child: Option<Child>,
+
+    #[tlv(skip)]
+    dirty: bool,
+
+    #[tlv(unknown)]
+    unknown: UnknownElements,
+}
+
+fn decode(data: &[u8]) -> Settings {
+    tlv_packed::decode_from_bytes(data).unwrap()
+}
+
+#[test]
+fn test_encode() {
+    let settings = Settings {
+        name: "AB".into(),
+        retries: 5,
+        timeout: Duration::from_secs(300),
+        child: Some(Child { value: None }),
+        dirty: true,
+        unknown: UnknownElements::default(),
+    };
+
+    let mut data = Vec::new();
+    settings.encode(TagValue::Anonymous, &mut data).unwrap();
+
+    assert_eq!(
+        data,
+        [
+            0x15, // anonymous structure
+            0x2C, 0x01, 0x02, 0x41, 0x42, // context 1: "AB"
+            0x24, 0x02, 0x05, // context 2: unsigned 5
+            0x25, 0x03, 0x2C, 0x01, // context 3: unsigned 300
+            0x35, 0x04, // context 4: structure
+            0x18, //    end of child
+            0x18, // end of structure
+        ]
+    );
+}
+
+#[test]
+fn test_round_trip_with_unknown() {
+    let data = [
+        0x15, // anonymous structure
+        0x2C, 0x01, 0x01, 0x41, // context 1: "A"
+        0x24, 0x03, 0x0A, // context 3: unsigned 10
+        0x36, 0x10, // context 16: list
+        0x04, 0x01, //    unsigned 1
+        0x18, //    end of list
+        0x29, 0x11, // context 17: true
+        0x18, // end of structure
+    ];
+
+    let settings = decode(&data);
+    assert_eq!(settings.name, "A");
+    assert_eq!(settings.timeout, Duration::from_secs(10));
+    assert!(!settings.unknown.is_empty());
+
+    let mut encoded = Vec::new();
+    settings.encode(TagValue::Anonymous, &mut encoded).unwrap();
+
+    // unknown elements are re-emitted after known fields
+    assert_eq!(
+        encoded,
+        [
+            0x15, // anonymous structure
+            0x2C, 0x01, 0x01, 0x41, // context 1: "A"
+            0x24, 0x02, 0x03, // context 2: default value 3
+            0x24, 0x03, 0x0A, // context 3: unsigned 10
+            0x36, 0x10, // context 16: list
+            0x04, 0x01, //    unsigned 1
+            0x18, //    end of list
+            0x29, 0x11, // context 17: true
+            0x18, // end of structure
+        ]
+    );
+
+    assert_eq!(decode(&encoded), settings);
+}
+
+#[test]
+fn test_default_for_absent_fields() {
+    // retries absent: set to its default
+    let settings = decode(&[0x15, 0x18]);
+    assert_eq!(settings.retries, 3);
+
+    // retries present: decoded value is kept
+    let settings = decode(&[0x15, 0x24, 0x02, 0x07, 0x18]);
+    assert_eq!(settings.retries, 7);
+}
+
+#[test]
+fn test_merge_keeps_absent_default_fields() {
+    let mut source = streaming_iterator::convert(Parser::new(&[0x15, 0x18]));
+    source.next();
+
+    let mut settings = Settings {
+        retries: 7,
+        ..Default::default()
+    };
+    settings.merge_decode(&mut source).unwrap();
+
+    assert_eq!(settings.retries, 7);
+}
+
+#[test]
+fn test_skipped_field() {
+    let mut source = streaming_iterator::convert(Parser::new(&[0x15, 0x18]));
+    source.next();
+
+    let mut settings = Settings {
+        dirty: true,
+        ..Default::default()
+    };
+    settings.merge_decode(&mut source).unwrap();
+
+    // skipped fields are left untouched by decoding
+    assert!(settings.dirty);
+}
+
+#[derive(Debug, Default, PartialEq, TlvMergeDecodable, TlvEncodable)]
+struct Generic<T> {
+    #[tlv(tag = 1)]
+    value: T,
+}
+
+#[test]
+fn test_generic_encode() {
+    let mut data = Vec::new();
+    Generic { value: -1i8 }
+        .encode(TagValue::ContextSpecific { tag: 2 }, &mut data)
+        .unwrap();
+
+    assert_eq!(data, [0x35, 0x02, 0x20, 0x01, 0xFF, 0x18]);
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, TlvMergeDecodable, TlvEncodable)]
+struct EndpointId(u16);
+
+#[derive(Debug, Default, PartialEq, TlvMergeDecodable, TlvEncodable)]
+struct Target {
+    #[tlv(tag = 1)]
+    endpoint: EndpointId,
+
+    #[tlv(tag = 2)]
+    other: Option<EndpointId>,
+}
+
+#[test]
+fn test_newtype_fields() {
+    let target = Target {
+        endpoint: EndpointId(1),
+        other: Some(EndpointId(0x1234)),
+    };
+
+    let mut data = Vec::new();
+    target.encode(TagValue::Anonymous, &mut data).unwrap();
+    assert_eq!(data, [0x15, 0x24, 0x01, 0x01, 0x25, 0x02, 0x34, 0x12, 0x18]);
+
+    let mut source = streaming_iterator::convert(Parser::new(&data));
+    source.next();
+
+    let mut decoded = Target::default();
+    decoded.merge_decode(&mut source).unwrap();
+    assert_eq!(decoded, target);
+}
+
+#[test]
+fn test_bytes_api() {
+    let tag = TagValue::Full {
+        vendor_id: 0xFFF1,
+        profile_id: 0x0001,
+        tag: 2,
+    };
+    let target = Target {
+        endpoint: EndpointId(3),
+        other: None,
+    };
+
+    let data = tlv_packed::encode_tagged_to_vec(&target, tag).unwrap();
+    assert_eq!(
+        data,
+        [0xD5, 0xF1, 0xFF, 0x01, 0x00, 0x02, 0x00, 0x24, 0x01, 0x03, 0x18]
+    );
+    assert_eq!(tlv_packed::decode_tagged_from_bytes(&data, tag), Ok(target));
+
+    assert_eq!(
+        tlv_packed::decode_from_bytes::<Target>(&data),
+        Err(DecodeError::UnexpectedTag)
+    );
+    assert_eq!(
+        tlv_packed::decode_tagged_from_bytes::<Target>(&data[..data.len() - 1], tag),
+        Err(DecodeError::InvalidNesting)
+    );
+}
@@ -0,0 +1,160 @@
+#[macro_use]
+extern crate tlv_derive;
+
+use tlv_packed::{
+    decode_from_bytes, encode_fabric_filtered_list, encode_list_for_fabric, encode_to_vec,
+    FabricScoped, TlvEncodable,
+};
+use tlv_stream::TagValue;
+
+#[derive(Debug, Clone, Default, PartialEq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+#[tlv(fabric_scoped)]
+struct AccessControlEntry {
+    #[tlv(tag = 1)]
+    privilege: u8,
+
+    #[tlv(tag = 3, fabric_sensitive)]
+    subjects: Vec<u64>,
+
+    #[tlv(fabric_index)]
+    fabric_index: Option<u8>,
+}
+
+fn entries() -> Vec<AccessControlEntry> {
+    vec![
+        AccessControlEntry {
+            privilege: 5,
+            subjects: vec![0x1122],
+            fabric_index: Some(1),
+        },
+        AccessControlEntry {
+            privilege: 3,
+            subjects: vec![0x3344],
+            fabric_index: Some(2),
+        },
+    ]
+}
+
+#[test]
+fn test_fabric_index_tag() {
+    let entry = entries().remove(0);
+    assert_eq!(entry.fabric_index(), Some(1));
+
+    let data = encode_to_vec(&entry).unwrap();
+    assert_eq!(
+        data,
+        [
+            0x15, // anonymous structure
+            0x24, 0x01, 0x05, // context 1: privilege 5
+            0x36, 0x03, // context 3: array
+            0x05, 0x22, 0x11, // subject 0x1122
+            0x18, // end of array
+            0x24, 0xFE, 0x01, // context 254: fabric index 1
+            0x18, // end of structure
+        ]
+    );
+    assert_eq!(decode_from_bytes::<AccessControlEntry>(&data), Ok(entry));
+}
+
+#[test]
+fn test_fabric_sensitive_fields() {
+    let entry = entries().remove(0);
+
+    let mut data = Vec::new();
+    entry
+        .encode_for_fabric(TagValue::Anonymous, 1, &mut data)
+        .unwrap();
+    assert_eq!(data, encode_to_vec(&entry).unwrap());
+
+    let mut data = Vec::new();
+    entry
+        .encode_for_fabric(TagValue::Anonymous, 2, &mut data)
+        .unwrap();
+    assert_eq!(
+        decode_from_bytes::<AccessControlEntry>(&data),
+        Ok(AccessControlEntry {
+            subjects: Vec::new(),
+            ..entry
+        })
+    );
+}
+
+#[test]
+fn test_fabric_lists() {
+    let tag = TagValue::Anonymous;
+
+    let mut all = Vec::new();
+    encode_list_for_fabric(&entries(), tag, 2, &mut all).unwrap();
+    let decoded: Vec<AccessControlEntry> = decode_from_bytes::<ListWrapper>(&wrap(&all))
+        .unwrap()
+        .entries;
+    assert_eq!(decoded.len(), 2);
+    assert!(decoded[0].subjects.is_empty());
+    assert_eq!(decoded[1], entries()[1]);
+
+    let mut filtered = Vec::new();
+    encode_fabric_filtered_list(&entries(), tag, 2, &mut filtered).unwrap();
+    let decoded = decode_from_bytes::<ListWrapper>(&wrap(&filtered))
+        .unwrap()
+        .entries;
+    assert_eq!(decoded, [entries()[1].clone()]);
+}
+
+#[derive(Debug, Default, TlvMergeDecodable)]
+struct ListWrapper {
+    #[tlv(tag = 1)]
+    entries: Vec<AccessControlEntry>,
+}
+
+/// Wraps an encoded anonymous list as context tag 1 of an anonymous structure.
+fn wrap(list: &[u8]) -> Vec<u8> {
+    let mut data = vec![0x15, 0x36, 0x01];
+    data.extend_from_slice(&list[1..]);
+    data.push(0x18);
+    data
+}
+
+#[test]
+fn test_tlv_encodable_is_unfiltered() {
+    let entry = entries().remove(1);
+    let mut data = Vec::new();
+    entry.encode(TagValue::Anonymous, &mut data).unwrap();
+    assert_eq!(decode_from_bytes::<AccessControlEntry>(&data), Ok(entry));
+}
+
+#[derive(Debug, Clone, Default, PartialEq, TlvMergeDecodable, TlvEncodable)]
+#[tlv(fabric_scoped)]
+struct GroupKeyMap {
+    #[tlv(tag = 1, fabric_sensitive)]
+    group_id: u16,
+
+    #[tlv(fabric_index)]
+    fabric_index: u8,
+}
+
+#[test]
+fn test_reserved_fabric_index() {
+    let map = GroupKeyMap {
+        group_id: 0x1234,
+        fabric_index: 0,
+    };
+    assert_eq!(map.fabric_index(), None);
+
+    // fabric index 0 matches no accessing fabric, not even 0
+    let mut data = Vec::new();
+    map.encode_for_fabric(TagValue::Anonymous, 0, &mut data)
+        .unwrap();
+    assert_eq!(
+        decode_from_bytes::<GroupKeyMap>(&data),
+        Ok(GroupKeyMap {
+            group_id: 0,
+            fabric_index: 0,
+        })
+    );
+
+    let map = GroupKeyMap {
+        fabric_index: 3,
+        ..map
+    };
+    assert_eq!(map.fabric_index(), Some(3));
+}
@@ -0,0 +1,47 @@
+#[macro_use]
+extern crate tlv_derive;
+
+use tlv_packed::testing::{check_unknown_tags_ignored, check_vectors};
+
+#[derive(Debug, Default, PartialEq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+struct Child {
+    #[tlv(tag = 1)]
+    id: u16,
+
+    #[tlv(tag = 2, nullable)]
+    label: Option<String>,
+}
+
+#[derive(Debug, Default, PartialEq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+struct Parent {
+    #[tlv(tag = 1)]
+    child: Child,
+
+    #[tlv(tag = 2)]
+    values: Vec<u32>,
+
+    #[tlv(tag = 3)]
+    enabled: Option<bool>,
+}
+
+#[derive(Debug, Default, PartialEq, TlvMergeDecodable, TlvEncodable, TlvSchema)]
+#[tlv(unknown_tags = "reject")]
+struct Strict {
+    #[tlv(tag = 1)]
+    id: u16,
+}
+
+#[test]
+fn test_derived_round_trip() {
+    check_vectors::<Child>(&["15 24 01 05 2C 02 01 61 18", "15 24 01 05 34 02 18"]);
+    check_vectors::<Parent>(&[
+        "15 35 01 24 01 05 34 02 18 36 02 04 01 05 00 01 18 29 03 18",
+        "15 36 02 18 18",
+    ]);
+}
+
+#[test]
+#[should_panic(expected = "decode structure with unknown elements")]
+fn test_rejected_unknown_tags() {
+    check_unknown_tags_ignored::<Strict>("15 24 01 05 18");
+}
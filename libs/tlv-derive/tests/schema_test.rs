@@ -0,0 +1,163 @@
+#[macro_use]
+extern crate tlv_derive;
+
+use tlv_packed::{FieldSchema, StructSchema, TlvSchema, TypeSchema, UnknownElements};
+use tlv_stream::{ContainerType, TagValue};
+
+/// Encodes timestamps as seconds, represented as an unsigned integer.
+mod seconds {
+    use tlv_packed::{EncodeError, TlvEncodable, TlvWriter, TypeSchema};
+    use tlv_stream::TagValue;
+
+    pub const SCHEMA: TypeSchema = TypeSchema::Unsigned;
+
+    pub fn encode<W: TlvWriter>(
+        value: &u64,
+        tag: TagValue,
+        writer: &mut W,
+    ) -> Result<(), EncodeError> {
+        (value / 1000).encode(tag, writer)
+    }
+}
+
+#[derive(Debug, Default, TlvSchema, TlvEncodable)]
+struct Child {
+    #[tlv(tag = 1)]
+    value: i16,
+}
+
+#[derive(Debug, Default, TlvSchema, TlvEncodable)]
+#[tlv(unknown_tags = "collect")]
+struct Parent<'a> {
+    #[tlv_tag = "context:1"]
+    name: &'a str,
+
+    #[tlv(tag = "full: 0xFFF1-0x1-2")]
+    child: Option<Child>,
+
+    #[tlv(tag = 3, nullable)]
+    label: Option<String>,
+
+    #[tlv(tag = 4, with = seconds)]
+    timestamp: u64,
+
+    #[tlv(skip)]
+    _cache: Vec<u8>,
+
+    #[tlv(unknown)]
+    unknown: UnknownElements,
+}
+
+fn struct_schema<T: TlvSchema>() -> &'static StructSchema {
+    match T::SCHEMA {
+        TypeSchema::Structure(schema) => schema,
+        other => panic!("Not a structure: {:?}", other),
+    }
+}
+
+#[test]
+fn test_struct_schema() {
+    let schema = struct_schema::<Parent>();
+
+    assert_eq!(schema.name, "Parent");
+    assert_eq!(
+        schema.fields.iter().map(|f| f.name).collect::<Vec<_>>(),
+        ["name", "child", "label", "timestamp", "unknown"]
+    );
+
+    assert_eq!(
+        schema.fields[0],
+        FieldSchema {
+            name: "name",
+            tag: Some(TagValue::ContextSpecific { tag: 1 }),
+            rust_type: "&'a str",
+            optional: false,
+            nullable: false,
+            schema: TypeSchema::Utf8,
+        }
+    );
+
+    let child = schema.field_by_name("child").unwrap();
+    assert_eq!(
+        child.tag,
+        Some(TagValue::Full {
+            vendor_id: 0xFFF1,
+            profile_id: 1,
+            tag: 2
+        })
+    );
+    assert!(child.optional);
+    assert!(!child.nullable);
+    assert_eq!(child.container_type(), Some(ContainerType::Structure));
+    assert_eq!(child.schema, Child::SCHEMA);
+
+    let label = schema
+        .field_by_tag(TagValue::ContextSpecific { tag: 3 })
+        .unwrap();
+    assert_eq!(label.name, "label");
+    assert!(!label.optional);
+    assert!(label.nullable);
+
+    assert_eq!(
+        schema.field_by_name("timestamp").unwrap().schema,
+        TypeSchema::Unsigned
+    );
+
+    let unknown = schema.field_by_name("unknown").unwrap();
+    assert_eq!(unknown.tag, None);
+    assert_eq!(unknown.schema, TypeSchema::Any);
+}
+
+#[test]
+fn test_schema_display() {
+    assert_eq!(
+        struct_schema::<Parent>().to_string(),
+        "Parent {
+    context:1 name: &'a str (utf8)
+    full:0xFFF1-0x1-2 child: Option<Child> (structure Child, optional)
+    context:3 label: Option<String> (utf8, nullable)
+    context:4 timestamp: u64 (unsigned)
+    * unknown: UnknownElements (any)
+}"
+    );
+}
+
+#[derive(TlvSchema, TlvEncodable)]
+struct Generic<T> {
+    #[tlv(tag = 1)]
+    value: T,
+}
+
+#[derive(TlvSchema, TlvEncodable)]
+struct Wrapper(u32);
+
+#[test]
+fn test_generic_and_newtype_schema() {
+    assert_eq!(
+        struct_schema::<Generic<bool>>().fields[0].schema,
+        TypeSchema::Bool
+    );
+    assert_eq!(
+        struct_schema::<Generic<Wrapper>>().fields[0].schema,
+        TypeSchema::Unsigned
+    );
+}
+
+#[test]
+fn test_nullable_encode() {
+    let parent = Parent {
+        timestamp: 1000,
+        ..Default::default()
+    };
+
+    assert_eq!(
+        tlv_packed::encode_to_vec(&parent).unwrap(),
+        [
+            0x15, // anonymous structure
+            0x2C, 0x01, 0x00, // context 1: ""
+            0x34, 0x03, // context 3: null
+            0x24, 0x04, 0x01, // context 4: unsigned 1
+            0x18, // end of structure
+        ]
+    );
+}
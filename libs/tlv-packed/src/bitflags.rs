@@ -0,0 +1,129 @@
+/// Implements [TlvMergeDecodable](crate::TlvMergeDecodable),
+/// [TlvEncodable](crate::TlvEncodable) and [TlvSchema](crate::TlvSchema) for
+/// a `bitflags` type, encoded as its underlying unsigned value.
+///
+/// By default, unknown bits are dropped while decoding (like `from_bits_truncate`).
+/// Add `reject_unknown_bits` to fail decoding with [DecodeError::UnknownBits](crate::DecodeError::UnknownBits)
+/// instead.
+///
+/// The flags type must implement `Default`.
+///
+/// ```
+/// use bitflags::bitflags;
+/// use streaming_iterator::StreamingIterator;
+/// use tlv_packed::{impl_tlv_bitflags, DecodeError, TlvMergeDecodable};
+/// use tlv_stream::{Record, TagValue, Value};
+///
+/// bitflags! {
+///     #[derive(Default)]
+///     struct Options: u8 {
+///         const A = 0b01;
+///         const B = 0b10;
+///     }
+/// }
+/// impl_tlv_bitflags!(Options: u8, reject_unknown_bits);
+///
+/// let records = [
+///     Record { tag: TagValue::Anonymous, value: Value::Unsigned(0b11) },
+///     Record { tag: TagValue::Anonymous, value: Value::Unsigned(0b111) },
+/// ];
+/// let mut source = streaming_iterator::convert(records.iter().copied());
+/// let mut options = Options::default();
+///
+/// source.next();
+/// options.merge_decode(&mut source).unwrap();
+/// assert_eq!(options, Options::A | Options::B);
+///
+/// source.next();
+/// assert_eq!(options.merge_decode(&mut source), Err(DecodeError::UnknownBits));
+/// ```
+#[macro_export]
+macro_rules! impl_tlv_bitflags {
+    ($type:ty : $bits:ty) => {
+        $crate::impl_tlv_bitflags!(@impl $type, $bits, |bits| {
+            ::core::result::Result::Ok(<$type>::from_bits_truncate(bits))
+        });
+    };
+
+    ($type:ty : $bits:ty, reject_unknown_bits) => {
+        $crate::impl_tlv_bitflags!(@impl $type, $bits, |bits| {
+            <$type>::from_bits(bits).ok_or($crate::DecodeError::UnknownBits)
+        });
+    };
+
+    (@impl $type:ty, $bits:ty, $from_bits:expr) => {
+        impl<'a, Source> $crate::TlvMergeDecodable<'a, Source> for $type
+        where
+            Source: $crate::__private::StreamingIterator<Item = $crate::__private::Record<'a>>,
+        {
+            fn merge_decode(
+                &mut self,
+                source: &mut Source,
+            ) -> ::core::result::Result<$crate::DecodeEnd, $crate::DecodeError> {
+                let mut bits: $bits = 0;
+                let decoded = $crate::TlvMergeDecodable::merge_decode(&mut bits, source)?;
+
+                let from_bits: fn($bits) -> ::core::result::Result<$type, $crate::DecodeError> =
+                    $from_bits;
+                *self = from_bits(bits)?;
+
+                ::core::result::Result::Ok(decoded)
+            }
+        }
+
+        impl $crate::TlvSchema for $type {
+            const SCHEMA: $crate::TypeSchema = $crate::TypeSchema::Unsigned;
+        }
+
+        impl $crate::TlvEncodable for $type {
+            fn encode<W: $crate::TlvWriter>(
+                &self,
+                tag: $crate::__private::TagValue,
+                writer: &mut W,
+            ) -> ::core::result::Result<(), $crate::EncodeError> {
+                $crate::TlvEncodable::encode(&self.bits(), tag, writer)
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use bitflags::bitflags;
+    use streaming_iterator::StreamingIterator;
+    use tlv_stream::{Record, TagValue, Value};
+
+    use crate::{TlvEncodable, TlvMergeDecodable};
+
+    bitflags! {
+        #[derive(Default)]
+        struct Flags: u16 {
+            const LOW = 0x0001;
+            const HIGH = 0x8000;
+        }
+    }
+    impl_tlv_bitflags!(Flags: u16);
+
+    #[test]
+    fn unknown_bits_truncated() {
+        let records = [Record {
+            tag: TagValue::Anonymous,
+            value: Value::Unsigned(0x8101),
+        }];
+        let mut source = streaming_iterator::convert(records.iter().copied());
+        source.next();
+
+        let mut flags = Flags::default();
+        flags.merge_decode(&mut source).unwrap();
+        assert_eq!(flags, Flags::LOW | Flags::HIGH);
+    }
+
+    #[test]
+    fn encode_bits() {
+        let mut data = Vec::new();
+        (Flags::LOW | Flags::HIGH)
+            .encode(TagValue::ContextSpecific { tag: 1 }, &mut data)
+            .unwrap();
+        assert_eq!(data, [0x25, 0x01, 0x01, 0x80]);
+    }
+}
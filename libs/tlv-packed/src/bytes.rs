@@ -0,0 +1,244 @@
+use streaming_iterator::StreamingIterator;
+use tlv_stream::{Parser, Record, TagValue};
+
+use crate::{DecodeEnd, DecodeError, EncodeError, TlvEncodable, TlvMergeDecodable, TlvWriter};
+
+/// A streaming source of records parsed from a byte buffer.
+///
+/// Unlike a plain [Parser] wrapped in [streaming_iterator::convert], this
+/// allows checking if all input bytes were consumed once the records run out.
+#[derive(Debug)]
+pub struct ParserSource<'a> {
+    parser: Parser<'a>,
+    current: Option<Record<'a>>,
+}
+
+impl<'a> ParserSource<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self {
+            parser: Parser::new(data),
+            current: None,
+        }
+    }
+
+    /// Check if all input data was parsed.
+    ///
+    /// See [Parser::done].
+    pub fn done(&self) -> bool {
+        self.parser.done()
+    }
+}
+
+impl<'a> StreamingIterator for ParserSource<'a> {
+    type Item = Record<'a>;
+
+    fn advance(&mut self) {
+        self.current = self.parser.next();
+    }
+
+    fn get(&self) -> Option<&Record<'a>> {
+        self.current.as_ref()
+    }
+}
+
+/// Decodes a value from a buffer containing a single anonymous TLV element.
+///
+/// ```
+/// use tlv_packed::decode_from_bytes;
+///
+/// assert_eq!(decode_from_bytes::<u16>(&[0x05, 0x34, 0x12]), Ok(0x1234));
+/// ```
+///
+/// See [decode_tagged_from_bytes] for the possible errors.
+pub fn decode_from_bytes<'a, T>(data: &'a [u8]) -> Result<T, DecodeError>
+where
+    T: TlvMergeDecodable<'a, ParserSource<'a>>,
+{
+    decode_tagged_from_bytes(data, TagValue::Anonymous)
+}
+
+/// Decodes a value from a buffer containing a single TLV element with the given tag.
+///
+/// ```
+/// use tlv_packed::{decode_tagged_from_bytes, DecodeError};
+/// use tlv_stream::TagValue;
+///
+/// let tag = TagValue::Full { vendor_id: 0xFFF1, profile_id: 0xDEED, tag: 1 };
+/// let data = [0xC4, 0xF1, 0xFF, 0xED, 0xDE, 0x01, 0x00, 0x2A];
+///
+/// assert_eq!(decode_tagged_from_bytes::<u8>(&data, tag), Ok(42));
+/// assert_eq!(
+///     decode_tagged_from_bytes::<u8>(&data, TagValue::Anonymous),
+///     Err(DecodeError::UnexpectedTag)
+/// );
+/// ```
+///
+/// Fails with:
+///   - [DecodeError::UnexpectedTag] if the element does not have the given tag
+///   - [DecodeError::TrailingData] if data remains after the element
+///   - [DecodeError::InvalidNesting] if the element is a container without an end
+///   - [DecodeError::InvalidData] if the buffer contains no valid element
+pub fn decode_tagged_from_bytes<'a, T>(data: &'a [u8], tag: TagValue) -> Result<T, DecodeError>
+where
+    T: TlvMergeDecodable<'a, ParserSource<'a>>,
+{
+    let mut source = ParserSource::new(data);
+
+    match source.next() {
+        None => return Err(DecodeError::InvalidData),
+        Some(record) if record.tag != tag => return Err(DecodeError::UnexpectedTag),
+        Some(_) => {}
+    }
+
+    let mut result = T::default();
+    match result.merge_decode(&mut source)? {
+        DecodeEnd::StreamFinished => Err(DecodeError::InvalidNesting),
+        DecodeEnd::DataConsumed => {
+            source.advance();
+            match source.get() {
+                None if source.done() => Ok(result),
+                _ => Err(DecodeError::TrailingData),
+            }
+        }
+    }
+}
+
+/// Writes encoded records into a fixed size buffer.
+#[derive(Debug)]
+pub struct SliceWriter<'a> {
+    data: &'a mut [u8],
+    written: usize,
+}
+
+impl<'a> SliceWriter<'a> {
+    pub fn new(data: &'a mut [u8]) -> Self {
+        Self { data, written: 0 }
+    }
+
+    /// Number of bytes written so far.
+    pub fn written(&self) -> usize {
+        self.written
+    }
+}
+
+impl<'a> TlvWriter for SliceWriter<'a> {
+    fn write_record(&mut self, record: Record<'_>) -> Result<(), EncodeError> {
+        crate::record_bytes(record, |bytes| {
+            let end = self.written + bytes.len();
+            if end > self.data.len() {
+                return Err(EncodeError::InsufficientSpace);
+            }
+            self.data[self.written..end].copy_from_slice(bytes);
+            self.written = end;
+            Ok(())
+        })
+    }
+}
+
+/// Encodes a value as an anonymous TLV element.
+///
+/// ```
+/// use tlv_packed::encode_to_vec;
+///
+/// assert_eq!(encode_to_vec(&0x1234u16), Ok(vec![0x05, 0x34, 0x12]));
+/// ```
+pub fn encode_to_vec<T>(value: &T) -> Result<Vec<u8>, EncodeError>
+where
+    T: TlvEncodable + ?Sized,
+{
+    encode_tagged_to_vec(value, TagValue::Anonymous)
+}
+
+/// Encodes a value as a TLV element with the given tag.
+pub fn encode_tagged_to_vec<T>(value: &T, tag: TagValue) -> Result<Vec<u8>, EncodeError>
+where
+    T: TlvEncodable + ?Sized,
+{
+    let mut result = Vec::new();
+    value.encode(tag, &mut result)?;
+    Ok(result)
+}
+
+/// Encodes a value as an anonymous TLV element into `data`.
+///
+/// Returns the number of bytes written.
+///
+/// ```
+/// use tlv_packed::{encode_to_slice, EncodeError};
+///
+/// let mut buffer = [0u8; 4];
+/// assert_eq!(encode_to_slice("A", &mut buffer), Ok(3));
+/// assert_eq!(buffer[..3], [0x0C, 0x01, 0x41]);
+///
+/// assert_eq!(encode_to_slice("ABCD", &mut buffer), Err(EncodeError::InsufficientSpace));
+/// ```
+pub fn encode_to_slice<T>(value: &T, data: &mut [u8]) -> Result<usize, EncodeError>
+where
+    T: TlvEncodable + ?Sized,
+{
+    encode_tagged_to_slice(value, TagValue::Anonymous, data)
+}
+
+/// Encodes a value as a TLV element with the given tag into `data`.
+///
+/// Returns the number of bytes written.
+pub fn encode_tagged_to_slice<T>(
+    value: &T,
+    tag: TagValue,
+    data: &mut [u8],
+) -> Result<usize, EncodeError>
+where
+    T: TlvEncodable + ?Sized,
+{
+    let mut writer = SliceWriter::new(data);
+    value.encode(tag, &mut writer)?;
+    Ok(writer.written())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trailing_data() {
+        assert_eq!(
+            decode_from_bytes::<u8>(&[0x04, 0x01, 0x04, 0x02]),
+            Err(DecodeError::TrailingData)
+        );
+
+        // trailing bytes that do not parse are rejected as well
+        assert_eq!(
+            decode_from_bytes::<u8>(&[0x04, 0x01, 0x05]),
+            Err(DecodeError::TrailingData)
+        );
+    }
+
+    #[test]
+    fn invalid_input() {
+        assert_eq!(decode_from_bytes::<u8>(&[]), Err(DecodeError::InvalidData));
+        assert_eq!(
+            decode_from_bytes::<u8>(&[0x05, 0x01]),
+            Err(DecodeError::InvalidData)
+        );
+        assert_eq!(
+            decode_from_bytes::<&str>(&[0x04, 0x01]),
+            Err(DecodeError::InvalidData)
+        );
+    }
+
+    #[test]
+    fn tagged_round_trip() {
+        let tag = TagValue::Full {
+            vendor_id: 0,
+            profile_id: 0,
+            tag: 0x1234,
+        };
+
+        let data = encode_tagged_to_vec("test", tag).unwrap();
+        assert_eq!(decode_tagged_from_bytes::<&str>(&data, tag), Ok("test"));
+
+        let mut buffer = [0u8; 32];
+        let len = encode_tagged_to_slice("test", tag, &mut buffer).unwrap();
+        assert_eq!(buffer[..len], data);
+    }
+}
@@ -0,0 +1,82 @@
+use core::ops::RangeBounds;
+
+use crate::DecodeError;
+
+/// Values with a length that can be constrained, like strings and octet strings.
+///
+/// The length of UTF-8 strings is their length in bytes, which is the length
+/// that is encoded in TLV.
+pub trait TlvLength {
+    fn tlv_len(&self) -> usize;
+}
+
+impl TlvLength for str {
+    fn tlv_len(&self) -> usize {
+        self.len()
+    }
+}
+
+impl TlvLength for [u8] {
+    fn tlv_len(&self) -> usize {
+        self.len()
+    }
+}
+
+impl TlvLength for String {
+    fn tlv_len(&self) -> usize {
+        self.len()
+    }
+}
+
+impl TlvLength for Vec<u8> {
+    fn tlv_len(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<T> TlvLength for &T
+where
+    T: TlvLength + ?Sized,
+{
+    fn tlv_len(&self) -> usize {
+        (**self).tlv_len()
+    }
+}
+
+/// Validates a `#[tlv(max_len = ...)]` constraint.
+///
+/// ```
+/// use tlv_packed::{check_max_len, DecodeError};
+///
+/// assert_eq!(check_max_len("abc", 3), Ok(()));
+/// assert_eq!(check_max_len("abcd", 3), Err(DecodeError::TooLong));
+/// ```
+pub fn check_max_len<T>(value: &T, max_len: usize) -> Result<(), DecodeError>
+where
+    T: TlvLength + ?Sized,
+{
+    if value.tlv_len() > max_len {
+        return Err(DecodeError::TooLong);
+    }
+    Ok(())
+}
+
+/// Validates a `#[tlv(range = ...)]` constraint.
+///
+/// ```
+/// use tlv_packed::{check_range, DecodeError};
+///
+/// assert_eq!(check_range(&1u8, 1..=254), Ok(()));
+/// assert_eq!(check_range(&255u8, 1..=254), Err(DecodeError::OutOfRange));
+/// assert_eq!(check_range(&-5i16, ..0), Ok(()));
+/// ```
+pub fn check_range<T, R>(value: &T, range: R) -> Result<(), DecodeError>
+where
+    T: PartialOrd,
+    R: RangeBounds<T>,
+{
+    if !range.contains(value) {
+        return Err(DecodeError::OutOfRange);
+    }
+    Ok(())
+}
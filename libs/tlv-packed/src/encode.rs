@@ -0,0 +1,206 @@
+use streaming_iterator::StreamingIterator;
+use tlv_stream::{Record, TagValue, TlvBytes, Value};
+
+use crate::{OwnedValue, UnknownElements};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum EncodeError {
+    InsufficientSpace, // output buffer cannot hold the encoded data
+    InvalidData,       // value cannot be represented as TLV
+}
+
+/// A destination of TLV records.
+pub trait TlvWriter {
+    /// Writes a single record (control byte, tag and value) to the output.
+    fn write_record(&mut self, record: Record<'_>) -> Result<(), EncodeError>;
+}
+
+/// Serializes a single record into the bytes it is encoded as.
+///
+/// `output` is called for every chunk of bytes of the record, in order.
+pub fn record_bytes<E>(
+    record: Record<'_>,
+    mut output: impl FnMut(&[u8]) -> Result<(), E>,
+) -> Result<(), E> {
+    let mut records = streaming_iterator::convert(core::iter::once(record));
+    let mut bytes = TlvBytes::new(&mut records);
+
+    while let Some(data) = bytes.next() {
+        output(data)?;
+    }
+
+    Ok(())
+}
+
+/// Appends encoded records to the vector.
+///
+/// ```
+/// use tlv_packed::TlvWriter;
+/// use tlv_stream::{Record, TagValue, Value};
+///
+/// let mut data = Vec::new();
+/// data.write_record(Record { tag: TagValue::ContextSpecific { tag: 1 }, value: Value::Unsigned(3) }).unwrap();
+///
+/// assert_eq!(data, [0x24, 0x01, 0x03]);
+/// ```
+impl TlvWriter for Vec<u8> {
+    fn write_record(&mut self, record: Record<'_>) -> Result<(), EncodeError> {
+        record_bytes(record, |data| {
+            self.extend_from_slice(data);
+            Ok(())
+        })
+    }
+}
+
+pub trait TlvEncodable {
+    /// Encodes the value as a single element with the given tag.
+    ///
+    /// Containers (like structures) write their start, all their content and
+    /// their end.
+    fn encode<W: TlvWriter>(&self, tag: TagValue, writer: &mut W) -> Result<(), EncodeError>;
+}
+
+macro_rules! encodable_by_value {
+    ($type:ty) => {
+        impl TlvEncodable for $type {
+            fn encode<W: TlvWriter>(
+                &self,
+                tag: TagValue,
+                writer: &mut W,
+            ) -> Result<(), EncodeError> {
+                writer.write_record(Record {
+                    tag,
+                    value: (*self).into(),
+                })
+            }
+        }
+    };
+}
+
+encodable_by_value!(i8);
+encodable_by_value!(i16);
+encodable_by_value!(i32);
+encodable_by_value!(i64);
+encodable_by_value!(u8);
+encodable_by_value!(u16);
+encodable_by_value!(u32);
+encodable_by_value!(u64);
+encodable_by_value!(bool);
+encodable_by_value!(f32);
+encodable_by_value!(f64);
+
+impl TlvEncodable for str {
+    fn encode<W: TlvWriter>(&self, tag: TagValue, writer: &mut W) -> Result<(), EncodeError> {
+        writer.write_record(Record {
+            tag,
+            value: Value::Utf8(self.as_bytes()),
+        })
+    }
+}
+
+impl TlvEncodable for [u8] {
+    fn encode<W: TlvWriter>(&self, tag: TagValue, writer: &mut W) -> Result<(), EncodeError> {
+        writer.write_record(Record {
+            tag,
+            value: Value::Bytes(self),
+        })
+    }
+}
+
+impl TlvEncodable for String {
+    fn encode<W: TlvWriter>(&self, tag: TagValue, writer: &mut W) -> Result<(), EncodeError> {
+        self.as_str().encode(tag, writer)
+    }
+}
+
+impl TlvEncodable for Vec<u8> {
+    fn encode<W: TlvWriter>(&self, tag: TagValue, writer: &mut W) -> Result<(), EncodeError> {
+        self.as_slice().encode(tag, writer)
+    }
+}
+
+impl<T> TlvEncodable for &T
+where
+    T: TlvEncodable + ?Sized,
+{
+    fn encode<W: TlvWriter>(&self, tag: TagValue, writer: &mut W) -> Result<(), EncodeError> {
+        (**self).encode(tag, writer)
+    }
+}
+
+/// Optional values are omitted entirely when `None`.
+///
+/// ```
+/// use tlv_packed::TlvEncodable;
+/// use tlv_stream::TagValue;
+///
+/// let mut data = Vec::new();
+/// None::<u32>.encode(TagValue::ContextSpecific { tag: 1 }, &mut data).unwrap();
+/// assert!(data.is_empty());
+///
+/// Some(true).encode(TagValue::ContextSpecific { tag: 1 }, &mut data).unwrap();
+/// assert_eq!(data, [0x29, 0x01]);
+/// ```
+impl<T> TlvEncodable for Option<T>
+where
+    T: TlvEncodable,
+{
+    fn encode<W: TlvWriter>(&self, tag: TagValue, writer: &mut W) -> Result<(), EncodeError> {
+        match self {
+            Some(value) => value.encode(tag, writer),
+            None => Ok(()),
+        }
+    }
+}
+
+impl TlvEncodable for OwnedValue {
+    fn encode<W: TlvWriter>(&self, tag: TagValue, writer: &mut W) -> Result<(), EncodeError> {
+        writer.write_record(Record {
+            tag,
+            value: self.as_value(),
+        })
+    }
+}
+
+/// Re-emits all collected elements, in the order they were decoded.
+///
+/// Every collected element keeps its original tag, so the `tag` argument
+/// is ignored.
+impl TlvEncodable for UnknownElements {
+    fn encode<W: TlvWriter>(&self, _tag: TagValue, writer: &mut W) -> Result<(), EncodeError> {
+        for record in self.records() {
+            writer.write_record(record)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_primitives() {
+        let mut data = Vec::new();
+
+        1u8.encode(TagValue::ContextSpecific { tag: 1 }, &mut data)
+            .unwrap();
+        (-2i32)
+            .encode(TagValue::ContextSpecific { tag: 2 }, &mut data)
+            .unwrap();
+        "AB".encode(TagValue::Anonymous, &mut data).unwrap();
+        vec![1u8, 2u8]
+            .encode(TagValue::Implicit { tag: 0x1234 }, &mut data)
+            .unwrap();
+
+        assert_eq!(
+            data,
+            [
+                0x24, 0x01, 0x01, // context 1, unsigned 1
+                0x20, 0x02, 0xFE, // context 2, signed -2
+                0x0C, 0x02, 0x41, 0x42, // anonymous, "AB"
+                0x90, 0x34, 0x12, 0x02, 0x01, 0x02, // implicit 0x1234, bytes [1, 2]
+            ]
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use tlv_stream::{ContainerType, Record, TagValue, Value};
+
+use crate::{EncodeError, TlvEncodable, TlvWriter};
+
+/// Context tag of the fabric index field of fabric-scoped structures.
+pub const FABRIC_INDEX_TAG: TagValue = TagValue::ContextSpecific { tag: 254 };
+
+/// Values usable as the fabric index field of a fabric-scoped structure.
+pub trait FabricIndexValue {
+    /// The fabric index, or `None` if not set.
+    fn fabric_index(&self) -> Option<u8>;
+}
+
+/// Fabric index 0 is reserved to indicate the absence of a fabric.
+impl FabricIndexValue for u8 {
+    fn fabric_index(&self) -> Option<u8> {
+        match *self {
+            0 => None,
+            index => Some(index),
+        }
+    }
+}
+
+impl<T> FabricIndexValue for Option<T>
+where
+    T: FabricIndexValue,
+{
+    fn fabric_index(&self) -> Option<u8> {
+        self.as_ref().and_then(T::fabric_index)
+    }
+}
+
+/// Structures that belong to a single fabric.
+///
+/// Fabric-scoped structures carry their fabric index at context tag 254 (see
+/// [FABRIC_INDEX_TAG]). Their fabric-sensitive fields are only encoded for
+/// the fabric they belong to.
+///
+/// Generally implemented via `#[derive(TlvEncodable)]` from `tlv_derive` on
+/// structures marked with `#[tlv(fabric_scoped)]`.
+pub trait FabricScoped: TlvEncodable {
+    /// Fabric the structure belongs to, if known.
+    fn fabric_index(&self) -> Option<u8>;
+
+    /// Encodes the structure for a client accessing from `accessing_fabric`.
+    ///
+    /// Fabric-sensitive fields are omitted unless the structure belongs to
+    /// `accessing_fabric`.
+    fn encode_for_fabric<W: TlvWriter>(
+        &self,
+        tag: TagValue,
+        accessing_fabric: u8,
+        writer: &mut W,
+    ) -> Result<(), EncodeError>;
+}
+
+/// Encodes all `items` as a TLV array for a client accessing from `accessing_fabric`.
+///
+/// Fabric-sensitive fields of items belonging to other fabrics are omitted.
+pub fn encode_list_for_fabric<T, W>(
+    items: &[T],
+    tag: TagValue,
+    accessing_fabric: u8,
+    writer: &mut W,
+) -> Result<(), EncodeError>
+where
+    T: FabricScoped,
+    W: TlvWriter,
+{
+    encode_array(items.iter(), tag, accessing_fabric, writer)
+}
+
+/// Encodes the `items` belonging to `accessing_fabric` as a TLV array.
+///
+/// This is the encoding used by fabric-filtered reads, where items of other
+/// fabrics are omitted entirely.
+pub fn encode_fabric_filtered_list<T, W>(
+    items: &[T],
+    tag: TagValue,
+    accessing_fabric: u8,
+    writer: &mut W,
+) -> Result<(), EncodeError>
+where
+    T: FabricScoped,
+    W: TlvWriter,
+{
+    let matching = items
+        .iter()
+        .filter(|item| item.fabric_index() == Some(accessing_fabric));
+
+    encode_array(matching, tag, accessing_fabric, writer)
+}
+
+fn encode_array<'a, T, W>(
+    items: impl Iterator<Item = &'a T>,
+    tag: TagValue,
+    accessing_fabric: u8,
+    writer: &mut W,
+) -> Result<(), EncodeError>
+where
+    T: FabricScoped + 'a,
+    W: TlvWriter,
+{
+    writer.write_record(Record {
+        tag,
+        value: Value::ContainerStart(ContainerType::Array),
+    })?;
+
+    for item in items {
+        item.encode_for_fabric(TagValue::Anonymous, accessing_fabric, writer)?;
+    }
+
+    writer.write_record(Record {
+        tag: TagValue::Anonymous,
+        value: Value::ContainerEnd,
+    })
+}
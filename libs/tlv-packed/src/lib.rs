@@ -1,10 +1,48 @@
 use streaming_iterator::StreamingIterator;
-use tlv_stream::Record;
+use tlv_stream::{Record, Value};
+
+mod bitflags;
+pub mod bytes;
+pub mod constraints;
+pub mod encode;
+pub mod fabric;
+pub mod list;
+pub mod schema;
+pub mod testing;
+pub mod unknown;
+
+pub use bytes::{
+    decode_from_bytes, decode_tagged_from_bytes, encode_tagged_to_slice, encode_tagged_to_vec,
+    encode_to_slice, encode_to_vec, ParserSource, SliceWriter,
+};
+pub use constraints::{check_max_len, check_range, TlvLength};
+pub use encode::{record_bytes, EncodeError, TlvEncodable, TlvWriter};
+pub use fabric::{
+    encode_fabric_filtered_list, encode_list_for_fabric, FabricIndexValue, FabricScoped,
+    FABRIC_INDEX_TAG,
+};
+pub use list::{decode_list, encode_list};
+pub use schema::{FieldSchema, StructSchema, TlvSchema, TypeSchema};
+pub use unknown::{OwnedValue, UnknownElements};
+
+/// Re-exports used by macros of this crate.
+#[doc(hidden)]
+pub mod __private {
+    pub use streaming_iterator::StreamingIterator;
+    pub use tlv_stream::{Record, TagValue};
+}
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum DecodeError {
     InvalidData,    // failed to decode some data
     InvalidNesting, // mismatched start/end structures
+    UnknownTag,     // tag not known by a decoder that rejects unknown tags
+    UnknownBits,    // bitmap with bits not known by a decoder that rejects unknown bits
+    UnexpectedTag,  // top level element does not have the expected tag
+    TrailingData,   // data remains after decoding a complete element
+    TooLong,        // string or octet string longer than its maximum length
+    OutOfRange,     // number outside of its allowed range
+    TooManyEntries, // list with more entries than allowed
     Internal,       // Internal logic error, should not happen
 }
 
@@ -50,6 +88,64 @@ where
     fn decode(source: &mut Source) -> Result<Self, DecodeError>;
 }
 
+/// Skips over the current element of a stream.
+///
+/// If the current element is a container start, the entire container (including
+/// any nested containers) is skipped, leaving `source` positioned on the matching
+/// container end. Any other element is left as-is since it is fully contained in
+/// the current record.
+///
+/// ```
+/// use tlv_packed::{skip_element, DecodeEnd};
+/// use tlv_stream::{ContainerType, Record, TagValue, Value};
+/// use streaming_iterator::StreamingIterator;
+///
+/// let records = [
+///     Record { tag: TagValue::ContextSpecific { tag: 1 }, value: Value::ContainerStart(ContainerType::Structure) },
+///     Record { tag: TagValue::ContextSpecific { tag: 1 }, value: Value::ContainerStart(ContainerType::Array) },
+///     Record { tag: TagValue::Anonymous, value: Value::Unsigned(1) },
+///     Record { tag: TagValue::Anonymous, value: Value::ContainerEnd },
+///     Record { tag: TagValue::Anonymous, value: Value::ContainerEnd },
+///     Record { tag: TagValue::ContextSpecific { tag: 2 }, value: Value::Bool(true) },
+/// ];
+/// let mut source = streaming_iterator::convert(records.iter().copied());
+///
+/// source.next();
+/// assert_eq!(skip_element(&mut source), Ok(DecodeEnd::DataConsumed));
+/// assert_eq!(source.next(), Some(&records[5]));
+/// ```
+pub fn skip_element<'a, Source>(source: &mut Source) -> Result<DecodeEnd, DecodeError>
+where
+    Source: StreamingIterator<Item = Record<'a>>,
+{
+    match source.get() {
+        None => return Err(DecodeError::InvalidData),
+        Some(Record {
+            value: Value::ContainerStart(_),
+            ..
+        }) => {}
+        Some(_) => return Ok(DecodeEnd::DataConsumed),
+    }
+
+    let mut depth = 1usize;
+    while depth > 0 {
+        match source.next() {
+            None => return Ok(DecodeEnd::StreamFinished),
+            Some(Record {
+                value: Value::ContainerStart(_),
+                ..
+            }) => depth += 1,
+            Some(Record {
+                value: Value::ContainerEnd,
+                ..
+            }) => depth -= 1,
+            Some(_) => {}
+        }
+    }
+
+    Ok(DecodeEnd::DataConsumed)
+}
+
 /// decodes a single value from a streaming iterator.
 ///
 /// Assumes that the iterator has already been positioned to a valid location.
@@ -0,0 +1,146 @@
+use streaming_iterator::StreamingIterator;
+use tlv_stream::{ContainerType, Record, TagValue, Value};
+
+use crate::{DecodeEnd, DecodeError, EncodeError, TlvEncodable, TlvMergeDecodable, TlvWriter};
+
+/// Decodes a TLV array or list into `items`, replacing any existing content.
+///
+/// Every element is decoded starting from its default value. Decoding fails
+/// with [DecodeError::TooManyEntries] as soon as more than `max_entries`
+/// elements are found.
+///
+/// ```
+/// use streaming_iterator::StreamingIterator;
+/// use tlv_packed::{decode_list, DecodeEnd};
+/// use tlv_stream::{ContainerType, Record, TagValue, Value};
+///
+/// let records = [
+///     Record { tag: TagValue::Anonymous, value: Value::ContainerStart(ContainerType::Array) },
+///     Record { tag: TagValue::Anonymous, value: Value::Unsigned(1) },
+///     Record { tag: TagValue::Anonymous, value: Value::Unsigned(2) },
+///     Record { tag: TagValue::Anonymous, value: Value::ContainerEnd },
+/// ];
+/// let mut source = streaming_iterator::convert(records.iter().copied());
+/// source.next();
+///
+/// let mut items: Vec<u16> = vec![10];
+/// assert_eq!(decode_list(&mut items, &mut source, None), Ok(DecodeEnd::DataConsumed));
+/// assert_eq!(items, [1, 2]);
+/// ```
+pub fn decode_list<'a, Source, T>(
+    items: &mut Vec<T>,
+    source: &mut Source,
+    max_entries: Option<usize>,
+) -> Result<DecodeEnd, DecodeError>
+where
+    Source: StreamingIterator<Item = Record<'a>>,
+    T: TlvMergeDecodable<'a, Source>,
+{
+    if !matches!(
+        source.get(),
+        Some(Record {
+            tag: _,
+            value: Value::ContainerStart(ContainerType::Array | ContainerType::List)
+        })
+    ) {
+        return Err(DecodeError::InvalidData);
+    }
+
+    items.clear();
+
+    loop {
+        match source.next() {
+            None => return Ok(DecodeEnd::StreamFinished),
+            Some(Record {
+                tag: _,
+                value: Value::ContainerEnd,
+            }) => return Ok(DecodeEnd::DataConsumed),
+            Some(_) => {}
+        }
+
+        if matches!(max_entries, Some(max) if items.len() >= max) {
+            return Err(DecodeError::TooManyEntries);
+        }
+
+        let mut item = T::default();
+        if item.merge_decode(source)? != DecodeEnd::DataConsumed {
+            return Err(DecodeError::InvalidNesting);
+        }
+        items.push(item);
+    }
+}
+
+/// Encodes `items` as a TLV array of anonymous elements.
+///
+/// ```
+/// use tlv_packed::encode_list;
+/// use tlv_stream::TagValue;
+///
+/// let mut data = Vec::new();
+/// encode_list(&[true, false], TagValue::ContextSpecific { tag: 1 }, &mut data).unwrap();
+///
+/// assert_eq!(data, [0x36, 0x01, 0x09, 0x08, 0x18]);
+/// ```
+pub fn encode_list<T, W>(items: &[T], tag: TagValue, writer: &mut W) -> Result<(), EncodeError>
+where
+    T: TlvEncodable,
+    W: TlvWriter,
+{
+    writer.write_record(Record {
+        tag,
+        value: Value::ContainerStart(ContainerType::Array),
+    })?;
+
+    for item in items {
+        item.encode(TagValue::Anonymous, writer)?;
+    }
+
+    writer.write_record(Record {
+        tag: TagValue::Anonymous,
+        value: Value::ContainerEnd,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn too_many_entries() {
+        let data = [0x16, 0x04, 0x01, 0x04, 0x02, 0x04, 0x03, 0x18];
+        let mut source = streaming_iterator::convert(tlv_stream::Parser::new(&data));
+        source.next();
+
+        let mut items: Vec<u8> = Vec::new();
+        assert_eq!(
+            decode_list(&mut items, &mut source, Some(2)),
+            Err(DecodeError::TooManyEntries)
+        );
+    }
+
+    #[test]
+    fn not_a_list() {
+        let data = [0x15, 0x18];
+        let mut source = streaming_iterator::convert(tlv_stream::Parser::new(&data));
+        source.next();
+
+        let mut items: Vec<u8> = Vec::new();
+        assert_eq!(
+            decode_list(&mut items, &mut source, None),
+            Err(DecodeError::InvalidData)
+        );
+    }
+
+    #[test]
+    fn truncated_list() {
+        let data = [0x17, 0x04, 0x01];
+        let mut source = streaming_iterator::convert(tlv_stream::Parser::new(&data));
+        source.next();
+
+        let mut items: Vec<u8> = Vec::new();
+        assert_eq!(
+            decode_list(&mut items, &mut source, None),
+            Ok(DecodeEnd::StreamFinished)
+        );
+    }
+}
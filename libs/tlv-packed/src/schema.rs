@@ -0,0 +1,219 @@
+use core::fmt::{self, Display};
+use tlv_stream::{ContainerType, TagValue};
+
+use crate::{OwnedValue, UnknownElements};
+
+/// Describes how a type is represented in TLV.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TypeSchema {
+    Signed,
+    Unsigned,
+    Bool,
+    Float,
+    Double,
+    Utf8,
+    Bytes,
+    Structure(&'static StructSchema),
+
+    /// A list of elements, encoded as a TLV array
+    Array(&'static TypeSchema),
+
+    /// Any element, including containers (e.g. collected unknown elements)
+    Any,
+}
+
+impl TypeSchema {
+    /// The container kind of the type, if the type is encoded as a container.
+    pub fn container_type(&self) -> Option<ContainerType> {
+        match self {
+            TypeSchema::Structure(_) => Some(ContainerType::Structure),
+            TypeSchema::Array(_) => Some(ContainerType::Array),
+            _ => None,
+        }
+    }
+}
+
+impl Display for TypeSchema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeSchema::Signed => write!(f, "signed"),
+            TypeSchema::Unsigned => write!(f, "unsigned"),
+            TypeSchema::Bool => write!(f, "bool"),
+            TypeSchema::Float => write!(f, "float"),
+            TypeSchema::Double => write!(f, "double"),
+            TypeSchema::Utf8 => write!(f, "utf8"),
+            TypeSchema::Bytes => write!(f, "bytes"),
+            TypeSchema::Structure(s) => write!(f, "structure {}", s.name),
+            TypeSchema::Array(item) => write!(f, "array of {}", item),
+            TypeSchema::Any => write!(f, "any"),
+        }
+    }
+}
+
+/// Describes a single field of a structure.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FieldSchema {
+    /// Name of the field in the Rust structure
+    pub name: &'static str,
+
+    /// Tag of the field. `None` for fields collecting unknown elements.
+    pub tag: Option<TagValue>,
+
+    /// Rust type of the field, as written in the structure
+    pub rust_type: &'static str,
+
+    /// The field may be absent from the encoded structure
+    pub optional: bool,
+
+    /// The field may be encoded as null
+    pub nullable: bool,
+
+    /// Encoding of the field value
+    pub schema: TypeSchema,
+}
+
+impl FieldSchema {
+    /// The container kind of the field value, if it is a container.
+    pub fn container_type(&self) -> Option<ContainerType> {
+        self.schema.container_type()
+    }
+}
+
+/// Describes a structure and all its (non-skipped) fields.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StructSchema {
+    pub name: &'static str,
+    pub fields: &'static [FieldSchema],
+}
+
+impl StructSchema {
+    /// Finds the field that is decoded from the given tag.
+    pub fn field_by_tag(&self, tag: TagValue) -> Option<&'static FieldSchema> {
+        self.fields.iter().find(|f| f.tag == Some(tag))
+    }
+
+    /// Finds a field by its Rust name.
+    pub fn field_by_name(&self, name: &str) -> Option<&'static FieldSchema> {
+        self.fields.iter().find(|f| f.name == name)
+    }
+}
+
+fn format_tag(tag: &TagValue) -> String {
+    match tag {
+        TagValue::Anonymous => "anonymous".into(),
+        TagValue::ContextSpecific { tag } => format!("context:{}", tag),
+        TagValue::Implicit { tag } => format!("implicit:{}", tag),
+        TagValue::Full {
+            vendor_id: 0,
+            profile_id: 0,
+            tag,
+        } => format!("full:{}", tag),
+        TagValue::Full {
+            vendor_id,
+            profile_id,
+            tag,
+        } => format!("full:0x{:X}-0x{:X}-{}", vendor_id, profile_id, tag),
+    }
+}
+
+/// Prints the layout of the structure, one field per line.
+///
+/// ```
+/// use tlv_packed::{FieldSchema, StructSchema, TypeSchema};
+/// use tlv_stream::TagValue;
+///
+/// let schema = StructSchema {
+///     name: "Example",
+///     fields: &[
+///         FieldSchema {
+///             name: "id",
+///             tag: Some(TagValue::ContextSpecific { tag: 1 }),
+///             rust_type: "u16",
+///             optional: false,
+///             nullable: false,
+///             schema: TypeSchema::Unsigned,
+///         },
+///         FieldSchema {
+///             name: "label",
+///             tag: Some(TagValue::ContextSpecific { tag: 2 }),
+///             rust_type: "Option<String>",
+///             optional: true,
+///             nullable: true,
+///             schema: TypeSchema::Utf8,
+///         },
+///     ],
+/// };
+///
+/// assert_eq!(
+///     schema.to_string(),
+///     "Example {\n    \
+///          context:1 id: u16 (unsigned)\n    \
+///          context:2 label: Option<String> (utf8, optional, nullable)\n\
+///      }"
+/// );
+/// ```
+impl Display for StructSchema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} {{", self.name)?;
+        for field in self.fields {
+            let tag = match field.tag {
+                Some(ref tag) => format_tag(tag),
+                None => "*".into(),
+            };
+
+            write!(
+                f,
+                "    {} {}: {} ({}",
+                tag, field.name, field.rust_type, field.schema
+            )?;
+            if field.optional {
+                write!(f, ", optional")?;
+            }
+            if field.nullable {
+                write!(f, ", nullable")?;
+            }
+            writeln!(f, ")")?;
+        }
+        write!(f, "}}")
+    }
+}
+
+/// Types with a statically known TLV representation.
+///
+/// Generally implemented via `#[derive(TlvSchema)]` from `tlv_derive`.
+pub trait TlvSchema {
+    const SCHEMA: TypeSchema;
+}
+
+macro_rules! schema_for {
+    ($schema:expr, $($type:ty),*) => {
+        $(
+            impl TlvSchema for $type {
+                const SCHEMA: TypeSchema = $schema;
+            }
+        )*
+    };
+}
+
+schema_for!(TypeSchema::Signed, i8, i16, i32, i64);
+schema_for!(TypeSchema::Unsigned, u8, u16, u32, u64);
+schema_for!(TypeSchema::Bool, bool);
+schema_for!(TypeSchema::Float, f32);
+schema_for!(TypeSchema::Double, f64);
+schema_for!(TypeSchema::Utf8, str, String);
+schema_for!(TypeSchema::Bytes, [u8], Vec<u8>);
+schema_for!(TypeSchema::Any, OwnedValue, UnknownElements);
+
+impl<T> TlvSchema for &T
+where
+    T: TlvSchema + ?Sized,
+{
+    const SCHEMA: TypeSchema = T::SCHEMA;
+}
+
+impl<T> TlvSchema for Option<T>
+where
+    T: TlvSchema,
+{
+    const SCHEMA: TypeSchema = T::SCHEMA;
+}
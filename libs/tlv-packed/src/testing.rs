@@ -0,0 +1,348 @@
+//! Reusable checks for types that are both decoded from and encoded to TLV.
+//!
+//! Given a type and some encoded test vectors (as hex strings), the checks
+//! verify that:
+//!   - decoding, encoding and decoding again gives the same value
+//!   - structure members decode the same in any order
+//!   - unknown elements in structures are skipped
+//!
+//! ```
+//! use tlv_packed::testing::check_vectors;
+//!
+//! check_vectors::<u16>(&["05 34 12", "04 01"]);
+//! ```
+//!
+//! All checks panic on failure, so they are meant for tests. Types borrowing
+//! from the decoded data are not supported, as the checks decode from
+//! temporary buffers.
+
+use core::fmt::Debug;
+
+use tlv_stream::{ContainerType, Parser, Record, TagValue, Value};
+
+use crate::{
+    decode_from_bytes, encode_to_vec, ParserSource, TlvEncodable, TlvMergeDecodable, TlvSchema,
+    TlvWriter, TypeSchema,
+};
+
+/// Tag for unknown elements, not expected to be used by any structure.
+const UNKNOWN_FULL_TAG: TagValue = TagValue::Full {
+    vendor_id: 0xFFF1,
+    profile_id: 0xDEED,
+    tag: 0xAA55,
+};
+
+/// Parses a hex string like `"15 24 01 2A 18"`.
+///
+/// Whitespace, `:`, `,` and `-` separators as well as `0x` prefixes are ignored.
+///
+/// ```
+/// use tlv_packed::testing::hex_to_bytes;
+///
+/// assert_eq!(hex_to_bytes("15 24:01,0x2A-18"), [0x15, 0x24, 0x01, 0x2A, 0x18]);
+/// ```
+pub fn hex_to_bytes(hex: &str) -> Vec<u8> {
+    let digits: Vec<u8> = hex
+        .replace("0x", "")
+        .replace("0X", "")
+        .chars()
+        .filter(|c| !c.is_whitespace() && !matches!(c, ':' | ',' | '-'))
+        .map(|c| match c.to_digit(16) {
+            Some(d) => d as u8,
+            None => panic!("Invalid hex digit `{}` in `{}`", c, hex),
+        })
+        .collect();
+
+    if !digits.len().is_multiple_of(2) {
+        panic!("Odd number of hex digits in `{}`", hex);
+    }
+
+    digits
+        .chunks(2)
+        .map(|pair| pair[0] << 4 | pair[1])
+        .collect()
+}
+
+fn to_hex(data: &[u8]) -> String {
+    data.iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn decode<T>(data: &[u8], description: &str) -> T
+where
+    T: for<'a> TlvMergeDecodable<'a, ParserSource<'a>>,
+{
+    match decode_from_bytes(data) {
+        Ok(value) => value,
+        Err(e) => panic!(
+            "Failed to decode {} `{}`: {:?}",
+            description,
+            to_hex(data),
+            e
+        ),
+    }
+}
+
+fn encode<T: TlvEncodable>(value: &T) -> Vec<u8> {
+    match encode_to_vec(value) {
+        Ok(data) => data,
+        Err(e) => panic!("Failed to encode {:?}", e),
+    }
+}
+
+fn records_to_bytes<'a>(records: impl IntoIterator<Item = &'a Record<'a>>) -> Vec<u8> {
+    let mut data = Vec::new();
+    for record in records {
+        data.write_record(*record).expect("Vec writes never fail");
+    }
+    data
+}
+
+/// Splits an encoded structure into its start record, members and end record.
+///
+/// Returns `None` if the data is not a single structure.
+fn structure_members<'a>(records: &'a [Record<'a>]) -> Option<Vec<&'a [Record<'a>]>> {
+    match records {
+        [Record {
+            value: Value::ContainerStart(ContainerType::Structure),
+            ..
+        }, .., Record {
+            value: Value::ContainerEnd,
+            ..
+        }] => {}
+        _ => return None,
+    }
+
+    let content = &records[1..records.len() - 1];
+    let mut members = Vec::new();
+    let mut start = 0;
+    let mut depth = 0usize;
+
+    for (idx, record) in content.iter().enumerate() {
+        match record.value {
+            Value::ContainerStart(_) => depth += 1,
+            Value::ContainerEnd => depth = depth.checked_sub(1)?,
+            _ => {}
+        }
+
+        if depth == 0 {
+            members.push(&content[start..=idx]);
+            start = idx + 1;
+        }
+    }
+
+    match depth {
+        0 => Some(members),
+        _ => None,
+    }
+}
+
+/// Re-assembles a structure from its members.
+fn assemble<'a>(records: &'a [Record<'a>], members: &[&'a [Record<'a>]]) -> Vec<u8> {
+    let start = records.first().into_iter();
+    let end = records.last().into_iter();
+
+    records_to_bytes(
+        start
+            .chain(members.iter().flat_map(|m| m.iter()))
+            .chain(end),
+    )
+}
+
+/// Checks that decoding `hex`, encoding the result and decoding again
+/// gives the same value, and that encoding is stable.
+pub fn check_round_trip<T>(hex: &str)
+where
+    T: for<'a> TlvMergeDecodable<'a, ParserSource<'a>> + TlvEncodable + PartialEq + Debug,
+{
+    let data = hex_to_bytes(hex);
+    let value: T = decode(&data, "test vector");
+
+    let encoded = encode(&value);
+    let decoded: T = decode(&encoded, "re-encoded value");
+    assert_eq!(
+        decoded,
+        value,
+        "Value changed after encoding as `{}`",
+        to_hex(&encoded)
+    );
+
+    assert_eq!(
+        to_hex(&encode(&decoded)),
+        to_hex(&encoded),
+        "Encoding of {:?} is not stable",
+        value
+    );
+}
+
+/// Checks that the members of the structure in `hex` decode to the same
+/// value when they are reordered (reversed and rotated).
+///
+/// Does nothing for vectors that are not structures or have fewer than two members.
+pub fn check_reordered_members<T>(hex: &str)
+where
+    T: for<'a> TlvMergeDecodable<'a, ParserSource<'a>> + PartialEq + Debug,
+{
+    let data = hex_to_bytes(hex);
+    let expected: T = decode(&data, "test vector");
+
+    let records: Vec<Record> = Parser::new(&data).collect();
+    let members = match structure_members(&records) {
+        Some(members) if members.len() > 1 => members,
+        _ => return,
+    };
+
+    let mut orders = Vec::new();
+    orders.push(members.iter().rev().copied().collect::<Vec<_>>());
+    for shift in 1..members.len() {
+        let mut rotated = members.clone();
+        rotated.rotate_left(shift);
+        orders.push(rotated);
+    }
+
+    for order in orders {
+        let reordered = assemble(&records, &order);
+        let value: T = decode(&reordered, "reordered structure");
+        assert_eq!(
+            value,
+            expected,
+            "Reordered structure `{}` decodes differently",
+            to_hex(&reordered)
+        );
+    }
+}
+
+/// Checks that unknown elements inserted into the structure in `hex` are
+/// skipped while decoding.
+///
+/// Inserts a primitive with a context tag that the schema of `T` does not
+/// use, a nested container and an element with a full tag. Does nothing for
+/// vectors that are not structures.
+pub fn check_unknown_tags_ignored<T>(hex: &str)
+where
+    T: for<'a> TlvMergeDecodable<'a, ParserSource<'a>> + TlvSchema + PartialEq + Debug,
+{
+    let data = hex_to_bytes(hex);
+    let expected: T = decode(&data, "test vector");
+
+    let records: Vec<Record> = Parser::new(&data).collect();
+    let members = match structure_members(&records) {
+        Some(members) => members,
+        None => return,
+    };
+
+    let unused_context_tag = match T::SCHEMA {
+        TypeSchema::Structure(schema) => (0..=253u32)
+            .rev()
+            .map(|tag| TagValue::ContextSpecific { tag })
+            .find(|tag| schema.field_by_tag(*tag).is_none()),
+        _ => None,
+    };
+
+    let mut unknown = Vec::new();
+    if let Some(tag) = unused_context_tag {
+        unknown.push(vec![Record {
+            tag,
+            value: Value::Unsigned(0x1234),
+        }]);
+    }
+    unknown.push(vec![
+        Record {
+            tag: UNKNOWN_FULL_TAG,
+            value: Value::ContainerStart(ContainerType::Structure),
+        },
+        Record {
+            tag: TagValue::ContextSpecific { tag: 1 },
+            value: Value::ContainerStart(ContainerType::Array),
+        },
+        Record {
+            tag: TagValue::Anonymous,
+            value: Value::Bool(true),
+        },
+        Record {
+            tag: TagValue::Anonymous,
+            value: Value::ContainerEnd,
+        },
+        Record {
+            tag: TagValue::Anonymous,
+            value: Value::ContainerEnd,
+        },
+    ]);
+    unknown.push(vec![Record {
+        tag: UNKNOWN_FULL_TAG,
+        value: Value::Utf8(b"unknown"),
+    }]);
+
+    // unknown elements first, in between members and last
+    let mut with_unknown: Vec<&[Record]> = Vec::new();
+    with_unknown.push(&unknown[0]);
+    for (idx, member) in members.iter().enumerate() {
+        with_unknown.push(member);
+        if idx == 0 && unknown.len() > 2 {
+            with_unknown.push(&unknown[1]);
+        }
+    }
+    with_unknown.push(unknown.last().expect("unknown elements are never empty"));
+
+    let modified = assemble(&records, &with_unknown);
+    let value: T = decode(&modified, "structure with unknown elements");
+    assert_eq!(
+        value,
+        expected,
+        "Structure with unknown elements `{}` decodes differently",
+        to_hex(&modified)
+    );
+}
+
+/// Runs all checks of this module on every test vector.
+pub fn check_vectors<T>(vectors: &[&str])
+where
+    T: for<'a> TlvMergeDecodable<'a, ParserSource<'a>>
+        + TlvEncodable
+        + TlvSchema
+        + PartialEq
+        + Debug,
+{
+    for hex in vectors {
+        check_round_trip::<T>(hex);
+        check_reordered_members::<T>(hex);
+        check_unknown_tags_ignored::<T>(hex);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_members() {
+        // { 1: 1, 2: [true], 3: "a" }
+        let data = hex_to_bytes("15 24 01 01 36 02 09 18 2C 03 01 61 18");
+        let records: Vec<Record> = Parser::new(&data).collect();
+
+        let members = structure_members(&records).unwrap();
+        assert_eq!(members.len(), 3);
+        assert_eq!(members[1].len(), 3);
+
+        let reversed: Vec<_> = members.iter().rev().copied().collect();
+        assert_eq!(
+            to_hex(&assemble(&records, &reversed)),
+            "15 2C 03 01 61 36 02 09 18 24 01 01 18"
+        );
+    }
+
+    #[test]
+    fn not_a_structure() {
+        let data = hex_to_bytes("16 04 01 18");
+        let records: Vec<Record> = Parser::new(&data).collect();
+        assert!(structure_members(&records).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid hex digit")]
+    fn invalid_hex() {
+        hex_to_bytes("1G");
+    }
+}
@@ -0,0 +1,217 @@
+use streaming_iterator::StreamingIterator;
+use tlv_stream::{ContainerType, Record, TagValue, Value};
+
+use crate::{DecodeEnd, DecodeError, TlvMergeDecodable};
+
+/// An owned version of [tlv_stream::Value].
+///
+/// Strings and byte strings are copied, so the value can outlive
+/// the buffer it was parsed from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OwnedValue {
+    Signed(i64),
+    Unsigned(u64),
+    Bool(bool),
+    Float(f32),
+    Double(f64),
+    Utf8(Vec<u8>),
+    Bytes(Vec<u8>),
+    Null,
+    ContainerStart(ContainerType),
+    ContainerEnd,
+}
+
+impl OwnedValue {
+    /// Borrows the owned value as a regular [Value].
+    pub fn as_value(&self) -> Value<'_> {
+        match self {
+            OwnedValue::Signed(n) => Value::Signed(*n),
+            OwnedValue::Unsigned(n) => Value::Unsigned(*n),
+            OwnedValue::Bool(b) => Value::Bool(*b),
+            OwnedValue::Float(f) => Value::Float(*f),
+            OwnedValue::Double(d) => Value::Double(*d),
+            OwnedValue::Utf8(data) => Value::Utf8(data.as_slice()),
+            OwnedValue::Bytes(data) => Value::Bytes(data.as_slice()),
+            OwnedValue::Null => Value::Null,
+            OwnedValue::ContainerStart(t) => Value::ContainerStart(*t),
+            OwnedValue::ContainerEnd => Value::ContainerEnd,
+        }
+    }
+}
+
+impl<'a> From<Value<'a>> for OwnedValue {
+    fn from(value: Value<'a>) -> Self {
+        match value {
+            Value::Signed(n) => OwnedValue::Signed(n),
+            Value::Unsigned(n) => OwnedValue::Unsigned(n),
+            Value::Bool(b) => OwnedValue::Bool(b),
+            Value::Float(f) => OwnedValue::Float(f),
+            Value::Double(d) => OwnedValue::Double(d),
+            Value::Utf8(data) => OwnedValue::Utf8(data.into()),
+            Value::Bytes(data) => OwnedValue::Bytes(data.into()),
+            Value::Null => OwnedValue::Null,
+            Value::ContainerStart(t) => OwnedValue::ContainerStart(t),
+            Value::ContainerEnd => OwnedValue::ContainerEnd,
+        }
+    }
+}
+
+/// Elements that a derived decoder did not recognize.
+///
+/// Used by structures that collect unknown tags (see the `unknown_tags`
+/// attribute of `tlv_derive`) so that data can be passed through without
+/// being understood. Unknown containers are stored in full, including
+/// their nested content and their container end.
+///
+/// Merge-decoding into this type appends the current element:
+///
+/// ```
+/// use tlv_packed::{DecodeEnd, TlvMergeDecodable, UnknownElements};
+/// use tlv_stream::{Record, TagValue, Value};
+/// use streaming_iterator::StreamingIterator;
+///
+/// let records = [
+///     Record { tag: TagValue::ContextSpecific { tag: 7 }, value: Value::Unsigned(3) },
+/// ];
+/// let mut source = streaming_iterator::convert(records.iter().copied());
+/// source.next();
+///
+/// let mut unknown = UnknownElements::default();
+/// assert_eq!(unknown.merge_decode(&mut source), Ok(DecodeEnd::DataConsumed));
+/// assert_eq!(unknown.records().collect::<Vec<_>>(), records);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UnknownElements {
+    records: Vec<(TagValue, OwnedValue)>,
+}
+
+impl UnknownElements {
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Iterates over all collected records, in the order they were decoded.
+    pub fn records(&self) -> impl Iterator<Item = Record<'_>> {
+        self.records.iter().map(|(tag, value)| Record {
+            tag: *tag,
+            value: value.as_value(),
+        })
+    }
+
+    pub fn clear(&mut self) {
+        self.records.clear();
+    }
+}
+
+impl<'a, Source> TlvMergeDecodable<'a, Source> for UnknownElements
+where
+    Source: StreamingIterator<Item = Record<'a>>,
+{
+    fn merge_decode(&mut self, source: &mut Source) -> Result<DecodeEnd, DecodeError> {
+        let mut depth = 0usize;
+        let mut record = source.get();
+
+        loop {
+            let current = match record {
+                None if depth == 0 => return Err(DecodeError::InvalidData),
+                None => return Ok(DecodeEnd::StreamFinished),
+                Some(current) => current,
+            };
+
+            match current.value {
+                Value::ContainerStart(_) => depth += 1,
+                Value::ContainerEnd if depth == 0 => return Err(DecodeError::InvalidNesting),
+                Value::ContainerEnd => depth -= 1,
+                _ => {}
+            }
+
+            self.records.push((current.tag, current.value.into()));
+
+            if depth == 0 {
+                return Ok(DecodeEnd::DataConsumed);
+            }
+
+            record = source.next();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collects_nested_containers() {
+        let records = [
+            Record {
+                tag: TagValue::ContextSpecific { tag: 10 },
+                value: Value::ContainerStart(ContainerType::Structure),
+            },
+            Record {
+                tag: TagValue::ContextSpecific { tag: 1 },
+                value: Value::Utf8(b"abc"),
+            },
+            Record {
+                tag: TagValue::ContextSpecific { tag: 2 },
+                value: Value::ContainerStart(ContainerType::Array),
+            },
+            Record {
+                tag: TagValue::Anonymous,
+                value: Value::Signed(-1),
+            },
+            Record {
+                tag: TagValue::Anonymous,
+                value: Value::ContainerEnd,
+            },
+            Record {
+                tag: TagValue::Anonymous,
+                value: Value::ContainerEnd,
+            },
+            Record {
+                tag: TagValue::ContextSpecific { tag: 11 },
+                value: Value::Null,
+            },
+        ];
+
+        let mut source = streaming_iterator::convert(records.iter().copied());
+        source.next();
+
+        let mut unknown = UnknownElements::default();
+        assert_eq!(
+            unknown.merge_decode(&mut source),
+            Ok(DecodeEnd::DataConsumed)
+        );
+        assert_eq!(unknown.records().collect::<Vec<_>>(), records[0..6]);
+
+        // stream positioned on the container end, next element can be collected too
+        source.next();
+        assert_eq!(
+            unknown.merge_decode(&mut source),
+            Ok(DecodeEnd::DataConsumed)
+        );
+        assert_eq!(unknown.records().collect::<Vec<_>>(), records);
+    }
+
+    #[test]
+    fn truncated_container() {
+        let records = [
+            Record {
+                tag: TagValue::ContextSpecific { tag: 10 },
+                value: Value::ContainerStart(ContainerType::List),
+            },
+            Record {
+                tag: TagValue::ContextSpecific { tag: 1 },
+                value: Value::Bool(false),
+            },
+        ];
+
+        let mut source = streaming_iterator::convert(records.iter().copied());
+        source.next();
+
+        let mut unknown = UnknownElements::default();
+        assert_eq!(
+            unknown.merge_decode(&mut source),
+            Ok(DecodeEnd::StreamFinished)
+        );
+    }
+}
@@ -1,5 +1,8 @@
 use std::time::Duration;
 
+/// Setup passcode used by test devices when none is given.
+pub const DEFAULT_PASSCODE: u32 = 20202021;
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum Command {
     List,
@@ -7,8 +10,8 @@ pub enum Command {
     Help,
     Exit,
 
-    // Generic test command
-    Test(u64),
+    // Generic test command: device index and setup passcode
+    Test(u64, u32),
 }
 
 impl Command {
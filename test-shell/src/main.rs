@@ -1,6 +1,6 @@
 use ast::Command;
 
-use matter_btp::{AsyncConnection, BlePeripheralConnection};
+use matter_btp::BlePeripheralConnection;
 
 use std::time::Duration;
 
@@ -20,6 +20,7 @@ lalrpop_mod!(
     pub cli
 );
 mod ast;
+mod pase;
 
 use matter_btp::advertising_data::Commissionable;
 
@@ -54,7 +55,7 @@ fn help() {
     println!("Available commands: {}", Command::all_strings().join(", "));
     println!("Some specific syntaxes: ");
     println!("   scan <number_of_seconds> ");
-    println!("   test <list_device_index> ");
+    println!("   test <list_device_index> [<setup_passcode>]");
 }
 
 /// The execution shell, to be stateful
@@ -142,7 +143,7 @@ impl<'a> Shell<'a> {
         Ok(())
     }
 
-    async fn test(&self, idx: usize) -> Result<()> {
+    async fn test(&self, idx: usize, passcode: u32) -> Result<()> {
         if idx >= self.available_peripherals.len() {
             return Err(anyhow!(
                 "No device with index {}. Cached {} devices. Run 'list' to refresh/re-list.",
@@ -163,17 +164,14 @@ impl<'a> Shell<'a> {
             .handshake()
             .await?;
 
-        // TODO: actually need to send PASE
-        println!("Sending fake data....");
-        conn.write(&[1, 2, 3, 4, 5, 6]).await?;
-
-        let data = conn.read().await?;
-        println!("DATA RECEIVED: {:?}", data);
+        let session = pase::establish(&mut conn, passcode).await?;
+        println!(
+            "PASE session established: local id {}, peer id {}",
+            session.local_session_id, session.peer_session_id
+        );
 
         // TODO:
-        //   - use connection for PASE
-        //   - use connection for cluster operations
-        //   - start implementing CHIP framing after that!
+        //   - use the session for cluster operations
         //
         println!("Need more implementation here");
 
@@ -216,7 +214,7 @@ async fn main() -> Result<()> {
                 Ok(())
             }
             Ok(Command::Exit) => break,
-            Ok(Command::Test(idx)) => shell.test(idx as usize).await,
+            Ok(Command::Test(idx, passcode)) => shell.test(idx as usize, passcode).await,
             Err(e) => Err(anyhow!("Command parse failed: {:?}", e)),
         };
 
@@ -0,0 +1,131 @@
+//! Runs PASE as commissioner over an established BTP connection.
+//!
+//! BTP is a reliable transport, so messages are sent without MRP.
+
+use anyhow::{anyhow, Result};
+use log::debug;
+use rand::Rng;
+
+use matter_btp::AsyncConnection;
+use matter_packets::message::{Message, ProtocolMessage};
+use matter_packets::packet;
+use matter_packets::pase::PaseInitiator;
+use matter_packets::payload::{self, ExchangeFlags, ProtocolOpCode, SecureChannelOpcode};
+use matter_packets::session::SecureSessionParams;
+use matter_packets::status_report::StatusReport;
+use matter_types::{ExchangeId, NodeId};
+
+/// The single unsecured exchange used for session establishment.
+struct UnsecuredExchange<'a, C: AsyncConnection> {
+    conn: &'a mut C,
+    source: NodeId,
+    counter: u32,
+    exchange: ExchangeId,
+}
+
+impl<'a, C: AsyncConnection> UnsecuredExchange<'a, C> {
+    fn new(conn: &'a mut C) -> Self {
+        let mut rng = rand::thread_rng();
+
+        Self {
+            conn,
+            source: NodeId(rng.gen()),
+            counter: rng.gen_range(1..=(1 << 28)),
+            exchange: ExchangeId(rng.gen()),
+        }
+    }
+
+    async fn send(&mut self, opcode: SecureChannelOpcode, data: &[u8]) -> Result<()> {
+        let mut message = Vec::new();
+        ProtocolMessage {
+            header: payload::HeaderBuilder::default()
+                .flags(ExchangeFlags::INITIATOR)
+                .protocol_opcode(ProtocolOpCode::SecureChannel(opcode))
+                .exchange(self.exchange)
+                .build()?,
+            secured_extensions: None,
+            payload: data,
+        }
+        .write(&mut message)?;
+
+        let mut frame = Vec::new();
+        Message {
+            header: packet::HeaderBuilder::default()
+                .session_id(0)
+                .source(Some(self.source))
+                .counter(self.counter)
+                .build()?,
+            message_extensions: None,
+            payload: &message,
+            mic: None,
+        }
+        .write(&mut frame)?;
+
+        self.counter = self.counter.wrapping_add(1);
+
+        debug!("Sending {:?}: {:02X?}", opcode, frame);
+        self.conn.write(&frame).await
+    }
+
+    /// Receives the next message of the exchange, failing on status reports
+    /// unless one is expected.
+    async fn receive(&mut self, expected: SecureChannelOpcode) -> Result<Vec<u8>> {
+        loop {
+            let frame = self.conn.read().await?;
+            debug!("Received: {:02X?}", frame);
+
+            let message = Message::parse(&frame)?;
+            let protocol_message = ProtocolMessage::parse(message.payload)?;
+
+            if protocol_message.header.exchange != self.exchange {
+                debug!(
+                    "Ignoring message for exchange {:?}",
+                    protocol_message.header.exchange
+                );
+                continue;
+            }
+
+            match protocol_message.header.protocol_opcode {
+                ProtocolOpCode::SecureChannel(opcode) if opcode == expected => {
+                    return Ok(protocol_message.payload.to_vec())
+                }
+                ProtocolOpCode::SecureChannel(SecureChannelOpcode::StatusReport) => {
+                    let report = StatusReport::parse(&mut &protocol_message.payload[..])?;
+                    return Err(anyhow!("PASE failed: {}", report));
+                }
+                other => return Err(anyhow!("Unexpected message {:?}", other)),
+            }
+        }
+    }
+}
+
+/// Establishes a PASE session with the device using its setup passcode.
+pub async fn establish<C: AsyncConnection>(
+    conn: &mut C,
+    passcode: u32,
+) -> Result<SecureSessionParams> {
+    let mut exchange = UnsecuredExchange::new(conn);
+    let mut initiator = PaseInitiator::new(passcode, rand::thread_rng().gen_range(1..=u16::MAX));
+
+    let request = initiator.start()?;
+    exchange
+        .send(SecureChannelOpcode::PbkdfParamRequest, &request)
+        .await?;
+
+    let response = exchange
+        .receive(SecureChannelOpcode::PbkdfParamResponse)
+        .await?;
+    let pake1 = initiator.on_pbkdf_param_response(&response)?;
+    exchange
+        .send(SecureChannelOpcode::PasePake1, &pake1)
+        .await?;
+
+    let pake2 = exchange.receive(SecureChannelOpcode::PasePake2).await?;
+    let pake3 = initiator.on_pake2(&pake2)?;
+    exchange
+        .send(SecureChannelOpcode::PasePake3, &pake3)
+        .await?;
+
+    let report = exchange.receive(SecureChannelOpcode::StatusReport).await?;
+    initiator.on_status_report(&StatusReport::parse(&mut report.as_slice())?)
+}
@@ -0,0 +1,17 @@
+use std::path::PathBuf;
+
+fn main() {
+    let fixtures = PathBuf::from("../libs/matter-codegen/fixtures");
+    let inputs = [
+        fixtures.join("identify-cluster.xml"),
+        fixtures.join("groups-cluster.xml"),
+        fixtures.join("access-control-cluster.xml"),
+    ];
+
+    for input in &inputs {
+        println!("cargo:rerun-if-changed={}", input.display());
+    }
+
+    let output = PathBuf::from(std::env::var("OUT_DIR").unwrap()).join("clusters.rs");
+    matter_codegen::generate_file(&inputs, output).unwrap();
+}
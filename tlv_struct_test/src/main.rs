@@ -1,7 +1,7 @@
 #[macro_use]
 extern crate tlv_derive;
 
-#[derive(Debug, Default, PartialEq, Clone, Copy, TlvMergeDecodable)]
+#[derive(Debug, Default, PartialEq, Clone, Copy, TlvMergeDecodable, TlvEncodable, TlvSchema)]
 struct Test {
     #[tlv_tag="context:1"]
     nr: u32,
@@ -13,6 +13,50 @@ struct Test {
     opt_nr: Option<u32>,
 }
 
+/// Clusters generated by matter-codegen from its fixture XML files.
+#[allow(dead_code)]
+mod clusters {
+    include!(concat!(env!("OUT_DIR"), "/clusters.rs"));
+}
+
 fn main() {
-    println!("Hello, world!");
+    if let tlv_packed::TypeSchema::Structure(schema) = <Test as tlv_packed::TlvSchema>::SCHEMA {
+        println!("{}", schema);
+    }
+
+    let schema =
+        <clusters::access_control::AccessControlEntryStruct as tlv_packed::TlvSchema>::SCHEMA;
+    if let tlv_packed::TypeSchema::Structure(schema) = schema {
+        println!("{}", schema);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clusters::{access_control, groups};
+    use tlv_packed::testing::check_vectors;
+
+    #[test]
+    fn test_round_trip() {
+        check_vectors::<Test>(&[
+            "15 26 01 45 23 01 00 24 02 07 44 CD AB 01 18",
+            "15 24 01 01 24 02 02 18",
+        ]);
+    }
+
+    #[test]
+    fn test_generated_round_trip() {
+        check_vectors::<access_control::AccessControlEntryStruct>(&[
+            // administer via CASE for one subject and cluster 6, fabric 1
+            "15 24 01 05 24 02 02 36 03 07 88 77 66 55 44 33 22 11 18 \
+             36 04 15 24 00 06 34 01 34 02 18 18 24 FE 01 18",
+            // fabric-sensitive fields omitted
+            "15 24 FE 02 18",
+        ]);
+
+        check_vectors::<groups::commands::AddGroup>(&[
+            "15 25 00 01 01 2C 01 07 4B 69 74 63 68 65 6E 18",
+        ]);
+    }
 }